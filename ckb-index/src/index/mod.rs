@@ -194,6 +194,13 @@ impl<'a> IndexDatabase<'a> {
             .map(|bytes| Script::new_unchecked(bytes.into()))
     }
 
+    pub fn get_live_cell_info(&self, out_point: OutPoint) -> Option<LiveCellInfo> {
+        let reader = RocksReader::new(self.db, self.cf);
+        reader
+            .get(&Key::LiveCellMap(out_point).to_bytes())
+            .map(|bytes| bincode::deserialize(&bytes).unwrap())
+    }
+
     pub fn get_live_cells_by_lock<F: FnMut(usize, &LiveCellInfo) -> (bool, bool)>(
         &self,
         lock_hash: Byte32,
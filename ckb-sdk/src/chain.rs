@@ -28,6 +28,17 @@ pub struct GenesisInfo {
 
 impl GenesisInfo {
     pub fn from_block(genesis_block: &BlockView) -> Result<GenesisInfo, String> {
+        GenesisInfo::parse(genesis_block, false).map(|(genesis_info, _)| genesis_info)
+    }
+
+    /// Parse the genesis block of a custom (dev) chain, only the sighash cells are required.
+    /// The missing multisig and dao cells are returned as errors, their hashes are zero.
+    pub fn from_dev_block(genesis_block: &BlockView) -> Result<(GenesisInfo, Vec<String>), String> {
+        GenesisInfo::parse(genesis_block, true)
+    }
+
+    // All the system cells are required unless `dev`
+    fn parse(genesis_block: &BlockView, dev: bool) -> Result<(GenesisInfo, Vec<String>), String> {
         let header = genesis_block.header();
         if header.number() != 0 {
             return Err(format!(
@@ -107,15 +118,34 @@ impl GenesisInfo {
             .ok_or_else(|| "No data hash(sighash) found in txs[0][1]".to_owned())?;
         let sighash_type_hash = sighash_type_hash
             .ok_or_else(|| "No type hash(sighash) found in txs[0][1]".to_owned())?;
-        let multisig_data_hash = multisig_data_hash
-            .ok_or_else(|| "No data hash(multisig) found in txs[0][4]".to_owned())?;
-        let multisig_type_hash = multisig_type_hash
-            .ok_or_else(|| "No type hash(multisig) found in txs[0][4]".to_owned())?;
-        let dao_data_hash =
-            dao_data_hash.ok_or_else(|| "No data hash(dao) found in txs[0][2]".to_owned())?;
-        let dao_type_hash =
-            dao_type_hash.ok_or_else(|| "No type hash(dao) found in txs[0][2]".to_owned())?;
-        Ok(GenesisInfo {
+        if dev
+            && out_points
+                .get(SIGHASH_GROUP_OUTPUT_LOC.0)
+                .and_then(|tx_out_points| tx_out_points.get(SIGHASH_GROUP_OUTPUT_LOC.1))
+                .is_none()
+        {
+            return Err("No dep group(sighash) found in txs[1][0]".to_owned());
+        }
+        let mut missing = Vec::new();
+        let mut require = |hash: Option<Byte32>, err: &str| match hash {
+            Some(hash) => Ok(hash),
+            None if dev => {
+                missing.push(err.to_owned());
+                Ok(Byte32::default())
+            }
+            None => Err(err.to_owned()),
+        };
+        let multisig_data_hash = require(
+            multisig_data_hash,
+            "No data hash(multisig) found in txs[0][4]",
+        )?;
+        let multisig_type_hash = require(
+            multisig_type_hash,
+            "No type hash(multisig) found in txs[0][4]",
+        )?;
+        let dao_data_hash = require(dao_data_hash, "No data hash(dao) found in txs[0][2]")?;
+        let dao_type_hash = require(dao_type_hash, "No type hash(dao) found in txs[0][2]")?;
+        let genesis_info = GenesisInfo {
             header,
             out_points,
             sighash_data_hash,
@@ -124,7 +154,8 @@ impl GenesisInfo {
             multisig_type_hash,
             dao_data_hash,
             dao_type_hash,
-        })
+        };
+        Ok((genesis_info, missing))
     }
 
     pub fn header(&self) -> &HeaderView {
@@ -10,12 +10,12 @@ pub mod wallet;
 
 pub use chain::{calc_max_mature_number, GenesisInfo};
 pub use error::Error;
-pub use rpc::HttpRpcClient;
+pub use rpc::{HttpRpcClient, RPC_TIMEOUT_ERROR};
 pub use transaction::{
     MockCellDep, MockInfo, MockInput, MockResourceLoader, MockTransaction, MockTransactionHelper,
     ReprMockCellDep, ReprMockInfo, ReprMockInput, ReprMockTransaction,
 };
-pub use tx_helper::{build_signature, MultisigConfig, SignerFn, TxHelper};
+pub use tx_helper::{build_signature, check_lock_script, MultisigConfig, SignerFn, TxHelper};
 pub use types::{
     Address, AddressPayload, AddressType, CodeHashIndex, HumanCapacity, NetworkType, OldAddress,
     OldAddressFormat, Since, SinceType,
@@ -6,7 +6,11 @@ use ckb_jsonrpc_types::{
     Uint64, Version,
 };
 
+use std::thread;
+use std::time::Duration;
+
 use super::types;
+use chrono::{DateTime, Utc};
 use ckb_types::{packed, H256};
 
 macro_rules! jsonrpc {
@@ -23,12 +27,28 @@ macro_rules! jsonrpc {
             pub client: reqwest::Client,
             pub url: reqwest::Url,
             pub id: u64,
+            pub max_throttle_wait: Duration,
         }
 
         impl $struct_name {
             pub fn new(uri: &str) -> Self {
                 let url = reqwest::Url::parse(uri).expect("ckb uri, e.g. \"http://127.0.0.1:8114\"");
-                $struct_name { url, id: 0, client: reqwest::Client::new(), }
+                $struct_name { url, id: 0, client: reqwest::Client::new(), max_throttle_wait: Duration::from_secs(0) }
+            }
+
+            /// Send a throttled (HTTP 429) request again after its `Retry-After`, for at most
+            /// `max_wait` in total, zero (the default) never retries
+            pub fn set_max_throttle_wait(&mut self, max_wait: Duration) {
+                self.max_throttle_wait = max_wait;
+            }
+
+            /// Set the timeout of each request, the error of a timeout request starts with
+            /// `RPC_TIMEOUT_ERROR`
+            pub fn set_timeout(&mut self, timeout: std::time::Duration) {
+                self.client = reqwest::Client::builder()
+                    .timeout(timeout)
+                    .build()
+                    .expect("build rpc http client");
             }
 
             $(
@@ -44,8 +64,10 @@ macro_rules! jsonrpc {
                     req_json.insert("method".to_owned(), serde_json::json!(method));
                     req_json.insert("params".to_owned(), params);
 
-                    let mut resp = $selff.client.post($selff.url.clone()).json(&req_json).send()?;
-                    let output = resp.json::<ckb_jsonrpc_types::response::Output>()?;
+                    let mut resp = send_with_retry_after(&$selff.url, $selff.max_throttle_wait, || {
+                        $selff.client.post($selff.url.clone()).json(&req_json).send()
+                    }).map_err(map_reqwest_error)?;
+                    let output = resp.json::<ckb_jsonrpc_types::response::Output>().map_err(map_reqwest_error)?;
                     match output {
                         ckb_jsonrpc_types::response::Output::Success(success) => {
                             serde_json::from_value(success.result).map_err(Into::into)
@@ -60,6 +82,82 @@ macro_rules! jsonrpc {
     )
 }
 
+/// The prefix of the error message when a rpc request timeout, to distinguish from the
+/// errors returned by the rpc server
+pub const RPC_TIMEOUT_ERROR: &str = "RPC request timeout";
+
+// The wait of a throttled response without a valid `Retry-After`
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// Send a rpc request, a throttled (HTTP 429) response is sent again after its `Retry-After`,
+/// the throttling is returned as an error once `max_wait` would be exceeded. A zero `max_wait`
+/// returns the throttled response as is.
+pub fn send_with_retry_after<F>(
+    url: &reqwest::Url,
+    max_wait: Duration,
+    mut send: F,
+) -> Result<reqwest::Response, reqwest::Error>
+where
+    F: FnMut() -> Result<reqwest::Response, reqwest::Error>,
+{
+    let mut waited = Duration::from_secs(0);
+    loop {
+        let resp = send()?;
+        if resp.status() != reqwest::StatusCode::TOO_MANY_REQUESTS
+            || max_wait == Duration::from_secs(0)
+        {
+            return Ok(resp);
+        }
+        let delay = resp
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| parse_retry_after(value, Utc::now()))
+            .unwrap_or(DEFAULT_RETRY_AFTER);
+        // The url may contain an api key, only the host is logged
+        let host = url.host_str().unwrap_or_default();
+        if waited + delay > max_wait {
+            log::warn!(
+                "Rpc {} is throttled, gave up after waiting {}s (retry after {}s)",
+                host,
+                waited.as_secs(),
+                delay.as_secs()
+            );
+            return resp.error_for_status();
+        }
+        log::warn!(
+            "Rpc {} is throttled (HTTP 429), retry after {}s",
+            host,
+            delay.as_secs()
+        );
+        thread::sleep(delay);
+        waited += delay;
+    }
+}
+
+/// Parse a `Retry-After` header, the delay in seconds or an HTTP-date (a past date is no delay)
+pub fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        date.with_timezone(&Utc)
+            .signed_duration_since(now)
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+fn map_reqwest_error(err: reqwest::Error) -> failure::Error {
+    if err.is_timeout() {
+        failure::format_err!("{}: {}", RPC_TIMEOUT_ERROR, err)
+    } else {
+        err.into()
+    }
+}
+
 macro_rules! serialize_parameters {
     () => ( serde_json::Value::Null );
     ($($arg_name:ident,)+) => ( serde_json::to_value(($($arg_name,)+))?)
@@ -162,6 +260,12 @@ impl HttpRpcClient {
     pub fn client(&mut self) -> &mut RawHttpRpcClient {
         &mut self.client
     }
+    pub fn set_timeout(&mut self, timeout: std::time::Duration) {
+        self.client.set_timeout(timeout);
+    }
+    pub fn set_max_throttle_wait(&mut self, max_wait: Duration) {
+        self.client.set_max_throttle_wait(max_wait);
+    }
 }
 
 impl HttpRpcClient {
@@ -468,3 +572,29 @@ impl HttpRpcClient {
             .map_err(|err| err.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = Utc.ymd(2015, 10, 21).and_hms(7, 27, 30);
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(parse_retry_after(" 0 ", now), Some(Duration::from_secs(0)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:27:00 GMT", now),
+            Some(Duration::from_secs(0))
+        );
+        assert_eq!(parse_retry_after("-1", now), None);
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+}
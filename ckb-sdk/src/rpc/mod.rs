@@ -2,7 +2,9 @@ mod client;
 mod primitive;
 mod types;
 
-pub use client::{HttpRpcClient, RawHttpRpcClient};
+pub use client::{
+    parse_retry_after, send_with_retry_after, HttpRpcClient, RawHttpRpcClient, RPC_TIMEOUT_ERROR,
+};
 pub use primitive::{Capacity, EpochNumberWithFraction, Since, Timestamp};
 pub use types::{
     Alert, AlertMessage, BannedAddr, Block, BlockReward, BlockView, Byte32, CellDep, CellInput,
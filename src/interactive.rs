@@ -13,8 +13,9 @@ use serde_json::json;
 
 use crate::plugin::PluginManager;
 use crate::subcommands::{
-    AccountSubCommand, CliSubCommand, DAOSubCommand, MockTxSubCommand, MoleculeSubCommand,
-    PluginSubCommand, RpcSubCommand, TxSubCommand, UtilSubCommand, WalletSubCommand,
+    AccountSubCommand, CkbBridgeSubCommand, CliSubCommand, DAOSubCommand, MockTxSubCommand,
+    MoleculeSubCommand, PluginSubCommand, RpcSubCommand, TxSubCommand, UtilSubCommand,
+    WalletSubCommand,
 };
 use crate::utils::{
     completer::CkbCompleter,
@@ -33,6 +34,7 @@ pub struct InteractiveEnv {
     config_file: PathBuf,
     history_file: PathBuf,
     index_dir: PathBuf,
+    bridge_dir: PathBuf,
     parser: clap::App<'static>,
     plugin_mgr: PluginManager,
     key_store: KeyStore,
@@ -59,6 +61,8 @@ impl InteractiveEnv {
         config_file.push("config");
         let mut index_dir = ckb_cli_dir.clone();
         index_dir.push(index_dirname());
+        let mut bridge_dir = ckb_cli_dir.clone();
+        bridge_dir.push("bridge");
 
         let mut env_file = ckb_cli_dir;
         env_file.push("env_vars");
@@ -78,6 +82,7 @@ impl InteractiveEnv {
             config,
             config_file,
             index_dir,
+            bridge_dir,
             history_file,
             parser,
             plugin_mgr,
@@ -415,6 +420,21 @@ impl InteractiveEnv {
                     output.print(format, color);
                     Ok(())
                 }
+                ("ckb-bridge", Some(sub_matches)) => {
+                    let genesis_info = self.genesis_info()?;
+                    let output = CkbBridgeSubCommand::new(
+                        &mut self.rpc_client,
+                        &mut self.plugin_mgr,
+                        Some(genesis_info),
+                        self.index_dir.clone(),
+                        self.bridge_dir.clone(),
+                        self.index_controller.clone(),
+                        wait_for_sync,
+                    )
+                    .process(&sub_matches, debug)?;
+                    output.print(format, color);
+                    Ok(())
+                }
                 ("exit", _) => {
                     return Ok(true);
                 }
@@ -18,16 +18,17 @@ use subcommands::{Output, TuiSubCommand};
 use interactive::InteractiveEnv;
 use plugin::PluginManager;
 use subcommands::{
-    start_index_thread, AccountSubCommand, ApiServerSubCommand, CliSubCommand, DAOSubCommand,
-    MockTxSubCommand, MoleculeSubCommand, PluginSubCommand, RpcSubCommand, TxSubCommand,
-    UtilSubCommand, WalletSubCommand,
+    start_index_thread, AccountSubCommand, ApiServerSubCommand, CkbBridgeSubCommand, CliSubCommand,
+    DAOSubCommand, MockTxSubCommand, MoleculeSubCommand, PluginSubCommand, RpcSubCommand,
+    TxSubCommand, UtilSubCommand, WalletSubCommand,
 };
 use utils::other::get_genesis_info;
 use utils::{
-    arg_parser::{ArgParser, UrlParser},
+    arg_parser::{ArgParser, FilePathParser, UrlParser},
     config::GlobalConfig,
     index::IndexThreadState,
     other::{check_alerts, get_key_store, get_network_type, index_dirname},
+    poll,
     printer::{ColorWhen, OutputFormat},
 };
 
@@ -117,6 +118,9 @@ fn main() -> Result<(), io::Error> {
     if let Some(format) = matches.value_of("output-format") {
         output_format = OutputFormat::from_str(format).unwrap();
     }
+    let output_file: Option<PathBuf> = FilePathParser::new(false)
+        .from_matches_opt(&matches, "output-file", false)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
     let mut key_store = get_key_store(&ckb_cli_dir).map_err(|err| {
         io::Error::new(
             io::ErrorKind::Other,
@@ -179,7 +183,27 @@ fn main() -> Result<(), io::Error> {
                 .process(&sub_matches, debug)
             })
         }
+        ("ckb-bridge", Some(sub_matches)) => {
+            // Ctrl-C stops the waiting of a transfer step, the transfer is resumed later
+            poll::cancel_on_signal();
+            CkbBridgeSubCommand::new(
+                &mut rpc_client,
+                &mut plugin_mgr,
+                None,
+                index_dir,
+                ckb_cli_dir.join("bridge"),
+                index_controller.clone(),
+                wait_for_sync,
+            )
+            .process(&sub_matches, debug)
+        }
         _ => {
+            // The interactive commands print their results to the terminal
+            if output_file.is_some() {
+                eprintln!("--output-file is not supported in interactive mode, give a subcommand");
+                index_controller.shutdown();
+                process::exit(1);
+            }
             if let Err(err) = InteractiveEnv::from_config(
                 ckb_cli_dir,
                 config,
@@ -200,8 +224,15 @@ fn main() -> Result<(), io::Error> {
 
     match result {
         Ok(output) => {
-            output.print(output_format, color);
+            if let Err(err) = output.print_to(output_format, color, output_file.as_deref()) {
+                eprintln!("{}", err);
+                index_controller.shutdown();
+                process::exit(1);
+            }
             index_controller.shutdown();
+            if output.exit_code() != 0 {
+                process::exit(output.exit_code());
+            }
         }
         Err(err) => {
             eprintln!("{}", err);
@@ -262,6 +293,7 @@ pub fn build_cli<'a>(version_short: &'a str, version_long: &'a str) -> App<'a> {
         .subcommand(MoleculeSubCommand::subcommand("molecule"))
         .subcommand(WalletSubCommand::subcommand())
         .subcommand(DAOSubCommand::subcommand())
+        .subcommand(CkbBridgeSubCommand::subcommand())
         .arg(
             Arg::with_name("url")
                 .long("url")
@@ -278,6 +310,14 @@ pub fn build_cli<'a>(version_short: &'a str, version_long: &'a str) -> App<'a> {
                 .global(true)
                 .about("Select output format"),
         )
+        .arg(
+            Arg::with_name("output-file")
+                .long("output-file")
+                .takes_value(true)
+                .global(true)
+                .validator(|input| FilePathParser::new(false).validate(input))
+                .about("Write the result to this file (replaced atomically, the parent directories are created) instead of stdout (not in interactive mode), the progress and warnings stay on stderr. The events of ckb-bridge --watch are appended to it as NDJSON"),
+        )
         .arg(
             Arg::with_name("no-color")
                 .long("no-color")
@@ -379,4 +419,5 @@ pub fn build_interactive() -> App<'static> {
         .subcommand(MoleculeSubCommand::subcommand("molecule"))
         .subcommand(WalletSubCommand::subcommand())
         .subcommand(DAOSubCommand::subcommand())
+        .subcommand(CkbBridgeSubCommand::subcommand())
 }
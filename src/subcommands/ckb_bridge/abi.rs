@@ -0,0 +1,656 @@
+//! Minimal solidity ABI encoding/decoding for the bridge and erc20 contract calls, the calls
+//! and events are looked up in the json ABI of the contracts (built-in or `--bridge-abi` /
+//! `--erc20-abi`)
+
+use std::fs;
+use std::path::Path;
+
+use ckb_types::{H160, H256};
+use serde_derive::Deserialize;
+
+use super::eth::{checksum_address, keccak256};
+
+/// Functions and events of the bridge contract used by ckb-bridge, `unlock` is only looked up
+/// by the from-ckb transfers
+pub const BRIDGE_FUNCTIONS: [&str; 1] = ["lock"];
+pub const BRIDGE_EVENTS: [&str; 1] = ["Locked"];
+/// Functions of the erc20 contracts used by ckb-bridge
+pub const ERC20_FUNCTIONS: [&str; 2] = ["allowance", "approve"];
+
+/// The ABI of the bridge contract deployed by `deploy-sol`
+pub const DEFAULT_BRIDGE_ABI: &str = r#"[
+  {"type": "function", "name": "lock", "stateMutability": "payable", "outputs": [], "inputs": [
+    {"name": "token", "type": "address"},
+    {"name": "amount", "type": "uint256"},
+    {"name": "recipientLockscript", "type": "bytes"}]},
+  {"type": "function", "name": "unlock", "stateMutability": "nonpayable", "outputs": [], "inputs": [
+    {"name": "ckbTx", "type": "bytes"},
+    {"name": "proof", "type": "bytes"}]},
+  {"type": "event", "name": "Locked", "anonymous": false, "inputs": [
+    {"name": "token", "type": "address", "indexed": true},
+    {"name": "sender", "type": "address", "indexed": true},
+    {"name": "lockedAmount", "type": "uint256", "indexed": false},
+    {"name": "recipientLockscript", "type": "bytes", "indexed": false}]}
+]"#;
+
+pub const DEFAULT_ERC20_ABI: &str = r#"[
+  {"type": "function", "name": "allowance", "stateMutability": "view",
+    "outputs": [{"name": "", "type": "uint256"}], "inputs": [
+    {"name": "owner", "type": "address"},
+    {"name": "spender", "type": "address"}]},
+  {"type": "function", "name": "approve", "stateMutability": "nonpayable",
+    "outputs": [{"name": "", "type": "bool"}], "inputs": [
+    {"name": "spender", "type": "address"},
+    {"name": "value", "type": "uint256"}]},
+  {"type": "function", "name": "balanceOf", "stateMutability": "view",
+    "outputs": [{"name": "", "type": "uint256"}], "inputs": [
+    {"name": "owner", "type": "address"}]},
+  {"type": "function", "name": "symbol", "stateMutability": "view", "inputs": [],
+    "outputs": [{"name": "", "type": "string"}]},
+  {"type": "function", "name": "decimals", "stateMutability": "view", "inputs": [],
+    "outputs": [{"name": "", "type": "uint8"}]}
+]"#;
+
+pub enum Token {
+    Address(H160),
+    Uint(u128),
+    Bytes(Vec<u8>),
+}
+
+impl Token {
+    fn is_dynamic(&self) -> bool {
+        matches!(self, Token::Bytes(_))
+    }
+}
+
+/// The first 4 bytes of keccak256 of the function signature, e.g. "approve(address,uint256)"
+pub fn selector(signature: &str) -> [u8; 4] {
+    let mut ret = [0u8; 4];
+    ret.copy_from_slice(&keccak256(signature.as_bytes())[0..4]);
+    ret
+}
+
+pub fn encode_call(signature: &str, tokens: &[Token]) -> Vec<u8> {
+    let mut data = selector(signature).to_vec();
+    data.extend(encode(tokens));
+    data
+}
+
+pub fn encode(tokens: &[Token]) -> Vec<u8> {
+    let head_size = 32 * tokens.len();
+    let mut head = Vec::with_capacity(head_size);
+    let mut tail = Vec::new();
+    for token in tokens {
+        if token.is_dynamic() {
+            head.extend_from_slice(&uint_word((head_size + tail.len()) as u128));
+        }
+        match token {
+            Token::Address(address) => {
+                let mut word = [0u8; 32];
+                word[12..].copy_from_slice(address.as_bytes());
+                head.extend_from_slice(&word);
+            }
+            Token::Uint(value) => head.extend_from_slice(&uint_word(*value)),
+            Token::Bytes(bytes) => {
+                tail.extend_from_slice(&uint_word(bytes.len() as u128));
+                tail.extend_from_slice(bytes);
+                let padding = (32 - bytes.len() % 32) % 32;
+                tail.extend(vec![0u8; padding]);
+            }
+        }
+    }
+    head.extend(tail);
+    head
+}
+
+fn uint_word(value: u128) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[16..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+/// Decode the `index`th word as uint, values larger than u128 are rejected
+pub fn decode_uint(data: &[u8], index: usize) -> Result<u128, String> {
+    let word = data
+        .get(index * 32..(index + 1) * 32)
+        .ok_or_else(|| format!("Invalid abi data length: {}", data.len()))?;
+    if word[0..16].iter().any(|byte| *byte != 0) {
+        return Err("Abi uint overflow u128".to_string());
+    }
+    let mut value = [0u8; 16];
+    value.copy_from_slice(&word[16..]);
+    Ok(u128::from_be_bytes(value))
+}
+
+/// Decode the `index`th word as a decimal string, all the 256 bits of it
+pub fn decode_uint_string(data: &[u8], index: usize) -> Result<String, String> {
+    let mut word = data
+        .get(index * 32..(index + 1) * 32)
+        .ok_or_else(|| format!("Invalid abi data length: {}", data.len()))?
+        .to_vec();
+    let mut digits = Vec::new();
+    // Long division of the big endian word by 10, one digit per round
+    while word.iter().any(|byte| *byte != 0) {
+        let mut remainder = 0u32;
+        for byte in word.iter_mut() {
+            let current = (remainder << 8) | u32::from(*byte);
+            *byte = (current / 10) as u8;
+            remainder = current % 10;
+        }
+        digits.push(b'0' + remainder as u8);
+    }
+    if digits.is_empty() {
+        digits.push(b'0');
+    }
+    digits.reverse();
+    Ok(String::from_utf8(digits).expect("decimal digits"))
+}
+
+/// Decode the dynamic bytes whose offset is at the `index`th word
+pub fn decode_bytes(data: &[u8], index: usize) -> Result<Vec<u8>, String> {
+    let offset = decode_uint(data, index)? as usize;
+    if offset % 32 != 0 {
+        return Err(format!("Invalid abi bytes offset: {}", offset));
+    }
+    let len = decode_uint(data, offset / 32)? as usize;
+    let start = offset + 32;
+    data.get(start..start + len)
+        .map(|bytes| bytes.to_vec())
+        .ok_or_else(|| format!("Invalid abi data length: {}", data.len()))
+}
+
+/// Decode the `index`th word as address
+pub fn decode_address(data: &[u8], index: usize) -> Result<H160, String> {
+    let word = data
+        .get(index * 32..(index + 1) * 32)
+        .ok_or_else(|| format!("Invalid abi data length: {}", data.len()))?;
+    if word[0..12].iter().any(|byte| *byte != 0) {
+        return Err("Invalid abi address, the high 12 bytes are not zero".to_string());
+    }
+    Ok(H160::from_slice(&word[12..]).expect("address length"))
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct AbiParam {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    indexed: bool,
+    #[serde(default)]
+    components: Vec<AbiParam>,
+}
+
+impl AbiParam {
+    /// The type in the canonical signature, tuples are expanded to their components
+    fn canonical_type(&self) -> String {
+        if self.kind.starts_with("tuple") {
+            let components = self
+                .components
+                .iter()
+                .map(AbiParam::canonical_type)
+                .collect::<Vec<_>>();
+            format!("({}){}", components.join(","), &self.kind["tuple".len()..])
+        } else {
+            self.kind.clone()
+        }
+    }
+
+    fn accepts(&self, token: &Token) -> bool {
+        match token {
+            Token::Address(_) => self.kind == "address",
+            Token::Uint(_) => self.kind.starts_with("uint") && !self.kind.ends_with(']'),
+            Token::Bytes(_) => self.kind == "bytes",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct AbiEntry {
+    // The type of an ABI entry defaults to function
+    #[serde(rename = "type", default = "function_type")]
+    kind: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    inputs: Vec<AbiParam>,
+    #[serde(default)]
+    outputs: Vec<AbiParam>,
+    #[serde(default)]
+    anonymous: bool,
+    #[serde(default, rename = "stateMutability")]
+    state_mutability: Option<String>,
+    // Before solidity 0.5 view functions are marked as constant
+    #[serde(default)]
+    constant: bool,
+}
+
+fn function_type() -> String {
+    "function".to_string()
+}
+
+/// A function or an event in a contract ABI
+#[derive(Clone, Debug)]
+pub struct AbiItem {
+    pub name: String,
+    /// The canonical signature, e.g. "approve(address,uint256)"
+    pub signature: String,
+    inputs: Vec<AbiParam>,
+    outputs: Vec<AbiParam>,
+    anonymous: bool,
+    /// A view or pure function, called without a transaction
+    pub read_only: bool,
+}
+
+impl AbiItem {
+    fn new(entry: AbiEntry) -> AbiItem {
+        let types = entry
+            .inputs
+            .iter()
+            .map(AbiParam::canonical_type)
+            .collect::<Vec<_>>();
+        AbiItem {
+            signature: format!("{}({})", entry.name, types.join(",")),
+            name: entry.name,
+            inputs: entry.inputs,
+            outputs: entry.outputs,
+            anonymous: entry.anonymous,
+            read_only: entry.constant
+                || match entry.state_mutability.as_deref() {
+                    Some("view") | Some("pure") => true,
+                    _ => false,
+                },
+        }
+    }
+
+    /// A read-only function without inputs returning a single address, uint or bool (e.g.
+    /// `paused()`, `owner()` or a limit), its result is shown by `bridge-state`
+    pub fn is_getter(&self) -> bool {
+        self.read_only
+            && self.inputs.is_empty()
+            && self.outputs.len() == 1
+            && is_static_output(&self.outputs[0].kind)
+    }
+
+    /// Decode the result of a getter (see `is_getter`, or a string getter) as json, uints are
+    /// strings since they may exceed u64
+    pub fn decode_output(&self, data: &[u8]) -> Result<serde_json::Value, String> {
+        let kind = self
+            .outputs
+            .first()
+            .map(|output| output.kind.as_str())
+            .unwrap_or_default();
+        match kind {
+            "address" => Ok(serde_json::json!(checksum_address(&decode_address(
+                data, 0
+            )?))),
+            "bool" => Ok(serde_json::json!(decode_uint(data, 0)? != 0)),
+            "string" => Ok(serde_json::json!(String::from_utf8_lossy(&decode_bytes(
+                data, 0
+            )?))),
+            // Some old tokens (e.g. MKR) return the symbol as bytes32
+            "bytes32" => {
+                let word = data
+                    .get(0..32)
+                    .ok_or_else(|| format!("Invalid abi data length: {}", data.len()))?;
+                let len = word.iter().position(|byte| *byte == 0).unwrap_or(32);
+                Ok(serde_json::json!(String::from_utf8_lossy(&word[..len])))
+            }
+            // A string, the value may be beyond what a JSON number keeps exactly
+            kind if kind.starts_with("uint") => Ok(serde_json::json!(decode_uint_string(data, 0)?)),
+            kind => Err(format!(
+                "Unsupported {} output of function {}",
+                kind, self.signature
+            )),
+        }
+    }
+
+    /// Encode a call of this function, the tokens must match the inputs in the ABI
+    pub fn encode_call(&self, tokens: &[Token]) -> Result<Vec<u8>, String> {
+        let matched = self.inputs.len() == tokens.len()
+            && self
+                .inputs
+                .iter()
+                .zip(tokens)
+                .all(|(input, token)| input.accepts(token));
+        if !matched {
+            return Err(format!(
+                "Function {} in the ABI does not take the arguments used by ckb-bridge, ABI mismatch",
+                self.signature
+            ));
+        }
+        Ok(encode_call(&self.signature, tokens))
+    }
+
+    /// The first topic of the logs of this event
+    pub fn topic(&self) -> H256 {
+        H256::from(keccak256(self.signature.as_bytes()))
+    }
+
+    /// Decode a log of this event into tokens in the order of the inputs, indexed inputs are
+    /// read from the topics and the others from the data
+    pub fn decode_log(&self, topics: &[H256], data: &[u8]) -> Result<Vec<Token>, String> {
+        let indexed_count = self.inputs.iter().filter(|input| input.indexed).count();
+        let topic_offset = if self.anonymous { 0 } else { 1 };
+        if topics.len() != indexed_count + topic_offset {
+            return Err(format!(
+                "Invalid {} event: expected {} topics, got {}",
+                self.name,
+                indexed_count + topic_offset,
+                topics.len()
+            ));
+        }
+        let mut topics = topics[topic_offset..].iter();
+        let mut data_index = 0;
+        let mut tokens = Vec::with_capacity(self.inputs.len());
+        for input in &self.inputs {
+            let token = if input.indexed {
+                let topic = topics.next().expect("checked topics count").as_bytes();
+                match input.kind.as_str() {
+                    "address" => Token::Address(decode_address(topic, 0)?),
+                    kind if kind.starts_with("uint") => Token::Uint(decode_uint(topic, 0)?),
+                    kind => {
+                        return Err(format!(
+                            "Unsupported indexed {} input in event {}",
+                            kind, self.signature
+                        ))
+                    }
+                }
+            } else {
+                data_index += 1;
+                match input.kind.as_str() {
+                    "address" => Token::Address(decode_address(data, data_index - 1)?),
+                    "bytes" => Token::Bytes(decode_bytes(data, data_index - 1)?),
+                    kind if kind.starts_with("uint") => {
+                        Token::Uint(decode_uint(data, data_index - 1)?)
+                    }
+                    kind => {
+                        return Err(format!(
+                            "Unsupported {} input in event {}",
+                            kind, self.signature
+                        ))
+                    }
+                }
+            };
+            tokens.push(token);
+        }
+        Ok(tokens)
+    }
+}
+
+/// The functions and events of a contract json ABI
+#[derive(Clone, Debug)]
+pub struct ContractAbi {
+    functions: Vec<AbiItem>,
+    events: Vec<AbiItem>,
+}
+
+impl ContractAbi {
+    /// Parse a json ABI, or a compiler artifact with the ABI in the `abi` field (e.g. truffle)
+    pub fn parse(content: &str) -> Result<ContractAbi, String> {
+        let mut value: serde_json::Value =
+            serde_json::from_str(content).map_err(|err| err.to_string())?;
+        if let Some(abi) = value.get_mut("abi") {
+            value = abi.take();
+        }
+        let entries: Vec<AbiEntry> =
+            serde_json::from_value(value).map_err(|err| err.to_string())?;
+        let mut functions = Vec::new();
+        let mut events = Vec::new();
+        for entry in entries {
+            match entry.kind.as_str() {
+                "function" => functions.push(AbiItem::new(entry)),
+                "event" => events.push(AbiItem::new(entry)),
+                _ => {}
+            }
+        }
+        Ok(ContractAbi { functions, events })
+    }
+
+    pub fn load(path: &Path) -> Result<ContractAbi, String> {
+        let content =
+            fs::read_to_string(path).map_err(|err| format!("Read {:?} error: {}", path, err))?;
+        ContractAbi::parse(&content).map_err(|err| format!("Parse ABI {:?} error: {}", path, err))
+    }
+
+    pub fn function(&self, name: &str) -> Result<&AbiItem, String> {
+        find_item(&self.functions, "function", name)
+    }
+
+    /// The function if it is in the ABI, for the optional functions like `paused()`
+    pub fn optional_function(&self, name: &str) -> Option<&AbiItem> {
+        self.functions.iter().find(|item| item.name == name)
+    }
+
+    /// The getters (see `AbiItem::is_getter`) in the ABI
+    pub fn getters(&self) -> impl Iterator<Item = &AbiItem> {
+        self.functions.iter().filter(|item| item.is_getter())
+    }
+
+    pub fn event(&self, name: &str) -> Result<&AbiItem, String> {
+        find_item(&self.events, "event", name)
+    }
+
+    /// Check the functions and events used by ckb-bridge are in the ABI
+    pub fn require(&self, functions: &[&str], events: &[&str]) -> Result<(), String> {
+        for name in functions {
+            self.function(name)?;
+        }
+        for name in events {
+            self.event(name)?;
+        }
+        Ok(())
+    }
+}
+
+fn is_static_output(kind: &str) -> bool {
+    kind == "address" || kind == "bool" || (kind.starts_with("uint") && !kind.ends_with(']'))
+}
+
+fn find_item<'a>(items: &'a [AbiItem], kind: &str, name: &str) -> Result<&'a AbiItem, String> {
+    let mut matched = items.iter().filter(|item| item.name == name);
+    match (matched.next(), matched.next()) {
+        (Some(item), None) => Ok(item),
+        (Some(_), Some(_)) => Err(format!(
+            "The {} {} is overloaded in the ABI, which is not supported",
+            kind, name
+        )),
+        (None, _) => Err(format!("The {} {} is not found in the ABI", kind, name)),
+    }
+}
+
+/// The ABIs of the contracts called by ckb-bridge
+#[derive(Clone, Debug)]
+pub struct BridgeAbis {
+    pub bridge: ContractAbi,
+    pub erc20: ContractAbi,
+}
+
+impl BridgeAbis {
+    /// Load the ABIs from the files, the built-in ABIs are used if absent
+    pub fn load(bridge_abi: Option<&Path>, erc20_abi: Option<&Path>) -> Result<BridgeAbis, String> {
+        let bridge = match bridge_abi {
+            Some(path) => ContractAbi::load(path)?,
+            None => ContractAbi::parse(DEFAULT_BRIDGE_ABI).expect("built-in bridge abi"),
+        };
+        bridge
+            .require(&BRIDGE_FUNCTIONS, &BRIDGE_EVENTS)
+            .map_err(|err| format!("Invalid bridge contract ABI: {}", err))?;
+        let erc20 = match erc20_abi {
+            Some(path) => ContractAbi::load(path)?,
+            None => ContractAbi::parse(DEFAULT_ERC20_ABI).expect("built-in erc20 abi"),
+        };
+        erc20
+            .require(&ERC20_FUNCTIONS, &[])
+            .map_err(|err| format!("Invalid erc20 contract ABI: {}", err))?;
+        Ok(BridgeAbis { bridge, erc20 })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_types::h160;
+
+    #[test]
+    fn test_encode_call() {
+        assert_eq!(
+            selector("transfer(address,uint256)"),
+            [0xa9, 0x05, 0x9c, 0xbb]
+        );
+        let data = encode_call(
+            "approve(address,uint256)",
+            &[
+                Token::Address(h160!("0x1111111111111111111111111111111111111111")),
+                Token::Uint(1000),
+            ],
+        );
+        assert_eq!(data.len(), 4 + 64);
+        assert_eq!(&data[0..4], &[0x09, 0x5e, 0xa7, 0xb3]);
+        assert_eq!(decode_uint(&data[4..], 1).unwrap(), 1000);
+    }
+
+    #[test]
+    fn test_encode_dynamic_bytes() {
+        let data = encode(&[Token::Uint(1), Token::Bytes(vec![0xab; 33])]);
+        assert_eq!(data.len(), 32 * 2 + 32 + 64);
+        assert_eq!(decode_uint(&data, 1).unwrap(), 64);
+        assert_eq!(decode_uint(&data, 2).unwrap(), 33);
+        assert_eq!(decode_bytes(&data, 1).unwrap(), vec![0xab; 33]);
+    }
+
+    #[test]
+    fn test_contract_abi() {
+        let abis = BridgeAbis::load(None, None).unwrap();
+        assert_eq!(
+            abis.bridge.function("lock").unwrap().signature,
+            "lock(address,uint256,bytes)"
+        );
+        let locked = abis.bridge.event("Locked").unwrap();
+        assert_eq!(locked.signature, "Locked(address,address,uint256,bytes)");
+        assert_eq!(
+            abis.erc20.function("approve").unwrap().signature,
+            "approve(address,uint256)"
+        );
+        let approve = abis.erc20.function("approve").unwrap();
+        assert!(approve.encode_call(&[Token::Uint(1)]).is_err());
+        let data = approve
+            .encode_call(&[Token::Address(H160::default()), Token::Uint(1)])
+            .unwrap();
+        assert_eq!(&data[0..4], &[0x09, 0x5e, 0xa7, 0xb3]);
+
+        // A truffle artifact with an upgraded lock function taking a tuple
+        let artifact = r#"{"contractName": "Bridge", "abi": [
+            {"type": "function", "name": "lock", "inputs": [
+                {"type": "tuple", "components": [{"type": "address"}, {"type": "uint256"}]},
+                {"type": "bytes"}]},
+            {"type": "constructor", "inputs": []}
+        ]}"#;
+        let abi = ContractAbi::parse(artifact).unwrap();
+        let lock = abi.function("lock").unwrap();
+        assert_eq!(lock.signature, "lock((address,uint256),bytes)");
+        assert!(lock
+            .encode_call(&[Token::Uint(1), Token::Bytes(Vec::new())])
+            .is_err());
+        assert!(abi.require(&BRIDGE_FUNCTIONS, &BRIDGE_EVENTS).is_err());
+        assert!(abi.event("Locked").unwrap_err().contains("not found"));
+    }
+
+    #[test]
+    fn test_decode_log() {
+        let abi = ContractAbi::parse(DEFAULT_BRIDGE_ABI).unwrap();
+        let locked = abi.event("Locked").unwrap();
+        let mut address_topic = [0u8; 32];
+        address_topic[31] = 1;
+        let topics = vec![locked.topic(), H256::from(address_topic), H256::default()];
+        let data = encode(&[Token::Uint(1000), Token::Bytes(vec![1, 2, 3])]);
+        let tokens = locked.decode_log(&topics, &data).unwrap();
+        assert_eq!(tokens.len(), 4);
+        assert!(matches!(&tokens[0], Token::Address(address) if address.as_bytes()[19] == 1));
+        assert!(matches!(&tokens[2], Token::Uint(1000)));
+        assert!(matches!(&tokens[3], Token::Bytes(bytes) if bytes == &vec![1, 2, 3]));
+        assert!(locked.decode_log(&topics[0..2], &data).is_err());
+    }
+
+    #[test]
+    fn test_getters() {
+        let abi = ContractAbi::parse(
+            r#"[
+            {"type": "function", "name": "paused", "stateMutability": "view", "inputs": [],
+                "outputs": [{"type": "bool"}]},
+            {"type": "function", "name": "owner", "constant": true, "inputs": [],
+                "outputs": [{"type": "address"}]},
+            {"type": "function", "name": "maxLockAmount", "stateMutability": "view",
+                "inputs": [], "outputs": [{"type": "uint256"}]},
+            {"type": "function", "name": "balanceOf", "stateMutability": "view",
+                "inputs": [{"type": "address"}], "outputs": [{"type": "uint256"}]},
+            {"type": "function", "name": "pause", "stateMutability": "nonpayable",
+                "inputs": [], "outputs": []}
+        ]"#,
+        )
+        .unwrap();
+        let names = abi
+            .getters()
+            .map(|item| item.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["paused", "owner", "maxLockAmount"]);
+        assert!(abi.optional_function("pause").is_some());
+        assert!(abi.optional_function("unpause").is_none());
+
+        let paused = abi.function("paused").unwrap();
+        assert_eq!(
+            paused.decode_output(&encode(&[Token::Uint(1)])).unwrap(),
+            serde_json::json!(true)
+        );
+        let owner = abi.function("owner").unwrap();
+        assert_eq!(
+            owner
+                .decode_output(&encode(&[Token::Address(H160::default())]))
+                .unwrap(),
+            serde_json::json!("0x0000000000000000000000000000000000000000")
+        );
+        let limit = abi.function("maxLockAmount").unwrap();
+        assert_eq!(
+            limit.decode_output(&encode(&[Token::Uint(100)])).unwrap(),
+            serde_json::json!("100")
+        );
+        assert!(limit.decode_output(&[]).is_err());
+        // No limit is usually `type(uint256).max`
+        let unlimited = limit.decode_output(&[0xff; 32]).unwrap();
+        let json = serde_json::to_string(&unlimited).unwrap();
+        assert_eq!(
+            json,
+            "\"115792089237316195423570985008687907853269984665640564039457584007913129639935\""
+        );
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+            unlimited
+        );
+        assert_eq!(
+            decode_uint_string(&uint_word(u128::max_value()), 0).unwrap(),
+            u128::max_value().to_string()
+        );
+        assert_eq!(decode_uint_string(&[0; 32], 0).unwrap(), "0");
+
+        let erc20 = ContractAbi::parse(DEFAULT_ERC20_ABI).unwrap();
+        let symbol = erc20.function("symbol").unwrap();
+        assert_eq!(
+            symbol
+                .decode_output(&encode(&[Token::Bytes(b"USDC".to_vec())]))
+                .unwrap(),
+            serde_json::json!("USDC")
+        );
+        let mkr = ContractAbi::parse(
+            r#"[{"name": "symbol", "constant": true, "inputs": [], "outputs": [{"type": "bytes32"}]}]"#,
+        )
+        .unwrap();
+        let mut word = [0u8; 32];
+        word[..3].copy_from_slice(b"MKR");
+        assert_eq!(
+            mkr.function("symbol")
+                .unwrap()
+                .decode_output(&word)
+                .unwrap(),
+            serde_json::json!("MKR")
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! The deployments resolved from registries and the contracts known to have code, cached per
+//! network (`<data-dir>/<network>/address-cache.json`) so that frequent runs skip the startup
+//! rpc calls. An entry is used for `--address-cache-ttl` after it is resolved and only through
+//! the ethereum rpc url it was resolved through, `--refresh-addresses` drops the cache.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use ckb_types::H160;
+use serde_derive::{Deserialize, Serialize};
+
+use super::registry::Registry;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AddressCache {
+    /// Keyed by the registry address
+    #[serde(default)]
+    pub registries: BTreeMap<H160, CachedRegistry>,
+    /// The contracts seen with code, keyed by the contract address. Only the presence of code
+    /// is cached, a missing contract is checked again on every run.
+    #[serde(default)]
+    pub code: BTreeMap<H160, CachedCode>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CachedRegistry {
+    pub eth_rpc_url: String,
+    pub resolved_at: u64,
+    pub bridge_contract: Option<H160>,
+    pub tokens: Vec<H160>,
+    pub light_client_type_script: Option<ckb_jsonrpc_types::Script>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CachedCode {
+    pub eth_rpc_url: String,
+    pub checked_at: u64,
+}
+
+impl AddressCache {
+    /// A missing or unreadable cache is an empty one, it is rebuilt from the chain
+    pub fn load(path: &Path) -> AddressCache {
+        if !path.exists() {
+            return AddressCache::default();
+        }
+        fs::read_to_string(path)
+            .map_err(|err| err.to_string())
+            .and_then(|content| serde_json::from_str(&content).map_err(|err| err.to_string()))
+            .unwrap_or_else(|err| {
+                log::warn!("Ignore the address cache {:?}: {}", path, err);
+                AddressCache::default()
+            })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        let content = serde_json::to_string_pretty(self).map_err(|err| err.to_string())?;
+        // Write to a temp file then rename, so concurrent runs never read a partial cache
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, content).map_err(|err| err.to_string())?;
+        fs::rename(&tmp_path, path).map_err(|err| err.to_string())
+    }
+
+    /// The registry at `address` resolved through `eth_rpc_url` less than `ttl` seconds ago
+    pub fn registry(
+        &self,
+        address: &H160,
+        eth_rpc_url: &str,
+        ttl: u64,
+        now: u64,
+    ) -> Option<Registry> {
+        self.registries
+            .get(address)
+            .filter(|cached| {
+                cached.eth_rpc_url == eth_rpc_url && is_fresh(cached.resolved_at, ttl, now)
+            })
+            .map(|cached| Registry {
+                address: address.clone(),
+                bridge_contract: cached.bridge_contract.clone(),
+                tokens: cached.tokens.clone(),
+                light_client_type_script: cached.light_client_type_script.clone(),
+            })
+    }
+
+    /// Record a resolved registry, `Registry::resolve` checked every contract it publishes
+    /// has code
+    pub fn insert_registry(&mut self, registry: &Registry, eth_rpc_url: &str, now: u64) {
+        self.registries.insert(
+            registry.address.clone(),
+            CachedRegistry {
+                eth_rpc_url: eth_rpc_url.to_string(),
+                resolved_at: now,
+                bridge_contract: registry.bridge_contract.clone(),
+                tokens: registry.tokens.clone(),
+                light_client_type_script: registry.light_client_type_script.clone(),
+            },
+        );
+        let contracts = registry
+            .bridge_contract
+            .iter()
+            .chain(registry.tokens.iter())
+            .chain(Some(&registry.address));
+        for contract in contracts {
+            self.insert_code(contract, eth_rpc_url, now);
+        }
+    }
+
+    /// `address` is seen with code through `eth_rpc_url` less than `ttl` seconds ago
+    pub fn has_code(&self, address: &H160, eth_rpc_url: &str, ttl: u64, now: u64) -> bool {
+        self.code.get(address).map_or(false, |cached| {
+            cached.eth_rpc_url == eth_rpc_url && is_fresh(cached.checked_at, ttl, now)
+        })
+    }
+
+    pub fn insert_code(&mut self, address: &H160, eth_rpc_url: &str, now: u64) {
+        self.code.insert(
+            address.clone(),
+            CachedCode {
+                eth_rpc_url: eth_rpc_url.to_string(),
+                checked_at: now,
+            },
+        );
+    }
+}
+
+// An entry from the future (the clock is set back) is stale
+fn is_fresh(at: u64, ttl: u64, now: u64) -> bool {
+    at <= now && now - at < ttl
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_types::h160;
+
+    const URL: &str = "http://127.0.0.1:8545";
+
+    fn registry() -> Registry {
+        Registry {
+            address: h160!("0x1111111111111111111111111111111111111111"),
+            bridge_contract: Some(h160!("0x2222222222222222222222222222222222222222")),
+            tokens: vec![h160!("0x3333333333333333333333333333333333333333")],
+            light_client_type_script: None,
+        }
+    }
+
+    #[test]
+    fn test_address_cache_ttl() {
+        let registry = registry();
+        let mut cache = AddressCache::default();
+        assert_eq!(cache.registry(&registry.address, URL, 600, 1000), None);
+        cache.insert_registry(&registry, URL, 1000);
+        assert_eq!(
+            cache.registry(&registry.address, URL, 600, 1599),
+            Some(registry.clone())
+        );
+        // Stale, from the future, or resolved through another node
+        assert_eq!(cache.registry(&registry.address, URL, 600, 1600), None);
+        assert_eq!(cache.registry(&registry.address, URL, 600, 999), None);
+        assert_eq!(
+            cache.registry(&registry.address, "http://127.0.0.1:8546", 600, 1000),
+            None
+        );
+        // A zero ttl disables the cache
+        assert_eq!(cache.registry(&registry.address, URL, 0, 1000), None);
+
+        // The published contracts are known to have code
+        let token = &registry.tokens[0];
+        assert!(cache.has_code(token, URL, 600, 1000));
+        assert!(!cache.has_code(token, URL, 600, 1600));
+        let other = h160!("0x4444444444444444444444444444444444444444");
+        assert!(!cache.has_code(&other, URL, 600, 1000));
+        cache.insert_code(&other, URL, 1000);
+        assert!(cache.has_code(&other, URL, 600, 1000));
+    }
+
+    #[test]
+    fn test_address_cache_load() {
+        let path = std::env::temp_dir()
+            .join(format!("ckb-bridge-address-cache-{}", std::process::id()))
+            .join("address-cache.json");
+        assert_eq!(AddressCache::load(&path), AddressCache::default());
+        let mut cache = AddressCache::default();
+        cache.insert_registry(&registry(), URL, 1000);
+        cache.save(&path).unwrap();
+        assert_eq!(AddressCache::load(&path), cache);
+        // A broken cache is ignored
+        fs::write(&path, "{").unwrap();
+        assert_eq!(AddressCache::load(&path), AddressCache::default());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,936 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use ckb_crypto::secp::SECP256K1;
+use ckb_sdk::{
+    constants::SIGHASH_TYPE_HASH, Address, AddressPayload, CodeHashIndex, MultisigConfig,
+    NetworkType,
+};
+use ckb_types::{bytes::Bytes, core::ScriptHashType, packed::Script, prelude::*, H160, H256};
+use clap::{Arg, ArgMatches};
+use url::Url;
+
+use super::eth::{read_access_list, AccessListSource, ChainTag, EthSigner, GasArgs};
+use super::keystore::{load_signer, read_password_file};
+use super::mint::{check_recipient_lock, MaxProofAge};
+use crate::utils::{
+    arg,
+    arg_parser::{
+        AddressParser, ArgParser, CapacityParser, DurationParser, EitherParser, EitherValue,
+        FilePathParser, FixedHashParser, FromStrParser, HexParser, PrivkeyPathParser,
+        PrivkeyWrapper, UrlParser,
+    },
+    other::read_password,
+};
+
+/// Arguments for the ckb transactions sent by ckb-bridge (fee payer)
+pub struct CkbTxArgs {
+    /// `None` if only the address is given (`--from-address`), the transaction can only be
+    /// built then (signed offline)
+    pub privkey: Option<PrivkeyWrapper>,
+    pub address: Address,
+    /// The fixed fee, or the min fee with `fee_rate`
+    pub tx_fee: u64,
+    /// Shannons per KB, the fee is computed from the transaction size if given
+    pub fee_rate: Option<u64>,
+}
+
+impl CkbTxArgs {
+    pub fn from_matches(m: &ArgMatches, network_type: NetworkType) -> Result<Self, String> {
+        let privkey: Option<PrivkeyWrapper> =
+            PrivkeyPathParser.from_matches_opt(m, "privkey-path", false)?;
+        let address = match privkey.as_ref() {
+            Some(privkey) => {
+                let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, privkey);
+                Address::new(network_type, AddressPayload::from_pubkey(&pubkey))
+            }
+            None => AddressParser::new_sighash()
+                .set_network(network_type)
+                .from_matches(m, "from-address")?,
+        };
+        if address.payload().code_hash() != SIGHASH_TYPE_HASH.pack() {
+            return Err(format!("{} is not a sighash address", address));
+        }
+        let tx_fee: u64 = CapacityParser.from_matches(m, "tx-fee")?;
+        let fee_rate: Option<u64> =
+            FromStrParser::<u64>::default().from_matches_opt(m, "fee-rate", false)?;
+        Ok(Self {
+            privkey,
+            address,
+            tx_fee,
+            fee_rate,
+        })
+    }
+
+    pub fn args<'a>() -> Vec<Arg<'a>> {
+        vec![
+            arg::privkey_path()
+                .required_unless("from-address")
+                .env("CKB_BRIDGE_PRIVKEY_PATH"),
+            Arg::with_name("from-address")
+                .long("from-address")
+                .takes_value(true)
+                .conflicts_with("privkey-path")
+                .validator(|input| AddressParser::new_sighash().validate(input))
+                .about("The sighash address paying the fee, instead of --privkey-path when the transaction is signed offline (--build-only)"),
+            arg::tx_fee().required(true).env("CKB_BRIDGE_TX_FEE"),
+            Arg::with_name("fee-rate")
+                .long("fee-rate")
+                .env("CKB_BRIDGE_FEE_RATE")
+                .takes_value(true)
+                .validator(|input| FromStrParser::<u64>::default().validate(input))
+                .about("Pay the mint transaction fee by this rate (shannons/KB) of its size, --tx-fee is the min fee then"),
+        ]
+    }
+
+    pub fn privkey(&self) -> Result<&PrivkeyWrapper, String> {
+        self.privkey.as_ref().ok_or_else(|| {
+            "<privkey-path> is required to sign the ckb transaction, or build it with --build-only and sign it offline".to_string()
+        })
+    }
+
+    pub fn sighash_args(&self) -> H160 {
+        H160::from_slice(self.address.payload().args().as_ref()).unwrap()
+    }
+}
+
+/// Arguments for the ethereum transactions sent by ckb-bridge
+pub struct EthTxArgs {
+    /// From `--eth-privkey-path` or `--eth-keystore`, `None` if only the address is given
+    /// (`--eth-from-address`), the transactions can only be built then (signed offline)
+    pub signer: Option<EthSigner>,
+    pub from: H160,
+    pub gas: GasArgs,
+}
+
+impl EthTxArgs {
+    /// `None` if no ethereum sender is given, only the steps sending an ethereum transaction
+    /// require it
+    pub fn from_matches_opt(m: &ArgMatches) -> Result<Option<Self>, String> {
+        if ["eth-privkey-path", "eth-keystore", "eth-from-address"]
+            .iter()
+            .any(|name| m.is_present(name))
+        {
+            Self::from_matches(m).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn from_matches(m: &ArgMatches) -> Result<Self, String> {
+        let from_address: Option<H160> =
+            FixedHashParser::<H160>::default().from_matches_opt(m, "eth-from-address", false)?;
+        let keystore: Option<PathBuf> =
+            FilePathParser::new(true).from_matches_opt(m, "eth-keystore", false)?;
+        let signer = match PrivkeyPathParser.from_matches_opt(m, "eth-privkey-path", false)? {
+            Some(privkey) => Some(EthSigner::new(privkey)),
+            None => match keystore {
+                Some(path) => {
+                    let password = match m.value_of("eth-password-file") {
+                        Some(password_file) => read_password_file(Path::new(password_file))?,
+                        None => read_password(false, Some("Ethereum keystore password"))?,
+                    };
+                    Some(load_signer(
+                        &path,
+                        from_address.as_ref(),
+                        password.as_bytes(),
+                    )?)
+                }
+                None => None,
+            },
+        };
+        let from = match signer.as_ref() {
+            Some(signer) => signer.address.clone(),
+            None => from_address.ok_or_else(|| {
+                "<eth-privkey-path>, <eth-keystore> or <eth-from-address> is required".to_string()
+            })?,
+        };
+        let buffer_pct: u64 = FromStrParser::<u64>::default().from_matches(m, "gas-buffer-pct")?;
+        let max_gas_limit: u64 = FromStrParser::<u64>::default().from_matches(m, "gas-limit")?;
+        let gas_price: Option<u64> =
+            FromStrParser::<u64>::default().from_matches_opt(m, "gas-price", false)?;
+        let chain_tag = m
+            .value_of("eth-chain-tag")
+            .map(ChainTag::from_name)
+            .transpose()?
+            .unwrap_or_default();
+        let access_list = match m.value_of("eth-access-list") {
+            Some(path) => Some(AccessListSource::Given(read_access_list(Path::new(path))?)),
+            None if m.is_present("auto-access-list") => Some(AccessListSource::Auto),
+            None => None,
+        };
+        Ok(Self {
+            signer,
+            from,
+            gas: GasArgs {
+                buffer_pct,
+                max_gas_limit,
+                gas_price,
+                chain_tag,
+                access_list,
+            },
+        })
+    }
+
+    pub fn args<'a>() -> Vec<Arg<'a>> {
+        vec![
+            Arg::with_name("eth-privkey-path")
+                .long("eth-privkey-path")
+                .env("CKB_BRIDGE_ETH_PRIVKEY_PATH")
+                .takes_value(true)
+                .validator(|input| PrivkeyPathParser.validate(input))
+                .about("Ethereum private key file path (only read first line)"),
+            Arg::with_name("eth-keystore")
+                .long("eth-keystore")
+                .env("CKB_BRIDGE_ETH_KEYSTORE")
+                .takes_value(true)
+                .conflicts_with("eth-privkey-path")
+                .validator(|input| FilePathParser::new(true).validate(input))
+                .about("Ethereum V3 keystore file, or a directory of keystores (select one with --eth-from-address), instead of --eth-privkey-path"),
+            Arg::with_name("eth-password-file")
+                .long("eth-password-file")
+                .takes_value(true)
+                .requires("eth-keystore")
+                .validator(|input| FilePathParser::new(true).validate(input))
+                .about("File of the --eth-keystore password (only read first line), prompted if not given"),
+            Arg::with_name("eth-from-address")
+                .long("eth-from-address")
+                .alias("eth-from")
+                .takes_value(true)
+                .conflicts_with("eth-privkey-path")
+                .validator(|input| FixedHashParser::<H160>::default().validate(input))
+                .about("The ethereum sender address, instead of --eth-privkey-path when the transactions are signed offline (--eth-build-only). With --eth-keystore, selects the keystore of this address"),
+            Arg::with_name("gas-buffer-pct")
+                .long("gas-buffer-pct")
+                .env("CKB_BRIDGE_GAS_BUFFER_PCT")
+                .takes_value(true)
+                .default_value("20")
+                .validator(|input| FromStrParser::<u64>::default().validate(input))
+                .about("Percentage added to the estimated gas when setting the gas limit"),
+            Arg::with_name("gas-limit")
+                .long("gas-limit")
+                .env("CKB_BRIDGE_GAS_LIMIT")
+                .takes_value(true)
+                .default_value("3000000")
+                .validator(|input| FromStrParser::<u64>::default().validate(input))
+                .about("Max gas limit of ethereum transactions, a transaction estimated (with the buffer) above it is not sent"),
+            Arg::with_name("gas-price")
+                .long("gas-price")
+                .env("CKB_BRIDGE_GAS_PRICE")
+                .takes_value(true)
+                .validator(|input| FromStrParser::<u64>::default().validate(input))
+                .about("Gas price (unit: wei), default is the node's suggested gas price"),
+            Arg::with_name("eth-chain-tag")
+                .long("eth-chain-tag")
+                .env("CKB_BRIDGE_ETH_CHAIN_TAG")
+                .takes_value(true)
+                .possible_values(&ChainTag::NAMES)
+                .default_value("pending")
+                .about("Block tag the nonce and the gas estimate are read at. pending counts the sender's transactions not mined yet (needed for back-to-back sends), latest only mined ones (deterministic, but reuses the nonce of a transaction still pending)"),
+            Arg::with_name("eth-access-list")
+                .long("eth-access-list")
+                .env("CKB_BRIDGE_ETH_ACCESS_LIST")
+                .takes_value(true)
+                .validator(|input| FilePathParser::new(true).validate(input))
+                .about("Send the ethereum transactions with this EIP-2930 access list (a JSON array of {\"address\", \"storageKeys\"}), only if it lowers the gas estimate"),
+            Arg::with_name("auto-access-list")
+                .long("auto-access-list")
+                .conflicts_with("eth-access-list")
+                .about("Send the ethereum transactions with the EIP-2930 access list generated by the node (eth_createAccessList), only if it lowers the gas estimate. Falls back to legacy transactions on nodes or chains without access lists"),
+        ]
+    }
+
+    pub fn signer(&self) -> Result<&EthSigner, String> {
+        self.signer.as_ref().ok_or_else(|| {
+            "<eth-privkey-path> or <eth-keystore> is required to sign the ethereum transaction, or build it with --eth-build-only and sign it offline".to_string()
+        })
+    }
+}
+
+/// Arguments for polling the mint transaction committed
+pub struct MintPollArgs {
+    pub poll_interval: Duration,
+    pub timeout: Duration,
+}
+
+impl MintPollArgs {
+    pub fn from_matches(m: &ArgMatches) -> Result<Self, String> {
+        let poll_interval: u64 =
+            FromStrParser::<u64>::default().from_matches(m, "ckb-poll-interval")?;
+        let timeout: u64 = FromStrParser::<u64>::default().from_matches(m, "mint-timeout")?;
+        if poll_interval == 0 {
+            return Err("<ckb-poll-interval> must be greater than 0".to_string());
+        }
+        Ok(Self {
+            poll_interval: Duration::from_secs(poll_interval),
+            timeout: Duration::from_secs(timeout),
+        })
+    }
+
+    pub fn args<'a>() -> Vec<Arg<'a>> {
+        vec![
+            Arg::with_name("ckb-poll-interval")
+                .long("ckb-poll-interval")
+                .env("CKB_BRIDGE_CKB_POLL_INTERVAL")
+                .takes_value(true)
+                .default_value("3")
+                .validator(|input| FromStrParser::<u64>::default().validate(input))
+                .about("Initial interval (in seconds) to poll the mint transaction status, doubled after each poll"),
+            Arg::with_name("mint-timeout")
+                .long("mint-timeout")
+                .env("CKB_BRIDGE_MINT_TIMEOUT")
+                .takes_value(true)
+                .default_value("600")
+                .validator(|input| FromStrParser::<u64>::default().validate(input))
+                .about("Max time (in seconds) to wait the mint transaction committed, the transfer can be resumed later"),
+        ]
+    }
+}
+
+/// Arguments used by the steps of a transfer from ethereum to ckb
+pub struct ToCkbStepArgs {
+    /// Only required by the approve and lock steps
+    pub eth_args: Option<EthTxArgs>,
+    /// Write the unsigned approve or lock transaction to this file instead of signing and
+    /// sending it
+    pub eth_build_only: Option<PathBuf>,
+    pub ckb_args: CkbTxArgs,
+    pub poll_args: MintPollArgs,
+    pub include_debug_proof: bool,
+    pub allow_precision_loss: bool,
+    /// Check the lock event signature and emitter against the config before parsing it
+    pub strict_abi: bool,
+    /// Write the unsigned mint transaction to this file instead of signing and sending it
+    pub build_only: Option<PathBuf>,
+    /// Write the unsigned mint transaction to this file in the `ckb-cli tx` format instead of
+    /// signing and sending it
+    pub dump_unsigned: Option<PathBuf>,
+    /// Send the mint transaction signed offline (by `sign-tx` or `ckb-cli tx`) in this file
+    pub broadcast_signed: Option<PathBuf>,
+    /// Run the scripts of the signed mint transaction locally before sending it
+    pub simulate_mint: bool,
+    /// Report the script groups and the cell deps of the built mint transaction
+    pub show_script_groups: bool,
+    /// Build the approve and lock transactions but send nothing on ethereum
+    pub dry_run_ethereum: bool,
+    /// Build, sign and verify the mint transaction but do not send it
+    pub dry_run_ckb: bool,
+    pub confirmations_source: ConfirmationsSource,
+    pub max_proof_age: MaxProofAge,
+}
+
+impl ToCkbStepArgs {
+    pub fn from_matches(m: &ArgMatches, network_type: NetworkType) -> Result<Self, String> {
+        Ok(Self {
+            eth_args: EthTxArgs::from_matches_opt(m)?,
+            eth_build_only: FilePathParser::new(false).from_matches_opt(
+                m,
+                "eth-build-only",
+                false,
+            )?,
+            ckb_args: CkbTxArgs::from_matches(m, network_type)?,
+            poll_args: MintPollArgs::from_matches(m)?,
+            include_debug_proof: m.is_present("include-debug-proof"),
+            allow_precision_loss: m.is_present("allow-precision-loss"),
+            strict_abi: m.is_present("strict-abi"),
+            build_only: FilePathParser::new(false).from_matches_opt(m, "build-only", false)?,
+            dump_unsigned: FilePathParser::new(false).from_matches_opt(
+                m,
+                "dump-unsigned",
+                false,
+            )?,
+            broadcast_signed: FilePathParser::new(true).from_matches_opt(
+                m,
+                "broadcast-signed",
+                false,
+            )?,
+            simulate_mint: m.is_present("simulate-mint"),
+            show_script_groups: m.is_present("show-script-groups"),
+            dry_run_ethereum: m.is_present("dry-run-ethereum"),
+            dry_run_ckb: m.is_present("dry-run-ckb"),
+            confirmations_source: match m.value_of("confirmations-source") {
+                Some("safe") => ConfirmationsSource::Safe,
+                Some("finalized") => ConfirmationsSource::Finalized,
+                _ => ConfirmationsSource::Head,
+            },
+            max_proof_age: match EitherParser::new(FromStrParser::<u64>::default(), DurationParser)
+                .from_matches(m, "max-proof-age")?
+            {
+                EitherValue::A(blocks) => MaxProofAge::Blocks(blocks),
+                EitherValue::B(duration) => MaxProofAge::Duration(duration),
+            },
+        })
+    }
+
+    pub fn args<'a>() -> Vec<Arg<'a>> {
+        let mut args = EthTxArgs::args();
+        args.push(
+            Arg::with_name("eth-build-only")
+                .long("eth-build-only")
+                .takes_value(true)
+                .validator(|input| FilePathParser::new(false).validate(input))
+                .about("Write the unsigned approve or lock transaction to this file and stop, sign it offline with sign-eth-tx and send it with broadcast-eth-tx"),
+        );
+        args.extend(Self::mint_args());
+        args
+    }
+
+    /// Arguments of the steps after the lock step (no ethereum transaction is sent)
+    pub fn mint_args<'a>() -> Vec<Arg<'a>> {
+        let mut args = CkbTxArgs::args();
+        args.extend(MintPollArgs::args());
+        args.push(
+            Arg::with_name("include-debug-proof")
+                .long("include-debug-proof")
+                .about("When the mint step fails, record the proof, the ethereum header and the attempted ckb transaction in the transfer log for offline analysis (may be large)"),
+        );
+        args.push(
+            Arg::with_name("allow-precision-loss")
+                .long("allow-precision-loss")
+                .about("Allow to bridge an amount whose low-order digits are truncated by the lower decimals of the token on ckb (the truncated amount is abandoned)"),
+        );
+        args.push(
+            Arg::with_name("strict-abi")
+                .long("strict-abi")
+                .about("Before parsing the lock event, check the receipt has a log of the configured Locked event signature emitted by the configured bridge contract"),
+        );
+        args.push(
+            Arg::with_name("build-only")
+                .long("build-only")
+                .takes_value(true)
+                .validator(|input| FilePathParser::new(false).validate(input))
+                .about("Write the unsigned mint transaction to this file and stop (status Signing), sign it offline with sign-tx"),
+        );
+        args.push(
+            Arg::with_name("dump-unsigned")
+                .long("dump-unsigned")
+                .takes_value(true)
+                .conflicts_with("build-only")
+                .validator(|input| FilePathParser::new(false).validate(input))
+                .about("Write the unsigned mint transaction to this file in the ckb-cli tx format and stop (status Signing), sign it with `ckb-cli tx sign-inputs --add-signatures`"),
+        );
+        args.push(
+            Arg::with_name("broadcast-signed")
+                .long("broadcast-signed")
+                .takes_value(true)
+                .conflicts_with_all(&["build-only", "dump-unsigned"])
+                .validator(|input| FilePathParser::new(true).validate(input))
+                .about("Send the mint transaction signed offline by sign-tx, or the ckb-cli tx file with the signatures added (the transfer must be in status Signing)"),
+        );
+        args.push(
+            Arg::with_name("simulate-mint")
+                .long("simulate-mint")
+                .conflicts_with_all(&["build-only", "dump-unsigned"])
+                .about("Verify the scripts of the mint transaction locally before sending it, report the cycles and abort (no fee is spent) if a script fails"),
+        );
+        args.push(
+            Arg::with_name("show-script-groups")
+                .long("show-script-groups")
+                .about("Report the script groups of the built mint transaction (each lock and type script with the input and output indices it runs for and the cell dep providing its code) and its resolved cell deps, to audit it before it is signed and sent"),
+        );
+        args.push(
+            Arg::with_name("dry-run-ethereum")
+                .long("dry-run-ethereum")
+                .conflicts_with("eth-build-only")
+                .about("Build the approve or lock transaction (nonce, gas) and check it by a call, but send nothing on ethereum. The transfer stays in its status and the output shows the simulated transaction"),
+        );
+        args.push(
+            Arg::with_name("dry-run-ckb")
+                .long("dry-run-ckb")
+                .conflicts_with_all(&["build-only", "dump-unsigned", "broadcast-signed"])
+                .about("Build and sign the mint transaction and verify its scripts locally, but do not send it. The transfer stays in WaitBlockSafe and the output shows the simulated transaction"),
+        );
+        args.push(
+            Arg::with_name("confirmations-source")
+                .long("confirmations-source")
+                .env("CKB_BRIDGE_CONFIRMATIONS_SOURCE")
+                .takes_value(true)
+                .possible_values(&["head", "safe", "finalized"])
+                .default_value("head")
+                .about("Mint after a fixed number of confirmations (head), or once the lock block is at or below the safe/finalized block of ethereum (falls back to head if the chain has no such tag)"),
+        );
+        args.push(
+            Arg::with_name("max-proof-age")
+                .long("max-proof-age")
+                .env("CKB_BRIDGE_MAX_PROOF_AGE")
+                .takes_value(true)
+                .default_value("50000")
+                .validator(|input| {
+                    EitherParser::new(FromStrParser::<u64>::default(), DurationParser)
+                        .validate(input)
+                })
+                .about("Refuse to mint with a proof whose block is older than this, in ethereum blocks or a duration (e.g. 7d), rebuild the proof with rebuild-proof instead"),
+        );
+        args
+    }
+}
+
+/// How the lock block is considered safe to mint on ckb
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConfirmationsSource {
+    /// A fixed number of confirmations after the lock block
+    Head,
+    /// The lock block is at or below the `safe` block of the chain
+    Safe,
+    /// The lock block is at or below the `finalized` block of the chain
+    Finalized,
+}
+
+impl ConfirmationsSource {
+    /// The block tag of `eth_getBlockByNumber`, `None` for `Head`
+    pub fn tag(self) -> Option<&'static str> {
+        match self {
+            ConfirmationsSource::Head => None,
+            ConfirmationsSource::Safe => Some("safe"),
+            ConfirmationsSource::Finalized => Some("finalized"),
+        }
+    }
+}
+
+/// Common arguments of the transfer subcommands
+pub struct TransferArgs {
+    pub transfer_id: Option<String>,
+    pub watch: bool,
+    pub pretty: bool,
+    pub webhook_url: Option<Url>,
+    /// Manually override the loaded status (with `--force`)
+    pub start_from_status: Option<String>,
+    /// Stop before executing the step of this status
+    pub halt_on: Option<String>,
+    /// Run only the step of this status, the transfer must be in it
+    pub only_step: Option<String>,
+    /// The approve was done out-of-band (with `--force`)
+    pub skip_approve: bool,
+    /// The lock transaction sent out-of-band (with `--force`)
+    pub skip_lock: Option<H256>,
+}
+
+impl TransferArgs {
+    pub fn from_matches(m: &ArgMatches) -> Result<Self, String> {
+        Ok(Self {
+            transfer_id: m.value_of("transfer-id").map(ToOwned::to_owned),
+            watch: m.is_present("watch"),
+            pretty: m.is_present("pretty"),
+            webhook_url: UrlParser.from_matches_opt(m, "webhook-url", false)?,
+            start_from_status: m.value_of("start-from-status").map(ToOwned::to_owned),
+            halt_on: m.value_of("halt-on").map(ToOwned::to_owned),
+            only_step: m.value_of("only-step").map(ToOwned::to_owned),
+            skip_approve: m.is_present("skip-approve"),
+            skip_lock: FixedHashParser::<H256>::default().from_matches_opt(
+                m,
+                "skip-lock",
+                false,
+            )?,
+        })
+    }
+
+    pub fn args<'a>() -> Vec<Arg<'a>> {
+        vec![
+            Arg::with_name("transfer-id")
+                .long("transfer-id")
+                .takes_value(true)
+                .about("Resume the transfer with this id, start a new transfer if not given. The token, amount and recipient are kept from the transfer, the fee, gas, confirmation and timeout arguments are taken from this run"),
+            Arg::with_name("watch")
+                .long("watch")
+                .about("Keep advancing the transfer until finished, stream each status transition as one json object per line (NDJSON)"),
+            Arg::with_name("pretty")
+                .long("pretty")
+                .requires("watch")
+                .about("Indent the streamed json objects (for interactive use)"),
+            Arg::with_name("webhook-url")
+                .long("webhook-url")
+                .takes_value(true)
+                .validator(|input| UrlParser.validate(input))
+                .about("POST each status transition as json to this url (best-effort, failures are only logged)"),
+            Arg::with_name("start-from-status")
+                .long("start-from-status")
+                .takes_value(true)
+                .requires_all(&["transfer-id", "force"])
+                .about("Override the status of the resumed transfer before stepping, for manual recovery only (e.g. Locked, ParseProof)"),
+            Arg::with_name("force")
+                .long("force")
+                .about("Acknowledge that --start-from-status, --skip-approve or --skip-lock may cause double-spends if misused"),
+            Arg::with_name("skip-approve")
+                .long("skip-approve")
+                .requires("force")
+                .conflicts_with_all(&["start-from-status", "only-step"])
+                .about("The approve was done out-of-band: mark the transfer Approved without sending it, once the allowance of <eth-from-address> to the bridge contract is checked to cover the amount"),
+            Arg::with_name("skip-lock")
+                .long("skip-lock")
+                .takes_value(true)
+                .requires("force")
+                .conflicts_with_all(&["start-from-status", "only-step"])
+                .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                .about("The lock was sent out-of-band by this ethereum transaction: mark the transfer Locked without sending it, once its lock event is checked to match the token, amount and recipient of the transfer"),
+            Arg::with_name("halt-on")
+                .long("halt-on")
+                .takes_value(true)
+                .about("Breakpoint: stop right before executing the step of this status (e.g. WaitBlockSafe), the transfer can be resumed later"),
+            Arg::with_name("only-step")
+                .long("only-step")
+                .takes_value(true)
+                .requires("transfer-id")
+                .conflicts_with_all(&["watch", "halt-on", "start-from-status"])
+                .about("Run exactly the step out of this status (e.g. Approved) and return, fail if the transfer is not in this status (for external schedulers)"),
+        ]
+    }
+}
+
+/// Arguments to start a new transfer from ethereum to ckb
+pub struct ToCkbArgs {
+    /// The erc20 contract address or a symbol configured in the bridge config
+    pub token: String,
+    pub amount: u128,
+    /// The recipient address (a multisig address with `--multisig-sighash-address`), `None`
+    /// if the lock script is given as raw arguments
+    pub recipient: Option<Address>,
+    pub recipient_lock: Script,
+    /// The transfer fails if not finished within this time
+    pub deadline: Option<Duration>,
+    /// The holder's signed intent when an operator relays the transfer
+    pub intent: Option<IntentArgs>,
+}
+
+/// A transfer intent signed by the holder of the tokens (`sign-transfer-intent`), the token,
+/// amount and recipient are the ones of the transfer
+pub struct IntentArgs {
+    pub signature: Vec<u8>,
+    pub signer: H160,
+    pub nonce: u64,
+    /// Unix time in seconds
+    pub deadline: u64,
+}
+
+impl ToCkbArgs {
+    /// Return None if no argument is given (resume a transfer)
+    pub fn from_matches_opt(
+        m: &ArgMatches,
+        network_type: NetworkType,
+    ) -> Result<Option<Self>, String> {
+        if ![
+            "token",
+            "amount",
+            "ckb-recipient",
+            "multisig-sighash-address",
+            "code-hash",
+            "transfer-deadline",
+            "intent-signature",
+        ]
+        .iter()
+        .any(|name| m.is_present(name))
+        {
+            return Ok(None);
+        }
+        let token = m
+            .value_of("token")
+            .map(ToOwned::to_owned)
+            .ok_or_else(|| "<token> is required".to_string())?;
+        let amount: u128 = FromStrParser::<u128>::default().from_matches(m, "amount")?;
+        let mut recipient: Option<Address> = AddressParser::default()
+            .set_network(network_type)
+            .from_matches_opt(m, "ckb-recipient", false)?;
+        if m.is_present("multisig-sighash-address") {
+            let payload = multisig_recipient(m, network_type)?;
+            recipient = Some(Address::new(network_type, payload));
+        }
+        let recipient_lock = match recipient.as_ref() {
+            Some(address) => Script::from(address.payload()),
+            None if m.is_present("code-hash") => {
+                let code_hash: H256 =
+                    FixedHashParser::<H256>::default().from_matches(m, "code-hash")?;
+                let hash_type = parse_hash_type(m.value_of("hash-type").unwrap_or_default())?;
+                let args: Vec<u8> = HexParser.from_matches(m, "args")?;
+                Script::new_builder()
+                    .code_hash(code_hash.pack())
+                    .hash_type(hash_type.into())
+                    .args(Bytes::from(args).pack())
+                    .build()
+            }
+            None => {
+                return Err(
+                    "<ckb-recipient>, <multisig-sighash-address> or <code-hash>, <hash-type> and <args> is required"
+                        .to_string(),
+                );
+            }
+        };
+        check_recipient_lock(&recipient_lock)?;
+        Ok(Some(Self {
+            token,
+            amount,
+            recipient,
+            recipient_lock,
+            deadline: DurationParser.from_matches_opt(m, "transfer-deadline", false)?,
+            intent: match HexParser.from_matches_opt(m, "intent-signature", false)? {
+                Some(signature) => Some(IntentArgs {
+                    signature,
+                    signer: FixedHashParser::<H160>::default().from_matches(m, "intent-signer")?,
+                    nonce: FromStrParser::<u64>::default().from_matches(m, "intent-nonce")?,
+                    deadline: FromStrParser::<u64>::default().from_matches(m, "intent-deadline")?,
+                }),
+                None => None,
+            },
+        }))
+    }
+
+    pub fn args<'a>() -> Vec<Arg<'a>> {
+        let mut args = Self::recipient_args();
+        args.extend(vec![
+            Arg::with_name("transfer-deadline")
+                .long("transfer-deadline")
+                .takes_value(true)
+                .validator(|input| DurationParser.validate(input))
+                .about("Fail the new transfer (status Failed) if it is not finished within this duration (e.g. 2h), checked before every step. A transfer whose mint transaction is sent is not failed"),
+            Arg::with_name("intent-signature")
+                .long("intent-signature")
+                .takes_value(true)
+                .requires_all(&["intent-signer", "intent-nonce", "intent-deadline"])
+                .validator(|input| HexParser.validate(input))
+                .about("Relay the transfer authorized by this signature of the token holder (see sign-transfer-intent), verified before anything is sent and passed to lock()"),
+            Arg::with_name("intent-signer")
+                .long("intent-signer")
+                .takes_value(true)
+                .requires("intent-signature")
+                .validator(|input| FixedHashParser::<H160>::default().validate(input))
+                .about("The ethereum address expected to sign the transfer intent"),
+            Arg::with_name("intent-nonce")
+                .long("intent-nonce")
+                .takes_value(true)
+                .requires("intent-signature")
+                .validator(|input| FromStrParser::<u64>::default().validate(input))
+                .about("The nonce of the signed transfer intent"),
+            Arg::with_name("intent-deadline")
+                .long("intent-deadline")
+                .takes_value(true)
+                .requires("intent-signature")
+                .validator(|input| FromStrParser::<u64>::default().validate(input))
+                .about("The unix time (seconds) the signed transfer intent expires at"),
+        ]);
+        args
+    }
+
+    /// The token, the amount and the recipient of a transfer
+    pub fn recipient_args<'a>() -> Vec<Arg<'a>> {
+        vec![
+            token_arg(),
+            Arg::with_name("amount")
+                .long("amount")
+                .takes_value(true)
+                .validator(|input| FromStrParser::<u128>::default().validate(input))
+                .about("The amount to transfer (in the token's smallest unit)"),
+            Arg::with_name("ckb-recipient")
+                .long("ckb-recipient")
+                .takes_value(true)
+                .validator(|input| AddressParser::default().validate(input))
+                .about("The ckb address to receive the token, a multisig address is supported"),
+            Arg::with_name("multisig-sighash-address")
+                .long("multisig-sighash-address")
+                .takes_value(true)
+                .multiple(true)
+                .conflicts_with("ckb-recipient")
+                .validator(|input| AddressParser::new_sighash().validate(input))
+                .about("Mint to the multisig lock of these sighash addresses (instead of <ckb-recipient>), with --multisig-threshold and --multisig-require-first-n"),
+            Arg::with_name("multisig-threshold")
+                .long("multisig-threshold")
+                .takes_value(true)
+                .default_value("1")
+                .validator(|input| FromStrParser::<u8>::default().validate(input))
+                .about("Multisig threshold of the recipient"),
+            Arg::with_name("multisig-require-first-n")
+                .long("multisig-require-first-n")
+                .takes_value(true)
+                .default_value("0")
+                .validator(|input| FromStrParser::<u8>::default().validate(input))
+                .about("Require the first n signatures of the multisig recipient"),
+            Arg::with_name("multisig-since-absolute-epoch")
+                .long("multisig-since-absolute-epoch")
+                .takes_value(true)
+                .requires("multisig-sighash-address")
+                .validator(|input| FromStrParser::<u64>::default().validate(input))
+                .about("The minted cell of the multisig recipient can only be spent since this absolute epoch number"),
+            Arg::with_name("code-hash")
+                .long("code-hash")
+                .takes_value(true)
+                .conflicts_with_all(&["ckb-recipient", "multisig-sighash-address"])
+                .requires_all(&["hash-type", "args"])
+                .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                .about("The code hash of the recipient lock script (instead of <ckb-recipient>)"),
+            Arg::with_name("hash-type")
+                .long("hash-type")
+                .takes_value(true)
+                .requires("code-hash")
+                .possible_values(&["data", "data1", "type"])
+                .about("The hash type of the recipient lock script"),
+            Arg::with_name("args")
+                .long("args")
+                .takes_value(true)
+                .requires("code-hash")
+                .validator(|input| HexParser.validate(input))
+                .about("The args of the recipient lock script (hex)"),
+        ]
+    }
+}
+
+/// The multisig lock of the recipient, the args is the hash of the multisig config
+fn multisig_recipient(m: &ArgMatches, network_type: NetworkType) -> Result<AddressPayload, String> {
+    let sighash_addresses: Vec<Address> = AddressParser::default()
+        .set_network(network_type)
+        .set_short(CodeHashIndex::Sighash)
+        .from_matches_vec(m, "multisig-sighash-address")?;
+    let require_first_n: u8 =
+        FromStrParser::<u8>::default().from_matches(m, "multisig-require-first-n")?;
+    let threshold: u8 = FromStrParser::<u8>::default().from_matches(m, "multisig-threshold")?;
+    let since_absolute_epoch: Option<u64> = FromStrParser::<u64>::default().from_matches_opt(
+        m,
+        "multisig-since-absolute-epoch",
+        false,
+    )?;
+    let sighash_addresses = sighash_addresses
+        .into_iter()
+        .map(|address| address.payload().clone())
+        .collect::<Vec<_>>();
+    let config = MultisigConfig::new_with(sighash_addresses, require_first_n, threshold)
+        .map_err(|err| format!("Invalid multisig recipient: {}", err))?;
+    Ok(config.to_address_payload(since_absolute_epoch))
+}
+
+/// Arguments used by the steps of a transfer from ckb to ethereum
+pub struct FromCkbStepArgs {
+    /// The holder of the bridged token cells, pays the burn transaction fee
+    pub ckb_args: CkbTxArgs,
+    pub poll_interval: Duration,
+    pub burn_timeout: Duration,
+    /// Build, sign and verify the burn transaction, do not send it
+    pub dry_run_ckb: bool,
+    /// Build the unlock transaction, do not send it
+    pub dry_run_ethereum: bool,
+}
+
+impl FromCkbStepArgs {
+    pub fn from_matches(m: &ArgMatches, network_type: NetworkType) -> Result<Self, String> {
+        let poll_interval: u64 =
+            FromStrParser::<u64>::default().from_matches(m, "ckb-poll-interval")?;
+        let burn_timeout: u64 = FromStrParser::<u64>::default().from_matches(m, "burn-timeout")?;
+        if poll_interval == 0 {
+            return Err("<ckb-poll-interval> must be greater than 0".to_string());
+        }
+        Ok(Self {
+            ckb_args: CkbTxArgs::from_matches(m, network_type)?,
+            poll_interval: Duration::from_secs(poll_interval),
+            burn_timeout: Duration::from_secs(burn_timeout),
+            // Shared with the to-ckb steps in resume-all
+            dry_run_ckb: m.is_present("dry-run-ckb"),
+            dry_run_ethereum: m.is_present("dry-run-ethereum"),
+        })
+    }
+
+    /// Includes `EthTxArgs::args`, parsed with `EthTxArgs::from_matches_opt` (the unlock step
+    /// sends an ethereum transaction)
+    pub fn args<'a>() -> Vec<Arg<'a>> {
+        let mut args = CkbTxArgs::args();
+        args.extend(EthTxArgs::args());
+        args.extend(vec![
+            Arg::with_name("ckb-poll-interval")
+                .long("ckb-poll-interval")
+                .env("CKB_BRIDGE_CKB_POLL_INTERVAL")
+                .takes_value(true)
+                .default_value("3")
+                .validator(|input| FromStrParser::<u64>::default().validate(input))
+                .about("Initial interval (in seconds) to poll the burn transaction status, doubled after each poll"),
+            Self::burn_timeout_arg(),
+            Arg::with_name("dry-run-ckb")
+                .long("dry-run-ckb")
+                .about("Build and sign the burn transaction and verify its scripts locally, but do not send it. The transfer stays in its status and the output shows the simulated transaction"),
+            Arg::with_name("dry-run-ethereum")
+                .long("dry-run-ethereum")
+                .about("Build the unlock transaction (nonce, gas), but send nothing on ethereum. The transfer stays in Mint and the output shows the simulated transaction"),
+        ]);
+        args
+    }
+
+    /// The only argument not shared with the to-ckb steps (resume-all)
+    pub fn burn_timeout_arg<'a>() -> Arg<'a> {
+        Arg::with_name("burn-timeout")
+            .long("burn-timeout")
+            .env("CKB_BRIDGE_BURN_TIMEOUT")
+            .takes_value(true)
+            .default_value("600")
+            .validator(|input| FromStrParser::<u64>::default().validate(input))
+            .about("Max time (in seconds) to wait the burn transaction committed, the transfer can be resumed later")
+    }
+}
+
+/// Arguments to start a new transfer from ckb to ethereum
+pub struct FromCkbArgs {
+    /// The erc20 contract address or a symbol configured in the bridge config
+    pub token: String,
+    /// The amount of the bridged token to burn, in its unit on ckb
+    pub amount: u128,
+    /// The ethereum address to receive the unlocked erc20 token
+    pub recipient: H160,
+    /// Check the amount against the bridged supply before the burn
+    pub check_supply: bool,
+}
+
+impl FromCkbArgs {
+    /// Return None if no argument is given (resume a transfer)
+    pub fn from_matches_opt(m: &ArgMatches) -> Result<Option<Self>, String> {
+        if !["token", "amount", "eth-recipient"]
+            .iter()
+            .any(|name| m.is_present(name))
+        {
+            return Ok(None);
+        }
+        let token = m
+            .value_of("token")
+            .map(ToOwned::to_owned)
+            .ok_or_else(|| "<token> is required".to_string())?;
+        let amount: u128 = FromStrParser::<u128>::default().from_matches(m, "amount")?;
+        if amount == 0 {
+            return Err("Amount must be greater than 0".to_string());
+        }
+        Ok(Some(Self {
+            token,
+            amount,
+            recipient: FixedHashParser::<H160>::default().from_matches(m, "eth-recipient")?,
+            check_supply: m.is_present("check-supply"),
+        }))
+    }
+
+    pub fn args<'a>() -> Vec<Arg<'a>> {
+        vec![
+            token_arg().conflicts_with("transfer-id"),
+            Arg::with_name("amount")
+                .long("amount")
+                .takes_value(true)
+                .conflicts_with("transfer-id")
+                .validator(|input| FromStrParser::<u128>::default().validate(input))
+                .about("The amount of the bridged token to burn (in its smallest unit on ckb)"),
+            Arg::with_name("eth-recipient")
+                .long("eth-recipient")
+                .takes_value(true)
+                .conflicts_with("transfer-id")
+                .validator(|input| FixedHashParser::<H160>::default().validate(input))
+                .about("The ethereum address to receive the unlocked erc20 token"),
+            Arg::with_name("check-supply")
+                .long("check-supply")
+                .requires("amount")
+                .about("Before the burn transaction is built, check the amount does not exceed the bridged supply (the supply minted on ckb and the balance of the bridge contract on ethereum), abort if it does"),
+        ]
+    }
+}
+
+pub fn token_arg<'a>() -> Arg<'a> {
+    Arg::with_name("token")
+        .long("token")
+        .takes_value(true)
+        .about("The erc20 token contract address, or a token symbol configured in the bridge config (e.g. USDC)")
+}
+
+fn parse_hash_type(input: &str) -> Result<ScriptHashType, String> {
+    match input {
+        "data" => Ok(ScriptHashType::Data),
+        "type" => Ok(ScriptHashType::Type),
+        "data1" => Err("Hash type data1 is not supported by this ckb version".to_string()),
+        _ => Err(format!("Invalid hash type: {}", input)),
+    }
+}
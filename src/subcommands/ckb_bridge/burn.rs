@@ -0,0 +1,323 @@
+//! The burn transaction of a transfer from ckb to ethereum
+
+use ckb_index::LiveCellInfo;
+use ckb_types::{
+    bytes::Bytes,
+    core::{BlockView, TransactionBuilder, TransactionView},
+    packed::{Byte32, CellDep, CellOutput, Script, WitnessArgs},
+    prelude::*,
+    utilities::{merkle_root, CBMT},
+    H160, H256,
+};
+
+use super::mint::token_amount;
+use super::rlp::{self, RlpItem};
+
+/// Take token cells (with their amounts) in order until `amount` is covered, return the taken
+/// cells and their total amount
+pub fn select_token_cells<T: Clone>(
+    cells: &[(T, u128)],
+    amount: u128,
+) -> Result<(Vec<T>, u128), String> {
+    let mut taken = Vec::new();
+    let mut total = 0u128;
+    for (cell, cell_amount) in cells {
+        if total >= amount {
+            break;
+        }
+        taken.push(cell.clone());
+        total = total
+            .checked_add(*cell_amount)
+            .ok_or_else(|| "Token amount overflow".to_string())?;
+    }
+    if total < amount {
+        return Err(format!(
+            "Token not enough: {} in {} token cells, {} to burn",
+            total,
+            taken.len(),
+            amount
+        ));
+    }
+    Ok((taken, total))
+}
+
+/// Witness 0 of the burn transaction, the ethereum recipient is put in `input_type` (the first
+/// input is a token cell), it is read by the bridge script and by the unlock on ethereum
+pub fn burn_witness(recipient: &H160) -> WitnessArgs {
+    WitnessArgs::new_builder()
+        .input_type(Some(Bytes::from(recipient.as_bytes().to_vec())).pack())
+        .build()
+}
+
+/// The ethereum recipient in witness 0 of a burn transaction
+pub fn witness_recipient(witness: &[u8]) -> Option<H160> {
+    let witness_args = WitnessArgs::from_slice(witness).ok()?;
+    let recipient = witness_args.input_type().to_opt()?;
+    H160::from_slice(&recipient.raw_data()).ok()
+}
+
+/// The amount of the bridged token burned by a ckb transaction: the token cells of
+/// `type_script` in its inputs minus the ones in its outputs
+pub fn burned_amount(
+    inputs: &[(CellOutput, Bytes)],
+    outputs: &[(CellOutput, Bytes)],
+    type_script: &Script,
+) -> Result<u128, String> {
+    let sum = |cells: &[(CellOutput, Bytes)]| -> Result<u128, String> {
+        let mut total = 0u128;
+        for (output, data) in cells {
+            if output.type_().to_opt().as_ref().map(Script::as_slice)
+                == Some(type_script.as_slice())
+            {
+                total = total
+                    .checked_add(token_amount(data)?)
+                    .ok_or_else(|| "Token amount overflow".to_string())?;
+            }
+        }
+        Ok(total)
+    };
+    let (burned, remaining) = (sum(inputs)?, sum(outputs)?);
+    burned.checked_sub(remaining).ok_or_else(|| {
+        format!(
+            "The transaction creates {} bridged tokens from {}, not a burn",
+            remaining, burned
+        )
+    })
+}
+
+/// The proof of the burn transaction in its ckb block, checked by the unlock on ethereum
+/// against the header of `block_hash`. The transactions root of a header is the merkle root of
+/// the transaction hashes root and the witness hashes root, the proof has the merkle path of
+/// the transaction hash and of its witness hash (the recipient is in the witness).
+#[derive(Clone, Debug, PartialEq)]
+pub struct BurnProof {
+    pub block_number: u64,
+    pub block_hash: H256,
+    pub tx_index: u32,
+    /// The number of transactions in the block, the leaves of both trees
+    pub tx_count: u32,
+    pub tx_lemmas: Vec<H256>,
+    pub witness_lemmas: Vec<H256>,
+}
+
+impl BurnProof {
+    /// Build the proof of the transaction `tx_hash` in `block`, checked against the
+    /// transactions root of the block
+    pub fn build(block: &BlockView, tx_hash: &H256) -> Result<BurnProof, String> {
+        let header = block.header();
+        let hash: Byte32 = tx_hash.pack();
+        let tx_index = block
+            .tx_hashes()
+            .iter()
+            .position(|leaf| leaf == &hash)
+            .ok_or_else(|| {
+                format!(
+                    "Transaction {:#x} is not in block {}",
+                    tx_hash,
+                    header.number()
+                )
+            })?;
+        let witness_hash = block.tx_witness_hashes()[tx_index].clone();
+        let indices = [tx_index as u32];
+        let build_error = || format!("Build the proof of transaction {:#x} error", tx_hash);
+        let tx_proof =
+            CBMT::build_merkle_proof(block.tx_hashes(), &indices).ok_or_else(build_error)?;
+        let witness_proof = CBMT::build_merkle_proof(block.tx_witness_hashes(), &indices)
+            .ok_or_else(build_error)?;
+        let roots = match (tx_proof.root(&[hash]), witness_proof.root(&[witness_hash])) {
+            (Some(tx_root), Some(witness_root)) => [tx_root, witness_root],
+            _ => return Err(build_error()),
+        };
+        if merkle_root(&roots) != header.transactions_root() {
+            return Err(format!(
+                "The proof of transaction {:#x} does not match the transactions root of block {}",
+                tx_hash,
+                header.number()
+            ));
+        }
+        Ok(BurnProof {
+            block_number: header.number(),
+            block_hash: header.hash().unpack(),
+            tx_index: tx_index as u32,
+            tx_count: block.tx_hashes().len() as u32,
+            tx_lemmas: tx_proof.lemmas().iter().map(Unpack::unpack).collect(),
+            witness_lemmas: witness_proof.lemmas().iter().map(Unpack::unpack).collect(),
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let lemmas = |lemmas: &[H256]| {
+            RlpItem::List(
+                lemmas
+                    .iter()
+                    .map(|lemma| RlpItem::Bytes(lemma.as_bytes().to_vec()))
+                    .collect(),
+            )
+        };
+        rlp::encode(&RlpItem::List(vec![
+            RlpItem::from_u64(self.block_number),
+            RlpItem::Bytes(self.block_hash.as_bytes().to_vec()),
+            RlpItem::from_u64(u64::from(self.tx_index)),
+            RlpItem::from_u64(u64::from(self.tx_count)),
+            lemmas(&self.tx_lemmas),
+            lemmas(&self.witness_lemmas),
+        ]))
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<BurnProof, String> {
+        let item = rlp::decode(data)?;
+        let fields = item.as_list()?;
+        if fields.len() != 6 {
+            return Err(format!(
+                "Invalid burn proof: expected 6 fields, got {}",
+                fields.len()
+            ));
+        }
+        let parse_h256 =
+            |bytes: &[u8]| H256::from_slice(bytes).map_err(|err| format!("Invalid hash: {}", err));
+        let parse_u32 = |item: &RlpItem| -> Result<u32, String> {
+            let value = item.as_u64()?;
+            if value > u64::from(u32::max_value()) {
+                return Err(format!("Invalid burn proof: {} overflows u32", value));
+            }
+            Ok(value as u32)
+        };
+        let lemmas = |item: &RlpItem| -> Result<Vec<H256>, String> {
+            item.as_list()?
+                .iter()
+                .map(|lemma| parse_h256(lemma.as_bytes()?))
+                .collect()
+        };
+        Ok(BurnProof {
+            block_number: fields[0].as_u64()?,
+            block_hash: parse_h256(fields[1].as_bytes()?)?,
+            tx_index: parse_u32(&fields[2])?,
+            tx_count: parse_u32(&fields[3])?,
+            tx_lemmas: lemmas(&fields[4])?,
+            witness_lemmas: lemmas(&fields[5])?,
+        })
+    }
+}
+
+/// Build the (unsigned) burn transaction:
+///   * inputs: the token cells to burn, then sighash cells to pay the transaction fee
+///   * output 0 (optional): the token cell of the amount not burned, back to the holder
+///   * output 1 (optional): change cell
+///
+/// The token cells and the fee cells have the same lock (the holder), they are signed as one
+/// group in witness 0.
+pub fn build_burn_tx(
+    recipient: &H160,
+    token_cells: &[LiveCellInfo],
+    token_change: Option<(CellOutput, Bytes)>,
+    cell_deps: Vec<CellDep>,
+    fee_cells: &[LiveCellInfo],
+    change_output: Option<CellOutput>,
+) -> TransactionView {
+    let mut builder = TransactionBuilder::default()
+        .cell_deps(cell_deps)
+        .inputs(token_cells.iter().chain(fee_cells).map(LiveCellInfo::input))
+        .witness(burn_witness(recipient).as_bytes().pack());
+    if let Some((output, data)) = token_change {
+        builder = builder.output(output).output_data(data.pack());
+    }
+    if let Some(change_output) = change_output {
+        builder = builder
+            .output(change_output)
+            .output_data(Bytes::new().pack());
+    }
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_types::{
+        core::{BlockBuilder, Capacity},
+        h160,
+    };
+
+    #[test]
+    fn test_select_token_cells() {
+        let cells = vec![(1, 70), (2, 30), (3, 50)];
+        assert_eq!(select_token_cells(&cells, 70), Ok((vec![1], 70)));
+        assert_eq!(select_token_cells(&cells, 80), Ok((vec![1, 2], 100)));
+        assert_eq!(select_token_cells(&cells, 150), Ok((vec![1, 2, 3], 150)));
+        assert!(select_token_cells(&cells, 151)
+            .unwrap_err()
+            .contains("150 in 3 token cells, 151 to burn"));
+        assert!(select_token_cells::<u8>(&[], 1).is_err());
+    }
+    #[test]
+    fn test_witness_recipient() {
+        let recipient = h160!("0x1111111111111111111111111111111111111111");
+        let witness = burn_witness(&recipient);
+        assert_eq!(
+            witness_recipient(witness.as_slice()),
+            Some(recipient.clone())
+        );
+        // The signature is added to the lock of the same witness
+        let signed = witness
+            .as_builder()
+            .lock(Some(Bytes::from(vec![0u8; 65])).pack())
+            .build();
+        assert_eq!(witness_recipient(signed.as_slice()), Some(recipient));
+        assert_eq!(witness_recipient(WitnessArgs::default().as_slice()), None);
+    }
+
+    #[test]
+    fn test_burned_amount() {
+        let type_script = |args: u8| {
+            Script::new_builder()
+                .args(Bytes::from(vec![args; 20]).pack())
+                .build()
+        };
+        let (token, other) = (type_script(1), type_script(2));
+        let cell = |type_script: &Script, amount: u128| {
+            (
+                CellOutput::new_builder()
+                    .type_(Some(type_script.clone()).pack())
+                    .build(),
+                Bytes::from(amount.to_le_bytes().to_vec()),
+            )
+        };
+        let plain = (CellOutput::default(), Bytes::new());
+        let inputs = vec![cell(&token, 70), cell(&token, 30), cell(&other, 5), plain];
+        let outputs = vec![cell(&token, 40), cell(&other, 5)];
+        assert_eq!(burned_amount(&inputs, &outputs, &token), Ok(60));
+        assert_eq!(burned_amount(&inputs, &[], &token), Ok(100));
+        assert!(burned_amount(&outputs, &inputs, &token).is_err());
+    }
+
+    #[test]
+    fn test_burn_proof() {
+        let recipient = h160!("0x1111111111111111111111111111111111111111");
+        let txs = (0..5u64)
+            .map(|index| {
+                TransactionBuilder::default()
+                    .output(
+                        CellOutput::new_builder()
+                            .capacity(Capacity::shannons(index).pack())
+                            .build(),
+                    )
+                    .output_data(Bytes::new().pack())
+                    .witness(burn_witness(&recipient).as_bytes().pack())
+                    .build()
+            })
+            .collect::<Vec<_>>();
+        let block = BlockBuilder::default()
+            .number(100u64.pack())
+            .transactions(txs.clone())
+            .build();
+        for tx in &txs {
+            let tx_hash: H256 = tx.hash().unpack();
+            let proof = BurnProof::build(&block, &tx_hash).unwrap();
+            assert_eq!(proof.block_number, 100);
+            assert_eq!(proof.tx_count, 5);
+            assert_eq!(BurnProof::from_bytes(&proof.to_bytes()), Ok(proof));
+        }
+        assert!(BurnProof::build(&block, &H256::default())
+            .unwrap_err()
+            .contains("is not in block 100"));
+    }
+}
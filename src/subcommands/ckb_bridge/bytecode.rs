@@ -0,0 +1,227 @@
+//! Compiled solidity artifacts and the check of the deployed runtime bytecode
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::utils::arg_parser::{ArgParser, HexParser};
+
+/// The parts of a compiled contract artifact used by `deploy-sol`. Truffle/hardhat artifacts
+/// (`bytecode`, `deployedBytecode` as hex strings) and solc standard json output
+/// (`evm.bytecode.object`, `evm.deployedBytecode.object`) are supported.
+#[derive(Clone, Debug)]
+pub struct ContractArtifact {
+    /// Creation bytecode (without constructor arguments)
+    pub bytecode: Vec<u8>,
+    /// Expected runtime bytecode, immutables are zero
+    pub deployed_bytecode: Vec<u8>,
+    /// Byte ranges `(start, length)` of the immutables in the runtime bytecode
+    pub immutables: Vec<(usize, usize)>,
+}
+
+impl ContractArtifact {
+    pub fn from_json(content: &str) -> Result<ContractArtifact, String> {
+        let value: serde_json::Value = serde_json::from_str(content)
+            .map_err(|err| format!("Parse contract artifact error: {}", err))?;
+        let root = if value.get("evm").is_some() {
+            &value["evm"]
+        } else {
+            &value
+        };
+        let bytecode = parse_code(&root["bytecode"], "bytecode")?;
+        let deployed_bytecode = parse_code(&root["deployedBytecode"], "deployedBytecode")?;
+        let references = match root["deployedBytecode"].get("immutableReferences") {
+            Some(references) => references,
+            None => &value["immutableReferences"],
+        };
+        let mut immutables = Vec::new();
+        if let Some(references) = references.as_object() {
+            for ranges in references.values() {
+                for range in ranges.as_array().map(Vec::as_slice).unwrap_or_default() {
+                    let start = range["start"].as_u64();
+                    let length = range["length"].as_u64();
+                    match (start, length) {
+                        (Some(start), Some(length)) => {
+                            immutables.push((start as usize, length as usize))
+                        }
+                        _ => return Err(format!("Invalid immutable reference: {}", range)),
+                    }
+                }
+            }
+        }
+        Ok(ContractArtifact {
+            bytecode,
+            deployed_bytecode,
+            immutables,
+        })
+    }
+}
+
+/// `code` is a hex string or an object with the hex string in `object`
+fn parse_code(code: &serde_json::Value, name: &str) -> Result<Vec<u8>, String> {
+    let hex = code
+        .as_str()
+        .or_else(|| code["object"].as_str())
+        .ok_or_else(|| format!("Contract artifact has no {}", name))?;
+    if hex.contains("__") {
+        return Err(format!(
+            "The {} in contract artifact has unlinked library placeholders",
+            name
+        ));
+    }
+    let code = HexParser
+        .parse(hex)
+        .map_err(|err| format!("Invalid {} in contract artifact: {}", name, err))?;
+    if code.is_empty() {
+        return Err(format!(
+            "The {} in contract artifact is empty (abstract contract or interface?)",
+            name
+        ));
+    }
+    Ok(code)
+}
+
+/// The result of comparing the on-chain runtime bytecode with the artifact
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BytecodeCheck {
+    pub matched: bool,
+    pub onchain_size: usize,
+    pub expected_size: usize,
+    /// Number of immutables ignored in the comparison
+    pub immutables: usize,
+    /// Whether the trailing solc metadata (CBOR) is also equal, a difference alone (e.g.
+    /// compiled from another path) does not fail the check
+    pub metadata_matched: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Compare the on-chain runtime bytecode with the expected one, ignoring the immutables
+/// (set by the constructor) and the metadata hash appended by solc.
+pub fn check_bytecode(onchain: &[u8], artifact: &ContractArtifact) -> BytecodeCheck {
+    let expected = &artifact.deployed_bytecode;
+    let mut check = BytecodeCheck {
+        matched: false,
+        onchain_size: onchain.len(),
+        expected_size: expected.len(),
+        immutables: artifact.immutables.len(),
+        metadata_matched: false,
+        error: None,
+    };
+    if onchain.len() != expected.len() {
+        check.error = Some(format!(
+            "Runtime bytecode size mismatch, expected: {}, on-chain: {}",
+            expected.len(),
+            onchain.len()
+        ));
+        return check;
+    }
+    let mut onchain = onchain.to_vec();
+    for (start, length) in &artifact.immutables {
+        match onchain.get_mut(*start..start + length) {
+            Some(range) => range.iter_mut().for_each(|byte| *byte = 0),
+            None => {
+                check.error = Some(format!(
+                    "Immutable reference out of range: start {}, length {}",
+                    start, length
+                ));
+                return check;
+            }
+        }
+    }
+    let code_len = expected.len() - metadata_len(expected);
+    check.metadata_matched = onchain[code_len..] == expected[code_len..];
+    if onchain[..code_len] == expected[..code_len] {
+        check.matched = true;
+    } else {
+        let offset = onchain
+            .iter()
+            .zip(expected.iter())
+            .position(|(a, b)| a != b)
+            .unwrap_or(0);
+        check.error = Some(format!(
+            "Runtime bytecode mismatch at offset {} (wrong compiler settings or tampered artifact?)",
+            offset
+        ));
+    }
+    check
+}
+
+/// Length of the CBOR encoded metadata appended by solc (including the 2 bytes length
+/// suffix), 0 if the code has no valid metadata suffix
+fn metadata_len(code: &[u8]) -> usize {
+    if code.len() < 2 {
+        return 0;
+    }
+    let cbor_len = usize::from(code[code.len() - 2]) << 8 | usize::from(code[code.len() - 1]);
+    if cbor_len == 0 || cbor_len + 2 > code.len() {
+        return 0;
+    }
+    // The CBOR map always starts with 0xa1..0xa5 (map with 1 to 5 entries)
+    let start = code.len() - 2 - cbor_len;
+    if (0xa1..=0xa5).contains(&code[start]) {
+        cbor_len + 2
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn artifact(deployed: Vec<u8>, immutables: Vec<(usize, usize)>) -> ContractArtifact {
+        ContractArtifact {
+            bytecode: vec![0x60, 0x80],
+            deployed_bytecode: deployed,
+            immutables,
+        }
+    }
+
+    #[test]
+    fn test_parse_artifact() {
+        let truffle = r#"{"bytecode": "0x6080", "deployedBytecode": "0x60806040", "immutableReferences": {"12": [{"start": 1, "length": 2}]}}"#;
+        let parsed = ContractArtifact::from_json(truffle).unwrap();
+        assert_eq!(parsed.bytecode, vec![0x60, 0x80]);
+        assert_eq!(parsed.deployed_bytecode, vec![0x60, 0x80, 0x60, 0x40]);
+        assert_eq!(parsed.immutables, vec![(1, 2)]);
+
+        let solc = r#"{"evm": {"bytecode": {"object": "6080"}, "deployedBytecode": {"object": "6080", "immutableReferences": {}}}}"#;
+        let parsed = ContractArtifact::from_json(solc).unwrap();
+        assert_eq!(parsed.deployed_bytecode, vec![0x60, 0x80]);
+        assert!(parsed.immutables.is_empty());
+
+        let unlinked = r#"{"bytecode": "0x73__$abc$__6080", "deployedBytecode": "0x6080"}"#;
+        assert!(ContractArtifact::from_json(unlinked).is_err());
+        let interface = r#"{"bytecode": "0x", "deployedBytecode": "0x"}"#;
+        assert!(ContractArtifact::from_json(interface).is_err());
+    }
+
+    #[test]
+    fn test_check_bytecode() {
+        // code: 60 80 | immutable (2 bytes) | 60 40 | metadata: a1 00 | length 00 02
+        let expected = vec![0x60, 0x80, 0, 0, 0x60, 0x40, 0xa1, 0x00, 0x00, 0x02];
+        let artifact = artifact(expected.clone(), vec![(2, 2)]);
+
+        let mut onchain = expected.clone();
+        onchain[2] = 0x12;
+        onchain[3] = 0x34;
+        let check = check_bytecode(&onchain, &artifact);
+        assert!(check.matched);
+        assert!(check.metadata_matched);
+
+        // Only the metadata differs
+        let mut onchain = expected.clone();
+        onchain[7] = 0xff;
+        let check = check_bytecode(&onchain, &artifact);
+        assert!(check.matched);
+        assert!(!check.metadata_matched);
+
+        let mut onchain = expected.clone();
+        onchain[5] = 0x41;
+        let check = check_bytecode(&onchain, &artifact);
+        assert!(!check.matched);
+        assert!(check.error.unwrap().contains("offset 5"));
+
+        let check = check_bytecode(&expected[1..], &artifact);
+        assert!(!check.matched);
+    }
+}
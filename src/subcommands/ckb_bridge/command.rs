@@ -1,58 +1,198 @@
-use clap::{App, ArgMatches};
-use ckb_sdk::{HttpRpcClient, GenesisInfo};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use ckb_crypto::secp::SECP256K1;
+use ckb_hash::blake2b_256;
+use ckb_index::{with_index_db, IndexDatabase, IndexError, LiveCellInfo};
+use ckb_jsonrpc_types::{JsonBytes, Status};
+use ckb_sdk::{
+    Address, AddressPayload, GenesisInfo, HttpRpcClient, HumanCapacity, MockInfo, MockTransaction,
+    MockTransactionHelper, NetworkType,
+};
+use ckb_types::{
+    bytes::Bytes,
+    core::{BlockView, DepType, ScriptHashType, TransactionView},
+    packed::{self, CellDep, CellOutput, OutPoint, Script},
+    prelude::*,
+    H160, H256,
+};
+use clap::{App, Arg, ArgMatches};
+
+use super::abi::{self, ContractAbi, Token};
+use super::address_cache::AddressCache;
+use super::args::{
+    CkbTxArgs, ConfirmationsSource, EthTxArgs, FromCkbArgs, FromCkbStepArgs, IntentArgs, ToCkbArgs,
+    ToCkbStepArgs, TransferArgs,
+};
+use super::burn::{self, BurnProof};
+use super::bytecode::{check_bytecode, ContractArtifact};
+use super::config::{BridgeConfig, LightClientConfig, ScriptConfig, TokenConfig};
+use super::deploy;
+use super::doctor::{batch_cell_status, parallel_map, Check, MAX_CHECK_WORKERS};
+use super::erc20;
+use super::eth::{
+    checksum_address, keccak256, parse_quantity, EthHeader, EthReceipt, EthRpcClient, EthSigner,
+};
+use super::exit_code::{self, BridgeError, ErrorKind};
+use super::fee;
+use super::intent::{IntentAuthorization, TransferIntent};
+use super::log::{
+    migrate_state, new_transfer_id, now_secs, AmountCheck, BalanceCheck, BurnBlock, DebugProof,
+    DryRun, EthTxCost, FromCkbLog, FromCkbLogStatus, LockBlock, LogStore, PendingEthTx, StepResult,
+    ToCkbLog, ToCkbLogStatus, TransferCounts, TransferEvent, TransferSummary, DIRECTION_FROM_CKB,
+    DIRECTION_TO_CKB,
+};
+use super::mint::{self, AmountLimits, CellDepInfo, MaxProofAge, ScriptGroups};
+use super::notify::{Transition, Webhook};
+use super::offline::{self, OfflineEthTx, OfflineTx};
+use super::price::{PriceSource, UsdEstimate};
+use super::progress::{Progress, ProgressMode};
+use super::proof::{
+    self, check_lock_event_abi, decode_receipt, DecodedLog, LockEvent, ProofBackend, ReceiptProof,
+};
+use super::reconcile::{nonce_gaps, reconcile_to_ckb, EthTxState, RecordedEthTx, ToCkbFacts};
+use super::registry::Registry;
+use super::relay::{
+    build_relay_tx, change_capacity, check_headers, gap_range, relay_start, LightClientCell,
+    LightClientTip, RelayCheckpoint,
+};
+use super::subscribe::{self, NewHeadsSubscription};
+use super::util::{
+    check_tx_size, send_transaction, sign_sighash_inputs, wait_tx_committed,
+    wait_tx_committed_with_backoff, CommitStatus, MAX_THROTTLE_WAIT,
+};
 use crate::plugin::PluginManager;
-use std::path::PathBuf;
-use crate::utils::index::IndexController;
-use ckb_index::{IndexDatabase, with_index_db};
-use crate::utils::other::{sync_to_tip, get_network_type, get_arg_value};
-use ckb_types::core::BlockView;
-use ckb_types::H256;
-use ckb_types::prelude::Unpack;
+use crate::subcommands::mock_tx::Loader;
 use crate::subcommands::{CliSubCommand, Output};
+use crate::utils::{
+    arg,
+    arg_parser::{
+        AddressParser, ArgParser, DurationParser, EitherParser, EitherValue, FilePathParser,
+        FixedHashParser, FromStrParser, HexParser, PrivkeyPathParser, PrivkeyWrapper, UrlParser,
+    },
+    index::{IndexController, IndexThreadState},
+    other::{
+        get_live_cell, get_live_cell_with_cache, get_max_mature_number, get_network_type,
+        is_mature, sync_to_tip,
+    },
+    poll::{poll_until, Poll, CANCELLED},
+    printer::is_a_tty,
+};
+
+// The environment variables of the options and the exit codes (after the help of ckb-bridge)
+const AFTER_HELP: &str = "ENVIRONMENT:
+    An option not given on the command line is read from the environment variable CKB_BRIDGE_
+    followed by its name in upper case with '-' replaced by '_', e.g. CKB_BRIDGE_ETH_RPC_URL for
+    --eth-rpc-url and CKB_BRIDGE_FEE_RATE for --fee-rate. Precedence: command line, environment,
+    config file, defaults. The options read from the environment are: --config, --data-dir,
+    --ckb-rpc-url, --eth-rpc-url, --ckb-rpc-timeout, --eth-rpc-timeout, --bridge-abi,
+    --erc20-abi, --proof-backend, --privkey-path, --eth-privkey-path, --eth-keystore, --tx-fee,
+    --fee-rate, --gas-price, --gas-limit, --gas-buffer-pct, --eth-chain-tag, --eth-access-list,
+    --confirmations-source, --mint-timeout, --burn-timeout, --ckb-poll-interval,
+    --max-proof-age, --ckb-reorg-confirmations, --registry-address, --address-cache-ttl.
+    Flags (without a value) are not read from the environment.
+    BRIDGE_ENABLE_REAL_TRANSFERS=1 is the same as --i-understand-the-risks, without it the
+    transfers run as dry runs (nothing is sent) and the deploy, relay and broadcast subcommands
+    are refused.
+
+EXIT CODES:
+    0 finished, 1 other errors (or a failed check), 3 not finished yet (run the same command
+    again later), 4 the transfer failed (resume-all: any step failed), 5 the bridge is not
+    deployed or the config is invalid, 6 the node is on another chain, 7 insufficient funds.";
+
+// Set to 1 to send real transactions, the same as `--i-understand-the-risks`
+const REAL_TRANSFERS_ENV: &str = "BRIDGE_ENABLE_REAL_TRANSFERS";
+// Subcommands run without a ckb node, the node is not checked, the data directory is not
+// migrated and the registry is not resolved for them
+const OFFLINE_SUBCOMMANDS: &[&str] = &["sign-tx", "sign-eth-tx", "decode-proof"];
+
+// Max time to wait a ckb transaction committed
+const CKB_TX_COMMIT_TIMEOUT: Duration = Duration::from_secs(600);
+// The cycles limit of the local script verification, the max cycles of a ckb block
+const MAX_VERIFY_CYCLES: u64 = 3_500_000_000;
+// Max time to wait an ethereum transaction mined
+const ETH_TX_TIMEOUT: Duration = Duration::from_secs(600);
+// Ethereum confirmations required before minting on ckb
+const ETH_CONFIRMATIONS: u64 = 15;
+// Max time to wait the lock block relayed to the light client with enough confirmations
+const BLOCK_SAFE_TIMEOUT: Duration = Duration::from_secs(1800);
+const LIGHT_CLIENT_POLL_INTERVAL: Duration = Duration::from_secs(15);
+// About one ckb block
+const CKB_BLOCK_POLL_INTERVAL: Duration = Duration::from_secs(8);
+// Max interval of the backoff polling of the mint transaction
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(60);
+// Ckb requests are mostly cheap queries on a trusted node, the mint polling is done by
+// repeated requests, so a long per-request timeout is fine
+const DEFAULT_CKB_RPC_TIMEOUT: &str = "60";
+// Ethereum queries (gas price, nonce, estimate) should fail fast on a slow public node
+const DEFAULT_ETH_RPC_TIMEOUT: &str = "10";
+// Ckb blocks on top of the burn block before the from-ckb proof is built
+const DEFAULT_CKB_REORG_CONFIRMATIONS: &str = "24";
+// A registry publishes a new deployment rarely, frequent runs share one resolution
+const DEFAULT_ADDRESS_CACHE_TTL: &str = "10m";
 
 pub struct CkbBridgeSubCommand<'a> {
     rpc_client: &'a mut HttpRpcClient,
     plugin_mgr: &'a mut PluginManager,
     genesis_info: Option<GenesisInfo>,
     index_dir: PathBuf,
+    bridge_dir: PathBuf,
     index_controller: IndexController,
     wait_for_sync: bool,
+    ckb_rpc_timeout: Duration,
+    eth_rpc_timeout: Duration,
+    // `--ckb-reorg-confirmations`, the finality of the burn block of a from-ckb transfer
+    ckb_reorg_confirmations: u64,
+    // `--bridge-abi` and `--erc20-abi`, override the files in the config
+    bridge_abi: Option<PathBuf>,
+    erc20_abi: Option<PathBuf>,
+    // `--proof-backend`, overrides the backend in the config
+    proof_backend: Option<String>,
+    // `--eth-rpc-url` and `--config`, override the url in the config and the config path
+    eth_rpc_url: Option<String>,
+    config_file: Option<PathBuf>,
+    // `--ckb-network`, the network of the transfer logs read by history and the summaries
+    ckb_network: Option<NetworkType>,
+    // The deployment resolved from `--registry-address` once per run, overrides the config
+    registry: Option<Registry>,
+    // `--address-cache-ttl`, the resolved registries and the contracts seen with code are
+    // reused for this long, zero disables the cache
+    address_cache_ttl: Duration,
+    // The lock amount limits of each token, read once per run
+    lock_limits: HashMap<H160, AmountLimits>,
+    // `--dev-genesis`, accept a genesis block without the multisig and dao system cells
+    dev_genesis: bool,
+    // `--quiet`, no informational messages on stderr and no output on success (except json)
+    quiet: bool,
+    // `--trim-amount-zeros`, drop the trailing zeros of the amounts shown in messages
+    trim_amount_zeros: bool,
+    // `--output-file` of ckb-cli, the `--watch` events are appended to it
+    output_file: Option<PathBuf>,
+    // `--i-understand-the-risks` or BRIDGE_ENABLE_REAL_TRANSFERS=1, send real transactions,
+    // otherwise the transfers are dry runs and the other state-changing subcommands are refused
+    real_transfers: bool,
+    // Non-fatal conditions of this run, added to the output
+    warnings: Vec<String>,
+    // The transaction simulated by the last step with --dry-run-ethereum or --dry-run-ckb
+    dry_run: Option<DryRun>,
+    // Why the last step is not done yet (the transaction it waits for is still pending)
+    pending: Option<String>,
+    // The script groups of the mint transaction built by the last step, --show-script-groups
+    script_groups: Option<ScriptGroups>,
 }
 
-pub struct ToCkbLog<'a> {
-    status: &'a mut ToCkbLogStatus,
-}
-
-pub struct FromCkbLog<'a> {
-    status: &'a mut FromCkbLogStatus,
-}
-
-#[derive(Clone, Copy, IntEnum, PartialEq, Debug)]
-pub enum ToCkbLogStatus {
-    UnKnow = 0,
-    Approved = 1,
-    Locked = 2,
-    ParseProof = 3,
-    WaitBlockSafe = 4,
-    Mint = 5,
-}
-
-#[derive(Clone, Copy, IntEnum, PartialEq, Debug)]
-pub enum FromCkbLogStatus {
-    UnKnow = 0,
-    Burned = 1,
-    ParseProof = 2,
-    WaitBlockSafe = 3,
-    Mint = 4,
-}
-
-
 impl<'a> CkbBridgeSubCommand<'a> {
     pub fn new(
         rpc_client: &'a mut HttpRpcClient,
         plugin_mgr: &'a mut PluginManager,
         genesis_info: Option<GenesisInfo>,
         index_dir: PathBuf,
+        bridge_dir: PathBuf,
         index_controller: IndexController,
         wait_for_sync: bool,
     ) -> CkbBridgeSubCommand<'a> {
@@ -61,11 +201,163 @@ impl<'a> CkbBridgeSubCommand<'a> {
             plugin_mgr,
             genesis_info,
             index_dir,
+            bridge_dir,
             index_controller,
             wait_for_sync,
+            ckb_rpc_timeout: Duration::from_secs(
+                DEFAULT_CKB_RPC_TIMEOUT.parse().expect("default timeout"),
+            ),
+            eth_rpc_timeout: Duration::from_secs(
+                DEFAULT_ETH_RPC_TIMEOUT.parse().expect("default timeout"),
+            ),
+            ckb_reorg_confirmations: DEFAULT_CKB_REORG_CONFIRMATIONS
+                .parse()
+                .expect("default confirmations"),
+            bridge_abi: None,
+            erc20_abi: None,
+            proof_backend: None,
+            eth_rpc_url: None,
+            config_file: None,
+            ckb_network: None,
+            registry: None,
+            address_cache_ttl: Duration::from_secs(0),
+            lock_limits: HashMap::new(),
+            dev_genesis: false,
+            quiet: false,
+            trim_amount_zeros: false,
+            output_file: None,
+            real_transfers: false,
+            warnings: Vec::new(),
+            dry_run: None,
+            pending: None,
+            script_groups: None,
+        }
+    }
+
+    /// An informational message on stderr, suppressed by `--quiet`
+    fn notice(&self, message: String) {
+        if !self.quiet {
+            eprintln!("{}", message);
+        }
+    }
+
+    fn print_breakpoint<S: std::fmt::Debug>(&self, transfer_id: &str, status: S) {
+        self.notice(format!(
+            "[BREAKPOINT] Transfer {} halted before the step of status {:?}, resume it with --transfer-id {}",
+            transfer_id, status, transfer_id
+        ));
+    }
+
+    /// Safe mode: turn the steps of `subcommand` into dry runs unless real transfers are enabled,
+    /// the build-only files are still written since they are not sent
+    fn safe_mode_dry_run(
+        &self,
+        subcommand: &str,
+        step_args: &mut ToCkbStepArgs,
+    ) -> Result<(), String> {
+        if self.real_transfers {
+            return Ok(());
+        }
+        if step_args.broadcast_signed.is_some() {
+            return self.require_real_transfers(&format!("{} --broadcast-signed", subcommand));
+        }
+        if step_args.eth_build_only.is_none() {
+            step_args.dry_run_ethereum = true;
+        }
+        if step_args.build_only.is_none() && step_args.dump_unsigned.is_none() {
+            step_args.dry_run_ckb = true;
+        }
+        self.notice(format!(
+            "Safe mode: {} runs as a dry run (--dry-run-ethereum --dry-run-ckb), nothing is sent. Pass --i-understand-the-risks or set {}=1 to send real transactions",
+            subcommand, REAL_TRANSFERS_ENV
+        ));
+        Ok(())
+    }
+
+    /// Safe mode: run the from-ckb steps (burn and unlock) as dry runs unless real transfers
+    /// are enabled
+    fn safe_mode_dry_run_from_ckb(&self, step_args: &mut FromCkbStepArgs) {
+        if self.real_transfers {
+            return;
+        }
+        step_args.dry_run_ckb = true;
+        step_args.dry_run_ethereum = true;
+        self.notice(format!(
+            "Safe mode: transfer-erc20-from-ckb runs as a dry run (--dry-run-ckb --dry-run-ethereum), nothing is sent. Pass --i-understand-the-risks or set {}=1 to send real transactions",
+            REAL_TRANSFERS_ENV
+        ));
+    }
+
+    /// Safe mode: refuse `subcommand`, which has no dry run, unless real transfers are enabled
+    fn require_real_transfers(&self, subcommand: &str) -> Result<(), String> {
+        if self.real_transfers {
+            Ok(())
+        } else {
+            Err(format!(
+                "Safe mode: {} sends real transactions, pass --i-understand-the-risks or set {}=1 to allow it",
+                subcommand, REAL_TRANSFERS_ENV
+            ))
         }
     }
 
+    /// Log a warning and add it to the output of this run
+    fn warn(&mut self, warning: String) {
+        log::warn!("{}", warning);
+        self.warnings.push(warning);
+    }
+
+    /// The output of a subcommand with the warnings of this run, an error is mapped to its
+    /// exit code (see `exit_code`)
+    fn finish(&mut self, result: Result<Output, String>) -> Output {
+        // The client is shared with the other commands (interactive mode), which never retry
+        self.rpc_client
+            .set_max_throttle_wait(Duration::from_secs(0));
+        let output = result.unwrap_or_else(|err| {
+            let exit_code = exit_code::of_error(&err);
+            Output::new_failure(err, exit_code)
+        });
+        output
+            .with_warnings(mem::take(&mut self.warnings))
+            .with_quiet(self.quiet)
+    }
+
+    fn warn_status_override<S: std::fmt::Debug>(&mut self, transfer_id: &str, from: S, to: S) {
+        self.warn(format!(
+            "Manual status override applied to transfer {}: {:?} => {:?}, a wrong status may cause double-spends",
+            transfer_id, from, to
+        ));
+    }
+
+    fn eth_client(&self, config: &BridgeConfig) -> Result<EthRpcClient, String> {
+        EthRpcClient::new(&config.eth_rpc_url, self.eth_rpc_timeout)
+    }
+
+    fn set_rpc_timeouts(&mut self, m: &ArgMatches) -> Result<(), String> {
+        let ckb_rpc_timeout: u64 =
+            FromStrParser::<u64>::default().from_matches(m, "ckb-rpc-timeout")?;
+        let eth_rpc_timeout: u64 =
+            FromStrParser::<u64>::default().from_matches(m, "eth-rpc-timeout")?;
+        if ckb_rpc_timeout == 0 || eth_rpc_timeout == 0 {
+            return Err("RPC timeout must be greater than 0".to_string());
+        }
+        self.ckb_rpc_timeout = Duration::from_secs(ckb_rpc_timeout);
+        self.rpc_client.set_timeout(self.ckb_rpc_timeout);
+        self.rpc_client.set_max_throttle_wait(MAX_THROTTLE_WAIT);
+        self.eth_rpc_timeout = Duration::from_secs(eth_rpc_timeout);
+        Ok(())
+    }
+
+    /// Check the ckb node given by `--ckb-rpc-url` is reachable before anything is done
+    fn check_ckb_rpc(&mut self, matches: &ArgMatches) -> Result<(), String> {
+        if let (_, Some(m)) = matches.subcommand() {
+            self.set_rpc_timeouts(m)?;
+        }
+        self.rpc_client
+            .get_blockchain_info()
+            .map(|_| ())
+            .map_err(|err| format!("Ckb node {} is unreachable: {}", self.rpc_client.url(), err))
+    }
+
     fn genesis_info(&mut self) -> Result<GenesisInfo, String> {
         if self.genesis_info.is_none() {
             let genesis_block: BlockView = self
@@ -73,14 +365,32 @@ impl<'a> CkbBridgeSubCommand<'a> {
                 .get_block_by_number(0)?
                 .expect("Can not get genesis block?")
                 .into();
-            self.genesis_info = Some(GenesisInfo::from_block(&genesis_block)?);
+            let genesis_info = if self.dev_genesis {
+                // The bridge only uses the sighash cells of the genesis block
+                let (genesis_info, missing) = GenesisInfo::from_dev_block(&genesis_block)?;
+                for err in missing {
+                    self.warn(format!(
+                        "Dev chain genesis: {}, not used by the bridge",
+                        err
+                    ));
+                }
+                genesis_info
+            } else {
+                GenesisInfo::from_block(&genesis_block).map_err(|err| {
+                    format!(
+                        "{}, use --dev-genesis on a custom dev chain without all the system cells",
+                        err
+                    )
+                })?
+            };
+            self.genesis_info = Some(genesis_info);
         }
         Ok(self.genesis_info.clone().unwrap())
     }
 
     fn with_db<F, T>(&mut self, func: F) -> Result<T, String>
-        where
-            F: FnOnce(IndexDatabase) -> T,
+    where
+        F: FnOnce(IndexDatabase) -> T,
     {
         if self.wait_for_sync {
             sync_to_tip(&self.index_controller)?;
@@ -88,29 +398,629 @@ impl<'a> CkbBridgeSubCommand<'a> {
         let network_type = get_network_type(self.rpc_client)?;
         let genesis_info = self.genesis_info()?;
         let genesis_hash: H256 = genesis_info.header().hash().unpack();
+        let db_dir = self.index_dir.join(format!("{:#x}", genesis_hash));
         with_index_db(&self.index_dir, genesis_hash, |backend, cf| {
             let db = IndexDatabase::from_db(backend, cf, network_type, genesis_info, false)?;
             Ok(func(db))
         })
-            .map_err(|_err| {
-                format!(
-                    "Index database may not ready, sync process: {}",
-                    self.index_controller.state().read().to_string()
-                )
-            })
+        .map_err(|err| index_db_error(err, &db_dir, &self.index_controller.state().read()))
+    }
+
+    /// Bridge state must not live in the index directory, which may be deleted to rebuild the
+    /// index. Any found there is moved to the data directory.
+    fn migrate_from_index_dir(&self) -> Result<(), String> {
+        for path in migrate_state(&self.index_dir, &self.bridge_dir)? {
+            log::info!(
+                "Migrated bridge state out of the index directory: {:?}",
+                path
+            );
+        }
+        Ok(())
+    }
+
+    fn config_path(&self) -> PathBuf {
+        self.config_file
+            .clone()
+            .unwrap_or_else(|| self.bridge_dir.join("config.json"))
+    }
+
+    /// The lock amount limits of the token in the config and the bridge contract (the stricter
+    /// ones), the contract is only queried once per token in a run
+    fn lock_limits(&mut self, config: &BridgeConfig, token: &H160) -> Result<AmountLimits, String> {
+        if let Some(limits) = self.lock_limits.get(token) {
+            return Ok(limits.clone());
+        }
+        let contract = config
+            .bridge_contract
+            .clone()
+            .ok_or_else(|| BridgeError::config("Bridge contract is not configured".to_string()))?;
+        let bridge_abi = config.abis()?.bridge;
+        let mut eth_client = self.eth_client(config)?;
+        let contract_limits = AmountLimits {
+            min: read_limit(
+                &mut eth_client,
+                &bridge_abi,
+                &contract,
+                "minLockAmount",
+                token,
+            )?,
+            // Zero means no maximum
+            max: read_limit(
+                &mut eth_client,
+                &bridge_abi,
+                &contract,
+                "maxLockAmount",
+                token,
+            )?
+            .filter(|max| *max > 0),
+        };
+        let limits = config.amount_limits(token).merge(contract_limits);
+        self.lock_limits.insert(token.clone(), limits.clone());
+        Ok(limits)
+    }
+
+    /// Load the config with the ABI files given in the arguments, the ABIs are checked here so
+    /// a wrong ABI fails before any transaction is sent
+    fn load_config(&self) -> Result<BridgeConfig, String> {
+        let mut config = BridgeConfig::load(&self.config_path())?;
+        if let Some(url) = self.eth_rpc_url.as_ref() {
+            config.eth_rpc_url = url.clone();
+        }
+        if self.bridge_abi.is_some() {
+            config.bridge_abi = self.bridge_abi.clone();
+        }
+        if self.erc20_abi.is_some() {
+            config.erc20_abi = self.erc20_abi.clone();
+        }
+        if config.bridge_abi.is_some() || config.erc20_abi.is_some() {
+            config.abis()?;
+        }
+        if self.proof_backend.is_some() {
+            config.proof_backend = self.proof_backend.clone();
+        }
+        config.proof_backend()?;
+        if let Some(registry) = self.registry.as_ref() {
+            registry.apply(&mut config);
+        }
+        Ok(config)
+    }
+
+    fn to_ckb_store(&mut self) -> Result<LogStore, String> {
+        self.log_store(DIRECTION_TO_CKB)
+    }
+
+    fn from_ckb_store(&mut self) -> Result<LogStore, String> {
+        self.log_store(DIRECTION_FROM_CKB)
+    }
+
+    /// Logs are namespaced by the network of the connected ckb node, logs of the old flat
+    /// layout are migrated into the current network.
+    fn log_store(&mut self, direction: &str) -> Result<LogStore, String> {
+        let network_type = get_network_type(self.rpc_client)?;
+        self.log_store_of(network_type, direction)
+    }
+
+    /// The logs read without sending anything (history and the summaries), the network is
+    /// `--ckb-network`, then `ckb_network` of the config, so no ckb node is needed with either,
+    /// then the connected node's
+    fn inspect_log_store(&mut self, direction: &str) -> Result<LogStore, String> {
+        let network_type = match self.ckb_network {
+            Some(network_type) => network_type,
+            None => match self.load_config()?.ckb_network()? {
+                Some(network_type) => network_type,
+                None => get_network_type(self.rpc_client)?,
+            },
+        };
+        self.log_store_of(network_type, direction)
+    }
+
+    fn log_store_of(&self, network_type: NetworkType, direction: &str) -> Result<LogStore, String> {
+        let store = LogStore::new(&self.bridge_dir, network_type, direction);
+        let migrated = store.migrate_flat(&self.bridge_dir.join(direction))?;
+        if migrated > 0 {
+            log::info!(
+                "Migrated {} {} transfer logs to network {}",
+                migrated,
+                direction,
+                network_type.to_str()
+            );
+        }
+        Ok(store)
     }
 
     pub fn subcommand() -> App<'static> {
         App::new("ckb-bridge")
             .about("ckb bridge cli tools")
+            .arg(
+                Arg::with_name("ckb-rpc-timeout")
+                    .long("ckb-rpc-timeout")
+                    .env("CKB_BRIDGE_CKB_RPC_TIMEOUT")
+                    .takes_value(true)
+                    .global(true)
+                    .default_value(DEFAULT_CKB_RPC_TIMEOUT)
+                    .validator(|input| FromStrParser::<u64>::default().validate(input))
+                    .about("Timeout (in seconds) of each ckb rpc request"),
+            )
+            .after_help(AFTER_HELP)
+            .arg(
+                Arg::with_name("eth-rpc-url")
+                    .long("eth-rpc-url")
+                    .env("CKB_BRIDGE_ETH_RPC_URL")
+                    .takes_value(true)
+                    .global(true)
+                    .validator(|input| UrlParser.validate(input))
+                    .about("The ethereum rpc url (http or ws), overrides the one in the config"),
+            )
+            .arg(
+                Arg::with_name("config")
+                    .long("config")
+                    .env("CKB_BRIDGE_CONFIG")
+                    .takes_value(true)
+                    .global(true)
+                    .validator(|input| FilePathParser::new(false).validate(input))
+                    .about("The bridge config file [default: config.json in the data directory]"),
+            )
+            .arg(
+                Arg::with_name("ckb-rpc-url")
+                    .long("ckb-rpc-url")
+                    .env("CKB_BRIDGE_CKB_RPC_URL")
+                    .takes_value(true)
+                    .global(true)
+                    .validator(|input| UrlParser.validate(input))
+                    .about("Send the ckb rpc requests of this command to this node instead of the global one (the local index is still synced from the global node)"),
+            )
+            .arg(
+                Arg::with_name("ckb-network")
+                    .long("ckb-network")
+                    .takes_value(true)
+                    .global(true)
+                    .possible_values(&["ckb", "ckb_testnet", "ckb_staging", "ckb_dev"])
+                    .about("The ckb network of the transfer logs listed by history and --summary-only, overrides ckb_network in the config [default: the network of the ckb node]"),
+            )
+            .arg(
+                Arg::with_name("eth-rpc-timeout")
+                    .long("eth-rpc-timeout")
+                    .env("CKB_BRIDGE_ETH_RPC_TIMEOUT")
+                    .takes_value(true)
+                    .global(true)
+                    .default_value(DEFAULT_ETH_RPC_TIMEOUT)
+                    .validator(|input| FromStrParser::<u64>::default().validate(input))
+                    .about("Timeout (in seconds) of each ethereum rpc request, should be short to fail fast on gas and nonce queries"),
+            )
+            .arg(
+                Arg::with_name("ckb-reorg-confirmations")
+                    .long("ckb-reorg-confirmations")
+                    .env("CKB_BRIDGE_CKB_REORG_CONFIRMATIONS")
+                    .takes_value(true)
+                    .global(true)
+                    .default_value(DEFAULT_CKB_REORG_CONFIRMATIONS)
+                    .validator(|input| FromStrParser::<u64>::default().validate(input))
+                    .about("Ckb blocks on top of the burn block before the from-ckb proof is built, the burn block is checked again before the unlock on ethereum and the proof is rebuilt if ckb reorged"),
+            )
+            .arg(
+                Arg::with_name("data-dir")
+                    .long("data-dir")
+                    .env("CKB_BRIDGE_DATA_DIR")
+                    .takes_value(true)
+                    .global(true)
+                    .validator(|input| FilePathParser::new(false).validate(input))
+                    .about("Directory of the bridge state (config and transfer logs), default ~/.ckb-cli/bridge. Kept apart from the index, which can be deleted and rebuilt"),
+            )
+            .arg(
+                Arg::with_name("dev-genesis")
+                    .long("dev-genesis")
+                    .global(true)
+                    .about("Acknowledge a custom dev chain: accept a genesis block without the multisig and dao system cells (only the sighash cells are required)"),
+            )
+            .arg(
+                Arg::with_name("quiet")
+                    .long("quiet")
+                    .short('q')
+                    .global(true)
+                    .about("No progress or informational messages, and no output on success (the json result is still printed with --output-format json, --watch prints only the events)"),
+            )
+            .arg(
+                Arg::with_name("trim-amount-zeros")
+                    .long("trim-amount-zeros")
+                    .global(true)
+                    .about("Drop the trailing zeros of the token amounts in messages (1.5 USDC instead of 1.500000 USDC), the json output is not affected"),
+            )
+            .arg(
+                Arg::with_name("i-understand-the-risks")
+                    .long("i-understand-the-risks")
+                    .global(true)
+                    .about("Send real transactions (also enabled by BRIDGE_ENABLE_REAL_TRANSFERS=1). Without it the transfers run as dry runs and deploy-sol, deploy-ckb, relay-header, broadcast-eth-tx and transfer-erc20-from-ckb are refused, the read-only subcommands are not affected"),
+            )
+            .arg(
+                Arg::with_name("bridge-abi")
+                    .long("bridge-abi")
+                    .env("CKB_BRIDGE_BRIDGE_ABI")
+                    .takes_value(true)
+                    .global(true)
+                    .validator(|input| FilePathParser::new(true).validate(input))
+                    .about("Json ABI file (or compiler artifact) of the bridge contract, overrides the config (the built-in ABI by default)"),
+            )
+            .arg(
+                Arg::with_name("registry-address")
+                    .long("registry-address")
+                    .env("CKB_BRIDGE_REGISTRY_ADDRESS")
+                    .takes_value(true)
+                    .global(true)
+                    .validator(|input| FixedHashParser::<H160>::default().validate(input))
+                    .about("Registry contract on ethereum publishing the canonical deployment, the bridge contract, the tokens and the light client it publishes override the config for this run"),
+            )
+            .arg(
+                Arg::with_name("address-cache-ttl")
+                    .long("address-cache-ttl")
+                    .env("CKB_BRIDGE_ADDRESS_CACHE_TTL")
+                    .takes_value(true)
+                    .global(true)
+                    .default_value(DEFAULT_ADDRESS_CACHE_TTL)
+                    .validator(|input| DurationParser.validate(input))
+                    .about("Reuse the registry resolved and the contracts seen with code by a run within this time (e.g. 30s, 10m, 1h), cached per network in the data directory, 0s always queries ethereum"),
+            )
+            .arg(
+                Arg::with_name("refresh-addresses")
+                    .long("refresh-addresses")
+                    .global(true)
+                    .about("Drop the address cache, the registry and the contract code are queried again"),
+            )
+            .arg(
+                Arg::with_name("proof-backend")
+                    .long("proof-backend")
+                    .env("CKB_BRIDGE_PROOF_BACKEND")
+                    .takes_value(true)
+                    .global(true)
+                    .validator(|input| proof::proof_backend(Some(input)).map(|_| ()))
+                    .about("The scheme of the lock proofs, overrides the config (receipt-mpt by default)"),
+            )
+            .arg(
+                Arg::with_name("erc20-abi")
+                    .long("erc20-abi")
+                    .env("CKB_BRIDGE_ERC20_ABI")
+                    .takes_value(true)
+                    .global(true)
+                    .validator(|input| FilePathParser::new(true).validate(input))
+                    .about("Json ABI file (or compiler artifact) of the erc20 tokens, overrides the config (the built-in ABI by default)"),
+            )
             .subcommands(vec![
-                App::new("transfer-erc20-to-ckb").about("transfer erc20 token from ethereum to ckb chain"),
+                App::new("transfer-erc20-to-ckb")
+                    .about("transfer erc20 token from ethereum to ckb chain")
+                    .args(&TransferArgs::args())
+                    .args(&ToCkbArgs::args())
+                    .args(&ToCkbStepArgs::args())
+                    .arg(
+                        Arg::with_name("price-source")
+                            .long("price-source")
+                            .takes_value(true)
+                            .validator(|input| UrlParser.validate(input))
+                            .about("Annotate the output with approximate USD values from this url (a json object from token address or symbol to USD price), informational only, ignored if unavailable"),
+                    ),
+                App::new("mint-from-lock")
+                    .about("Mint on ckb from a lock transaction sent by another tool (skip the approve and lock steps), the token, amount and recipient are read from the lock event")
+                    .args(&ToCkbStepArgs::mint_args())
+                    .arg(
+                        Arg::with_name("eth-tx-hash")
+                            .long("eth-tx-hash")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .about("The ethereum transaction calling lock() of the bridge contract"),
+                    )
+                    .arg(
+                        Arg::with_name("confirm-recipient")
+                            .long("confirm-recipient")
+                            .takes_value(true)
+                            .validator(|input| AddressParser::default().validate(input))
+                            .about("The ckb address the lock event mints to, without it the decoded recipient is shown and nothing is minted"),
+                    )
+                    .arg(
+                        Arg::with_name("pretty")
+                            .long("pretty")
+                            .about("Indent the streamed json objects (for interactive use)"),
+                    ),
+                App::new("sign-tx")
+                    .about("Sign the mint transaction written by --build-only (offline, no network access), send the result with --broadcast-signed")
+                    .arg(arg::privkey_path().required(true))
+                    .arg(
+                        Arg::with_name("tx-file")
+                            .long("tx-file")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .about("The unsigned transaction file written by --build-only"),
+                    )
+                    .arg(
+                        Arg::with_name("output")
+                            .long("output")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(false).validate(input))
+                            .about("Write the signed transaction to this file"),
+                    ),
+                App::new("sign-eth-tx")
+                    .about("Sign the approve or lock transaction written by --eth-build-only (offline, no network access), send the result with broadcast-eth-tx")
+                    .arg(
+                        Arg::with_name("eth-privkey-path")
+                            .long("eth-privkey-path")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| PrivkeyPathParser.validate(input))
+                            .about("Ethereum private key file path (only read first line)"),
+                    )
+                    .arg(
+                        Arg::with_name("tx-file")
+                            .long("tx-file")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .about("The unsigned transaction file written by --eth-build-only"),
+                    )
+                    .arg(
+                        Arg::with_name("output")
+                            .long("output")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(false).validate(input))
+                            .about("Write the signed transaction to this file"),
+                    ),
+                App::new("sign-transfer-intent")
+                    .about("Sign the intent of a transfer as the token holder, for an operator to relay it with --intent-signature (offline, no transaction is sent)")
+                    .args(&ToCkbArgs::recipient_args())
+                    .arg(
+                        Arg::with_name("eth-privkey-path")
+                            .long("eth-privkey-path")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| PrivkeyPathParser.validate(input))
+                            .about("Ethereum private key file path of the token holder (only read first line)"),
+                    )
+                    .arg(
+                        Arg::with_name("intent-nonce")
+                            .long("intent-nonce")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .about("A nonce not used by another intent of the holder"),
+                    )
+                    .arg(
+                        Arg::with_name("expires-in")
+                            .long("expires-in")
+                            .takes_value(true)
+                            .default_value("1h")
+                            .validator(|input| DurationParser.validate(input))
+                            .about("The intent expires after this duration"),
+                    ),
+                App::new("broadcast-eth-tx")
+                    .about("Send the approve or lock transaction signed by sign-eth-tx and record it in the transfer, then resume the transfer to continue")
+                    .arg(
+                        Arg::with_name("tx-file")
+                            .long("tx-file")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .about("The transaction file signed by sign-eth-tx"),
+                    ),
                 App::new("transfer-erc20-from-ckb")
-                    .about("transfer erc20 token from ckb chain to ethereum"),
+                    .about("transfer erc20 token from ckb chain to ethereum")
+                    .args(&TransferArgs::args())
+                    .args(&FromCkbArgs::args())
+                    .args(&FromCkbStepArgs::args()),
                 App::new("deploy-sol")
-                    .about("set btc difficulty cell and write the outpoint to config"),
+                    .about("Deploy the bridge contract on ethereum, verify the deployed runtime bytecode against the artifact and write the address to config (only if matched)")
+                    .args(&EthTxArgs::args())
+                    .arg(
+                        Arg::with_name("artifact")
+                            .long("artifact")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .about("The compiled bridge contract artifact (truffle/hardhat artifact or solc standard json output of the contract)"),
+                    )
+                    .arg(
+                        Arg::with_name("constructor-args")
+                            .long("constructor-args")
+                            .takes_value(true)
+                            .validator(|input| HexParser.validate(input))
+                            .about("The abi encoded constructor arguments (hex)"),
+                    ),
                 App::new("deploy-ckb")
-                    .about("deploy toCKB scripts"),
+                    .about("deploy toCKB scripts and create the light client cell, the deployed scripts are written to config")
+                    .args(&CkbTxArgs::args())
+                    .arg(
+                        Arg::with_name("bridge-script")
+                            .long("bridge-script")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .about("The toCKB script binary"),
+                    )
+                    .arg(
+                        Arg::with_name("light-client-script")
+                            .long("light-client-script")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .about("The light client script binary, guards both the lock and the type of the light client cell"),
+                    )
+                    .arg(
+                        Arg::with_name("type-id")
+                            .long("type-id")
+                            .about("Deploy the toCKB script with a type id, the token type scripts reference it by type hash (upgradable) instead of data hash"),
+                    ),
+                App::new("relay-header")
+                    .about("Relay ethereum headers to the ckb light client cell in ascending order from its tip, safe to run repeatedly (e.g. from cron)")
+                    .args(&CkbTxArgs::args())
+                    .arg(
+                        Arg::with_name("from-block")
+                            .long("from-block")
+                            .takes_value(true)
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .about("The first ethereum block number to relay (inclusive), already relayed headers are skipped and a gap after the light client tip is relayed first. Default is the block after the relay checkpoint (the last header relayed by relay-header)"),
+                    )
+                    .arg(
+                        Arg::with_name("to-block")
+                            .long("to-block")
+                            .takes_value(true)
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .about("The last ethereum block number to relay (inclusive), default is the ethereum tip"),
+                    )
+                    .arg(
+                        Arg::with_name("headers-per-tx")
+                            .long("headers-per-tx")
+                            .alias("batch-size")
+                            .takes_value(true)
+                            .default_value("10")
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .about("Max headers relayed in one ckb transaction, the range is relayed in chunks of this size one after another. Lower it if a relay transaction exceeds the size or cycle limit"),
+                    ),
+                App::new("resume-all")
+                    .about("Run exactly one step of every unfinished transfer of the connected ckb network, continue past failures (exit code 4 if any step failed, 0 if the rest are only waiting)")
+                    .args(&ToCkbStepArgs::args())
+                    .arg(FromCkbStepArgs::burn_timeout_arg())
+                    .arg(
+                        Arg::with_name("direction")
+                            .long("direction")
+                            .takes_value(true)
+                            .possible_values(&["to-ckb", "from-ckb"])
+                            .about("Only resume the transfers of this direction, default is both"),
+                    )
+                    .arg(
+                        Arg::with_name("max-concurrent")
+                            .long("max-concurrent")
+                            .takes_value(true)
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .about("Check the transfers waiting for a transaction or a safe block concurrently with at most this many rpc requests at a time (one by one by default), only the ready ones are stepped (transactions are still sent one by one)"),
+                    )
+                    .arg(
+                        Arg::with_name("progress")
+                            .long("progress")
+                            .takes_value(true)
+                            .possible_values(&["auto", "plain", "ndjson", "none"])
+                            .default_value("auto")
+                            .about("Progress written to stderr per transfer: auto (updated in place on a terminal, plain lines otherwise), plain, ndjson (one json object per transfer) or none"),
+                    ),
+                App::new("history")
+                    .about("List the transfer logs of the ckb network (--ckb-network, ckb_network in the config or the connected node) page by page, sorted by the last update (only the logs of the page are loaded)")
+                    .arg(
+                        Arg::with_name("direction")
+                            .long("direction")
+                            .takes_value(true)
+                            .possible_values(&["to-ckb", "from-ckb"])
+                            .about("Only list the transfers of this direction, default is both"),
+                    )
+                    .arg(
+                        Arg::with_name("limit")
+                            .long("limit")
+                            .takes_value(true)
+                            .default_value("20")
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .about("Max number of transfers in the page"),
+                    )
+                    .arg(
+                        Arg::with_name("offset")
+                            .long("offset")
+                            .takes_value(true)
+                            .default_value("0")
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .about("Number of transfers skipped before the page"),
+                    )
+                    .arg(
+                        Arg::with_name("sort")
+                            .long("sort")
+                            .takes_value(true)
+                            .possible_values(&["newest", "oldest"])
+                            .default_value("newest")
+                            .about("Sort by the last update time"),
+                    )
+                    .arg(
+                        Arg::with_name("count")
+                            .long("count")
+                            .conflicts_with_all(&["limit", "offset"])
+                            .about("Only report the total number of transfers"),
+                    )
+                    .arg(
+                        Arg::with_name("summary-only")
+                            .long("summary-only")
+                            .conflicts_with_all(&["limit", "offset", "sort", "count"])
+                            .about("Only report the aggregates of all the transfers: the counts by direction and status, the amount bridged by token and the fees paid"),
+                    ),
+                App::new("list-deployments")
+                    .about("List the configured bridge deployments and check they are live on chain (read-only)"),
+                App::new("doctor")
+                    .about("Check the rpc endpoints and all the configured bridge deployments (read-only, exit code is nonzero if any check failed)"),
+                App::new("tokens")
+                    .about("List the tokens configured for the bridge: symbol, erc20 address, decimals on both chains, limits and flags (read-only)")
+                    .arg(
+                        Arg::with_name("check")
+                            .long("check")
+                            .about("Check each token contract on ethereum has code and the configured symbol and decimals (exit code is nonzero if any check failed)"),
+                    )
+                    .arg(
+                        Arg::with_name("check-supply")
+                            .long("check-supply")
+                            .about("Compare the supply minted on ckb (live token cells in the index) with the balance of the bridge contract on ethereum, warn and exit nonzero if the minted supply is not backed"),
+                    ),
+                App::new("bridge-state")
+                    .about("Show the paused state, the owner and the limits of the bridge contract, read by the getters in its ABI (read-only)"),
+                App::new("bridge-info")
+                    .about("Overview of the bridge environment: the config, both chains, the deployments, the light client tip and the transfer counts (read-only)")
+                    .arg(
+                        Arg::with_name("summary-only")
+                            .long("summary-only")
+                            .about("Only report the aggregates of the transfers (as `history --summary-only`), the chains and the deployments are not checked"),
+                    ),
+                App::new("reconcile")
+                    .about("Correct the status of local transfer logs from the on-chain state of their transactions (never send any transaction)"),
+                App::new("list-pending-eth-txs")
+                    .about("Check the ethereum transactions of the unfinished transfers (pending, mined, reverted or dropped) ordered by sender and nonce, and report the nonce gaps blocking pending ones (read-only)"),
+                App::new("clean-locks")
+                    .about("Remove the transfer locks left by killed processes (whose process is gone), a locked transfer can not be stepped by another process")
+                    .arg(
+                        Arg::with_name("force")
+                            .long("force")
+                            .about("Remove all the transfer locks, including the ones of running processes"),
+                    ),
+                App::new("rebuild-proof")
+                    .about("Build the receipt proof of a transfer in Locked, ParseProof or WaitBlockSafe again against the current block of its lock transaction, after a reorg invalidated the stored proof")
+                    .arg(
+                        Arg::with_name("transfer-id")
+                            .long("transfer-id")
+                            .takes_value(true)
+                            .required(true)
+                            .about("The transfer to rebuild the proof"),
+                    ),
+                App::new("decode-proof")
+                    .about("Decode a receipt proof produced by ckb-bridge (no network access unless --proof-block-override is given)")
+                    .arg(
+                        Arg::with_name("proof")
+                            .long("proof")
+                            .takes_value(true)
+                            .required_unless("proof-file")
+                            .validator(|input| HexParser.validate(input))
+                            .about("The proof in hex"),
+                    )
+                    .arg(
+                        Arg::with_name("proof-file")
+                            .long("proof-file")
+                            .takes_value(true)
+                            .conflicts_with("proof")
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .about("The file contains the proof in hex"),
+                    )
+                    .arg(
+                        Arg::with_name("proof-block-override")
+                            .long("proof-block-override")
+                            .takes_value(true)
+                            .validator(|input| {
+                                EitherParser::new(
+                                    FixedHashParser::<H256>::default(),
+                                    FromStrParser::<u64>::default(),
+                                )
+                                .validate(input)
+                            })
+                            .about("Rebuild and check the proof against this ethereum block (number or hash) instead of the block in the proof, for reproducible debugging (requires ethereum rpc)"),
+                    ),
             ])
     }
 
@@ -122,92 +1032,4702 @@ impl<'a> CkbBridgeSubCommand<'a> {
         3. 通过上一步得到的tx_hash, 解析得到 spv proof data => status: parseProof
         4. 等待 eth block 达到 commit 状态 => status: waitBlockSafe
         5. 组装 ckb tx, 验证 eth spv proof => status: mint
-        6. issue token => status: UnKnow
+        6. issue token => status: finished
     */
-    pub fn transfer_to_ckb(&mut self) -> Result<Output, String>{
-        let log = load_to_ckb_log();
+    pub fn transfer_to_ckb(
+        &mut self,
+        transfer_args: TransferArgs,
+        to_ckb_args: Option<ToCkbArgs>,
+        step_args: ToCkbStepArgs,
+        mut price_source: Option<PriceSource>,
+        debug: bool,
+    ) -> Result<Output, String> {
+        let halt_on = transfer_args
+            .halt_on
+            .as_ref()
+            .map(|name| ToCkbLogStatus::from_name(name))
+            .transpose()?
+            // Stop when the unsigned mint transaction is written by --build-only or
+            // --dump-unsigned
+            .or_else(|| {
+                step_args
+                    .build_only
+                    .as_ref()
+                    .or_else(|| step_args.dump_unsigned.as_ref())
+                    .map(|_| ToCkbLogStatus::Signing)
+            });
+        let config = self.load_config()?;
+        self.check_bridge_deployed(&config)?;
+        let store = self.to_ckb_store()?;
+        let (mut log, _transfer_lock) = match (transfer_args.transfer_id, to_ckb_args) {
+            (Some(id), None) => {
+                let transfer_lock = store.lock(&id)?;
+                let mut log = store.load::<ToCkbLog>(&id)?;
+                if let Some(name) = transfer_args.start_from_status.as_ref() {
+                    let status = ToCkbLogStatus::from_name(name)?;
+                    self.warn_status_override(&log.id, log.status, status);
+                    log.set_status(status);
+                    store.save(&log.id, &log)?;
+                }
+                if let Some(name) = transfer_args.only_step.as_ref() {
+                    let status = ToCkbLogStatus::from_name(name)?;
+                    check_only_step(&log.id, log.status, status, ToCkbLogStatus::Finished)?;
+                }
+                (log, transfer_lock)
+            }
+            (None, Some(args)) => {
+                let token = config.resolve_token(&args.token)?;
+                let unit = config.token_unit(&token, self.trim_amount_zeros);
+                // Check before anything is locked on ethereum
+                mint::check_dust(args.amount, config.decimals_conversion(&token), &unit)?;
+                mint::check_precision(
+                    args.amount,
+                    config.decimals_conversion(&token),
+                    step_args.allow_precision_loss,
+                )?;
+                self.lock_limits(&config, &token)?
+                    .check(args.amount, &unit)?;
+                let intent = args
+                    .intent
+                    .as_ref()
+                    .map(|intent| verify_intent(&config, &token, &args, intent))
+                    .transpose()?;
+                let mut log = ToCkbLog::new(
+                    new_transfer_id(),
+                    token,
+                    args.amount,
+                    args.recipient.map(|address| address.to_string()),
+                    args.recipient_lock.into(),
+                );
+                log.decimals = config.token_decimals(&log.token);
+                log.deadline = args
+                    .deadline
+                    .map(|deadline| log.created_at + deadline.as_secs());
+                log.intent = intent;
+                let transfer_lock = store.lock(&log.id)?;
+                store.save(&log.id, &log)?;
+                (log, transfer_lock)
+            }
+            (Some(_), Some(_)) => {
+                return Err(
+                    "<token>, <amount> and the recipient can not be changed when resume a transfer"
+                        .to_string(),
+                );
+            }
+            (None, None) => {
+                return Err(
+                    "<token>, <amount> and the recipient are required to start a new transfer"
+                        .to_string(),
+                );
+            }
+        };
+        if self.skip_steps(&config, &step_args, &transfer_args, &mut log)? {
+            store.save(&log.id, &log)?;
+        }
+        let webhook = transfer_args.webhook_url.map(Webhook::new).transpose()?;
+        let ckb_fee = step_args.ckb_args.tx_fee;
+        if !transfer_args.watch {
+            if halt_on == Some(log.status) {
+                self.print_breakpoint(&log.id, log.status);
+                return Ok(Output::new_output(log.event(None)).with_exit_code(exit_code::PENDING));
+            }
+            let old_status = log.status;
+            // Always persist the log, the step may have sent a transaction before failing
+            let result = self.advance_to_ckb(&config, &step_args, &mut log, debug);
+            store.save(&log.id, &log)?;
+            let mut event = log.event(result?);
+            event.dry_run = self.dry_run.take();
+            event.pending = self.pending.take();
+            event.script_groups = self.script_groups.take();
+            if log.status != old_status {
+                notify_transition(webhook.as_ref(), DIRECTION_TO_CKB, old_status, &event);
+            }
+            event.usd_estimate = estimate_usd(price_source.as_mut(), &config, &log, ckb_fee);
+            let exit_code = exit_code::of_status(
+                log.status == ToCkbLogStatus::Finished || event.dry_run.is_some(),
+                log.status == ToCkbLogStatus::Failed,
+            );
+            return Ok(Output::new_output(event).with_exit_code(exit_code));
+        }
+
+        let mut event = log.event(None);
+        event.usd_estimate = estimate_usd(price_source.as_mut(), &config, &log, ckb_fee);
+        let mut dry_run = false;
+        print_event(&event, transfer_args.pretty, self.output_file.as_deref())?;
+        while !log.status.is_terminal() {
+            if halt_on == Some(log.status) {
+                self.print_breakpoint(&log.id, log.status);
+                break;
+            }
+            let old_status = log.status;
+            let result = self.advance_to_ckb(&config, &step_args, &mut log, debug);
+            store.save(&log.id, &log)?;
+            let mut event = log.event(result?);
+            event.dry_run = self.dry_run.take();
+            event.pending = self.pending.take();
+            event.script_groups = self.script_groups.take();
+            event.usd_estimate = estimate_usd(price_source.as_mut(), &config, &log, ckb_fee);
+            if log.status != old_status {
+                notify_transition(webhook.as_ref(), DIRECTION_TO_CKB, old_status, &event);
+            }
+            print_event(&event, transfer_args.pretty, self.output_file.as_deref())?;
+            // Stop when the unsigned approve or lock transaction is written by --eth-build-only,
+            // the step is simulated, or its transaction is still pending (the transfer stays in
+            // its status)
+            dry_run = event.dry_run.is_some();
+            if log.pending_eth_tx.is_some() || dry_run || event.pending.is_some() {
+                break;
+            }
+        }
+        if let Some(failure) = log.failure.as_ref() {
+            return Err(BridgeError::transfer_failed(format!(
+                "Transfer {} failed: {}",
+                log.id, failure
+            ))
+            .into());
+        }
+        // Halted at a breakpoint, waiting for the offline signature or a pending transaction
+        let exit_code = exit_code::of_status(log.status.is_terminal() || dry_run, false);
+        Ok(Output::new_success().with_exit_code(exit_code))
+    }
+
+    /// Advance the transfer past the steps done out-of-band (`--skip-approve`, `--skip-lock`),
+    /// only once their effect is confirmed on ethereum. Return true if any step is skipped.
+    fn skip_steps(
+        &mut self,
+        config: &BridgeConfig,
+        step_args: &ToCkbStepArgs,
+        transfer_args: &TransferArgs,
+        log: &mut ToCkbLog,
+    ) -> Result<bool, String> {
+        if !transfer_args.skip_approve && transfer_args.skip_lock.is_none() {
+            return Ok(false);
+        }
+        let contract = config
+            .bridge_contract
+            .clone()
+            .ok_or_else(|| BridgeError::config("Bridge contract is not configured".to_string()))?;
+        let unit = config.token_unit(&log.token, self.trim_amount_zeros);
+        if transfer_args.skip_approve {
+            if log.status != ToCkbLogStatus::UnKnow {
+                return Err(format!(
+                    "Can't skip the approve step, transfer {} is {:?}",
+                    log.id, log.status
+                ));
+            }
+            let owner = required_eth_args(&step_args.eth_args)?.from.clone();
+            let erc20_abi = config.abis()?.erc20;
+            let allowance = self.eth_client(config)?.call(
+                &owner,
+                &log.token,
+                &erc20::allowance_call(&erc20_abi, &owner, &contract)?,
+            )?;
+            let allowance = abi::decode_uint(allowance.as_bytes(), 0)?;
+            if allowance < log.amount {
+                return Err(format!(
+                    "Can't skip the approve step: the allowance of {} to the bridge contract is {}, less than the amount {}",
+                    checksum_address(&owner),
+                    unit.format(allowance),
+                    unit.format(log.amount)
+                ));
+            }
+            self.record_skipped_step(
+                log,
+                format!(
+                    "approve: the allowance of {} is {}",
+                    checksum_address(&owner),
+                    unit.format(allowance)
+                ),
+            );
+            log.set_status(ToCkbLogStatus::Approved);
+        }
+        if let Some(lock_tx) = transfer_args.skip_lock.as_ref() {
+            if log.status != ToCkbLogStatus::UnKnow && log.status != ToCkbLogStatus::Approved {
+                return Err(format!(
+                    "Can't skip the lock step, transfer {} is {:?}",
+                    log.id, log.status
+                ));
+            }
+            if log.lock_tx.is_some() || log.pending_eth_tx.is_some() {
+                return Err(format!(
+                    "Can't skip the lock step, transfer {} already has a lock transaction, resume it instead",
+                    log.id
+                ));
+            }
+            let lock_event = self.unused_lock_event(config, step_args, &contract, lock_tx)?;
+            let recipient_lock: Script = log.recipient_lock.clone().into();
+            if lock_event.token != log.token
+                || lock_event.recipient_lockscript != recipient_lock.as_slice()
+            {
+                return Err(format!(
+                    "Can't skip the lock step: the lock event of {:#x} is of another token or recipient than transfer {}",
+                    lock_tx, log.id
+                ));
+            }
+            let fee_on_transfer = config.fee_on_transfer(&log.token);
+            if !fee_on_transfer && lock_event.amount != log.amount {
+                return Err(format!(
+                    "Can't skip the lock step: {:#x} locked {}, not the amount {} of transfer {}",
+                    lock_tx,
+                    unit.format(lock_event.amount),
+                    unit.format(log.amount),
+                    log.id
+                ));
+            }
+            mint::mint_amount(log.amount, lock_event.amount, fee_on_transfer)?;
+            log.lock_tx = Some(lock_tx.clone());
+            self.record_skipped_step(log, format!("lock: locked by {:#x}", lock_tx));
+            log.set_status(ToCkbLogStatus::Locked);
+        }
+        Ok(true)
+    }
+
+    fn record_skipped_step(&mut self, log: &mut ToCkbLog, step: String) {
+        self.warn(format!(
+            "Transfer {}: skipped the step {} (done out-of-band, --force)",
+            log.id, step
+        ));
+        log.skipped_steps.push(step);
+    }
+
+    /// The lock event of a mined lock transaction not used by any transfer yet
+    fn unused_lock_event(
+        &mut self,
+        config: &BridgeConfig,
+        step_args: &ToCkbStepArgs,
+        contract: &H160,
+        lock_tx: &H256,
+    ) -> Result<LockEvent, String> {
+        check_lock_tx_unused(&self.to_ckb_store()?, lock_tx)?;
+
+        let receipt = self
+            .eth_client(config)?
+            .get_transaction_receipt(lock_tx)?
+            .ok_or_else(|| format!("Lock transaction {:#x} receipt not found", lock_tx))?;
+        if !receipt.is_success()? {
+            return Err(format!("Lock transaction {:#x} reverted", lock_tx));
+        }
+        if receipt.to.as_ref() != Some(contract) {
+            return Err(format!(
+                "Transaction {:#x} is not a call to the bridge contract {}",
+                lock_tx,
+                checksum_address(contract)
+            ));
+        }
+        let logs = receipt
+            .logs
+            .into_iter()
+            .map(DecodedLog::from)
+            .collect::<Vec<_>>();
+        lock_event_from_logs(config, step_args, contract, &logs)
+            .map_err(|err| format!("Transaction {:#x}: {}", lock_tx, err))
+    }
+
+    /// Start a transfer at `ParseProof` from a lock transaction sent by another tool, then
+    /// advance it until finished (as `--watch`). The same lock transaction is never minted
+    /// twice by ckb-bridge.
+    pub fn mint_from_lock(
+        &mut self,
+        lock_tx: H256,
+        step_args: ToCkbStepArgs,
+        confirm_recipient: Option<Address>,
+        pretty: bool,
+        debug: bool,
+    ) -> Result<Output, String> {
+        let config = self.load_config()?;
+        self.check_bridge_deployed(&config)?;
+        let contract = config
+            .bridge_contract
+            .clone()
+            .ok_or_else(|| BridgeError::config("Bridge contract is not configured".to_string()))?;
+        // The transfer (token, amount, recipient) is decoded from the lock event in the receipt,
+        // the proof is only built once it is found
+        let lock_event = self.unused_lock_event(&config, &step_args, &contract, &lock_tx)?;
+        let recipient_lock = Script::from_slice(&lock_event.recipient_lockscript)
+            .map_err(|err| format!("Invalid recipient lock script in the lock event: {}", err))?;
+        mint::check_recipient_lock(&recipient_lock)?;
+        mint::check_precision(
+            lock_event.amount,
+            config.decimals_conversion(&lock_event.token),
+            step_args.allow_precision_loss,
+        )?;
+        // The recipient is decided by the lock transaction, not by the user, it must be
+        // confirmed before anything is minted
+        let recipient = Address::new(
+            get_network_type(self.rpc_client)?,
+            AddressPayload::from(recipient_lock.clone()),
+        );
+        match confirm_recipient {
+            Some(address) => {
+                if Script::from(address.payload()) != recipient_lock {
+                    return Err(format!(
+                        "Recipient mismatch: {} in the lock event, {} confirmed, abort",
+                        recipient, address
+                    ));
+                }
+            }
+            None => {
+                let resp = serde_json::json!({
+                    "lock_tx": lock_tx,
+                    "token": checksum_address(&lock_event.token),
+                    "sender": checksum_address(&lock_event.sender),
+                    "amount": lock_event.amount.to_string(),
+                    "amount_decimal": config
+                        .token_decimals(&lock_event.token)
+                        .map(|decimals| mint::format_decimal(lock_event.amount, decimals)),
+                    "recipient": recipient.to_string(),
+                    "recipient_lock": ckb_jsonrpc_types::Script::from(recipient_lock),
+                    "confirmed": false,
+                });
+                self.notice(format!(
+                    "The lock event mints {} to {}, run again with --confirm-recipient {} to mint",
+                    config
+                        .token_unit(&lock_event.token, self.trim_amount_zeros)
+                        .format(lock_event.amount),
+                    recipient,
+                    recipient
+                ));
+                return Ok(Output::new_output(resp).with_exit_code(exit_code::ERROR));
+            }
+        }
+        let proof = self.build_lock_proof(&config, &lock_tx)?;
+        if parse_lock_event(&config, &step_args, &contract, &proof)? != lock_event {
+            return Err(format!(
+                "The lock event in the receipt proof of {:#x} differs from the receipt returned by the ethereum node, abort",
+                lock_tx
+            ));
+        }
+        let mut log = ToCkbLog::new(
+            new_transfer_id(),
+            lock_event.token,
+            lock_event.amount,
+            Some(recipient.to_string()),
+            recipient_lock.into(),
+        );
+        log.decimals = config.token_decimals(&log.token);
+        log.lock_tx = Some(lock_tx);
+        log.set_proof(proof.to_bytes(), lock_block(&proof));
+        log.set_status(ToCkbLogStatus::ParseProof);
+        let store = self.to_ckb_store()?;
+        // Another run may have created a transfer from the same lock transaction since the
+        // check above, the logs are searched again and the new one saved under the store lock
+        let store_lock = store.lock_store()?;
+        check_lock_tx_unused(&store, &lock_tx)?;
+        // Released before the transfer is resumed below, which takes the lock again
+        let transfer_lock = store.lock(&log.id)?;
+        store.save(&log.id, &log)?;
+        drop(transfer_lock);
+        drop(store_lock);
+        log::info!(
+            "Transfer {} created from lock transaction {:#x}",
+            log.id,
+            log.lock_tx.as_ref().expect("lock tx")
+        );
+
+        let transfer_args = TransferArgs {
+            transfer_id: Some(log.id),
+            watch: true,
+            pretty,
+            webhook_url: None,
+            start_from_status: None,
+            halt_on: None,
+            only_step: None,
+            skip_approve: false,
+            skip_lock: None,
+        };
+        self.transfer_to_ckb(transfer_args, None, step_args, None, debug)
+    }
+
+    /// Run one step of the transfer, return the transaction hash sent by the step (if any)
+    fn advance_to_ckb(
+        &mut self,
+        config: &BridgeConfig,
+        args: &ToCkbStepArgs,
+        log: &mut ToCkbLog,
+        debug: bool,
+    ) -> Result<Option<H256>, String> {
+        if log.check_deadline(now_secs()) {
+            self.warn(format!(
+                "Transfer {} failed: {}",
+                log.id,
+                log.failure.as_deref().unwrap_or_default()
+            ));
+            return Ok(None);
+        }
+        let ToCkbStepArgs {
+            eth_args,
+            eth_build_only,
+            dry_run_ethereum,
+            poll_args,
+            include_debug_proof,
+            ..
+        } = args;
+        let contract = config
+            .bridge_contract
+            .clone()
+            .ok_or_else(|| BridgeError::config("Bridge contract is not configured".to_string()))?;
         match log.status {
-            ToCkbLogStatus::UnKnow =>{
-                //TODO: do erc20 approve()
-            },
-            ToCkbLogStatus::Approved =>{
-                //TODO: do lock()
-            },
+            ToCkbLogStatus::UnKnow => {
+                let eth_args = required_eth_args(eth_args)?;
+                check_pending_eth_tx(log, eth_build_only.is_some())?;
+                let mut eth_client = self.eth_client(config)?;
+                let mut tx_hash = log.approve_tx.clone();
+                if let Some(tx_hash) = tx_hash.as_ref() {
+                    // Sent but not confirmed last time
+                    let receipt = eth_client.wait_receipt(tx_hash, None, ETH_TX_TIMEOUT)?;
+                    record_eth_cost(&mut eth_client, log, &receipt)?;
+                }
+                check_not_paused(config, &mut eth_client, &contract)?;
+                let unit = config.token_unit(&log.token, self.trim_amount_zeros);
+                mint::check_dust(log.amount, config.decimals_conversion(&log.token), &unit)?;
+                self.lock_limits(config, &log.token)?
+                    .check(log.amount, &unit)?;
+                let erc20_abi = config.abis()?.erc20;
+                let owner = eth_args.from.clone();
+                let allowance = eth_client.call(
+                    &owner,
+                    &log.token,
+                    &erc20::allowance_call(&erc20_abi, &owner, &contract)?,
+                )?;
+                let allowance = abi::decode_uint(allowance.as_bytes(), 0)?;
+                let plan = erc20::approve_plan(
+                    allowance,
+                    log.amount,
+                    config.requires_zero_first(&log.token),
+                );
+                let strict = config.strict_bool_return(&log.token);
+                for value in plan {
+                    let data = erc20::approve_call(&erc20_abi, &contract, value)?;
+                    // The return value is not in the receipt, check it by a call first
+                    let result = eth_client.call(&owner, &log.token, &data)?;
+                    erc20::check_bool_return("approve", result.as_bytes(), strict)?;
+                    if let Some(path) = eth_build_only.as_ref() {
+                        let token = log.token.clone();
+                        write_unsigned_eth_tx(
+                            &mut eth_client,
+                            eth_args,
+                            log,
+                            "approve",
+                            &token,
+                            data,
+                            path,
+                        )?;
+                        self.notice(unsigned_eth_tx_notice("approve", &log.id, path));
+                        return Ok(None);
+                    }
+                    if *dry_run_ethereum {
+                        let token = log.token.clone();
+                        self.dry_run_eth_tx(
+                            &mut eth_client,
+                            eth_args,
+                            &log.id,
+                            "approve",
+                            &token,
+                            data,
+                        )?;
+                        return Ok(None);
+                    }
+                    let (new_tx_hash, gas_limit) = eth_client.send_call(
+                        eth_args.signer()?,
+                        &eth_args.gas,
+                        &log.token,
+                        data,
+                    )?;
+                    log.approve_tx = Some(new_tx_hash.clone());
+                    let receipt =
+                        eth_client.wait_receipt(&new_tx_hash, Some(gas_limit), ETH_TX_TIMEOUT)?;
+                    record_eth_cost(&mut eth_client, log, &receipt)?;
+                    tx_hash = Some(new_tx_hash);
+                }
+                log.set_status(ToCkbLogStatus::Approved);
+                Ok(tx_hash)
+            }
+            ToCkbLogStatus::Approved => {
+                check_pending_eth_tx(log, eth_build_only.is_some())?;
+                let mut eth_client = self.eth_client(config)?;
+                let tx_hash = match log.lock_tx.clone() {
+                    Some(tx_hash) => {
+                        let receipt = eth_client.wait_receipt(&tx_hash, None, ETH_TX_TIMEOUT)?;
+                        record_eth_cost(&mut eth_client, log, &receipt)?;
+                        tx_hash
+                    }
+                    None => {
+                        let eth_args = required_eth_args(eth_args)?;
+                        check_not_paused(config, &mut eth_client, &contract)?;
+                        let unit = config.token_unit(&log.token, self.trim_amount_zeros);
+                        self.lock_limits(config, &log.token)?
+                            .check(log.amount, &unit)?;
+                        let recipient_lock: Script = log.recipient_lock.clone().into();
+                        let mut tokens = vec![
+                            Token::Address(log.token.clone()),
+                            Token::Uint(log.amount),
+                            Token::Bytes(recipient_lock.as_slice().to_vec()),
+                        ];
+                        if let Some(intent) = log.intent.as_ref() {
+                            intent.check_deadline(now_secs())?;
+                            tokens.push(Token::Bytes(intent.encode()));
+                        }
+                        let data = config
+                            .abis()?
+                            .bridge
+                            .function("lock")?
+                            .encode_call(&tokens)?;
+                        if let Some(path) = eth_build_only.as_ref() {
+                            write_unsigned_eth_tx(
+                                &mut eth_client,
+                                eth_args,
+                                log,
+                                "lock",
+                                &contract,
+                                data,
+                                path,
+                            )?;
+                            self.notice(unsigned_eth_tx_notice("lock", &log.id, path));
+                            return Ok(None);
+                        }
+                        if *dry_run_ethereum {
+                            self.dry_run_eth_tx(
+                                &mut eth_client,
+                                eth_args,
+                                &log.id,
+                                "lock",
+                                &contract,
+                                data,
+                            )?;
+                            return Ok(None);
+                        }
+                        let (tx_hash, gas_limit) = eth_client.send_call(
+                            eth_args.signer()?,
+                            &eth_args.gas,
+                            &contract,
+                            data,
+                        )?;
+                        log.lock_tx = Some(tx_hash.clone());
+                        let receipt =
+                            eth_client.wait_receipt(&tx_hash, Some(gas_limit), ETH_TX_TIMEOUT)?;
+                        record_eth_cost(&mut eth_client, log, &receipt)?;
+                        tx_hash
+                    }
+                };
+                log.set_status(ToCkbLogStatus::Locked);
+                Ok(Some(tx_hash))
+            }
             ToCkbLogStatus::Locked => {
-                // TODO: do parse proof
-            },
-            ToCkbLogStatus::ParseProof =>{
-                // TODO: do wait block safe
-            },
+                let lock_tx = log
+                    .lock_tx
+                    .clone()
+                    .ok_or_else(|| "Lock transaction is missing".to_string())?;
+                let proof = self.build_lock_proof(config, &lock_tx)?;
+                if args.strict_abi {
+                    parse_lock_event(config, args, &contract, &proof)?;
+                }
+                log.set_proof(proof.to_bytes(), lock_block(&proof));
+                log.set_status(ToCkbLogStatus::ParseProof);
+                Ok(None)
+            }
+            ToCkbLogStatus::ParseProof => {
+                let proof = parse_log_proof(log)?;
+                // A lock to another recipient than the transfer's is rejected before waiting
+                // for the block to be safe, not when the mint is built
+                let lock_event = parse_lock_event(config, args, &contract, &proof)?;
+                mint::check_recipient(
+                    &lock_event.recipient_lockscript,
+                    &log.recipient_lock.clone().into(),
+                )
+                .map_err(|err| format!("Transfer {}: {}", log.id, err))?;
+                if self.wait_block_safe(config, &proof, args.confirmations_source)? {
+                    log.set_status(ToCkbLogStatus::WaitBlockSafe);
+                } else {
+                    // Reorged, build the proof again
+                    log.clear_proof();
+                    log.set_status(ToCkbLogStatus::Locked);
+                }
+                Ok(None)
+            }
             ToCkbLogStatus::WaitBlockSafe => {
-                // TODO: do send ckb tx to verify spv proof
-            },
+                let proof = parse_log_proof(log)?;
+                self.check_proof_age(config, &proof, args.max_proof_age)
+                    .map_err(|err| {
+                        format!(
+                            "Transfer {}: {}, rebuild it with `rebuild-proof --transfer-id {}`",
+                            log.id, err, log.id
+                        )
+                    })?;
+                let balance_check = self.balance_before_mint(config, log);
+                let mut attempted_tx = None;
+                let result = self.send_mint_tx(
+                    config,
+                    args,
+                    &contract,
+                    &proof,
+                    log,
+                    &mut attempted_tx,
+                    debug,
+                );
+                match result {
+                    Ok(Some(tx_hash)) => {
+                        log.mint_tx = Some(tx_hash.clone());
+                        log.balance_check = Some(balance_check);
+                        log.debug_proof = None;
+                        log.set_status(ToCkbLogStatus::Mint);
+                        Ok(Some(tx_hash))
+                    }
+                    Ok(None) if args.dry_run_ckb => {
+                        log.debug_proof = None;
+                        Ok(None)
+                    }
+                    Ok(None) => {
+                        log.debug_proof = None;
+                        log.set_status(ToCkbLogStatus::Signing);
+                        Ok(None)
+                    }
+                    Err(err) => {
+                        if *include_debug_proof {
+                            log.debug_proof =
+                                Some(self.debug_proof(config, &proof, attempted_tx, &err));
+                        }
+                        Err(err)
+                    }
+                }
+            }
+            ToCkbLogStatus::Signing => {
+                let balance_check = self.balance_before_mint(config, log);
+                let tx_hash = self.broadcast_signed_mint_tx(args, log, debug)?;
+                log.unsigned_mint_tx = None;
+                log.mint_tx = Some(tx_hash.clone());
+                log.balance_check = Some(balance_check);
+                log.set_status(ToCkbLogStatus::Mint);
+                Ok(Some(tx_hash))
+            }
             ToCkbLogStatus::Mint => {
-                // TODO: do issue new token.
-            },
+                let tx_hash = log
+                    .mint_tx
+                    .clone()
+                    .ok_or_else(|| "Mint transaction is missing".to_string())?;
+                let status = wait_tx_committed_with_backoff(
+                    self.rpc_client,
+                    &tx_hash,
+                    poll_args.poll_interval,
+                    MAX_POLL_INTERVAL,
+                    poll_args.timeout,
+                )?;
+                match status {
+                    CommitStatus::Committed => {
+                        let fee = self.ckb_tx_fee(&tx_hash)?;
+                        log.record_ckb_fee(fee);
+                        // The mint is committed whatever the balance is, a mismatch is only
+                        // reported to be investigated
+                        if let Err(err) = self.verify_minted_balance(config, log, &tx_hash) {
+                            let warning = format!(
+                                "Transfer {}: {}, the mint transaction {:#x} is committed, please investigate",
+                                log.id, err, tx_hash
+                            );
+                            self.warn(warning.clone());
+                            log.warning = Some(warning);
+                        }
+                        log.set_status(ToCkbLogStatus::Finished);
+                        Ok(Some(tx_hash))
+                    }
+                    CommitStatus::Pending => {
+                        // The status stays Mint, the next run polls the same transaction
+                        self.pending = Some(format!(
+                            "Mint transaction {:#x} is still pending after {}s, resume later with --transfer-id {}",
+                            tx_hash,
+                            poll_args.timeout.as_secs(),
+                            log.id
+                        ));
+                        Ok(Some(tx_hash))
+                    }
+                    CommitStatus::Unknown => {
+                        // Evicted from the tx pool, build the mint transaction again
+                        log::warn!("Mint transaction {:#x} is unknown, rebuild it", tx_hash);
+                        log.mint_tx = None;
+                        log.set_status(ToCkbLogStatus::WaitBlockSafe);
+                        Ok(None)
+                    }
+                }
+            }
+            ToCkbLogStatus::Finished | ToCkbLogStatus::Failed => Ok(None),
         }
-        Ok(Output::new_output("finished to transfer erc20 to ckb."))
     }
 
-    pub fn transfer_from_ckb(&mut self) -> Result<Output, String>{
-        let log = load_from_ckb_log();
-        match log.status {
-            FromCkbLogStatus::UnKnow =>{
-                //TODO: do erc20 approve()
-            },
-            FromCkbLogStatus::Burned =>{
-                //TODO: do lock()
-            },
-            FromCkbLogStatus::ParseProof =>{
-                // TODO: do wait block safe
-            },
-            FromCkbLogStatus::WaitBlockSafe => {
-                // TODO: do send ckb tx to verify spv proof
-            },
-            FromCkbLogStatus::Mint => {
-                // TODO: do issue new token.
-            },
+    /// The recipient's token balance before the mint transaction is sent, not recorded if the
+    /// index can not be read
+    fn balance_before_mint(&mut self, config: &BridgeConfig, log: &ToCkbLog) -> BalanceCheck {
+        let before = match self.recipient_token_balance(config, log) {
+            Ok((balance, _)) => Some(balance.to_string()),
+            Err(err) => {
+                log::warn!("Read the recipient's token balance error: {}", err);
+                None
+            }
+        };
+        BalanceCheck {
+            before,
+            ..Default::default()
         }
-        Ok(Output::new_output("finished to transfer erc20 from ckb."))
     }
 
-    pub fn deploy_sol(&mut self) {
-        todo!()
+    /// Check the minted token cell is live in the index and the recipient's token balance grew
+    /// by the minted amount, the observed balance is recorded in `balance_check`
+    fn verify_minted_balance(
+        &mut self,
+        config: &BridgeConfig,
+        log: &mut ToCkbLog,
+        mint_tx: &H256,
+    ) -> Result<(), String> {
+        let minted: u128 = log
+            .amount_check
+            .as_ref()
+            .ok_or_else(|| "the minted amount is not recorded".to_string())?
+            .minted
+            .parse()
+            .map_err(|err| format!("invalid minted amount: {}", err))?;
+        // The index must include the block of the mint transaction
+        sync_to_tip(&self.index_controller)?;
+        let (after, cells) = self.recipient_token_balance(config, log)?;
+        let minted_cell_live = cells
+            .iter()
+            .any(|cell| &cell.tx_hash == mint_tx && cell.output_index == 0);
+        let check = log.balance_check.get_or_insert_with(Default::default);
+        check.after = Some(after.to_string());
+        check.minted_cell_live = Some(minted_cell_live);
+        if !minted_cell_live {
+            return Err(format!(
+                "the minted token cell {:#x}:0 is not live",
+                mint_tx
+            ));
+        }
+        match check.before.as_ref() {
+            Some(before) => {
+                let before = before
+                    .parse()
+                    .map_err(|err| format!("invalid balance before the mint: {}", err))?;
+                mint::check_balance_increase(before, after, minted)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// The recipient's balance of the transfer's token (the live token cells with the
+    /// recipient lock in the index) and those cells
+    fn recipient_token_balance(
+        &mut self,
+        config: &BridgeConfig,
+        log: &ToCkbLog,
+    ) -> Result<(u128, Vec<LiveCellInfo>), String> {
+        let bridge_script = config
+            .bridge_script
+            .as_ref()
+            .ok_or_else(|| BridgeError::config("Bridge script is not configured".to_string()))?;
+        let type_hash = mint::token_type_script(bridge_script, &log.token).calc_script_hash();
+        let lock_hash: H256 = Script::from(log.recipient_lock.clone())
+            .calc_script_hash()
+            .unpack();
+        let cells = self.with_db(|db| {
+            db.get_live_cells_by_type(type_hash, None, |_, cell| {
+                (false, cell.lock_hash == lock_hash)
+            })
+        })?;
+        let mut balance = 0u128;
+        for cell in &cells {
+            let (_, data) = get_live_cell(self.rpc_client, cell.out_point(), true)?;
+            balance = balance
+                .checked_add(mint::token_amount(&data)?)
+                .ok_or_else(|| "Token balance overflow".to_string())?;
+        }
+        Ok((balance, cells))
+    }
+
+    /// Check the lock event in the proof, then build and send the mint transaction. The built
+    /// transaction is kept in `attempted_tx` even if it fails to be sent. With `--build-only`
+    /// the unsigned transaction is written to the file instead and `None` is returned.
+    #[allow(clippy::too_many_arguments)]
+    fn send_mint_tx(
+        &mut self,
+        config: &BridgeConfig,
+        step_args: &ToCkbStepArgs,
+        contract: &H160,
+        proof: &ReceiptProof,
+        log: &mut ToCkbLog,
+        attempted_tx: &mut Option<TransactionView>,
+        debug: bool,
+    ) -> Result<Option<H256>, String> {
+        let lock_event = parse_lock_event(config, step_args, contract, proof)?;
+        if lock_event.token != log.token {
+            return Err(format!(
+                "Token mismatch: locked {}, but going to mint {}, abort",
+                checksum_address(&lock_event.token),
+                checksum_address(&log.token)
+            ));
+        }
+        let amount = mint::mint_amount(
+            log.amount,
+            lock_event.amount,
+            config.fee_on_transfer(&log.token),
+        )?;
+        let unit = config.token_unit(&log.token, self.trim_amount_zeros);
+        if amount != log.amount {
+            log::info!(
+                "Transfer {}: fee-on-transfer token {}, requested {}, locked {}",
+                log.id,
+                checksum_address(&log.token),
+                unit.format(log.amount),
+                unit.format(amount)
+            );
+        }
+        let conversion = config.decimals_conversion(&log.token);
+        // The locked amount of a fee-on-transfer token may be dust
+        mint::check_dust(amount, conversion, &unit)?;
+        let truncated = mint::check_precision(amount, conversion, step_args.allow_precision_loss)?;
+        if let Some(truncated) = truncated {
+            self.warn(format!(
+                "Transfer {}: {} of the amount is truncated on ckb (--allow-precision-loss)",
+                log.id,
+                unit.format(truncated)
+            ));
+        }
+        log.truncated_amount = truncated.map(|truncated| truncated.to_string());
+        let (amount, expected_amount) = match conversion {
+            Some((decimals, ckb_decimals)) => (
+                mint::convert_decimals(amount, decimals, ckb_decimals)?.0,
+                mint::convert_decimals(lock_event.amount, decimals, ckb_decimals)?.0,
+            ),
+            None => (amount, lock_event.amount),
+        };
+        let token_cell = mint::token_cell(
+            config.bridge_script.as_ref().ok_or_else(|| {
+                BridgeError::config(
+                    "Bridge script is not configured, please run deploy-ckb first".to_string(),
+                )
+            })?,
+            &log.token,
+            amount,
+            log.recipient_lock.clone().into(),
+        )?;
+        let payer = step_args.ckb_args.address.to_string();
+        let (tx, fee_idxs) = self
+            .build_mint_tx(config, &step_args.ckb_args, proof, token_cell)
+            .map_err(|err| {
+                if exit_code::kind_of(&err) == Some(ErrorKind::InsufficientFunds) {
+                    format!(
+                        "{}, resume transfer {} with another --privkey-path (or --from-address) to pay the mint from a different account",
+                        err, log.id
+                    )
+                } else {
+                    err
+                }
+            })?;
+        if let Some(previous) = log
+            .mint_payer
+            .as_ref()
+            .filter(|previous| **previous != payer)
+        {
+            self.notice(format!(
+                "Transfer {}: the mint transaction is rebuilt with the fee paid by {} instead of {}, the proof and the token cell are unchanged",
+                log.id, payer, previous
+            ));
+        }
+        *attempted_tx = Some(tx.clone());
+        // The minted amount must equal the amount in the lock event (not the one in
+        // the local log, scaled to the ckb decimals), in case of any encoding or parsing bug
+        let minted_amount = mint::token_amount(
+            &tx.outputs_data()
+                .get(0)
+                .expect("token cell data")
+                .raw_data(),
+        )?;
+        log.amount_check = Some(AmountCheck::check(expected_amount, minted_amount)?);
+        if step_args.show_script_groups {
+            self.script_groups = Some(self.tx_script_groups(&tx)?);
+        }
+        if let Some(path) = step_args.build_only.as_ref() {
+            // The signature added offline is small, fail before the offline round trip
+            check_tx_size(&tx)?;
+            let offline_tx = OfflineTx::new(
+                log.id.clone(),
+                step_args.ckb_args.sighash_args(),
+                fee_idxs,
+                &tx,
+            );
+            offline_tx.write(path)?;
+            log.unsigned_mint_tx = Some(offline_tx.tx_hash());
+            log.mint_payer = Some(payer);
+            self.notice(format!(
+                "The unsigned mint transaction of transfer {} is written to {:?}, sign it offline with sign-tx, then send it with --broadcast-signed",
+                log.id, path
+            ));
+            return Ok(None);
+        }
+        if let Some(path) = step_args.dump_unsigned.as_ref() {
+            check_tx_size(&tx)?;
+            offline::write_tx_file(path, &tx, get_network_type(self.rpc_client)?)?;
+            log.unsigned_mint_tx = Some(tx.hash().unpack());
+            log.mint_payer = Some(payer);
+            self.notice(format!(
+                "The unsigned mint transaction of transfer {} is written to {:?}, add the signatures with `ckb-cli tx sign-inputs --add-signatures`, then send it with --broadcast-signed",
+                log.id, path
+            ));
+            return Ok(None);
+        }
+        let tx = sign_sighash_inputs(
+            tx,
+            step_args.ckb_args.privkey()?.clone(),
+            step_args.ckb_args.sighash_args(),
+            &fee_idxs,
+        )?;
+        if step_args.dry_run_ckb {
+            let cycles = self.simulate_tx("mint", &log.id, &tx)?;
+            let tx_hash: H256 = tx.hash().unpack();
+            self.notice(format!(
+                "Dry run (ckb): the mint transaction {:#x} of transfer {} is not sent",
+                tx_hash, log.id
+            ));
+            self.dry_run = Some(DryRun {
+                side: "ckb".to_string(),
+                step: "mint".to_string(),
+                tx: serde_json::json!({
+                    "tx_hash": tx_hash,
+                    "size": tx.data().serialized_size_in_block(),
+                    "cycles": cycles,
+                }),
+            });
+            return Ok(None);
+        }
+        if step_args.simulate_mint {
+            self.simulate_tx("mint", &log.id, &tx)?;
+        }
+        let proof_used_error = config
+            .bridge_script
+            .as_ref()
+            .and_then(|bridge_script| bridge_script.proof_used_error);
+        match send_transaction(self.rpc_client, &tx, debug) {
+            Ok(tx_hash) => {
+                log.mint_payer = Some(payer);
+                Ok(Some(tx_hash))
+            }
+            Err(err) if mint::is_proof_used_error(&err, proof_used_error) => {
+                // Minted by a previous run which failed before recording the transaction, the
+                // payer of that run is kept
+                let tx_hash = self.find_mint_tx(config, &log.token, proof)?.ok_or_else(|| {
+                    format!(
+                        "{}, the proof is already used but no live token cell is minted by it (spent already?), check the transfer manually",
+                        err
+                    )
+                })?;
+                log::info!(
+                    "Transfer {}: the proof is already used by mint transaction {:#x}",
+                    log.id,
+                    tx_hash
+                );
+                Ok(Some(tx_hash))
+            }
+            Err(err) if mint::is_cycles_exceeded_error(&err) => Err(cycles_exceeded_error(&err)),
+            Err(err) => Err(err),
+        }
     }
 
-    pub fn deploy_ckb(&mut self) {
-        todo!()
+    /// Build the ethereum transaction of the step (nonce, gas) without sending it, it is
+    /// reported in the step output
+    fn dry_run_eth_tx(
+        &mut self,
+        eth_client: &mut EthRpcClient,
+        eth_args: &EthTxArgs,
+        transfer_id: &str,
+        step: &str,
+        to: &H160,
+        data: Vec<u8>,
+    ) -> Result<(), String> {
+        let (tx, chain_id) =
+            eth_client.build_transaction(&eth_args.from, &eth_args.gas, Some(to), data)?;
+        self.notice(format!(
+            "Dry run (ethereum): the {} transaction of transfer {} is not sent",
+            step, transfer_id
+        ));
+        self.dry_run = Some(DryRun {
+            side: "ethereum".to_string(),
+            step: step.to_string(),
+            tx: serde_json::json!({
+                "from": checksum_address(&eth_args.from),
+                "to": tx.to.as_ref().map(checksum_address),
+                "nonce": tx.nonce,
+                "gas_price": tx.gas_price,
+                "gas_limit": tx.gas_limit,
+                "chain_id": chain_id,
+                "data": JsonBytes::from_vec(tx.data.clone()),
+                "access_list": tx.access_list,
+            }),
+        });
+        Ok(())
     }
 
+    /// The script groups of a built transaction, the inputs and cell deps are resolved from the
+    /// ckb node
+    fn tx_script_groups(&mut self, tx: &TransactionView) -> Result<ScriptGroups, String> {
+        let mut inputs = Vec::new();
+        for input in tx.inputs() {
+            let (output, _) = get_live_cell(self.rpc_client, input.previous_output(), false)?;
+            inputs.push(output);
+        }
+        let mut cell_deps = Vec::new();
+        for cell_dep in tx.cell_deps() {
+            let out_point = cell_dep.out_point();
+            let (output, data) = get_live_cell(self.rpc_client, out_point.clone(), true)?;
+            let dep_type = if cell_dep.dep_type() == DepType::DepGroup.into() {
+                "dep_group"
+            } else {
+                "code"
+            };
+            cell_deps.push(CellDepInfo {
+                out_point: out_point.into(),
+                dep_type: dep_type.to_string(),
+                data_hash: H256(blake2b_256(&data)),
+                type_hash: output
+                    .type_()
+                    .to_opt()
+                    .map(|type_script| type_script.calc_script_hash().unpack()),
+            });
+        }
+        let outputs = tx.outputs().into_iter().collect::<Vec<_>>();
+        Ok(ScriptGroups {
+            groups: mint::script_groups(&inputs, &outputs, &cell_deps),
+            cell_deps,
+        })
+    }
 
+    /// Run the scripts of the signed mint or burn transaction (`step`) locally, the inputs and
+    /// cell deps are loaded from the ckb node. A failed script is reported with its error code.
+    fn simulate_tx(
+        &mut self,
+        step: &str,
+        transfer_id: &str,
+        tx: &TransactionView,
+    ) -> Result<u64, String> {
+        let mut mock_tx = MockTransaction {
+            mock_info: MockInfo::default(),
+            tx: tx.data(),
+        };
+        let loader = Loader {
+            rpc_client: self.rpc_client,
+        };
+        match MockTransactionHelper::new(&mut mock_tx).verify(MAX_VERIFY_CYCLES, loader) {
+            Ok(cycles) => {
+                self.notice(format!(
+                    "The {} transaction {} of transfer {} passed the local script verification, cycles: {}",
+                    step,
+                    tx.hash(),
+                    transfer_id,
+                    cycles
+                ));
+                if let Some(warning) = mint::cycles_warning(cycles, MAX_VERIFY_CYCLES) {
+                    self.warn(warning);
+                }
+                Ok(cycles)
+            }
+            Err(err) if mint::is_cycles_exceeded_error(&err) => Err(cycles_exceeded_error(&err)),
+            Err(err) => Err(format!(
+                "The {} transaction of transfer {} failed the local script verification (script error code: {}), it is not sent: {}",
+                step,
+                transfer_id,
+                mint::script_error_code(&err)
+                    .map(|code| code.to_string())
+                    .unwrap_or_else(|| "none".to_string()),
+                err
+            )),
+        }
+    }
 
+    /// Find the mint transaction of the proof from the live token cells, the proof is in the
+    /// witness of the transaction
+    fn find_mint_tx(
+        &mut self,
+        config: &BridgeConfig,
+        token: &H160,
+        proof: &ReceiptProof,
+    ) -> Result<Option<H256>, String> {
+        let bridge_script = config.bridge_script.as_ref().ok_or_else(|| {
+            BridgeError::config(
+                "Bridge script is not configured, please run deploy-ckb first".to_string(),
+            )
+        })?;
+        let type_hash = mint::token_type_script(bridge_script, token).calc_script_hash();
+        let cells = self.with_db(|db| {
+            db.get_live_cells_by_type(type_hash.clone(), None, |_, _| (false, true))
+        })?;
+        // The cells of a transaction are not adjacent, fetch each transaction once
+        let tx_hashes = cells
+            .iter()
+            .map(|cell| cell.tx_hash.clone())
+            .collect::<BTreeSet<_>>();
+        for tx_hash in tx_hashes {
+            let tx = match self.rpc_client.get_transaction(tx_hash.clone())? {
+                Some(tx_with_status) => tx_with_status.transaction.inner,
+                None => continue,
+            };
+            let minted_proof = tx
+                .witnesses
+                .get(0)
+                .and_then(|witness| mint::witness_proof(witness.as_bytes()));
+            if let Some(minted_proof) = minted_proof {
+                if mint::same_proof(&minted_proof, proof) {
+                    return Ok(Some(tx_hash));
+                }
+            }
+        }
+        Ok(None)
+    }
 
-}
+    /// Send the mint transaction signed offline, it must be the one built by `--build-only`
+    /// (signed by `sign-tx`) or `--dump-unsigned` (signed by `ckb-cli tx sign-inputs`)
+    fn broadcast_signed_mint_tx(
+        &mut self,
+        step_args: &ToCkbStepArgs,
+        log: &ToCkbLog,
+        debug: bool,
+    ) -> Result<H256, String> {
+        let unsigned_hash = log
+            .unsigned_mint_tx
+            .clone()
+            .ok_or_else(|| "Unsigned mint transaction is missing".to_string())?;
+        let path = step_args.broadcast_signed.as_ref().ok_or_else(|| {
+            format!(
+                "Transfer {} waits for the mint transaction {:#x} signed offline, sign it with sign-tx and send it with --broadcast-signed",
+                log.id, unsigned_hash
+            )
+        })?;
+        // The ckb-cli tx file has no transfer id, the transaction hash is checked below
+        if let Ok(helper) = offline::read_tx_file(path) {
+            let tx_hash: H256 = helper.transaction().hash().unpack();
+            if tx_hash != unsigned_hash {
+                return Err(format!(
+                    "The transaction {:#x} in {:?} is not the built mint transaction {:#x}, it may be modified after --dump-unsigned",
+                    tx_hash,
+                    path,
+                    unsigned_hash
+                ));
+            }
+            let mut live_cell_cache = HashMap::new();
+            let rpc_client = &mut *self.rpc_client;
+            let tx = helper.build_tx(
+                |out_point, with_data| {
+                    get_live_cell_with_cache(&mut live_cell_cache, rpc_client, out_point, with_data)
+                        .map(|(output, _)| output)
+                },
+                false,
+            )?;
+            return send_transaction(self.rpc_client, &tx, debug);
+        }
+        let offline_tx = OfflineTx::read(path)?;
+        if offline_tx.transfer_id != log.id {
+            return Err(format!(
+                "The signed transaction in {:?} belongs to transfer {}, not {}",
+                path, offline_tx.transfer_id, log.id
+            ));
+        }
+        if offline_tx.tx_hash() != unsigned_hash {
+            return Err(format!(
+                "The signed transaction {:#x} is not the built mint transaction {:#x}, it may be modified after --build-only",
+                offline_tx.tx_hash(),
+                unsigned_hash
+            ));
+        }
+        if !offline_tx.signed {
+            return Err(format!(
+                "The transaction in {:?} is not signed, sign it with sign-tx first",
+                path
+            ));
+        }
+        send_transaction(self.rpc_client, &offline_tx.tx_view(), debug)
+    }
 
-fn load_to_ckb_log() -> ToCkbLog {
-    ToCkbLog{ status: &mut ToCkbLogStatus::UnKnow }
-}
+    /// Collect the diagnostics of a failed mint step, the ethereum header is fetched best
+    /// effort
+    fn debug_proof(
+        &mut self,
+        config: &BridgeConfig,
+        proof: &ReceiptProof,
+        ckb_tx: Option<TransactionView>,
+        error: &str,
+    ) -> DebugProof {
+        let block_header = self
+            .eth_client(config)
+            .and_then(|mut eth_client| eth_client.get_header_by_hash(&proof.block_hash))
+            .unwrap_or_else(|err| {
+                log::warn!(
+                    "Get ethereum header {:#x} of the debug proof failed: {}",
+                    proof.block_hash,
+                    err
+                );
+                None
+            });
+        DebugProof {
+            error: error.to_string(),
+            timestamp: now_secs(),
+            proof: JsonBytes::from_vec(proof.to_bytes()),
+            block_header,
+            ckb_tx: ckb_tx.map(Into::into),
+        }
+    }
 
-fn load_from_ckb_log() -> FromCkbLog {
-    FromCkbLog{ status: &mut FromCkbLogStatus::UnKnow }
-}
+    /// Check the proof block is not older than `max_age` by the ethereum tip and its timestamp
+    fn check_proof_age(
+        &mut self,
+        config: &BridgeConfig,
+        proof: &ReceiptProof,
+        max_age: MaxProofAge,
+    ) -> Result<(), String> {
+        let mut eth_client = self.eth_client(config)?;
+        let tip_number = eth_client.block_number()?;
+        let header = eth_client
+            .get_header_by_hash(&proof.block_hash)?
+            .ok_or_else(|| format!("Ethereum header {:#x} not found", proof.block_hash))?;
+        let timestamp = parse_quantity(&header.timestamp)?;
+        max_age.check(proof.block_number, timestamp, tip_number, now_secs())
+    }
 
-impl<'a> CliSubCommand for CkbBridgeSubCommand<'a> {
-    fn process(&mut self, matches: &ArgMatches, debug: bool) -> Result<Output, String> {
-        match matches.subcommand() {
-            ("transfer-erc20-to-ckb", Some(m)) => {
-                self.transfer_to_ckb()
+    /// The fee paid by a ckb transaction: the input capacity minus the output capacity
+    fn ckb_tx_fee(&mut self, tx_hash: &H256) -> Result<u64, String> {
+        let mut get_tx = |tx_hash: &H256| {
+            self.rpc_client
+                .get_transaction(tx_hash.clone())?
+                .map(|tx_with_status| tx_with_status.transaction.inner)
+                .ok_or_else(|| format!("Ckb transaction {:#x} not found", tx_hash))
+        };
+        let tx = get_tx(tx_hash)?;
+        let mut input_capacity = 0u64;
+        for input in &tx.inputs {
+            let out_point = &input.previous_output;
+            let prev_tx = get_tx(&out_point.tx_hash)?;
+            let output = prev_tx
+                .outputs
+                .get(out_point.index.value() as usize)
+                .ok_or_else(|| format!("Input cell {:?} not found", out_point))?;
+            input_capacity += output.capacity.0;
+        }
+        let output_capacity: u64 = tx.outputs.iter().map(|output| output.capacity.0).sum();
+        input_capacity
+            .checked_sub(output_capacity)
+            .ok_or_else(|| format!("Ckb transaction {:#x} creates capacity", tx_hash))
+    }
+
+    /// Build the receipt proof of the lock transaction
+    fn build_lock_proof(
+        &mut self,
+        config: &BridgeConfig,
+        lock_tx: &H256,
+    ) -> Result<ReceiptProof, String> {
+        let mut eth_client = self.eth_client(config)?;
+        let receipt = eth_client
+            .get_transaction_receipt(lock_tx)?
+            .ok_or_else(|| format!("Lock transaction {:#x} receipt not found", lock_tx))?;
+        let block_number = receipt.block_number()?;
+        let header = eth_client
+            .get_header_by_number(block_number)?
+            .ok_or_else(|| format!("Ethereum header {} not found", block_number))?;
+        if header.hash != receipt.block_hash {
+            return Err(format!(
+                "Lock transaction {:#x} is not in block {}, maybe a reorg happened, please retry",
+                lock_tx, block_number
+            ));
+        }
+        let receipts = eth_client
+            .get_block_receipts(&header)?
+            .iter()
+            .map(EthReceipt::rlp_bytes)
+            .collect::<Result<Vec<_>, String>>()?;
+        config.proof_backend()?.generate(
+            block_number,
+            header.hash.clone(),
+            &header.receipts_root,
+            &receipts,
+            receipt.transaction_index()?,
+        )
+    }
+
+    /// Wait until the proof block is relayed to the light client with enough confirmations,
+    /// return false if the proof block is no longer on the ethereum main chain. With
+    /// websocket ethereum rpc url, each new ethereum head triggers a check immediately.
+    fn wait_block_safe(
+        &mut self,
+        config: &BridgeConfig,
+        proof: &ReceiptProof,
+        mut source: ConfirmationsSource,
+    ) -> Result<bool, String> {
+        let light_client_config = config.light_client.as_ref().ok_or_else(|| {
+            BridgeError::config(
+                "Light client is not configured, please run deploy-ckb first".to_string(),
+            )
+        })?;
+        let mut eth_client = self.eth_client(config)?;
+        let safe_number = proof.block_number + ETH_CONFIRMATIONS;
+        // Dropped (unsubscribed) when the waiting is over
+        let mut new_heads = if subscribe::is_ws_url(&config.eth_rpc_url) {
+            Some(NewHeadsSubscription::new(config.eth_rpc_url.clone()))
+        } else {
+            None
+        };
+        // A new head ends the waiting of a check early, there is no sleep between the checks
+        let interval = if new_heads.is_some() {
+            Duration::from_secs(0)
+        } else {
+            LIGHT_CLIENT_POLL_INTERVAL
+        };
+        // The final block and the light client tip of the last check
+        let mut last_check = (None, 0);
+        let safe = poll_until(
+            || {
+                let header = eth_client.get_header_by_number(proof.block_number)?;
+                if header.map(|header| header.hash) != Some(proof.block_hash.clone()) {
+                    return Ok(Poll::Ready(false));
+                }
+                let final_number = match source.tag() {
+                    Some(tag) => match eth_client.get_header_by_tag(tag) {
+                        Ok(Some(header)) => Some(header.number()?),
+                        result => {
+                            self.warn(format!(
+                                "Ethereum has no {} block ({:?}), fall back to {} confirmations",
+                                tag,
+                                result.err(),
+                                ETH_CONFIRMATIONS
+                            ));
+                            source = ConfirmationsSource::Head;
+                            None
+                        }
+                    },
+                    None => None,
+                };
+                sync_to_tip(&self.index_controller)?;
+                let light_client = self.get_light_client_cell(light_client_config)?;
+                let tip_number = light_client.tip.map(|tip| tip.number).unwrap_or(0);
+                if is_block_safe(proof.block_number, tip_number, final_number) {
+                    return Ok(Poll::Ready(true));
+                }
+                last_check = (final_number, tip_number);
+                if let Some(new_heads) = new_heads.as_mut() {
+                    if let Some(number) = new_heads.wait_new_head(LIGHT_CLIENT_POLL_INTERVAL) {
+                        log::debug!("New ethereum head {}", number);
+                    }
+                }
+                Ok(Poll::Pending)
+            },
+            interval,
+            Some(BLOCK_SAFE_TIMEOUT),
+            &CANCELLED,
+        )?;
+        let (final_number, tip_number) = last_check;
+        match safe {
+            Poll::Ready(safe) => Ok(safe),
+            Poll::Cancelled => Err(BridgeError::pending(format!(
+                "Wait block {} safe cancelled, resume later",
+                proof.block_number
+            ))
+            .into()),
+            Poll::Pending | Poll::TimedOut => Err(BridgeError::pending(match final_number {
+                Some(final_number) => format!(
+                    "Block {} is not {:?} (at {}, light client tip {}) after {}s, resume later",
+                    proof.block_number,
+                    source,
+                    final_number,
+                    tip_number,
+                    BLOCK_SAFE_TIMEOUT.as_secs()
+                ),
+                None => format!(
+                    "Light client tip {} is lower than block {} (with {} confirmations) after {}s, please relay headers and resume later",
+                    tip_number,
+                    safe_number,
+                    ETH_CONFIRMATIONS,
+                    BLOCK_SAFE_TIMEOUT.as_secs()
+                ),
+            })
+            .into()),
+        }
+    }
+
+    /// Build the unsigned mint transaction, the fee and the token cell capacity are paid by
+    /// the sighash cells of `ckb_args`. The indexes of the fee inputs are returned for signing.
+    fn build_mint_tx(
+        &mut self,
+        config: &BridgeConfig,
+        ckb_args: &CkbTxArgs,
+        proof: &ReceiptProof,
+        token_cell: (CellOutput, Bytes),
+    ) -> Result<(TransactionView, Vec<usize>), String> {
+        let light_client_config = config.light_client.as_ref().ok_or_else(|| {
+            BridgeError::config(
+                "Light client is not configured, please run deploy-ckb first".to_string(),
+            )
+        })?;
+        let bridge_script = config.bridge_script.as_ref().ok_or_else(|| {
+            BridgeError::config(
+                "Bridge script is not configured, please run deploy-ckb first".to_string(),
+            )
+        })?;
+        let bridge_dep: CellDep = bridge_script.cell_dep.clone().into();
+        self.check_cell_deps_live(&[("toCKB script", bridge_dep.clone())])?;
+        self.check_bridge_script(bridge_script)?;
+        let light_client = self.get_light_client_cell(light_client_config)?;
+        let tip_number = light_client.tip.as_ref().map(|tip| tip.number);
+        if tip_number
+            .map(|number| number < proof.block_number)
+            .unwrap_or(true)
+        {
+            return Err(format!(
+                "The light client cell {} (tip: {:?}) has no header of the proof block {}, relay the headers first",
+                light_client.out_point, tip_number, proof.block_number
+            ));
+        }
+        // The light client cell is read by the toCKB type script to verify the proof, the
+        // sighash dep unlocks the fee cells
+        let cell_deps = vec![
+            CellDep::new_builder()
+                .out_point(light_client.out_point)
+                .build(),
+            bridge_dep,
+            self.genesis_info()?.sighash_dep(),
+        ];
+
+        let tx_fee = match ckb_args.fee_rate {
+            Some(fee_rate) => fee::settle_fee(ckb_args.tx_fee, fee_rate, |tx_fee| {
+                let (tx, fee_idxs) = self.assemble_mint_tx(
+                    ckb_args,
+                    proof,
+                    token_cell.clone(),
+                    cell_deps.clone(),
+                    tx_fee,
+                )?;
+                Ok(tx.data().serialized_size_in_block())
+            })?,
+            None => ckb_args.tx_fee,
+        };
+        self.assemble_mint_tx(ckb_args, proof, token_cell, cell_deps, tx_fee)
+    }
+
+    /// Build the mint transaction paying `tx_fee`, the fee cells are collected until the
+    /// token cell capacity and the fee are paid, the rest goes to one change cell.
+    fn assemble_mint_tx(
+        &mut self,
+        ckb_args: &CkbTxArgs,
+        proof: &ReceiptProof,
+        token_cell: (CellOutput, Bytes),
+        cell_deps: Vec<CellDep>,
+        tx_fee: u64,
+    ) -> Result<(TransactionView, Vec<usize>), String> {
+        let token_capacity: u64 = token_cell.0.capacity().unpack();
+        let fee_cells = self.collect_sighash_cells(ckb_args, token_capacity + tx_fee)?;
+        let rest_capacity = change_capacity(&fee_cells, token_capacity + tx_fee)?;
+        let change_output = if rest_capacity.as_u64() > 0 {
+            Some(
+                CellOutput::new_builder()
+                    .capacity(rest_capacity.pack())
+                    .lock(Script::from(ckb_args.address.payload()))
+                    .build(),
+            )
+        } else {
+            None
+        };
+        let tx = mint::build_mint_tx(proof, token_cell, cell_deps, &fee_cells, change_output);
+        let fee_idxs = (0..fee_cells.len()).collect::<Vec<_>>();
+        // The signature is signed into the placeholder, the size paid by the fee is not changed
+        let tx = fee::with_signature_placeholder(&tx, fee_idxs[0]);
+        Ok((tx, fee_idxs))
+    }
+
+    pub fn transfer_from_ckb(
+        &mut self,
+        transfer_args: TransferArgs,
+        from_ckb_args: Option<FromCkbArgs>,
+        step_args: FromCkbStepArgs,
+        eth_args: Option<EthTxArgs>,
+        debug: bool,
+    ) -> Result<Output, String> {
+        let halt_on = transfer_args
+            .halt_on
+            .as_ref()
+            .map(|name| FromCkbLogStatus::from_name(name))
+            .transpose()?;
+        let config = self.load_config()?;
+        self.check_bridge_deployed(&config)?;
+        let store = self.from_ckb_store()?;
+        let (mut log, _transfer_lock) = match (transfer_args.transfer_id, from_ckb_args) {
+            (Some(id), None) => {
+                let transfer_lock = store.lock(&id)?;
+                let mut log = store.load::<FromCkbLog>(&id)?;
+                if let Some(name) = transfer_args.start_from_status.as_ref() {
+                    let status = FromCkbLogStatus::from_name(name)?;
+                    self.warn_status_override(&log.id, log.status, status);
+                    log.set_status(status);
+                    store.save(&log.id, &log)?;
+                }
+                if let Some(name) = transfer_args.only_step.as_ref() {
+                    let status = FromCkbLogStatus::from_name(name)?;
+                    check_only_step(&log.id, log.status, status, FromCkbLogStatus::Finished)?;
+                }
+                (log, transfer_lock)
             }
-            ("transfer-erc20-from-ckb", Some(m)) => {
-                self.transfer_from_ckb()
+            (None, Some(args)) => {
+                let token = config.resolve_token(&args.token)?;
+                let mut log = FromCkbLog::new(new_transfer_id(), Some(token));
+                log.amount = Some(args.amount);
+                log.recipient = Some(args.recipient);
+                log.check_supply = args.check_supply;
+                let transfer_lock = store.lock(&log.id)?;
+                store.save(&log.id, &log)?;
+                (log, transfer_lock)
             }
-            ("deploy-sol", Some(m)) => {
-                self.deploy_sol()
+            (Some(_), Some(_)) => {
+                return Err(
+                    "<token>, <amount> and <eth-recipient> can not be changed when resume a transfer"
+                        .to_string(),
+                );
             }
-            ("deploy-ckb", Some(m)) => {
-                self.deploy_ckb()
+            (None, None) => {
+                return Err(
+                    "<token>, <amount> and <eth-recipient> are required to start a new transfer"
+                        .to_string(),
+                );
             }
-            _ => Err(Self::subcommand().generate_usage()),
+        };
+        let webhook = transfer_args.webhook_url.map(Webhook::new).transpose()?;
+        if !transfer_args.watch {
+            if halt_on == Some(log.status) {
+                self.print_breakpoint(&log.id, log.status);
+                return Ok(Output::new_output(log.event(None)).with_exit_code(exit_code::PENDING));
+            }
+            let old_status = log.status;
+            let result = self.advance_from_ckb(&config, &step_args, &eth_args, &mut log, debug);
+            store.save(&log.id, &log)?;
+            let mut event = log.event(result?);
+            event.dry_run = self.dry_run.take();
+            event.pending = self.pending.take();
+            if log.status != old_status {
+                notify_transition(webhook.as_ref(), DIRECTION_FROM_CKB, old_status, &event);
+            }
+            let exit_code = exit_code::of_status(
+                log.status == FromCkbLogStatus::Finished || event.dry_run.is_some(),
+                false,
+            );
+            return Ok(Output::new_output(event).with_exit_code(exit_code));
+        }
+
+        print_event(
+            &log.event(None),
+            transfer_args.pretty,
+            self.output_file.as_deref(),
+        )?;
+        let mut dry_run = false;
+        while log.status != FromCkbLogStatus::Finished {
+            if halt_on == Some(log.status) {
+                self.print_breakpoint(&log.id, log.status);
+                break;
+            }
+            let old_status = log.status;
+            let result = self.advance_from_ckb(&config, &step_args, &eth_args, &mut log, debug);
+            store.save(&log.id, &log)?;
+            let mut event = log.event(result?);
+            event.dry_run = self.dry_run.take();
+            event.pending = self.pending.take();
+            if log.status != old_status {
+                notify_transition(webhook.as_ref(), DIRECTION_FROM_CKB, old_status, &event);
+            }
+            print_event(&event, transfer_args.pretty, self.output_file.as_deref())?;
+            // The step is simulated or the burn transaction is still pending, the transfer
+            // stays in its status
+            dry_run = event.dry_run.is_some();
+            if dry_run || event.pending.is_some() {
+                break;
+            }
+        }
+        let exit_code =
+            exit_code::of_status(log.status == FromCkbLogStatus::Finished || dry_run, false);
+        Ok(Output::new_success().with_exit_code(exit_code))
+    }
+
+    fn advance_from_ckb(
+        &mut self,
+        config: &BridgeConfig,
+        args: &FromCkbStepArgs,
+        eth_args: &Option<EthTxArgs>,
+        log: &mut FromCkbLog,
+        debug: bool,
+    ) -> Result<Option<H256>, String> {
+        match log.status {
+            FromCkbLogStatus::UnKnow => {
+                if log.check_supply {
+                    self.check_burn_supply(config, log)?;
+                }
+                let ckb_args = &args.ckb_args;
+                let (tx, input_idxs) = self.build_burn_tx(config, ckb_args, log)?;
+                let tx = sign_sighash_inputs(
+                    tx,
+                    ckb_args.privkey()?.clone(),
+                    ckb_args.sighash_args(),
+                    &input_idxs,
+                )?;
+                if args.dry_run_ckb {
+                    let cycles = self.simulate_tx("burn", &log.id, &tx)?;
+                    let tx_hash: H256 = tx.hash().unpack();
+                    self.notice(format!(
+                        "Dry run (ckb): the burn transaction {:#x} of transfer {} is not sent",
+                        tx_hash, log.id
+                    ));
+                    self.dry_run = Some(DryRun {
+                        side: "ckb".to_string(),
+                        step: "burn".to_string(),
+                        tx: serde_json::json!({
+                            "tx_hash": tx_hash,
+                            "size": tx.data().serialized_size_in_block(),
+                            "cycles": cycles,
+                        }),
+                    });
+                    return Ok(None);
+                }
+                let tx_hash = send_transaction(self.rpc_client, &tx, debug)?;
+                log.burn_tx = Some(tx_hash.clone());
+                log.set_status(FromCkbLogStatus::Burned);
+                Ok(Some(tx_hash))
+            }
+            FromCkbLogStatus::Burned => {
+                let tx_hash = log
+                    .burn_tx
+                    .clone()
+                    .ok_or_else(|| "Burn transaction is missing".to_string())?;
+                let status = wait_tx_committed_with_backoff(
+                    self.rpc_client,
+                    &tx_hash,
+                    args.poll_interval,
+                    MAX_POLL_INTERVAL,
+                    args.burn_timeout,
+                )?;
+                match status {
+                    CommitStatus::Committed => {
+                        log.set_status(FromCkbLogStatus::ParseProof);
+                    }
+                    CommitStatus::Pending => {
+                        // The status stays Burned, the next run polls the same transaction
+                        self.pending = Some(format!(
+                            "Burn transaction {:#x} is still pending after {}s, resume later with --transfer-id {}",
+                            tx_hash,
+                            args.burn_timeout.as_secs(),
+                            log.id
+                        ));
+                    }
+                    CommitStatus::Unknown => {
+                        // Evicted from the tx pool, nothing is burned, build it again
+                        log::warn!("Burn transaction {:#x} is unknown, rebuild it", tx_hash);
+                        log.burn_tx = None;
+                        log.set_status(FromCkbLogStatus::UnKnow);
+                        return Ok(None);
+                    }
+                }
+                Ok(Some(tx_hash))
+            }
+            FromCkbLogStatus::ParseProof => {
+                self.wait_burn_block_safe(log)?;
+                let proof = self.build_burn_proof(log)?;
+                log.proof = Some(JsonBytes::from_vec(proof.to_bytes()));
+                log.set_status(FromCkbLogStatus::WaitBlockSafe);
+                Ok(None)
+            }
+            FromCkbLogStatus::WaitBlockSafe => {
+                if !self.check_burn_block(log)? {
+                    return Ok(None);
+                }
+                // The unlock releases what the proven transaction burns, it must be the
+                // transfer's
+                let (burned, recipient) = self.burned_on_chain(config, log)?;
+                check_burn(log, burned, recipient)?;
+                log.set_status(FromCkbLogStatus::Mint);
+                Ok(None)
+            }
+            FromCkbLogStatus::Mint => {
+                let unlock_tx = match log.unlock_tx.clone() {
+                    Some(tx_hash) => {
+                        // Sent but not confirmed last time, the proof was checked before
+                        let mut eth_client = self.eth_client(config)?;
+                        eth_client.wait_receipt(&tx_hash, None, ETH_TX_TIMEOUT)?;
+                        tx_hash
+                    }
+                    None => {
+                        // Never send a proof of a reorged burn block to the unlock on ethereum
+                        if !self.check_burn_block(log)? {
+                            return Ok(None);
+                        }
+                        let mut eth_client = self.eth_client(config)?;
+                        let eth_args = required_eth_args(eth_args)?;
+                        let contract = config.bridge_contract.clone().ok_or_else(|| {
+                            BridgeError::config("Bridge contract is not configured".to_string())
+                        })?;
+                        check_not_paused(config, &mut eth_client, &contract)?;
+                        let data = self.unlock_call(config, log)?;
+                        if args.dry_run_ethereum {
+                            self.dry_run_eth_tx(
+                                &mut eth_client,
+                                eth_args,
+                                &log.id,
+                                "unlock",
+                                &contract,
+                                data,
+                            )?;
+                            return Ok(None);
+                        }
+                        let (tx_hash, gas_limit) = eth_client.send_call(
+                            eth_args.signer()?,
+                            &eth_args.gas,
+                            &contract,
+                            data,
+                        )?;
+                        log.unlock_tx = Some(tx_hash.clone());
+                        eth_client.wait_receipt(&tx_hash, Some(gas_limit), ETH_TX_TIMEOUT)?;
+                        tx_hash
+                    }
+                };
+                log.amount_check = Some(self.check_unlock_amount(config, log, &unlock_tx)?);
+                log.set_status(FromCkbLogStatus::Finished);
+                Ok(Some(unlock_tx))
+            }
+            FromCkbLogStatus::Finished => Ok(None),
+        }
+    }
+
+    /// The proof of the burn transaction in the burn block recorded by `wait_burn_block_safe`
+    fn build_burn_proof(&mut self, log: &FromCkbLog) -> Result<BurnProof, String> {
+        let (burn_tx, burn_block) = match (log.burn_tx.as_ref(), log.burn_block.as_ref()) {
+            (Some(burn_tx), Some(burn_block)) => (burn_tx, burn_block),
+            _ => {
+                return Err(format!(
+                    "Transfer {}: the burn transaction or its block is not recorded",
+                    log.id
+                ))
+            }
+        };
+        let block: BlockView = self
+            .rpc_client
+            .get_block(burn_block.hash.clone())?
+            .ok_or_else(|| format!("Ckb block {:#x} not found", burn_block.hash))?
+            .into();
+        BurnProof::build(&block, burn_tx)
+    }
+
+    /// The call data of `unlock()`: the burn transaction (with the witnesses) and its proof
+    fn unlock_call(&mut self, config: &BridgeConfig, log: &FromCkbLog) -> Result<Vec<u8>, String> {
+        let proof = log
+            .proof
+            .as_ref()
+            .ok_or_else(|| format!("Transfer {}: the burn proof is not recorded", log.id))?;
+        let burn_tx = log
+            .burn_tx
+            .clone()
+            .ok_or_else(|| format!("Transfer {}: the burn transaction is not recorded", log.id))?;
+        let tx = self
+            .rpc_client
+            .get_transaction(burn_tx.clone())?
+            .map(|tx_with_status| tx_with_status.transaction.inner)
+            .ok_or_else(|| format!("Ckb transaction {:#x} not found", burn_tx))?;
+        let tx: packed::Transaction = tx.into();
+        config.abis()?.bridge.function("unlock")?.encode_call(&[
+            Token::Bytes(tx.as_slice().to_vec()),
+            Token::Bytes(proof.as_bytes().to_vec()),
+        ])
+    }
+
+    /// The amount of the bridged token burned by the burn transaction (in the ckb decimals):
+    /// the token cells it spends minus the ones it creates, and the ethereum recipient in its
+    /// witness
+    fn burned_on_chain(
+        &mut self,
+        config: &BridgeConfig,
+        log: &FromCkbLog,
+    ) -> Result<(u128, Option<H160>), String> {
+        let token = log
+            .token
+            .clone()
+            .ok_or_else(|| format!("Transfer {}: the token is not recorded", log.id))?;
+        let burn_tx = log
+            .burn_tx
+            .clone()
+            .ok_or_else(|| format!("Transfer {}: the burn transaction is not recorded", log.id))?;
+        let bridge_script = config
+            .bridge_script
+            .as_ref()
+            .ok_or_else(|| BridgeError::config("Bridge script is not configured".to_string()))?;
+
+        let mut get_tx = |tx_hash: &H256| {
+            self.rpc_client
+                .get_transaction(tx_hash.clone())?
+                .map(|tx_with_status| tx_with_status.transaction.inner)
+                .ok_or_else(|| format!("Ckb transaction {:#x} not found", tx_hash))
+        };
+        let cells = |tx: &ckb_jsonrpc_types::Transaction| {
+            tx.outputs
+                .iter()
+                .zip(&tx.outputs_data)
+                .map(|(output, data)| (output.clone().into(), data.clone().into_bytes()))
+                .collect::<Vec<(CellOutput, Bytes)>>()
+        };
+        let tx = get_tx(&burn_tx)?;
+        let mut inputs = Vec::with_capacity(tx.inputs.len());
+        for input in &tx.inputs {
+            let out_point = &input.previous_output;
+            let cell = cells(&get_tx(&out_point.tx_hash)?)
+                .into_iter()
+                .nth(out_point.index.value() as usize)
+                .ok_or_else(|| format!("Input cell {:?} not found", out_point))?;
+            inputs.push(cell);
         }
+        let burned = burn::burned_amount(
+            &inputs,
+            &cells(&tx),
+            &mint::token_type_script(bridge_script, &token),
+        )?;
+        let recipient = tx
+            .witnesses
+            .get(0)
+            .and_then(|witness| burn::witness_recipient(witness.as_bytes()));
+        Ok((burned, recipient))
     }
-}
\ No newline at end of file
+
+    /// The burn must not exceed the bridged supply of the token (`--check-supply`), checked
+    /// before the burn transaction is built
+    fn check_burn_supply(&mut self, config: &BridgeConfig, log: &FromCkbLog) -> Result<(), String> {
+        let token = log
+            .token
+            .clone()
+            .ok_or_else(|| format!("Transfer {}: the token is not recorded", log.id))?;
+        let amount = log
+            .amount
+            .ok_or_else(|| format!("Transfer {}: the amount to burn is not recorded", log.id))?;
+        let erc20_abi = config.abis()?.erc20;
+        let mut eth_client = self.eth_client(config)?;
+        let supply = self.bridged_supply(config, &mut eth_client, &erc20_abi, &token)?;
+        self.notice(format!(
+            "Transfer {}: burn {}, minted on ckb {}, releasable on ethereum {}",
+            log.id, amount, supply.minted, supply.releasable
+        ));
+        supply
+            .check_burn(amount)
+            .map_err(|err| format!("Transfer {}: {}", log.id, err))
+    }
+
+    /// The erc20 amount released by the unlock transaction must equal the amount burned by the
+    /// burn transaction (scaled to the erc20 decimals), in case of any conversion or parsing bug
+    fn check_unlock_amount(
+        &mut self,
+        config: &BridgeConfig,
+        log: &FromCkbLog,
+        unlock_tx: &H256,
+    ) -> Result<AmountCheck, String> {
+        let token = log
+            .token
+            .clone()
+            .ok_or_else(|| format!("Transfer {}: the token is not recorded", log.id))?;
+        let contract = config
+            .bridge_contract
+            .clone()
+            .ok_or_else(|| BridgeError::config("Bridge contract is not configured".to_string()))?;
+        let (mut burned, _) = self.burned_on_chain(config, log)?;
+        if let Some((decimals, ckb_decimals)) = config.decimals_conversion(&token) {
+            burned = mint::convert_decimals(burned, ckb_decimals, decimals)?.0;
+        }
+
+        let receipt = self
+            .eth_client(config)?
+            .get_transaction_receipt(unlock_tx)?
+            .ok_or_else(|| format!("Unlock transaction {:#x} is not mined", unlock_tx))?;
+        if !receipt.is_success()? {
+            return Err(format!("Unlock transaction {:#x} reverted", unlock_tx));
+        }
+        let unlocked = erc20::transferred_from(&receipt.logs, &token, &contract)?;
+        AmountCheck::check_unlock(burned, unlocked)
+    }
+
+    /// Build the burn transaction of the transfer: the token cells of the holder (the fee
+    /// payer) are taken until the amount is covered, the rest of their amount goes back to the
+    /// holder in a new token cell. Return the transaction and the inputs to sign.
+    fn build_burn_tx(
+        &mut self,
+        config: &BridgeConfig,
+        ckb_args: &CkbTxArgs,
+        log: &FromCkbLog,
+    ) -> Result<(TransactionView, Vec<usize>), String> {
+        let (token, amount, recipient) = match (&log.token, log.amount, &log.recipient) {
+            (Some(token), Some(amount), Some(recipient)) => (token, amount, recipient),
+            _ => {
+                return Err(format!(
+                    "Transfer {}: the token, amount or recipient is not recorded",
+                    log.id
+                ))
+            }
+        };
+        let bridge_script = config.bridge_script.as_ref().ok_or_else(|| {
+            BridgeError::config(
+                "Bridge script is not configured, please run deploy-ckb first".to_string(),
+            )
+        })?;
+        let bridge_dep: CellDep = bridge_script.cell_dep.clone().into();
+        self.check_cell_deps_live(&[("toCKB script", bridge_dep.clone())])?;
+        self.check_bridge_script(bridge_script)?;
+
+        let holder_lock = Script::from(ckb_args.address.payload());
+        let lock_hash: H256 = holder_lock.calc_script_hash().unpack();
+        let type_hash = mint::token_type_script(bridge_script, token).calc_script_hash();
+        let cells = self.with_db(|db| {
+            db.get_live_cells_by_type(type_hash, None, |_, cell| {
+                (false, cell.lock_hash == lock_hash)
+            })
+        })?;
+        let mut token_cells = Vec::with_capacity(cells.len());
+        for cell in cells {
+            let (_, data) = get_live_cell(self.rpc_client, cell.out_point(), true)?;
+            let cell_amount = mint::token_amount(&data)?;
+            token_cells.push((cell, cell_amount));
+        }
+        let (token_cells, total) = burn::select_token_cells(&token_cells, amount)
+            .map_err(|err| format!("{} of {}", err, ckb_args.address))?;
+        let token_change = if total > amount {
+            Some(mint::token_cell(
+                bridge_script,
+                token,
+                total - amount,
+                holder_lock,
+            )?)
+        } else {
+            None
+        };
+        // The bridge script checks the burn, the sighash dep unlocks the token and fee cells
+        let cell_deps = vec![bridge_dep, self.genesis_info()?.sighash_dep()];
+
+        let tx_fee = match ckb_args.fee_rate {
+            Some(fee_rate) => fee::settle_fee(ckb_args.tx_fee, fee_rate, |tx_fee| {
+                let (tx, _) = self.assemble_burn_tx(
+                    ckb_args,
+                    recipient,
+                    &token_cells,
+                    token_change.clone(),
+                    cell_deps.clone(),
+                    tx_fee,
+                )?;
+                Ok(tx.data().serialized_size_in_block())
+            })?,
+            None => ckb_args.tx_fee,
+        };
+        self.assemble_burn_tx(
+            ckb_args,
+            recipient,
+            &token_cells,
+            token_change,
+            cell_deps,
+            tx_fee,
+        )
+    }
+
+    /// Build the burn transaction paying `tx_fee`, the capacity of the burned token cells is
+    /// returned to the holder in the change cell with the rest of the fee cells.
+    fn assemble_burn_tx(
+        &mut self,
+        ckb_args: &CkbTxArgs,
+        recipient: &H160,
+        token_cells: &[LiveCellInfo],
+        token_change: Option<(CellOutput, Bytes)>,
+        cell_deps: Vec<CellDep>,
+        tx_fee: u64,
+    ) -> Result<(TransactionView, Vec<usize>), String> {
+        let token_capacity = token_cells.iter().map(|cell| cell.capacity).sum::<u64>();
+        let change_token_capacity: u64 = token_change
+            .as_ref()
+            .map(|(output, _)| output.capacity().unpack())
+            .unwrap_or(0);
+        let fee_cells = self.collect_sighash_cells(ckb_args, tx_fee)?;
+        let rest_capacity = (change_capacity(&fee_cells, tx_fee)?.as_u64() + token_capacity)
+            .checked_sub(change_token_capacity)
+            .ok_or_else(|| {
+                BridgeError::insufficient_funds(
+                    "Capacity not enough for the token change cell".to_string(),
+                )
+            })?;
+        let change_output = if rest_capacity > 0 {
+            Some(
+                CellOutput::new_builder()
+                    .capacity(rest_capacity.pack())
+                    .lock(Script::from(ckb_args.address.payload()))
+                    .build(),
+            )
+        } else {
+            None
+        };
+        let tx = burn::build_burn_tx(
+            recipient,
+            token_cells,
+            token_change,
+            cell_deps,
+            &fee_cells,
+            change_output,
+        );
+        let input_idxs = (0..token_cells.len() + fee_cells.len()).collect::<Vec<_>>();
+        // The signature is signed into the placeholder, the size paid by the fee is not changed
+        let tx = fee::with_signature_placeholder(&tx, input_idxs[0]);
+        Ok((tx, input_idxs))
+    }
+
+    /// The block committing the ckb transaction, `None` if it is not committed (pending, or
+    /// reorged out of the chain)
+    fn ckb_tx_block(&mut self, tx_hash: &H256) -> Result<Option<BurnBlock>, String> {
+        let block_hash = match self.rpc_client.get_transaction(tx_hash.clone())? {
+            Some(tx_with_status) if tx_with_status.tx_status.status == Status::Committed => {
+                tx_with_status.tx_status.block_hash
+            }
+            _ => None,
+        };
+        match block_hash {
+            Some(hash) => {
+                let header = self
+                    .rpc_client
+                    .get_header(hash.clone())?
+                    .ok_or_else(|| format!("Ckb header {:#x} not found", hash))?;
+                Ok(Some(BurnBlock {
+                    number: header.inner.number,
+                    hash,
+                }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Wait until the burn transaction has `--ckb-reorg-confirmations` blocks on top, then
+    /// record its block, the proof is built against it. The block is fetched again on each
+    /// poll, so a reorg while waiting is followed.
+    fn wait_burn_block_safe(&mut self, log: &mut FromCkbLog) -> Result<(), String> {
+        let burn_tx = log
+            .burn_tx
+            .clone()
+            .ok_or_else(|| format!("Transfer {}: the burn transaction is not recorded", log.id))?;
+        let confirmations = self.ckb_reorg_confirmations;
+        let mut last_check = None;
+        let mut notified = false;
+        let safe = poll_until(
+            || {
+                let block = self.ckb_tx_block(&burn_tx)?.ok_or_else(|| {
+                    format!(
+                        "Transfer {}: the burn transaction {:#x} is not committed",
+                        log.id, burn_tx
+                    )
+                })?;
+                let tip_number = self.rpc_client.get_tip_block_number()?;
+                if block.is_confirmed(tip_number, confirmations) {
+                    return Ok(Poll::Ready(block));
+                }
+                if !notified {
+                    self.notice(format!(
+                        "Transfer {}: waiting for {} confirmations of the burn block {}",
+                        log.id, confirmations, block.number
+                    ));
+                    notified = true;
+                }
+                last_check = Some((block.number, tip_number));
+                Ok(Poll::Pending)
+            },
+            CKB_BLOCK_POLL_INTERVAL,
+            Some(BLOCK_SAFE_TIMEOUT),
+            &CANCELLED,
+        )?;
+        let waiting = match last_check {
+            Some((number, tip_number)) => format!(
+                "Wait {} confirmations of the burn block {} (tip {})",
+                confirmations, number, tip_number
+            ),
+            None => "Wait the burn block confirmed".to_string(),
+        };
+        log.burn_block = Some(
+            safe.ready(&waiting, Some(BLOCK_SAFE_TIMEOUT))
+                .map_err(BridgeError::pending)?,
+        );
+        Ok(())
+    }
+
+    /// The burn transaction must still be committed in the block the proof is built against.
+    /// If ckb reorged, the burn block is cleared and the transfer is reset to `ParseProof` to
+    /// build the proof again, false is returned then.
+    fn check_burn_block(&mut self, log: &mut FromCkbLog) -> Result<bool, String> {
+        let (burn_tx, burn_block) = match (log.burn_tx.clone(), log.burn_block.clone()) {
+            (Some(burn_tx), Some(burn_block)) => (burn_tx, burn_block),
+            _ => return Ok(true),
+        };
+        let current = self.ckb_tx_block(&burn_tx)?;
+        if current.as_ref() == Some(&burn_block) {
+            return Ok(true);
+        }
+        self.warn(format!(
+            "Transfer {}: ckb reorged, the burn transaction {:#x} is no longer in block {} ({:#x}) but {}, the proof is built again",
+            log.id,
+            burn_tx,
+            burn_block.number,
+            burn_block.hash,
+            current
+                .map(|block| format!("in block {} ({:#x})", block.number, block.hash))
+                .unwrap_or_else(|| "not committed".to_string())
+        ));
+        log.burn_block = None;
+        log.proof = None;
+        log.set_status(FromCkbLogStatus::ParseProof);
+        Ok(false)
+    }
+
+    /// List the transfer logs sorted by the last update (the log file modification time), only
+    /// the logs in the page are loaded.
+    pub fn history(
+        &mut self,
+        directions: &[&str],
+        offset: usize,
+        limit: usize,
+        newest_first: bool,
+    ) -> Result<Output, String> {
+        let mut stores = Vec::new();
+        let mut entries = Vec::new();
+        for (index, direction) in directions.iter().enumerate() {
+            let store = self.inspect_log_store(direction)?;
+            for (id, modified) in store.list_modified()? {
+                entries.push((modified, id, index));
+            }
+            stores.push(store);
+        }
+        // Ties are ordered by the id, so the pages are stable
+        entries.sort();
+        if newest_first {
+            entries.reverse();
+        }
+        let total = entries.len();
+        let mut transfers = Vec::new();
+        for (_, id, index) in entries.into_iter().skip(offset).take(limit) {
+            let entry = if directions[index] == DIRECTION_TO_CKB {
+                stores[index]
+                    .load::<ToCkbLog>(&id)
+                    .map(|log| log.history_entry())
+            } else {
+                stores[index]
+                    .load::<FromCkbLog>(&id)
+                    .map(|log| log.history_entry())
+            };
+            match entry {
+                Ok(entry) => transfers.push(entry),
+                Err(err) => self.warn(format!("Skip transfer log {}: {}", id, err)),
+            }
+        }
+        let resp = serde_json::json!({
+            "total": total,
+            "offset": offset,
+            "limit": limit,
+            "transfers": transfers,
+        });
+        Ok(Output::new_output(resp))
+    }
+
+    /// The aggregates of the transfers in the given directions, the logs are loaded one at a
+    /// time and only the sums are kept
+    pub fn transfer_summary(&mut self, directions: &[&str]) -> Result<Output, String> {
+        let mut summary = TransferSummary::default();
+        for direction in directions {
+            let store = self.inspect_log_store(direction)?;
+            for id in store.list_ids()? {
+                let loaded = if *direction == DIRECTION_TO_CKB {
+                    store
+                        .load::<ToCkbLog>(&id)
+                        .map(|log| summary.add_to_ckb(&log))
+                } else {
+                    store
+                        .load::<FromCkbLog>(&id)
+                        .map(|log| summary.add_from_ckb(&log))
+                };
+                if let Err(err) = loaded {
+                    summary.add_unreadable(direction);
+                    self.warn(format!("Skip transfer log {}: {}", id, err));
+                }
+            }
+        }
+        Ok(Output::new_output(summary.to_json()))
+    }
+
+    /// Run one step of every unfinished transfer in the given directions, the failed steps
+    /// are collected in the summary instead of aborting the remaining transfers. The progress
+    /// of each transfer is written to stderr and the summary is the output.
+    pub fn resume_all(
+        &mut self,
+        step_args: &ToCkbStepArgs,
+        from_ckb_args: &FromCkbStepArgs,
+        directions: &[&str],
+        progress_mode: ProgressMode,
+        max_concurrent: Option<usize>,
+        debug: bool,
+    ) -> Result<Output, String> {
+        let config = self.load_config()?;
+        self.check_bridge_deployed(&config)?;
+        // Load the unfinished transfers first, so the progress knows the total
+        let mut to_ckb_logs = Vec::new();
+        if directions.contains(&DIRECTION_TO_CKB) {
+            let store = self.to_ckb_store()?;
+            for id in store.list_ids()? {
+                match store.load::<ToCkbLog>(&id) {
+                    // A transfer waiting for the offline signature is resumed by its own
+                    // --broadcast-signed file (or broadcast-eth-tx)
+                    Ok(log)
+                        if log.status.is_terminal()
+                            || log.status == ToCkbLogStatus::Signing
+                            || log.pending_eth_tx.is_some() => {}
+                    Ok(log) => to_ckb_logs.push(Ok(log)),
+                    Err(err) => to_ckb_logs.push(Err((id, err))),
+                }
+            }
+        }
+        let mut from_ckb_logs = Vec::new();
+        if directions.contains(&DIRECTION_FROM_CKB) {
+            let store = self.from_ckb_store()?;
+            for id in store.list_ids()? {
+                match store.load::<FromCkbLog>(&id) {
+                    Ok(log) if log.status == FromCkbLogStatus::Finished => {}
+                    Ok(log) => from_ckb_logs.push(Ok(log)),
+                    Err(err) => from_ckb_logs.push(Err((id, err))),
+                }
+            }
+        }
+
+        // Only the transfers ready to advance are stepped (serially, the transactions are sent
+        // from one lane), the others keep waiting
+        let ready = self.check_ready(
+            &config,
+            step_args,
+            &to_ckb_logs,
+            max_concurrent.unwrap_or(1),
+        )?;
+        let mut progress = Progress::stderr(progress_mode, to_ckb_logs.len() + from_ckb_logs.len());
+        let mut results = Vec::new();
+        if !to_ckb_logs.is_empty() {
+            let store = self.to_ckb_store()?;
+            for (log, ready) in to_ckb_logs.into_iter().zip(ready) {
+                let result = match log {
+                    Ok(log) if !ready => {
+                        step_result(DIRECTION_TO_CKB, &log.id, log.status, log.status, Ok(None))
+                    }
+                    Ok(mut log) => match store.lock(&log.id) {
+                        Ok(_transfer_lock) => {
+                            let old_status = log.status;
+                            // Always persist the log, the step may have sent a transaction
+                            // before failing
+                            let result = self.advance_to_ckb(&config, step_args, &mut log, debug);
+                            let result = store.save(&log.id, &log).and_then(|()| result);
+                            step_result(DIRECTION_TO_CKB, &log.id, old_status, log.status, result)
+                        }
+                        // Stepped by another process
+                        Err(err) => {
+                            step_result(DIRECTION_TO_CKB, &log.id, log.status, log.status, Err(err))
+                        }
+                    },
+                    Err((id, err)) => load_failed(DIRECTION_TO_CKB, &id, err),
+                };
+                progress.record(&result);
+                results.push(result);
+            }
+        }
+        if !from_ckb_logs.is_empty() {
+            let store = self.from_ckb_store()?;
+            for log in from_ckb_logs {
+                let result = match log {
+                    Ok(mut log) => match store.lock(&log.id) {
+                        Ok(_transfer_lock) => {
+                            let old_status = log.status;
+                            // Always persist the log, the step may have sent a transaction
+                            // before failing
+                            let result = self.advance_from_ckb(
+                                &config,
+                                from_ckb_args,
+                                &step_args.eth_args,
+                                &mut log,
+                                debug,
+                            );
+                            let result = store.save(&log.id, &log).and_then(|()| result);
+                            step_result(DIRECTION_FROM_CKB, &log.id, old_status, log.status, result)
+                        }
+                        // Stepped by another process
+                        Err(err) => step_result(
+                            DIRECTION_FROM_CKB,
+                            &log.id,
+                            log.status,
+                            log.status,
+                            Err(err),
+                        ),
+                    },
+                    Err((id, err)) => load_failed(DIRECTION_FROM_CKB, &id, err),
+                };
+                progress.record(&result);
+                results.push(result);
+            }
+        }
+
+        let (failed, rest): (Vec<_>, Vec<_>) = results.into_iter().partition(StepResult::is_failed);
+        let (advanced, waiting): (Vec<_>, Vec<_>) = rest
+            .into_iter()
+            .partition(|result| result.status != result.old_status);
+        let resp = serde_json::json!({
+            "network": get_network_type(self.rpc_client)?.to_str(),
+            "advanced": advanced,
+            "waiting": waiting,
+            "failed": failed,
+        });
+        // Waiting transfers are resumed by the next run, only the failures are reported
+        let exit_code = if failed.is_empty() {
+            exit_code::SUCCESS
+        } else {
+            exit_code::TRANSFER_FAILED
+        };
+        Ok(Output::new_output(resp).with_exit_code(exit_code))
+    }
+
+    /// Whether each transfer can advance now. The transfers waiting for a sent transaction
+    /// are checked concurrently (at most `max_concurrent` requests at a time), the transfers
+    /// waiting for a safe block are checked against the light client tip read once. Logs
+    /// failed to load and failed checks are ready, the step reports the error.
+    fn check_ready(
+        &mut self,
+        config: &BridgeConfig,
+        step_args: &ToCkbStepArgs,
+        logs: &[Result<ToCkbLog, (String, String)>],
+        max_concurrent: usize,
+    ) -> Result<Vec<bool>, String> {
+        let mut ready = vec![true; logs.len()];
+        let waiting_safe = logs.iter().any(|log| {
+            log.as_ref()
+                .map(|log| log.status == ToCkbLogStatus::ParseProof)
+                .unwrap_or(false)
+        });
+        let safe_numbers = if waiting_safe {
+            match self.safe_numbers(config, step_args.confirmations_source) {
+                Ok(numbers) => Some(numbers),
+                Err(err) => {
+                    log::warn!("Read the light client tip error: {}", err);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        if let Some((tip_number, final_number)) = safe_numbers {
+            for (ready, log) in ready.iter_mut().zip(logs) {
+                if let Ok(log) = log {
+                    if log.status == ToCkbLogStatus::ParseProof {
+                        *ready = parse_log_proof(log)
+                            .map(|proof| {
+                                is_block_safe(proof.block_number, tip_number, final_number)
+                            })
+                            .unwrap_or(true);
+                    }
+                }
+            }
+        }
+
+        let checks = logs
+            .iter()
+            .enumerate()
+            .filter_map(|(index, log)| {
+                let check = WaitCheck::of(log.as_ref().ok()?)?;
+                Some((index, check))
+            })
+            .collect::<Vec<_>>();
+        let eth_url = config.eth_rpc_url.clone();
+        let eth_rpc_timeout = self.eth_rpc_timeout;
+        let ckb_url = self.rpc_client.url().to_string();
+        let ckb_rpc_timeout = self.ckb_rpc_timeout;
+        let results = parallel_map(checks, max_concurrent, move |(index, check)| {
+            (
+                index,
+                check.is_ready(&eth_url, eth_rpc_timeout, &ckb_url, ckb_rpc_timeout),
+            )
+        });
+        for (index, is_ready) in results {
+            ready[index] = is_ready;
+        }
+        Ok(ready)
+    }
+
+    /// The light client tip and the number of the safe or finalized ethereum block (`None`
+    /// for head confirmations or if the chain has no such block)
+    fn safe_numbers(
+        &mut self,
+        config: &BridgeConfig,
+        source: ConfirmationsSource,
+    ) -> Result<(u64, Option<u64>), String> {
+        let light_client_config = config.light_client.as_ref().ok_or_else(|| {
+            BridgeError::config(
+                "Light client is not configured, please run deploy-ckb first".to_string(),
+            )
+        })?;
+        let light_client = self.get_light_client_cell(light_client_config)?;
+        let tip_number = light_client.tip.map(|tip| tip.number).unwrap_or(0);
+        let final_number = match source.tag() {
+            Some(tag) => match self.eth_client(config)?.get_header_by_tag(tag) {
+                Ok(Some(header)) => Some(header.number()?),
+                _ => None,
+            },
+            None => None,
+        };
+        Ok((tip_number, final_number))
+    }
+
+    /// Check all the artifacts required by a transfer are deployed, so the user get one clear
+    /// error before any transaction is sent.
+    fn check_bridge_deployed(&mut self, config: &BridgeConfig) -> Result<(), String> {
+        let mut missing = Vec::new();
+        match config.bridge_contract.as_ref() {
+            Some(contract) => {
+                if !self.contract_has_code(config, contract)? {
+                    missing.push(format!(
+                        "no code at bridge contract {}",
+                        checksum_address(contract)
+                    ));
+                }
+            }
+            None => missing.push("bridge contract address is not configured".to_string()),
+        }
+        match config.light_client.as_ref() {
+            Some(light_client) => {
+                if let Err(err) = self.get_light_client_cell(light_client) {
+                    missing.push(err);
+                }
+            }
+            None => missing.push("light client cell is not configured".to_string()),
+        }
+        match config.bridge_script.as_ref() {
+            Some(script) => {
+                if let Err(err) = self.check_bridge_script(script) {
+                    missing.push(err);
+                }
+            }
+            None => missing.push("bridge script is not configured".to_string()),
+        }
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(BridgeError::config(format!(
+                "The bridge is not deployed ({}), run `ckb-bridge doctor` to check every deployment, or `ckb-bridge deploy-sol` and `ckb-bridge deploy-ckb` to deploy the bridge first (config: {:?})",
+                missing.join("; "),
+                self.config_path()
+            ))
+            .into())
+        }
+    }
+
+    /// Check every configured deployment, ethereum contracts are checked concurrently and the
+    /// ckb cells in one batch request. The order of the checks is deterministic.
+    fn deployment_checks(&mut self, config: &BridgeConfig) -> Result<Vec<Check>, String> {
+        let mut contracts = Vec::new();
+        if let Some(contract) = config.bridge_contract.as_ref() {
+            contracts.push(("bridge contract".to_string(), contract.clone()));
+        }
+        let mut tokens = config.tokens.iter().collect::<Vec<_>>();
+        tokens.sort_by_key(|(address, _)| (*address).clone());
+        for (address, token_config) in tokens {
+            let name = match token_config.symbol.as_ref() {
+                Some(symbol) => format!("token {}", symbol),
+                None => "token".to_string(),
+            };
+            contracts.push((name, address.clone()));
+        }
+        let eth_rpc_url = config.eth_rpc_url.clone();
+        let eth_rpc_timeout = self.eth_rpc_timeout;
+        let mut checks = parallel_map(contracts, MAX_CHECK_WORKERS, move |(name, address)| {
+            let result = EthRpcClient::new(&eth_rpc_url, eth_rpc_timeout)
+                .and_then(|mut eth_client| eth_client.get_code(&address))
+                .and_then(|code| {
+                    if code.is_empty() {
+                        Err(format!("no code at {}", checksum_address(&address)))
+                    } else {
+                        Ok(format!(
+                            "{} ({} bytes)",
+                            checksum_address(&address),
+                            code.len()
+                        ))
+                    }
+                });
+            Check::new(name, result)
+        });
+
+        let mut cells = Vec::new();
+        if let Some(script) = config.bridge_script.as_ref() {
+            cells.push((
+                "bridge script cell dep".to_string(),
+                script.cell_dep.out_point.clone(),
+            ));
+        }
+        if let Some(light_client) = config.light_client.as_ref() {
+            for cell_dep in &light_client.cell_deps {
+                cells.push((
+                    "light client cell dep".to_string(),
+                    cell_dep.out_point.clone(),
+                ));
+            }
+        }
+        let out_points = cells
+            .iter()
+            .map(|(_, out_point)| out_point.clone())
+            .collect::<Vec<_>>();
+        let statuses = batch_cell_status(self.rpc_client.url(), self.ckb_rpc_timeout, &out_points)?;
+        for ((name, out_point), status) in cells.into_iter().zip(statuses) {
+            let result = status.and_then(|status| {
+                let detail = format!(
+                    "{:#x}:{} {}",
+                    out_point.tx_hash,
+                    out_point.index.value(),
+                    status
+                );
+                if status == "live" {
+                    Ok(detail)
+                } else {
+                    Err(detail)
+                }
+            });
+            checks.push(Check::new(name, result));
+        }
+        if let Some(light_client) = config.light_client.as_ref() {
+            let result = self
+                .inspect_light_client_cell(light_client)
+                .map(|cell| match cell.tip {
+                    Some(tip) => format!("{} (tip: {})", cell.out_point, tip.number),
+                    None => format!("{} (no header relayed)", cell.out_point),
+                });
+            checks.push(Check::new("light client cell".to_string(), result));
+        }
+        Ok(checks)
+    }
+
+    pub fn list_deployments(&mut self) -> Result<Output, String> {
+        let config = self.load_config()?;
+        let checks = self.deployment_checks(&config)?;
+        Ok(Output::new_output(checks))
+    }
+
+    /// Sign the intent of a transfer as the token holder, the output are the values of the
+    /// `--intent-*` arguments of the operator
+    pub fn sign_transfer_intent(
+        &mut self,
+        signer: &EthSigner,
+        args: ToCkbArgs,
+        nonce: u64,
+        expires_in: Duration,
+    ) -> Result<Output, String> {
+        let config = self.load_config()?;
+        let token = config.resolve_token(&args.token)?;
+        let intent = TransferIntent {
+            token: token.clone(),
+            amount: args.amount,
+            recipient_lock: args.recipient_lock.as_slice().to_vec(),
+            nonce,
+            deadline: now_secs() + expires_in.as_secs(),
+        };
+        let authorization = intent.sign(signer);
+        let resp = serde_json::json!({
+            "token": checksum_address(&token),
+            "amount": args.amount.to_string(),
+            "recipient": args.recipient.map(|address| address.to_string()),
+            "intent_signer": checksum_address(&authorization.signer),
+            "intent_nonce": authorization.nonce,
+            "intent_deadline": authorization.deadline,
+            "intent_signature": authorization.signature,
+        });
+        Ok(Output::new_output(resp))
+    }
+
+    /// Send an approve or lock transaction signed by `sign-eth-tx` and record it in the
+    /// transfer, resume the transfer to wait for it
+    pub fn broadcast_eth_tx(&mut self, tx_file: &Path) -> Result<Output, String> {
+        let offline_tx = OfflineEthTx::read(tx_file)?;
+        let raw_tx = offline_tx.raw_tx.as_ref().ok_or_else(|| {
+            format!(
+                "The transaction in {:?} is not signed, sign it with sign-eth-tx first",
+                tx_file
+            )
+        })?;
+        let store = self.to_ckb_store()?;
+        let _transfer_lock = store.lock(&offline_tx.transfer_id)?;
+        let mut log: ToCkbLog = store.load(&offline_tx.transfer_id)?;
+        let expected = PendingEthTx {
+            step: offline_tx.step.clone(),
+            nonce: offline_tx.nonce,
+        };
+        if log.pending_eth_tx.as_ref() != Some(&expected) {
+            return Err(format!(
+                "Transfer {} does not wait for the {} transaction (nonce {}) in {:?}, was it built again or sent already?",
+                log.id, expected.step, expected.nonce, tx_file
+            ));
+        }
+        let config = self.load_config()?;
+        let mut eth_client = self.eth_client(&config)?;
+        let chain_id = eth_client.chain_id()?;
+        if chain_id != offline_tx.chain_id {
+            return Err(BridgeError::network_mismatch(format!(
+                "The transaction is signed for chain {}, but the ethereum node is on chain {}",
+                offline_tx.chain_id, chain_id
+            ))
+            .into());
+        }
+        let tx_hash = eth_client.broadcast(raw_tx.as_bytes())?;
+        match offline_tx.step.as_str() {
+            "approve" => log.approve_tx = Some(tx_hash.clone()),
+            _ => log.lock_tx = Some(tx_hash.clone()),
+        }
+        log.pending_eth_tx = None;
+        log.set_status(log.status);
+        store.save(&log.id, &log)?;
+        Ok(Output::new_output(log.event(Some(tx_hash))))
+    }
+
+    /// List the tokens in the config, with `check` the token contracts are checked on chain
+    /// (code, symbol and decimals), the exit code is nonzero if any check failed
+    pub fn list_tokens(&mut self, check: bool, check_supply: bool) -> Result<Output, String> {
+        let config = self.load_config()?;
+        let erc20_abi = config.abis()?.erc20;
+        let mut eth_client = if check || check_supply {
+            Some(self.eth_client(&config)?)
+        } else {
+            None
+        };
+        let mut tokens = config.tokens.iter().collect::<Vec<_>>();
+        tokens.sort_by_key(|(address, token_config)| (token_config.symbol.clone(), *address));
+        let mut healthy = true;
+        let mut entries = Vec::with_capacity(tokens.len());
+        for (address, token_config) in tokens {
+            let mut entry = serde_json::json!({
+                "address": checksum_address(address),
+                "symbol": token_config.symbol,
+                "decimals": token_config.decimals,
+                "ckb_decimals": token_config.ckb_decimals,
+                "requires_zero_first": token_config.requires_zero_first,
+                "fee_on_transfer": token_config.fee_on_transfer,
+                "min_amount": token_config.min_amount.map(|amount| amount.to_string()),
+                "max_amount": token_config.max_amount.map(|amount| amount.to_string()),
+            });
+            if let Some(eth_client) = eth_client.as_mut().filter(|_| check) {
+                let result = check_token(eth_client, &erc20_abi, address, token_config);
+                let check = Check::new("token contract".to_string(), result);
+                healthy &= check.ok;
+                entry["check"] = serde_json::json!(check);
+            }
+            if let Some(eth_client) = eth_client.as_mut().filter(|_| check_supply) {
+                match self.bridged_supply(&config, eth_client, &erc20_abi, address) {
+                    Ok(supply) => {
+                        entry["supply"] = serde_json::json!({
+                            "minted": supply.minted.to_string(),
+                            "releasable": supply.releasable.to_string(),
+                        });
+                        if let Err(err) = supply.check_backing() {
+                            healthy = false;
+                            self.warn(format!("Token {}: {}", checksum_address(address), err));
+                        }
+                    }
+                    Err(err) => {
+                        healthy = false;
+                        entry["supply"] = serde_json::json!({ "error": err });
+                    }
+                }
+            }
+            entries.push(entry);
+        }
+        let exit_code = if healthy {
+            exit_code::SUCCESS
+        } else {
+            exit_code::ERROR
+        };
+        Ok(Output::new_output(serde_json::json!({ "tokens": entries })).with_exit_code(exit_code))
+    }
+
+    /// The supply of a token minted on ckb (the live token cells in the index) and releasable
+    /// on ethereum (`balanceOf()` the bridge contract), in the ckb decimals
+    fn bridged_supply(
+        &mut self,
+        config: &BridgeConfig,
+        eth_client: &mut EthRpcClient,
+        erc20_abi: &ContractAbi,
+        token: &H160,
+    ) -> Result<mint::BridgedSupply, String> {
+        let contract = config
+            .bridge_contract
+            .clone()
+            .ok_or_else(|| BridgeError::config("Bridge contract is not configured".to_string()))?;
+        let bridge_script = config
+            .bridge_script
+            .as_ref()
+            .ok_or_else(|| BridgeError::config("Bridge script is not configured".to_string()))?;
+        let type_hash = mint::token_type_script(bridge_script, token).calc_script_hash();
+        let cells = self.with_db(|db| {
+            db.get_live_cells_by_type(type_hash.clone(), None, |_, _| (false, true))
+        })?;
+        let mut minted = 0u128;
+        for cell in cells {
+            let (_, data) = get_live_cell(self.rpc_client, cell.out_point(), true)?;
+            minted = minted
+                .checked_add(mint::token_amount(&data)?)
+                .ok_or_else(|| "Minted supply overflow".to_string())?;
+        }
+        let data = erc20_abi
+            .function("balanceOf")?
+            .encode_call(&[Token::Address(contract)])?;
+        let balance = eth_client.call(&H160::default(), token, &data)?;
+        let mut releasable = abi::decode_uint(balance.as_bytes(), 0)?;
+        if let Some((decimals, ckb_decimals)) = config.decimals_conversion(token) {
+            releasable = mint::convert_decimals(releasable, decimals, ckb_decimals)?.0;
+        }
+        Ok(mint::BridgedSupply { minted, releasable })
+    }
+
+    /// Read the paused state, the owner and the limits (any getter in the ABI) of the bridge
+    /// contract, never send any transaction
+    pub fn bridge_state(&mut self) -> Result<Output, String> {
+        let config = self.load_config()?;
+        let contract = config
+            .bridge_contract
+            .clone()
+            .ok_or_else(|| BridgeError::config("Bridge contract is not configured".to_string()))?;
+        let bridge_abi = config.abis()?.bridge;
+        let mut eth_client = self.eth_client(&config)?;
+        let mut state = serde_json::Map::new();
+        for getter in bridge_abi.getters() {
+            let data = getter.encode_call(&[])?;
+            let value = eth_client
+                .call(&H160::default(), &contract, &data)
+                .and_then(|result| getter.decode_output(result.as_bytes()))
+                .map_err(|err| format!("Call {} error: {}", getter.signature, err))?;
+            state.insert(getter.name.clone(), value);
+        }
+        let resp = serde_json::json!({
+            "bridge_contract": checksum_address(&contract),
+            "paused": state.get("paused").cloned(),
+            "owner": state.get("owner").cloned(),
+            "state": state,
+        });
+        Ok(Output::new_output(resp))
+    }
+
+    /// One report of the bridge environment, an unreachable endpoint is reported in place
+    /// instead of failing the report
+    pub fn bridge_info(&mut self) -> Result<Output, String> {
+        let config_path = self.config_path();
+        let config = self.load_config()?;
+        let ckb = match get_network_type(self.rpc_client) {
+            Ok(network_type) => self.rpc_client.get_tip_block_number().map(|tip| {
+                serde_json::json!({
+                    "url": self.rpc_client.url(),
+                    "network": network_type.to_str(),
+                    "tip": tip,
+                })
+            }),
+            Err(err) => Err(err),
+        };
+        let ethereum = self.eth_client(&config).and_then(|mut eth_client| {
+            Ok(serde_json::json!({
+                "url": config.eth_rpc_url,
+                "chain_id": eth_client.chain_id()?,
+                "head": eth_client.block_number()?,
+            }))
+        });
+        let light_client_tip = match config.light_client.as_ref() {
+            Some(light_client_config) => self
+                .get_light_client_cell(light_client_config)
+                .map(|light_client| light_client.tip.map(|tip| tip.number)),
+            None => Ok(None),
+        };
+        let deployments = self.deployment_checks(&config)?;
+
+        let mut transfers = serde_json::Map::new();
+        if ckb.is_ok() {
+            let store = self.to_ckb_store()?;
+            let mut counts = TransferCounts::default();
+            for id in store.list_ids()? {
+                match store.load::<ToCkbLog>(&id) {
+                    Ok(log) => counts.add(log.status.is_terminal(), log.debug_proof.is_some()),
+                    Err(_) => counts.add(false, true),
+                }
+            }
+            transfers.insert(DIRECTION_TO_CKB.to_string(), serde_json::json!(counts));
+            let store = self.from_ckb_store()?;
+            let mut counts = TransferCounts::default();
+            for id in store.list_ids()? {
+                match store.load::<FromCkbLog>(&id) {
+                    Ok(log) => counts.add(log.status == FromCkbLogStatus::Finished, false),
+                    Err(_) => counts.add(false, true),
+                }
+            }
+            transfers.insert(DIRECTION_FROM_CKB.to_string(), serde_json::json!(counts));
+        }
+
+        let report = |result: Result<serde_json::Value, String>| match result {
+            Ok(value) => value,
+            Err(err) => serde_json::json!({ "error": err }),
+        };
+        let resp = serde_json::json!({
+            "config": {
+                "path": config_path,
+                "exists": config_path.exists(),
+                "bridge_abi": config.bridge_abi,
+                "erc20_abi": config.erc20_abi,
+                "tokens": config.tokens.len(),
+            },
+            "data_dir": self.bridge_dir,
+            "ckb": report(ckb),
+            "ethereum": report(ethereum),
+            "deployments": deployments,
+            "light_client_tip": report(light_client_tip.map(|tip| serde_json::json!(tip))),
+            // The logs are namespaced by the ckb network, not counted if it is unreachable
+            "transfers": transfers,
+        });
+        Ok(Output::new_output(resp))
+    }
+
+    /// Check both rpc endpoints and all the deployments, never send any transaction
+    pub fn doctor(&mut self) -> Result<Output, String> {
+        let config = self.load_config()?;
+        let mut checks = vec![Check::new(
+            "ckb rpc".to_string(),
+            self.rpc_client
+                .get_tip_block_number()
+                .map(|number| format!("{} (tip: {})", self.rpc_client.url(), number)),
+        )];
+        let eth_result = self.eth_client(&config).and_then(|mut eth_client| {
+            let chain_id = eth_client.chain_id()?;
+            let number = eth_client.block_number()?;
+            Ok(format!(
+                "{} (chain id: {}, tip: {})",
+                config.eth_rpc_url, chain_id, number
+            ))
+        });
+        checks.push(Check::new("ethereum rpc".to_string(), eth_result));
+        checks.extend(self.deployment_checks(&config)?);
+        let healthy = checks.iter().all(|check| check.ok);
+        let resp = serde_json::json!({
+            "healthy": healthy,
+            "checks": checks,
+        });
+        let exit_code = if healthy {
+            exit_code::SUCCESS
+        } else {
+            exit_code::ERROR
+        };
+        Ok(Output::new_output(resp).with_exit_code(exit_code))
+    }
+
+    /// Re-query both chains for the transactions recorded in the local logs and correct
+    /// their status, only the logs are changed.
+    pub fn reconcile(&mut self) -> Result<Output, String> {
+        let config = self.load_config()?;
+        let mut eth_client = self.eth_client(&config)?;
+        let store = self.to_ckb_store()?;
+        let ids = store.list_ids()?;
+        let mut corrections = Vec::new();
+        let mut skipped = Vec::new();
+        for id in &ids {
+            // A transfer stepped by another process is left to the next run
+            let _transfer_lock = match store.lock(id) {
+                Ok(transfer_lock) => transfer_lock,
+                Err(err) => {
+                    self.warn(format!("Skip transfer {}: {}", id, err));
+                    skipped.push(id.clone());
+                    continue;
+                }
+            };
+            let mut log: ToCkbLog = store.load(id)?;
+            let facts = ToCkbFacts {
+                approve_success: eth_tx_success(&mut eth_client, log.approve_tx.as_ref())?,
+                lock_success: eth_tx_success(&mut eth_client, log.lock_tx.as_ref())?,
+                mint_committed: match log.mint_tx.as_ref() {
+                    Some(tx_hash) => {
+                        let status = self
+                            .rpc_client
+                            .get_transaction(tx_hash.clone())?
+                            .map(|tx_with_status| tx_with_status.tx_status.status);
+                        match status {
+                            Some(Status::Committed) => Some(true),
+                            Some(_) => None,
+                            None => Some(false),
+                        }
+                    }
+                    None => None,
+                },
+            };
+            if let Some(correction) = reconcile_to_ckb(&mut log, &facts) {
+                store.save(&log.id, &log)?;
+                corrections.push(correction);
+            }
+        }
+        let resp = serde_json::json!({
+            "checked": ids.len() - skipped.len(),
+            "skipped": skipped,
+            "corrections": corrections,
+        });
+        Ok(Output::new_output(resp))
+    }
+
+    /// Check the ethereum transactions recorded in the unfinished transfers: pending, mined,
+    /// reverted or dropped, ordered by sender and nonce. A nonce not taken by any pending
+    /// transaction of the sender blocks the ones after it, reported as a gap.
+    pub fn list_pending_eth_txs(&mut self) -> Result<Output, String> {
+        let config = self.load_config()?;
+        let mut eth_client = self.eth_client(&config)?;
+        let store = self.to_ckb_store()?;
+        let mut txs = Vec::new();
+        for id in store.list_ids()? {
+            let log: ToCkbLog = store.load(&id)?;
+            if log.status == ToCkbLogStatus::Finished {
+                continue;
+            }
+            let recorded = vec![("approve", &log.approve_tx), ("lock", &log.lock_tx)];
+            for (step, tx_hash) in recorded {
+                if let Some(tx_hash) = tx_hash {
+                    txs.push(recorded_eth_tx(&mut eth_client, &log.id, step, tx_hash)?);
+                }
+            }
+            if let Some(pending) = log.pending_eth_tx.as_ref() {
+                txs.push(RecordedEthTx {
+                    transfer_id: log.id.clone(),
+                    step: pending.step.clone(),
+                    tx_hash: None,
+                    from: None,
+                    nonce: Some(pending.nonce),
+                    state: EthTxState::Unsigned,
+                });
+            }
+        }
+        txs.sort_by_key(|tx| (tx.from.clone(), tx.nonce.is_none(), tx.nonce));
+
+        let mut pending_nonces: BTreeMap<H160, Vec<u64>> = BTreeMap::new();
+        for tx in txs.iter().filter(|tx| tx.state == EthTxState::Pending) {
+            if let (Some(from), Some(nonce)) = (tx.from.as_ref(), tx.nonce) {
+                pending_nonces.entry(from.clone()).or_default().push(nonce);
+            }
+        }
+        let mut gaps = Vec::new();
+        for (from, nonces) in pending_nonces {
+            let confirmed = eth_client.confirmed_nonce(&from)?;
+            let missing = nonce_gaps(confirmed, &nonces);
+            if !missing.is_empty() {
+                gaps.push(serde_json::json!({
+                    "from": checksum_address(&from),
+                    "confirmed_nonce": confirmed,
+                    "missing_nonces": missing,
+                }));
+            }
+        }
+        let resp = serde_json::json!({
+            "transactions": txs,
+            "nonce_gaps": gaps,
+        });
+        Ok(Output::new_output(resp))
+    }
+
+    /// Remove the transfer locks whose holder process is gone, or all of them with `force`
+    pub fn clean_locks(&mut self, force: bool) -> Result<Output, String> {
+        let mut cleaned = Vec::new();
+        let mut kept = Vec::new();
+        let mut running = 0;
+        for direction in &[DIRECTION_TO_CKB, DIRECTION_FROM_CKB] {
+            let store = self.log_store(direction)?;
+            for (id, holder) in store.list_locks()? {
+                // A lock file can not be parsed if its process was killed while writing it
+                let alive = match holder.as_ref() {
+                    Some(holder) => holder.is_alive(),
+                    None => Some(false),
+                };
+                let lock = serde_json::json!({
+                    "direction": direction,
+                    "transfer_id": id,
+                    "pid": holder.as_ref().map(|holder| holder.pid),
+                    "created_at": holder.as_ref().map(|holder| holder.created_at),
+                    "alive": alive,
+                });
+                if force || alive == Some(false) {
+                    store.remove_lock(&id)?;
+                    if alive != Some(false) {
+                        running += 1;
+                    }
+                    cleaned.push(lock);
+                } else {
+                    kept.push(lock);
+                }
+            }
+        }
+        if running > 0 {
+            self.warn(format!(
+                "--force removed {} locks whose process may still be running, that process may step the same transfer as another one",
+                running
+            ));
+        }
+        if kept.iter().any(|lock| lock["alive"].is_null()) {
+            self.notice(
+                "Whether the holder process is alive can not be told on this platform, remove its lock with --force once it is gone".to_string(),
+            );
+        }
+        let resp = serde_json::json!({
+            "cleaned": cleaned,
+            "kept": kept,
+        });
+        Ok(Output::new_output(resp))
+    }
+
+    /// Build the receipt proof of a transfer's lock transaction again against the block it is
+    /// currently mined in, for a proof invalidated by a reorg. The transfer is left at
+    /// `ParseProof` to continue, the funds are not locked again.
+    pub fn rebuild_proof(&mut self, transfer_id: &str) -> Result<Output, String> {
+        let config = self.load_config()?;
+        let store = self.to_ckb_store()?;
+        let _transfer_lock = store.lock(transfer_id)?;
+        let mut log: ToCkbLog = store.load(transfer_id)?;
+        let rebuildable = [
+            ToCkbLogStatus::Locked,
+            ToCkbLogStatus::ParseProof,
+            ToCkbLogStatus::WaitBlockSafe,
+        ];
+        if !rebuildable.contains(&log.status) {
+            return Err(format!(
+                "Transfer {} is {:?}, only a transfer in Locked, ParseProof or WaitBlockSafe can rebuild its proof",
+                log.id, log.status
+            ));
+        }
+        let lock_tx = log
+            .lock_tx
+            .clone()
+            .ok_or_else(|| "Lock transaction is missing".to_string())?;
+        let receipt = self
+            .eth_client(&config)?
+            .get_transaction_receipt(&lock_tx)?
+            .ok_or_else(|| format!("Lock transaction {:#x} is not mined", lock_tx))?;
+        if !receipt.is_success()? {
+            return Err(format!("Lock transaction {:#x} reverted", lock_tx));
+        }
+        // The old proof is replaced even if it is out of sync with the stored lock block
+        let old_block = match log.proof.as_ref() {
+            Some(proof) => {
+                let proof = ReceiptProof::from_bytes(proof.as_bytes())?;
+                Some(serde_json::json!({
+                    "block_number": proof.block_number,
+                    "block_hash": proof.block_hash,
+                }))
+            }
+            None => None,
+        };
+        let proof = self.build_lock_proof(&config, &lock_tx)?;
+        log.set_proof(proof.to_bytes(), lock_block(&proof));
+        log.set_status(ToCkbLogStatus::ParseProof);
+        store.save(&log.id, &log)?;
+        let resp = serde_json::json!({
+            "transfer_id": log.id,
+            "lock_tx": lock_tx,
+            "old_block": old_block,
+            "block_number": proof.block_number,
+            "block_hash": proof.block_hash,
+            "status": format!("{:?}", log.status),
+        });
+        Ok(Output::new_output(resp))
+    }
+
+    /// Deploy the bridge contract, the address is written to config only if the deployed
+    /// runtime bytecode matches the artifact. The exit code is nonzero on mismatch.
+    pub fn deploy_sol(
+        &mut self,
+        eth_args: &EthTxArgs,
+        artifact: &ContractArtifact,
+        constructor_args: Vec<u8>,
+    ) -> Result<Output, String> {
+        let mut config = self.load_config()?;
+        let mut eth_client = self.eth_client(&config)?;
+        let mut code = artifact.bytecode.clone();
+        code.extend(constructor_args);
+        let (tx_hash, gas_limit) =
+            eth_client.send_create(eth_args.signer()?, &eth_args.gas, code)?;
+        let receipt = eth_client.wait_receipt(&tx_hash, Some(gas_limit), ETH_TX_TIMEOUT)?;
+        let contract = receipt.contract_address.clone().ok_or_else(|| {
+            format!(
+                "Ethereum transaction {:#x} created no contract address",
+                tx_hash
+            )
+        })?;
+        let onchain_code = eth_client.get_code(&contract)?;
+        let check = check_bytecode(onchain_code.as_bytes(), artifact);
+        if check.matched {
+            config.bridge_contract = Some(contract.clone());
+            config.save(&self.config_path())?;
+        } else {
+            log::error!(
+                "Deployed bytecode of {} mismatch, config is not changed: {}",
+                checksum_address(&contract),
+                check.error.as_deref().unwrap_or_default()
+            );
+        }
+        let exit_code = if check.matched {
+            exit_code::SUCCESS
+        } else {
+            exit_code::ERROR
+        };
+        let resp = serde_json::json!({
+            "contract_address": checksum_address(&contract),
+            "tx_hash": tx_hash,
+            "block_number": receipt.block_number()?,
+            "bytecode_check": check,
+            "config_updated": check.matched,
+        });
+        Ok(Output::new_output(resp).with_exit_code(exit_code))
+    }
+
+    /// Deploy the toCKB script and the light client script as code cells, then create the
+    /// (uninitialized) light client cell, the deployed scripts are written to config. With
+    /// `type_id` the toCKB script is referenced by type hash.
+    pub fn deploy_ckb(
+        &mut self,
+        tx_args: &CkbTxArgs,
+        bridge_binary: Bytes,
+        light_client_binary: Bytes,
+        type_id: bool,
+        debug: bool,
+    ) -> Result<Output, String> {
+        let mut config = self.load_config()?;
+        let sighash_dep = self.genesis_info()?.sighash_dep();
+        let deployer_lock = Script::from(tx_args.address.payload());
+
+        let code_cells = vec![
+            deploy::code_cell(bridge_binary.clone(), deployer_lock.clone(), type_id)?,
+            deploy::code_cell(light_client_binary.clone(), deployer_lock, false)?,
+        ];
+        let code_capacity = code_cells
+            .iter()
+            .map(|(output, _)| {
+                let capacity: u64 = output.capacity().unpack();
+                capacity
+            })
+            .sum::<u64>();
+        let code_tx = self.send_deploy_tx(
+            tx_args,
+            code_cells,
+            code_capacity,
+            vec![sighash_dep.clone()],
+            debug,
+        )?;
+        let code_out_points = code_tx.output_pts();
+        let bridge_dep = CellDep::new_builder()
+            .out_point(code_out_points[0].clone())
+            .build();
+        let light_client_dep = CellDep::new_builder()
+            .out_point(code_out_points[1].clone())
+            .build();
+
+        // The type script args depends on the first input, so the fee cells are collected
+        // before building the light client cell
+        let fee_cells = self.collect_sighash_cells(tx_args, tx_args.tx_fee)?;
+        let first_input = fee_cells
+            .first()
+            .map(LiveCellInfo::out_point)
+            .ok_or_else(|| "No live cell to create the light client cell".to_string())?;
+        let light_client_output = deploy::light_client_cell(&light_client_binary, &first_input)?;
+        let light_client_capacity: u64 = light_client_output.capacity().unpack();
+        let light_client_tx = self.send_deploy_tx(
+            tx_args,
+            vec![(light_client_output.clone(), Bytes::new())],
+            light_client_capacity,
+            vec![light_client_dep.clone(), sighash_dep],
+            debug,
+        )?;
+
+        let light_client_type = light_client_output
+            .type_()
+            .to_opt()
+            .expect("light client type script");
+        let bridge_hash_type = if type_id {
+            ScriptHashType::Type
+        } else {
+            ScriptHashType::Data
+        };
+        let bridge_code_hash = deploy::script_code_hash(
+            &code_tx.outputs().get(0).expect("bridge code cell"),
+            &bridge_binary,
+            bridge_hash_type,
+        )?;
+        config.bridge_script = Some(ScriptConfig {
+            code_hash: bridge_code_hash.unpack(),
+            hash_type: bridge_hash_type.into(),
+            cell_dep: bridge_dep.into(),
+            proof_used_error: None,
+        });
+        config.light_client = Some(LightClientConfig {
+            type_script: light_client_type.into(),
+            cell_deps: vec![light_client_dep.into()],
+            out_point: Some(light_client_tx.output_pts()[0].clone().into()),
+        });
+        config.save(&self.config_path())?;
+
+        let transactions: Vec<H256> =
+            vec![code_tx.hash().unpack(), light_client_tx.hash().unpack()];
+        let resp = serde_json::json!({
+            "bridge_script": config.bridge_script,
+            "light_client": config.light_client,
+            "transactions": transactions,
+        });
+        Ok(Output::new_output(resp))
+    }
+
+    /// Send a transaction creating the given cells, the capacity and fee are paid by the
+    /// sighash cells of `tx_args`. Wait until committed.
+    fn send_deploy_tx(
+        &mut self,
+        tx_args: &CkbTxArgs,
+        outputs: Vec<(CellOutput, Bytes)>,
+        outputs_capacity: u64,
+        cell_deps: Vec<CellDep>,
+        debug: bool,
+    ) -> Result<TransactionView, String> {
+        let target_capacity = outputs_capacity + tx_args.tx_fee;
+        let fee_cells = self.collect_sighash_cells(tx_args, target_capacity)?;
+        let rest_capacity = change_capacity(&fee_cells, target_capacity)?;
+        let change_output = if rest_capacity.as_u64() > 0 {
+            Some(
+                CellOutput::new_builder()
+                    .capacity(rest_capacity.pack())
+                    .lock(Script::from(tx_args.address.payload()))
+                    .build(),
+            )
+        } else {
+            None
+        };
+        let tx = deploy::build_deploy_tx(outputs, cell_deps, &fee_cells, change_output);
+        let fee_idxs = (0..fee_cells.len()).collect::<Vec<_>>();
+        let tx = sign_sighash_inputs(
+            tx,
+            tx_args.privkey()?.clone(),
+            tx_args.sighash_args(),
+            &fee_idxs,
+        )?;
+        let tx_hash = send_transaction(self.rpc_client, &tx, debug)?;
+        wait_tx_committed(self.rpc_client, &tx_hash, CKB_TX_COMMIT_TIMEOUT)?;
+        // Make sure the spent cells are removed from index database
+        sync_to_tip(&self.index_controller)?;
+        Ok(tx)
+    }
+
+    /// Relay ethereum headers in [from_block, to_block] to the light client cell in chunks of
+    /// `headers_per_tx`, the headers already relayed are skipped. The checkpoint is saved after
+    /// every chunk, `from_block` defaults to the block after it.
+    pub fn relay_header(
+        &mut self,
+        tx_args: &CkbTxArgs,
+        from_block: Option<u64>,
+        to_block: Option<u64>,
+        headers_per_tx: u64,
+        debug: bool,
+    ) -> Result<Output, String> {
+        if headers_per_tx == 0 {
+            return Err("<headers-per-tx> must be greater than 0".to_string());
+        }
+        let config = self.load_config()?;
+        let light_client_config = config.light_client.clone().ok_or_else(|| {
+            BridgeError::config(
+                "Light client is not configured, please run deploy-ckb first".to_string(),
+            )
+        })?;
+        let checkpoint_path = self.relay_checkpoint_path()?;
+        let checkpoint = RelayCheckpoint::load(&checkpoint_path)?;
+        let mut eth_client = self.eth_client(&config)?;
+        let eth_tip = eth_client.block_number()?;
+        let to_block = to_block.unwrap_or(eth_tip);
+        let from_block = match (from_block, checkpoint.as_ref()) {
+            (Some(from_block), _) => from_block,
+            // Up to date if the checkpoint is at or above <to-block>
+            (None, Some(checkpoint)) => std::cmp::min(checkpoint.number + 1, to_block),
+            (None, None) => {
+                return Err(format!(
+                    "<from-block> is required, no relay checkpoint {:?} yet",
+                    checkpoint_path
+                ));
+            }
+        };
+        if to_block > eth_tip {
+            return Err(format!(
+                "<to-block> {} is higher than ethereum tip {}",
+                to_block, eth_tip
+            ));
+        }
+        if from_block > to_block {
+            return Err(format!(
+                "<from-block> {} is higher than <to-block> {}",
+                from_block, to_block
+            ));
+        }
+
+        let light_client_deps = light_client_config
+            .cell_deps
+            .iter()
+            .cloned()
+            .map(|cell_dep| ("light client script", cell_dep.into()))
+            .collect::<Vec<_>>();
+        self.check_cell_deps_live(&light_client_deps)?;
+        let cell_deps: Vec<CellDep> = light_client_deps
+            .into_iter()
+            .map(|(_, cell_dep)| cell_dep)
+            .chain(std::iter::once(self.genesis_info()?.sighash_dep()))
+            .collect();
+        let mut light_client = self.get_light_client_cell(&light_client_config)?;
+        let tip_number = light_client.tip.as_ref().map(|tip| tip.number);
+        // Headers at or below the tip are skipped, a gap up to <from-block> is filled first
+        let gap = gap_range(tip_number, from_block);
+        let mut next = relay_start(tip_number, from_block, to_block);
+
+        let mut relayed = 0;
+        let mut transactions = Vec::new();
+        while let Some(number) = next {
+            // Another relayer may have moved the light client meanwhile, continue from its tip
+            if get_live_cell(self.rpc_client, light_client.out_point.clone(), false).is_err() {
+                sync_to_tip(&self.index_controller)?;
+                let consumed = light_client.out_point.clone();
+                light_client = self.get_light_client_cell(&light_client_config)?;
+                if light_client.out_point == consumed {
+                    return Err(format!(
+                        "The light client cell {} is consumed, but no newer one is found",
+                        consumed
+                    ));
+                }
+                let tip_number = light_client.tip.as_ref().map(|tip| tip.number);
+                next = relay_start(tip_number, from_block, to_block);
+                continue;
+            }
+            let end = std::cmp::min(number + headers_per_tx - 1, to_block);
+            let (tx, tx_hash, headers) = self
+                .relay_chunk(
+                    &mut eth_client,
+                    tx_args,
+                    &light_client,
+                    &cell_deps,
+                    (number, end),
+                    debug,
+                )
+                .map_err(|err| {
+                    format!(
+                        "Relay headers {}..={} failed: {}. {} headers relayed by this run, the last relayed height is {}, run relay-header again to resume from it (lower --headers-per-tx if the transaction is too large)",
+                        number,
+                        end,
+                        err,
+                        relayed,
+                        light_client
+                            .tip
+                            .as_ref()
+                            .map(|tip| tip.number.to_string())
+                            .unwrap_or_else(|| "none".to_string())
+                    )
+                })?;
+
+            let last = headers.last().expect("headers not empty");
+            light_client = LightClientCell {
+                out_point: tx.output_pts()[0].clone(),
+                output: light_client.output,
+                tip: Some(LightClientTip {
+                    number: last.number()?,
+                    hash: last.hash.clone(),
+                }),
+            };
+            let checkpoint = RelayCheckpoint {
+                number: last.number()?,
+                hash: last.hash.clone(),
+                tx_hash: tx_hash.clone(),
+                updated_at: now_secs(),
+            };
+            // The relayed headers are on chain already, the light client tip is the source of
+            // truth
+            if let Err(err) = checkpoint.save(&checkpoint_path) {
+                self.warn(format!(
+                    "Save relay checkpoint {:?} error: {}",
+                    checkpoint_path, err
+                ));
+            }
+            relayed += headers.len();
+            transactions.push(tx_hash);
+            next = relay_start(Some(end), from_block, to_block);
+        }
+
+        let resp = serde_json::json!({
+            "relayed": relayed,
+            "gap_filled": gap.map(|(start, end)| serde_json::json!([start, end])),
+            "tip": light_client.tip.map(|tip| serde_json::json!({
+                "number": tip.number,
+                "hash": tip.hash,
+            })),
+            "transactions": transactions,
+            "checkpoint": checkpoint_path,
+        });
+        Ok(Output::new_output(resp))
+    }
+
+    fn address_cache_path(&mut self) -> Result<PathBuf, String> {
+        let network_type = get_network_type(self.rpc_client)?;
+        Ok(self
+            .bridge_dir
+            .join(network_type.to_str())
+            .join("address-cache.json"))
+    }
+
+    /// Resolve the registry at `address`, a resolution of the cache younger than
+    /// `--address-cache-ttl` is reused
+    fn resolve_registry(
+        &mut self,
+        config: &BridgeConfig,
+        address: &H160,
+    ) -> Result<Registry, String> {
+        let ttl = self.address_cache_ttl.as_secs();
+        if ttl == 0 {
+            return Registry::resolve(&mut self.eth_client(config)?, address);
+        }
+        let path = self.address_cache_path()?;
+        let mut cache = AddressCache::load(&path);
+        let now = now_secs();
+        if let Some(registry) = cache.registry(address, &config.eth_rpc_url, ttl, now) {
+            log::info!(
+                "Registry {} resolved from the address cache {:?}",
+                checksum_address(address),
+                path
+            );
+            return Ok(registry);
+        }
+        let registry = Registry::resolve(&mut self.eth_client(config)?, address)?;
+        cache.insert_registry(&registry, &config.eth_rpc_url, now);
+        self.save_address_cache(&cache, &path);
+        Ok(registry)
+    }
+
+    /// Whether `address` has contract code, a contract seen with code less than
+    /// `--address-cache-ttl` ago is not queried again
+    fn contract_has_code(&mut self, config: &BridgeConfig, address: &H160) -> Result<bool, String> {
+        let ttl = self.address_cache_ttl.as_secs();
+        if ttl == 0 {
+            return Ok(!self.eth_client(config)?.get_code(address)?.is_empty());
+        }
+        let path = self.address_cache_path()?;
+        let mut cache = AddressCache::load(&path);
+        let now = now_secs();
+        if cache.has_code(address, &config.eth_rpc_url, ttl, now) {
+            return Ok(true);
+        }
+        let has_code = !self.eth_client(config)?.get_code(address)?.is_empty();
+        if has_code {
+            cache.insert_code(address, &config.eth_rpc_url, now);
+            self.save_address_cache(&cache, &path);
+        }
+        Ok(has_code)
+    }
+
+    // The cache only saves rpc calls, failing to write it does not fail the run
+    fn save_address_cache(&mut self, cache: &AddressCache, path: &Path) {
+        if let Err(err) = cache.save(path) {
+            self.warn(format!("Save the address cache {:?} error: {}", path, err));
+        }
+    }
+
+    fn relay_checkpoint_path(&mut self) -> Result<PathBuf, String> {
+        let network_type = get_network_type(self.rpc_client)?;
+        Ok(self
+            .bridge_dir
+            .join(network_type.to_str())
+            .join("relay-checkpoint.json"))
+    }
+
+    /// Build, send and wait committed the relay transaction of the headers in `range`, return
+    /// it with the relayed headers
+    fn relay_chunk(
+        &mut self,
+        eth_client: &mut EthRpcClient,
+        tx_args: &CkbTxArgs,
+        light_client: &LightClientCell,
+        cell_deps: &[CellDep],
+        range: (u64, u64),
+        debug: bool,
+    ) -> Result<(TransactionView, H256, Vec<EthHeader>), String> {
+        let headers = (range.0..=range.1)
+            .map(|n| {
+                eth_client
+                    .get_header_by_number(n)?
+                    .ok_or_else(|| format!("Ethereum header {} not found", n))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        check_headers(light_client.tip.as_ref(), &headers)?;
+
+        let fee_cells = self.collect_sighash_cells(tx_args, tx_args.tx_fee)?;
+        let rest_capacity = change_capacity(&fee_cells, tx_args.tx_fee)?;
+        let change_output = if rest_capacity.as_u64() > 0 {
+            Some(
+                CellOutput::new_builder()
+                    .capacity(rest_capacity.pack())
+                    .lock(Script::from(tx_args.address.payload()))
+                    .build(),
+            )
+        } else {
+            None
+        };
+        let tx = build_relay_tx(
+            light_client,
+            &headers,
+            cell_deps.to_vec(),
+            &fee_cells,
+            change_output,
+        )?;
+        let fee_idxs = (1..=fee_cells.len()).collect::<Vec<_>>();
+        let tx = sign_sighash_inputs(
+            tx,
+            tx_args.privkey()?.clone(),
+            tx_args.sighash_args(),
+            &fee_idxs,
+        )?;
+        let tx_hash = send_transaction(self.rpc_client, &tx, debug)?;
+        wait_tx_committed(self.rpc_client, &tx_hash, CKB_TX_COMMIT_TIMEOUT)?;
+        // Make sure the spent fee cells are removed from index database
+        sync_to_tip(&self.index_controller)?;
+        Ok((tx, tx_hash, headers))
+    }
+
+    /// Rebuild the proof against a pinned ethereum block instead of the block in the proof,
+    /// the pinned block must contain the receipt referenced by the proof.
+    fn decode_proof_at_block(
+        &mut self,
+        data: &[u8],
+        block: EitherValue<H256, u64>,
+    ) -> Result<Output, String> {
+        let config = self.load_config()?;
+        let proof = ReceiptProof::from_bytes(data)?;
+        let mut eth_client = self.eth_client(&config)?;
+        let header = match block {
+            EitherValue::A(hash) => eth_client
+                .get_header_by_hash(&hash)?
+                .ok_or_else(|| format!("Ethereum block {:#x} not found", hash))?,
+            EitherValue::B(number) => eth_client
+                .get_header_by_number(number)?
+                .ok_or_else(|| format!("Ethereum header {} not found", number))?,
+        };
+        let index = proof.receipt_index as usize;
+        let tx_hash = header.transactions.get(index).cloned().ok_or_else(|| {
+            format!(
+                "Ethereum block {:#x} has no transaction at index {} ({} transactions)",
+                header.hash,
+                index,
+                header.transactions.len()
+            )
+        })?;
+        let receipts = eth_client
+            .get_block_receipts(&header)?
+            .iter()
+            .map(EthReceipt::rlp_bytes)
+            .collect::<Result<Vec<_>, String>>()?;
+        if receipts[index] != proof.receipt {
+            return Err(format!(
+                "Transaction {:#x} at index {} of ethereum block {:#x} is not the one referenced by the proof",
+                tx_hash, index, header.hash
+            ));
+        }
+        let backend = config.proof_backend()?;
+        let pinned = backend.generate(
+            header.number()?,
+            header.hash.clone(),
+            &header.receipts_root,
+            &receipts,
+            proof.receipt_index,
+        )?;
+        let mut resp = proof_json(&pinned, backend.as_ref())?;
+        resp["tx_hash"] = serde_json::json!(tx_hash);
+        resp["same_as_input"] = serde_json::json!(pinned.to_bytes() == proof.to_bytes());
+        Ok(Output::new_output(resp))
+    }
+
+    /// Locate the live light client cell: the out point recorded in config if still live,
+    /// otherwise (moved by relaying) the unique live cell of the light client type script.
+    fn get_light_client_cell(
+        &mut self,
+        light_client_config: &LightClientConfig,
+    ) -> Result<LightClientCell, String> {
+        let type_script: Script = light_client_config.type_script.clone().into();
+        let type_hash = type_script.calc_script_hash();
+        let recorded: Option<OutPoint> = light_client_config.out_point.clone().map(Into::into);
+        let cells = self.with_db(|db| {
+            let recorded = recorded
+                .and_then(|out_point| db.get_live_cell_info(out_point))
+                .filter(|info| {
+                    info.type_hashes.as_ref().map(|(_, hash)| hash.pack())
+                        == Some(type_hash.clone())
+                });
+            match recorded {
+                Some(info) => vec![info],
+                None => db.get_live_cells_by_type(type_hash.clone(), None, |_, _| (false, true)),
+            }
+        })?;
+        match cells.len() {
+            0 => {
+                return Err(BridgeError::network_mismatch(format!(
+                    "No live light client cell found (type hash: {}), is the light client deployed on this network? (run deploy-ckb)",
+                    type_hash
+                ))
+                .into());
+            }
+            1 => {}
+            count => {
+                return Err(format!(
+                    "Expect exactly one light client cell (type hash: {}), found: {}",
+                    type_hash, count
+                ));
+            }
+        }
+        let out_point = cells[0].out_point();
+        let (output, data) = get_live_cell(self.rpc_client, out_point.clone(), true)?;
+        Ok(LightClientCell {
+            out_point,
+            output,
+            tip: LightClientTip::from_slice(&data)?,
+        })
+    }
+
+    /// Locate the light client cell for the read-only commands, which must work while the
+    /// index database is rebuilding: the recorded out point is checked by rpc, the index is
+    /// only searched if it is synced already.
+    fn inspect_light_client_cell(
+        &mut self,
+        light_client_config: &LightClientConfig,
+    ) -> Result<LightClientCell, String> {
+        if let Some(out_point) = light_client_config.out_point.clone() {
+            let out_point: OutPoint = out_point.into();
+            if let Ok((output, data)) = get_live_cell(self.rpc_client, out_point.clone(), true) {
+                let type_script: Script = light_client_config.type_script.clone().into();
+                if output.type_().to_opt() == Some(type_script) {
+                    return Ok(LightClientCell {
+                        out_point,
+                        output,
+                        tip: LightClientTip::from_slice(&data)?,
+                    });
+                }
+            }
+        }
+        let state = self.index_controller.state().read().clone();
+        if !state.is_synced() {
+            return Err(format!(
+                "The light client cell moved since the config was written, it can be located once the index database is synced (sync process: {})",
+                state.to_string()
+            ));
+        }
+        self.get_light_client_cell(light_client_config)
+    }
+
+    /// Check the code hash and hash type of the toCKB script resolve to the code cell in its
+    /// cell dep, a mismatch makes the minted token cells unverifiable.
+    fn check_bridge_script(&mut self, bridge_script: &ScriptConfig) -> Result<(), String> {
+        let cell_dep: CellDep = bridge_script.cell_dep.clone().into();
+        let (code_cell, code_data) = get_live_cell(self.rpc_client, cell_dep.out_point(), true)
+            .map_err(|err| format!("bridge script cell dep is not live: {}", err))?;
+        deploy::check_script_reference(
+            &bridge_script.code_hash.pack(),
+            bridge_script.hash_type.clone().into(),
+            &code_cell,
+            &code_data,
+        )
+        .map_err(|err| {
+            format!(
+                "The toCKB script reference is invalid ({}), please fix the config: {:?}",
+                err,
+                self.config_path()
+            )
+        })
+    }
+
+    /// Check the cell deps recorded by `deploy-ckb` are still live in the index database, a
+    /// consumed code cell usually means the contract was upgraded.
+    fn check_cell_deps_live(&mut self, cell_deps: &[(&str, CellDep)]) -> Result<(), String> {
+        let out_points = cell_deps
+            .iter()
+            .map(|(_, cell_dep)| cell_dep.out_point())
+            .collect::<Vec<_>>();
+        let live = self.with_db(|db| {
+            out_points
+                .into_iter()
+                .map(|out_point| db.get_live_cell_info(out_point).is_some())
+                .collect::<Vec<_>>()
+        })?;
+        for ((name, cell_dep), live) in cell_deps.iter().zip(live) {
+            if !live {
+                return Err(format!(
+                    "The {} cell dep {} is not live, the contract cell moved (upgraded?), please update the config: {:?}",
+                    name,
+                    cell_dep.out_point(),
+                    self.config_path()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn collect_sighash_cells(
+        &mut self,
+        tx_args: &CkbTxArgs,
+        target_capacity: u64,
+    ) -> Result<Vec<LiveCellInfo>, String> {
+        let lock_hash = Script::from(tx_args.address.payload()).calc_script_hash();
+        let max_mature_number = get_max_mature_number(self.rpc_client)?;
+        let mut enough = false;
+        let mut take_capacity = 0;
+        let terminator = |_, cell: &LiveCellInfo| {
+            if !(cell.type_hashes.is_none() && cell.data_bytes == 0)
+                || !is_mature(cell, max_mature_number)
+            {
+                return (false, false);
+            }
+            take_capacity += cell.capacity;
+            if fee::is_enough(take_capacity, target_capacity) {
+                enough = true;
+            }
+            (enough, true)
+        };
+        let cells = self.with_db(|db| db.get_live_cells_by_lock(lock_hash, None, terminator))?;
+        if !enough {
+            return Err(BridgeError::insufficient_funds(format!(
+                "Capacity not enough: {} has {} in mature plain cells, {} required",
+                tx_args.address,
+                HumanCapacity(take_capacity),
+                HumanCapacity(target_capacity),
+            ))
+            .into());
+        }
+        Ok(cells)
+    }
+}
+
+/// The precondition of `--only-step`: the transfer is in the named status and it has a step
+fn check_only_step<S: PartialEq + std::fmt::Debug>(
+    transfer_id: &str,
+    status: S,
+    expected: S,
+    finished: S,
+) -> Result<(), String> {
+    if status != expected {
+        return Err(format!(
+            "Transfer {} is in status {:?}, not {:?}, no step is run",
+            transfer_id, status, expected
+        ));
+    }
+    if status == finished {
+        return Err(format!(
+            "Transfer {} is {:?}, there is no step to run",
+            transfer_id, status
+        ));
+    }
+    Ok(())
+}
+
+/// Sign the fee inputs of a transaction written by `--build-only`, the key must match the
+/// recorded sighash args
+fn sign_offline_tx(
+    privkey: PrivkeyWrapper,
+    tx_file: &Path,
+    output: &Path,
+) -> Result<Output, String> {
+    let mut offline_tx = OfflineTx::read(tx_file)?;
+    if offline_tx.signed {
+        return Err(format!(
+            "The transaction in {:?} is already signed",
+            tx_file
+        ));
+    }
+    let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, &privkey);
+    let payload = AddressPayload::from_pubkey(&pubkey);
+    let sighash_args = H160::from_slice(payload.args().as_ref()).expect("sighash args");
+    if sighash_args != offline_tx.sighash_args {
+        return Err(format!(
+            "The private key (sighash args {:#x}) does not match the inputs to sign ({:#x})",
+            sighash_args, offline_tx.sighash_args
+        ));
+    }
+    let tx = sign_sighash_inputs(
+        offline_tx.tx_view(),
+        privkey,
+        sighash_args,
+        &offline_tx.input_idxs,
+    )?;
+    offline_tx.transaction = tx.data().into();
+    offline_tx.signed = true;
+    offline_tx.write(output)?;
+    Ok(Output::new_output(serde_json::json!({
+        "transfer_id": offline_tx.transfer_id,
+        "tx_hash": offline_tx.tx_hash(),
+        "output": output,
+    })))
+}
+
+/// Fails if a transfer in the store was created from the lock transaction
+fn check_lock_tx_unused(store: &LogStore, lock_tx: &H256) -> Result<(), String> {
+    for id in store.list_ids()? {
+        let log: ToCkbLog = store.load(&id)?;
+        if log.lock_tx.as_ref() == Some(lock_tx) {
+            return Err(format!(
+                "Lock transaction {:#x} is already used by transfer {} (status: {:?}), resume it with --transfer-id instead",
+                lock_tx, log.id, log.status
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Sign an ethereum transaction written by --eth-build-only (offline, no network access)
+fn sign_offline_eth_tx(
+    signer: &EthSigner,
+    tx_file: &Path,
+    output: &Path,
+) -> Result<Output, String> {
+    let mut offline_tx = OfflineEthTx::read(tx_file)?;
+    if offline_tx.raw_tx.is_some() {
+        return Err(format!(
+            "The transaction in {:?} is already signed",
+            tx_file
+        ));
+    }
+    offline_tx.sign(signer)?;
+    offline_tx.write(output)?;
+    Ok(Output::new_output(serde_json::json!({
+        "transfer_id": offline_tx.transfer_id,
+        "step": offline_tx.step,
+        "tx_hash": offline_tx.tx_hash(),
+        "output": output,
+    })))
+}
+
+/// Write the unsigned approve or lock transaction (`--eth-build-only`), the transfer stays
+/// in its status until the signed transaction is sent by `broadcast-eth-tx`
+fn write_unsigned_eth_tx(
+    eth_client: &mut EthRpcClient,
+    eth_args: &EthTxArgs,
+    log: &mut ToCkbLog,
+    step: &str,
+    to: &H160,
+    data: Vec<u8>,
+    path: &Path,
+) -> Result<(), String> {
+    let (tx, chain_id) =
+        eth_client.build_transaction(&eth_args.from, &eth_args.gas, Some(to), data)?;
+    let nonce = tx.nonce;
+    let offline_tx = OfflineEthTx::new(log.id.clone(), step, eth_args.from.clone(), chain_id, tx);
+    offline_tx.write(path)?;
+    log.pending_eth_tx = Some(PendingEthTx {
+        step: step.to_string(),
+        nonce,
+    });
+    Ok(())
+}
+
+fn cycles_exceeded_error(err: &str) -> String {
+    format!(
+        "The mint transaction exceeds the cycle limit of ckb ({} cycles), the proof is too expensive to verify, try a lock transaction in a block with fewer receipts (a smaller proof): {}",
+        MAX_VERIFY_CYCLES, err
+    )
+}
+
+fn unsigned_eth_tx_notice(step: &str, transfer_id: &str, path: &Path) -> String {
+    format!(
+        "The unsigned {} transaction of transfer {} is written to {:?}, sign it offline with sign-eth-tx, then send it with broadcast-eth-tx",
+        step, transfer_id, path
+    )
+}
+
+/// A transfer with an unsigned ethereum transaction waits for `broadcast-eth-tx`, unless the
+/// transaction is built again
+/// The burn transaction must burn the amount of the transfer for its recipient
+fn check_burn(log: &FromCkbLog, burned: u128, recipient: Option<H160>) -> Result<(), String> {
+    if Some(burned) != log.amount {
+        return Err(format!(
+            "Transfer {}: the burn transaction burns {}, not the amount {} of the transfer, abort",
+            log.id,
+            burned,
+            log.amount
+                .map(|amount| amount.to_string())
+                .unwrap_or_else(|| "(not recorded)".to_string())
+        ));
+    }
+    if recipient != log.recipient {
+        let show = |recipient: Option<&H160>| {
+            recipient
+                .map(checksum_address)
+                .unwrap_or_else(|| "none".to_string())
+        };
+        return Err(format!(
+            "Recipient mismatch: {} in the burn transaction, {} in the transfer {}, abort",
+            show(recipient.as_ref()),
+            show(log.recipient.as_ref()),
+            log.id
+        ));
+    }
+    Ok(())
+}
+
+fn check_pending_eth_tx(log: &ToCkbLog, rebuild: bool) -> Result<(), String> {
+    match log.pending_eth_tx.as_ref() {
+        Some(pending) if !rebuild => Err(format!(
+            "Transfer {} waits for the {} transaction (nonce {}) signed offline, send it with broadcast-eth-tx or build it again with --eth-build-only",
+            log.id, pending.step, pending.nonce
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn notify_transition<S: std::fmt::Debug>(
+    webhook: Option<&Webhook>,
+    direction: &str,
+    old_status: S,
+    event: &TransferEvent,
+) {
+    if let Some(webhook) = webhook {
+        webhook.notify(&Transition::new(
+            direction,
+            format!("{:?}", old_status),
+            event,
+        ));
+    }
+}
+
+fn required_eth_args(eth_args: &Option<EthTxArgs>) -> Result<&EthTxArgs, String> {
+    eth_args
+        .as_ref()
+        .ok_or_else(|| "<eth-privkey-path> or <eth-keystore> (or <eth-from-address> with --eth-build-only) is required by the approve and lock steps".to_string())
+}
+
+fn step_result<S: std::fmt::Debug>(
+    direction: &str,
+    transfer_id: &str,
+    old_status: S,
+    status: S,
+    result: Result<Option<H256>, String>,
+) -> StepResult {
+    let (tx_hash, error) = match result {
+        Ok(tx_hash) => (tx_hash, None),
+        Err(err) => (None, Some(err)),
+    };
+    StepResult {
+        transfer_id: transfer_id.to_string(),
+        direction: direction.to_string(),
+        old_status: format!("{:?}", old_status),
+        status: format!("{:?}", status),
+        tx_hash,
+        error,
+    }
+}
+
+fn load_failed(direction: &str, transfer_id: &str, error: String) -> StepResult {
+    StepResult {
+        transfer_id: transfer_id.to_string(),
+        direction: direction.to_string(),
+        old_status: String::new(),
+        status: String::new(),
+        tx_hash: None,
+        error: Some(error),
+    }
+}
+
+/// Approximate USD values of the transfer amount and the ckb fee, `None` without a price
+/// source or if no price is available, never fails the transfer
+fn estimate_usd(
+    price_source: Option<&mut PriceSource>,
+    config: &BridgeConfig,
+    log: &ToCkbLog,
+    ckb_fee: u64,
+) -> Option<UsdEstimate> {
+    let price_source = price_source?;
+    let token_config = config.tokens.get(&log.token);
+    price_source.estimate(
+        &log.token,
+        token_config.and_then(|token_config| token_config.symbol.as_deref()),
+        log.amount,
+        token_config.and_then(|token_config| token_config.decimals),
+        Some(ckb_fee),
+    )
+}
+
+/// Print one transfer event to stdout, flush immediately so external monitors see it live.
+/// With `--output-file` the event is appended to the file as one line (NDJSON) instead.
+fn print_event(
+    event: &TransferEvent,
+    pretty: bool,
+    output_file: Option<&Path>,
+) -> Result<(), String> {
+    if let Some(path) = output_file {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|err| format!("Create {:?} error: {}", dir, err))?;
+        }
+        let line = serde_json::to_string(event).map_err(|err| err.to_string())?;
+        return fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| writeln!(file, "{}", line))
+            .map_err(|err| format!("Write {:?} error: {}", path, err));
+    }
+    let line = if pretty {
+        serde_json::to_string_pretty(event)
+    } else {
+        serde_json::to_string(event)
+    }
+    .map_err(|err| err.to_string())?;
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    writeln!(handle, "{}", line)
+        .and_then(|()| handle.flush())
+        .map_err(|err| err.to_string())
+}
+
+/// The receipt status of a recorded ethereum transaction, `None` if not recorded or not mined
+fn eth_tx_success(
+    eth_client: &mut EthRpcClient,
+    tx_hash: Option<&H256>,
+) -> Result<Option<bool>, String> {
+    match tx_hash {
+        Some(tx_hash) => eth_client
+            .get_transaction_receipt(tx_hash)?
+            .map(|receipt| receipt.is_success())
+            .transpose(),
+        None => Ok(None),
+    }
+}
+
+/// Read a limit by the getter `name` of the bridge contract if it is in the ABI, the getter
+/// takes no input or the token address
+fn read_limit(
+    eth_client: &mut EthRpcClient,
+    bridge_abi: &ContractAbi,
+    contract: &H160,
+    name: &str,
+    token: &H160,
+) -> Result<Option<u128>, String> {
+    let getter = match bridge_abi.optional_function(name) {
+        Some(getter) if getter.read_only => getter,
+        _ => return Ok(None),
+    };
+    let data = getter
+        .encode_call(&[])
+        .or_else(|_| getter.encode_call(&[Token::Address(token.clone())]))?;
+    let result = eth_client
+        .call(&H160::default(), contract, &data)
+        .map_err(|err| format!("Call {} error: {}", getter.signature, err))?;
+    abi::decode_uint(result.as_bytes(), 0).map(Some)
+}
+
+/// Check the token contract has code, and its symbol and decimals match the config (if the
+/// getters are in the erc20 ABI)
+fn check_token(
+    eth_client: &mut EthRpcClient,
+    erc20_abi: &ContractAbi,
+    address: &H160,
+    token_config: &TokenConfig,
+) -> Result<String, String> {
+    if eth_client.get_code(address)?.as_bytes().is_empty() {
+        return Err(format!("No contract code at {}", checksum_address(address)));
+    }
+    let mut read = |name: &str| -> Result<Option<serde_json::Value>, String> {
+        match erc20_abi.optional_function(name) {
+            Some(getter) if getter.read_only => {
+                let data = getter.encode_call(&[])?;
+                let result = eth_client.call(&H160::default(), address, &data)?;
+                getter.decode_output(result.as_bytes()).map(Some)
+            }
+            _ => Ok(None),
+        }
+    };
+    let symbol = read("symbol")?.and_then(|value| value.as_str().map(ToOwned::to_owned));
+    let decimals = read("decimals")?
+        .and_then(|value| value.as_str().and_then(|value| value.parse::<u8>().ok()));
+    erc20::check_metadata(token_config, symbol.as_deref(), decimals)
+}
+
+/// Verify the holder signed the intent of the new transfer, and the `lock()` of the bridge
+/// ABI takes the authorization
+fn verify_intent(
+    config: &BridgeConfig,
+    token: &H160,
+    args: &ToCkbArgs,
+    intent: &IntentArgs,
+) -> Result<IntentAuthorization, String> {
+    let authorization = TransferIntent {
+        token: token.clone(),
+        amount: args.amount,
+        recipient_lock: args.recipient_lock.as_slice().to_vec(),
+        nonce: intent.nonce,
+        deadline: intent.deadline,
+    }
+    .verify(&intent.signature, &intent.signer, now_secs())?;
+    let lock = config.abis()?.bridge.function("lock")?;
+    lock.encode_call(&[
+        Token::Address(token.clone()),
+        Token::Uint(args.amount),
+        Token::Bytes(args.recipient_lock.as_slice().to_vec()),
+        Token::Bytes(authorization.encode()),
+    ])
+    .map_err(|_| {
+        format!(
+            "{} of the bridge ABI takes no authorization argument, the transfer intent can't be relayed",
+            lock.signature
+        )
+    })?;
+    Ok(authorization)
+}
+
+/// Abort before an approve or lock transaction reverted by a paused bridge, only checked if
+/// `paused()` is in the bridge ABI
+fn check_not_paused(
+    config: &BridgeConfig,
+    eth_client: &mut EthRpcClient,
+    contract: &H160,
+) -> Result<(), String> {
+    let bridge_abi = config.abis()?.bridge;
+    let paused = match bridge_abi.optional_function("paused") {
+        Some(paused) if paused.is_getter() => paused,
+        _ => return Ok(()),
+    };
+    let result = eth_client.call(&H160::default(), contract, &paused.encode_call(&[])?)?;
+    if paused.decode_output(result.as_bytes())? == serde_json::json!(true) {
+        return Err(format!(
+            "The bridge is currently paused (bridge contract: {}), please try again later",
+            checksum_address(contract)
+        ));
+    }
+    Ok(())
+}
+
+/// Whether the block can be minted from: it is at or below the safe (finalized) block, or
+/// has enough confirmations without one. The light client must have the header in any case.
+fn is_block_safe(block_number: u64, tip_number: u64, final_number: Option<u64>) -> bool {
+    match final_number {
+        Some(final_number) => final_number >= block_number && tip_number >= block_number,
+        None => tip_number >= block_number + ETH_CONFIRMATIONS,
+    }
+}
+
+/// The read-only check of a transfer waiting for its sent transaction, `resume-all` runs them
+/// concurrently and only steps the transfers whose transaction is done
+enum WaitCheck {
+    /// The approve or lock transaction is sent, the step waits for its receipt
+    EthReceipt(H256),
+    /// The mint transaction is sent, the step waits until it is committed (or dropped)
+    CkbCommitted(H256),
+}
+
+impl WaitCheck {
+    fn of(log: &ToCkbLog) -> Option<WaitCheck> {
+        match log.status {
+            ToCkbLogStatus::UnKnow => log.approve_tx.clone().map(WaitCheck::EthReceipt),
+            ToCkbLogStatus::Approved => log.lock_tx.clone().map(WaitCheck::EthReceipt),
+            ToCkbLogStatus::Mint => log.mint_tx.clone().map(WaitCheck::CkbCommitted),
+            _ => None,
+        }
+    }
+
+    /// A failed check is ready, so the step runs and reports the error
+    fn is_ready(
+        &self,
+        eth_url: &str,
+        eth_rpc_timeout: Duration,
+        ckb_url: &str,
+        ckb_rpc_timeout: Duration,
+    ) -> bool {
+        let result = match self {
+            WaitCheck::EthReceipt(tx_hash) => EthRpcClient::new(eth_url, eth_rpc_timeout)
+                .and_then(|mut eth_client| eth_client.get_transaction_receipt(tx_hash))
+                .map(|receipt| receipt.is_some()),
+            WaitCheck::CkbCommitted(tx_hash) => {
+                let mut rpc_client = HttpRpcClient::new(ckb_url.to_string());
+                rpc_client.set_timeout(ckb_rpc_timeout);
+                rpc_client.set_max_throttle_wait(MAX_THROTTLE_WAIT);
+                rpc_client
+                    .get_transaction(tx_hash.clone())
+                    .map(|tx_with_status| match tx_with_status {
+                        Some(tx_with_status) => {
+                            tx_with_status.tx_status.status == Status::Committed
+                        }
+                        // Dropped, the step builds it again
+                        None => true,
+                    })
+            }
+        };
+        result.unwrap_or(true)
+    }
+}
+
+/// The state, sender and nonce of an ethereum transaction recorded in a transfer
+fn recorded_eth_tx(
+    eth_client: &mut EthRpcClient,
+    transfer_id: &str,
+    step: &str,
+    tx_hash: &H256,
+) -> Result<RecordedEthTx, String> {
+    let tx = eth_client.get_transaction(tx_hash)?;
+    let from = tx
+        .as_ref()
+        .and_then(|tx| serde_json::from_value::<H160>(tx["from"].clone()).ok());
+    let nonce = tx
+        .as_ref()
+        .and_then(|tx| tx["nonce"].as_str())
+        .map(parse_quantity)
+        .transpose()?;
+    let state = match eth_client.get_transaction_receipt(tx_hash)? {
+        Some(receipt) => {
+            if receipt.is_success()? {
+                EthTxState::Mined
+            } else {
+                EthTxState::Reverted
+            }
+        }
+        None if tx.is_some() => EthTxState::Pending,
+        None => EthTxState::Dropped,
+    };
+    Ok(RecordedEthTx {
+        transfer_id: transfer_id.to_string(),
+        step: step.to_string(),
+        tx_hash: Some(tx_hash.clone()),
+        from,
+        nonce,
+        state,
+    })
+}
+
+/// Record the gas used and the gas price of a mined ethereum transaction of the transfer
+fn record_eth_cost(
+    eth_client: &mut EthRpcClient,
+    log: &mut ToCkbLog,
+    receipt: &EthReceipt,
+) -> Result<(), String> {
+    log.record_eth_cost(EthTxCost {
+        tx_hash: receipt.transaction_hash.clone(),
+        gas_used: receipt.gas_used()?,
+        gas_price: eth_client.effective_gas_price(receipt)?,
+    });
+    Ok(())
+}
+
+/// The stored proof, checked against the stored lock block
+fn parse_log_proof(log: &ToCkbLog) -> Result<ReceiptProof, String> {
+    let proof = log
+        .proof
+        .as_ref()
+        .ok_or_else(|| format!("Transfer {} has no proof", log.id))?;
+    let proof = ReceiptProof::from_bytes(proof.as_bytes())?;
+    log.check_lock_block(proof.block_number, &proof.block_hash)?;
+    Ok(proof)
+}
+
+fn lock_block(proof: &ReceiptProof) -> LockBlock {
+    LockBlock {
+        number: proof.block_number,
+        hash: proof.block_hash.clone(),
+    }
+}
+
+/// The lock event in the proven receipt, the event ABI is checked first with `--strict-abi`
+fn parse_lock_event(
+    config: &BridgeConfig,
+    step_args: &ToCkbStepArgs,
+    contract: &H160,
+    proof: &ReceiptProof,
+) -> Result<LockEvent, String> {
+    let receipt = decode_receipt(&proof.receipt)?;
+    lock_event_from_logs(config, step_args, contract, &receipt.logs)
+}
+
+/// Decode the lock event emitted by the bridge `contract` among the logs of the lock
+/// transaction receipt, `--strict-abi` checks the configured event signature first
+fn lock_event_from_logs(
+    config: &BridgeConfig,
+    step_args: &ToCkbStepArgs,
+    contract: &H160,
+    logs: &[DecodedLog],
+) -> Result<LockEvent, String> {
+    let bridge_abi = config.abis()?.bridge;
+    let event = bridge_abi.event("Locked")?;
+    if step_args.strict_abi {
+        let signature = config
+            .lock_event_signature
+            .as_deref()
+            .unwrap_or(&event.signature);
+        check_lock_event_abi(logs, contract, signature, event)?;
+    }
+    LockEvent::from_logs(logs, contract, event)
+}
+
+/// Decode and verify a receipt proof offline
+fn decode_proof(data: &[u8], backend: &dyn ProofBackend) -> Result<Output, String> {
+    let proof = ReceiptProof::from_bytes(data)?;
+    Ok(Output::new_output(proof_json(&proof, backend)?))
+}
+
+fn proof_json(
+    proof: &ReceiptProof,
+    backend: &dyn ProofBackend,
+) -> Result<serde_json::Value, String> {
+    let receipt = decode_receipt(&proof.receipt)?;
+    let nodes = proof
+        .nodes
+        .iter()
+        .map(|node| {
+            serde_json::json!({
+                "hash": H256::from(keccak256(node)),
+                "size": node.len(),
+            })
+        })
+        .collect::<Vec<_>>();
+    let verify_error = backend.verify(proof).err();
+    Ok(serde_json::json!({
+        "block_number": proof.block_number,
+        "block_hash": proof.block_hash,
+        "receipts_root": proof.receipts_root,
+        "receipt_index": proof.receipt_index,
+        "receipt": receipt,
+        "nodes": nodes,
+        "backend": backend.name(),
+        "valid": verify_error.is_none(),
+        "error": verify_error,
+    }))
+}
+
+impl<'a> CkbBridgeSubCommand<'a> {
+    /// Drop the address cache with `--refresh-addresses` and resolve the deployment of
+    /// `--registry-address`, the subcommand then uses it instead of the config
+    fn resolve_registry_arg(&mut self, m: &ArgMatches) -> Result<(), String> {
+        if m.is_present("refresh-addresses") {
+            let path = self.address_cache_path()?;
+            if path.exists() {
+                fs::remove_file(&path)
+                    .map_err(|err| format!("Remove {:?} error: {}", path, err))?;
+                self.notice(format!("Dropped the address cache {:?}", path));
+            }
+        }
+        if let Some(address) =
+            FixedHashParser::<H160>::default().from_matches_opt(m, "registry-address", false)?
+        {
+            let config = self.load_config()?;
+            let registry = self.resolve_registry(&config, &address)?;
+            self.notice(format!(
+                "Registry {}: bridge contract {}, {} tokens, light client {}",
+                checksum_address(&address),
+                registry
+                    .bridge_contract
+                    .as_ref()
+                    .map(checksum_address)
+                    .unwrap_or_else(|| "not published".to_string()),
+                registry.tokens.len(),
+                if registry.light_client_type_script.is_some() {
+                    "published"
+                } else {
+                    "not published"
+                }
+            ));
+            self.registry = Some(registry);
+        }
+        Ok(())
+    }
+
+    fn process_subcommand(&mut self, matches: &ArgMatches, debug: bool) -> Result<Output, String> {
+        if let (name, Some(m)) = matches.subcommand() {
+            self.set_rpc_timeouts(m)?;
+            self.bridge_abi = FilePathParser::new(true).from_matches_opt(m, "bridge-abi", false)?;
+            self.erc20_abi = FilePathParser::new(true).from_matches_opt(m, "erc20-abi", false)?;
+            self.proof_backend = m.value_of("proof-backend").map(ToString::to_string);
+            self.eth_rpc_url = UrlParser
+                .from_matches_opt(m, "eth-rpc-url", false)?
+                .map(|url| url.to_string());
+            self.config_file = FilePathParser::new(false).from_matches_opt(m, "config", false)?;
+            self.ckb_network = m
+                .value_of("ckb-network")
+                .and_then(NetworkType::from_raw_str);
+            self.dev_genesis = m.is_present("dev-genesis");
+            self.quiet = m.is_present("quiet");
+            self.trim_amount_zeros = m.is_present("trim-amount-zeros");
+            self.ckb_reorg_confirmations =
+                FromStrParser::<u64>::default().from_matches(m, "ckb-reorg-confirmations")?;
+            self.output_file =
+                FilePathParser::new(false).from_matches_opt(m, "output-file", false)?;
+            self.real_transfers = m.is_present("i-understand-the-risks")
+                || env::var(REAL_TRANSFERS_ENV).ok().as_deref() == Some("1");
+            if let Some(data_dir) =
+                FilePathParser::new(false).from_matches_opt(m, "data-dir", false)?
+            {
+                self.bridge_dir = data_dir;
+            }
+            self.address_cache_ttl = DurationParser.from_matches(m, "address-cache-ttl")?;
+            self.registry = None;
+            if !OFFLINE_SUBCOMMANDS.contains(&name) {
+                self.migrate_from_index_dir()?;
+                self.resolve_registry_arg(m)?;
+            }
+        }
+        match matches.subcommand() {
+            ("transfer-erc20-to-ckb", Some(m)) => {
+                let network_type = get_network_type(self.rpc_client)?;
+                let to_ckb_args = ToCkbArgs::from_matches_opt(m, network_type)?;
+                let mut step_args = ToCkbStepArgs::from_matches(m, network_type)?;
+                self.safe_mode_dry_run("transfer-erc20-to-ckb", &mut step_args)?;
+                let price_source = UrlParser
+                    .from_matches_opt(m, "price-source", false)?
+                    .map(PriceSource::new)
+                    .transpose()?;
+                self.transfer_to_ckb(
+                    TransferArgs::from_matches(m)?,
+                    to_ckb_args,
+                    step_args,
+                    price_source,
+                    debug,
+                )
+            }
+            ("mint-from-lock", Some(m)) => {
+                let network_type = get_network_type(self.rpc_client)?;
+                let lock_tx: H256 =
+                    FixedHashParser::<H256>::default().from_matches(m, "eth-tx-hash")?;
+                let mut step_args = ToCkbStepArgs::from_matches(m, network_type)?;
+                self.safe_mode_dry_run("mint-from-lock", &mut step_args)?;
+                let confirm_recipient: Option<Address> =
+                    AddressParser::default().from_matches_opt(m, "confirm-recipient", false)?;
+                self.mint_from_lock(
+                    lock_tx,
+                    step_args,
+                    confirm_recipient,
+                    m.is_present("pretty"),
+                    debug,
+                )
+            }
+            ("sign-tx", Some(m)) => {
+                let privkey: PrivkeyWrapper = PrivkeyPathParser.from_matches(m, "privkey-path")?;
+                let tx_file: PathBuf = FilePathParser::new(true).from_matches(m, "tx-file")?;
+                let output: PathBuf = FilePathParser::new(false).from_matches(m, "output")?;
+                sign_offline_tx(privkey, &tx_file, &output)
+            }
+            ("sign-eth-tx", Some(m)) => {
+                let privkey: PrivkeyWrapper =
+                    PrivkeyPathParser.from_matches(m, "eth-privkey-path")?;
+                let tx_file: PathBuf = FilePathParser::new(true).from_matches(m, "tx-file")?;
+                let output: PathBuf = FilePathParser::new(false).from_matches(m, "output")?;
+                sign_offline_eth_tx(&EthSigner::new(privkey), &tx_file, &output)
+            }
+            ("sign-transfer-intent", Some(m)) => {
+                let network_type = get_network_type(self.rpc_client)?;
+                let args = ToCkbArgs::from_matches_opt(m, network_type)?
+                    .ok_or_else(|| "<token> and <amount> are required".to_string())?;
+                let privkey: PrivkeyWrapper =
+                    PrivkeyPathParser.from_matches(m, "eth-privkey-path")?;
+                let nonce: u64 = FromStrParser::<u64>::default().from_matches(m, "intent-nonce")?;
+                let expires_in = DurationParser.from_matches(m, "expires-in")?;
+                self.sign_transfer_intent(&EthSigner::new(privkey), args, nonce, expires_in)
+            }
+            ("broadcast-eth-tx", Some(m)) => {
+                self.require_real_transfers("broadcast-eth-tx")?;
+                let tx_file: PathBuf = FilePathParser::new(true).from_matches(m, "tx-file")?;
+                self.broadcast_eth_tx(&tx_file)
+            }
+            ("transfer-erc20-from-ckb", Some(m)) => {
+                let network_type = get_network_type(self.rpc_client)?;
+                let mut step_args = FromCkbStepArgs::from_matches(m, network_type)?;
+                self.safe_mode_dry_run_from_ckb(&mut step_args);
+                self.transfer_from_ckb(
+                    TransferArgs::from_matches(m)?,
+                    FromCkbArgs::from_matches_opt(m)?,
+                    step_args,
+                    EthTxArgs::from_matches_opt(m)?,
+                    debug,
+                )
+            }
+            ("deploy-sol", Some(m)) => {
+                self.require_real_transfers("deploy-sol")?;
+                let eth_args = EthTxArgs::from_matches(m)?;
+                let path: PathBuf = FilePathParser::new(true).from_matches(m, "artifact")?;
+                let content = fs::read_to_string(&path)
+                    .map_err(|err| format!("Read {:?} error: {}", path, err))?;
+                let artifact = ContractArtifact::from_json(&content)?;
+                let constructor_args: Vec<u8> = HexParser
+                    .from_matches_opt(m, "constructor-args", false)?
+                    .unwrap_or_default();
+                self.deploy_sol(&eth_args, &artifact, constructor_args)
+            }
+            ("deploy-ckb", Some(m)) => {
+                self.require_real_transfers("deploy-ckb")?;
+                let network_type = get_network_type(self.rpc_client)?;
+                let tx_args = CkbTxArgs::from_matches(m, network_type)?;
+                let read_binary = |name| -> Result<Bytes, String> {
+                    let path: PathBuf = FilePathParser::new(true).from_matches(m, name)?;
+                    fs::read(&path)
+                        .map(Bytes::from)
+                        .map_err(|err| format!("Read {:?} error: {}", path, err))
+                };
+                let bridge_binary = read_binary("bridge-script")?;
+                let light_client_binary = read_binary("light-client-script")?;
+                let type_id = m.is_present("type-id");
+                self.deploy_ckb(&tx_args, bridge_binary, light_client_binary, type_id, debug)
+            }
+            ("relay-header", Some(m)) => {
+                self.require_real_transfers("relay-header")?;
+                let network_type = get_network_type(self.rpc_client)?;
+                let tx_args = CkbTxArgs::from_matches(m, network_type)?;
+                let from_block: Option<u64> =
+                    FromStrParser::<u64>::default().from_matches_opt(m, "from-block", false)?;
+                let to_block: Option<u64> =
+                    FromStrParser::<u64>::default().from_matches_opt(m, "to-block", false)?;
+                let headers_per_tx: u64 =
+                    FromStrParser::<u64>::default().from_matches(m, "headers-per-tx")?;
+                self.relay_header(&tx_args, from_block, to_block, headers_per_tx, debug)
+            }
+            ("resume-all", Some(m)) => {
+                let network_type = get_network_type(self.rpc_client)?;
+                let mut step_args = ToCkbStepArgs::from_matches(m, network_type)?;
+                self.safe_mode_dry_run("resume-all", &mut step_args)?;
+                let mut from_ckb_args = FromCkbStepArgs::from_matches(m, network_type)?;
+                // Notified by `safe_mode_dry_run` already
+                if !self.real_transfers {
+                    from_ckb_args.dry_run_ckb = true;
+                    from_ckb_args.dry_run_ethereum = true;
+                }
+                let directions = match m.value_of("direction") {
+                    Some("to-ckb") => vec![DIRECTION_TO_CKB],
+                    Some("from-ckb") => vec![DIRECTION_FROM_CKB],
+                    _ => vec![DIRECTION_TO_CKB, DIRECTION_FROM_CKB],
+                };
+                let progress_mode = if self.quiet {
+                    ProgressMode::None
+                } else {
+                    ProgressMode::parse(m.value_of("progress").unwrap_or("auto"), is_a_tty(true))?
+                };
+                let max_concurrent: Option<usize> = FromStrParser::<usize>::default()
+                    .from_matches_opt(m, "max-concurrent", false)?;
+                if max_concurrent == Some(0) {
+                    return Err("<max-concurrent> must be greater than 0".to_string());
+                }
+                self.resume_all(
+                    &step_args,
+                    &from_ckb_args,
+                    &directions,
+                    progress_mode,
+                    max_concurrent,
+                    debug,
+                )
+            }
+            ("history", Some(m)) => {
+                let directions = match m.value_of("direction") {
+                    Some("to-ckb") => vec![DIRECTION_TO_CKB],
+                    Some("from-ckb") => vec![DIRECTION_FROM_CKB],
+                    _ => vec![DIRECTION_TO_CKB, DIRECTION_FROM_CKB],
+                };
+                if m.is_present("summary-only") {
+                    return self.transfer_summary(&directions);
+                }
+                let limit: usize = if m.is_present("count") {
+                    0
+                } else {
+                    FromStrParser::<usize>::default().from_matches(m, "limit")?
+                };
+                let offset: usize = FromStrParser::<usize>::default().from_matches(m, "offset")?;
+                let newest_first = m.value_of("sort") != Some("oldest");
+                self.history(&directions, offset, limit, newest_first)
+            }
+            ("list-deployments", Some(_m)) => self.list_deployments(),
+            ("doctor", Some(_m)) => self.doctor(),
+            ("bridge-state", Some(_m)) => self.bridge_state(),
+            ("bridge-info", Some(m)) => {
+                if m.is_present("summary-only") {
+                    self.transfer_summary(&[DIRECTION_TO_CKB, DIRECTION_FROM_CKB])
+                } else {
+                    self.bridge_info()
+                }
+            }
+            ("tokens", Some(m)) => {
+                self.list_tokens(m.is_present("check"), m.is_present("check-supply"))
+            }
+            ("reconcile", Some(_m)) => self.reconcile(),
+            ("list-pending-eth-txs", Some(_m)) => self.list_pending_eth_txs(),
+            ("clean-locks", Some(m)) => self.clean_locks(m.is_present("force")),
+            ("rebuild-proof", Some(m)) => {
+                let transfer_id = m.value_of("transfer-id").unwrap();
+                self.rebuild_proof(transfer_id)
+            }
+            ("decode-proof", Some(m)) => {
+                let proof =
+                    match FilePathParser::new(true).from_matches_opt(m, "proof-file", false)? {
+                        Some(path) => {
+                            let content =
+                                fs::read_to_string(&path).map_err(|err| err.to_string())?;
+                            HexParser.parse(content.trim())?
+                        }
+                        None => HexParser.from_matches(m, "proof")?,
+                    };
+                let block_override = EitherParser::new(
+                    FixedHashParser::<H256>::default(),
+                    FromStrParser::<u64>::default(),
+                )
+                .from_matches_opt(m, "proof-block-override", false)?;
+                match block_override {
+                    Some(block) => self.decode_proof_at_block(&proof, block),
+                    None => decode_proof(&proof, self.load_config()?.proof_backend()?.as_ref()),
+                }
+            }
+            _ => Err(Self::subcommand().generate_usage()),
+        }
+    }
+}
+
+impl<'a> CliSubCommand for CkbBridgeSubCommand<'a> {
+    fn process(&mut self, matches: &ArgMatches, debug: bool) -> Result<Output, String> {
+        let ckb_rpc_url = match matches.subcommand() {
+            (_, Some(m)) => UrlParser.from_matches_opt(m, "ckb-rpc-url", false)?,
+            _ => None,
+        };
+        let url = match ckb_rpc_url {
+            Some(url)
+                if !OFFLINE_SUBCOMMANDS.contains(&matches.subcommand_name().unwrap_or("")) =>
+            {
+                url
+            }
+            _ => {
+                let result = self.process_subcommand(matches, debug);
+                return Ok(self.finish(result));
+            }
+        };
+        // Only this subcommand uses the given node, restore the shared client (and the genesis
+        // of its chain) afterwards
+        let original_client = mem::replace(self.rpc_client, HttpRpcClient::new(url.to_string()));
+        let original_genesis = self.genesis_info.take();
+        let result = self
+            .check_ckb_rpc(matches)
+            .and_then(|()| self.process_subcommand(matches, debug));
+        *self.rpc_client = original_client;
+        self.genesis_info = original_genesis;
+        Ok(self.finish(result))
+    }
+}
+
+/// What went wrong opening the index database in `dir` and what to do about it
+fn index_db_error(err: ckb_index::Error, dir: &Path, state: &IndexThreadState) -> String {
+    match err {
+        ckb_index::Error::Index(IndexError::InvalidGenesis(detail)) => {
+            BridgeError::network_mismatch(format!(
+                "The index database {:?} was built for another chain (genesis {}), delete the \
+                 directory to rebuild the index, the bridge state is kept in the data directory",
+                dir, detail
+            ))
+            .into()
+        }
+        ckb_index::Error::Index(IndexError::InvalidNetworkType(detail)) => {
+            BridgeError::network_mismatch(format!(
+                "The index database {:?} was built for another network ({}), delete the \
+                 directory to rebuild the index, the bridge state is kept in the data directory",
+                dir, detail
+            ))
+            .into()
+        }
+        ckb_index::Error::Io(err) => format!(
+            "The index directory {:?} is missing or not writable: {}, check the permissions of \
+             CKB_CLI_HOME",
+            dir, err
+        ),
+        err => match (state.sync_progress(), state.get_error()) {
+            (_, Some(state_err)) => format!("The index thread failed: {}", state_err),
+            (Some((number, tip_number)), _) if number < tip_number => format!(
+                "The index is still syncing: block {} of tip {}, retry later or run with \
+                 --wait-for-sync",
+                number, tip_number
+            ),
+            (None, _) => format!(
+                "The index is not ready ({}), retry later or run with --wait-for-sync",
+                state
+            ),
+            (Some(_), _) => format!(
+                "Open the index database {:?} error: {} (is it opened by another ckb-cli?)",
+                dir, err
+            ),
+        },
+    }
+}
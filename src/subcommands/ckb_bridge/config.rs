@@ -0,0 +1,262 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ckb_jsonrpc_types::{CellDep, OutPoint, Script, ScriptHashType};
+use ckb_sdk::NetworkType;
+use ckb_types::{H160, H256};
+use serde_derive::{Deserialize, Serialize};
+
+use super::abi::BridgeAbis;
+use super::exit_code::BridgeError;
+use super::mint::{AmountLimits, TokenUnit};
+use super::proof::{self, ProofBackend};
+use crate::utils::arg_parser::{ArgParser, FixedHashParser};
+
+pub const DEFAULT_ETH_RPC_URL: &str = "http://127.0.0.1:8545";
+
+/// Bridge config, stored as json in `<ckb-cli-home>/bridge/config.json`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BridgeConfig {
+    /// Http or websocket (`ws://`, `wss://`) url, a websocket is also subscribed to new heads
+    /// while waiting for confirmations
+    pub eth_rpc_url: String,
+    /// The bridge contract on ethereum, set by `deploy-sol`
+    #[serde(default)]
+    pub bridge_contract: Option<H160>,
+    /// Signature of the `Locked` event of the deployed bridge contract, checked against the
+    /// receipt logs with `--strict-abi`, the `Locked` event in the bridge ABI if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lock_event_signature: Option<String>,
+    /// Json ABI file of the bridge contract, the built-in ABI is used if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bridge_abi: Option<PathBuf>,
+    /// Json ABI file of the erc20 tokens, the built-in ABI is used if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub erc20_abi: Option<PathBuf>,
+    /// The scheme of the lock proofs verified by the bridge script, `receipt-mpt` if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof_backend: Option<String>,
+    /// The ckb network (`ckb`, `ckb_testnet`, `ckb_staging` or `ckb_dev`) of the transfer logs
+    /// read by history and the summaries, the network of the ckb node if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ckb_network: Option<String>,
+    #[serde(default)]
+    pub light_client: Option<LightClientConfig>,
+    /// The toCKB script on ckb, set by `deploy-ckb`
+    #[serde(default)]
+    pub bridge_script: Option<ScriptConfig>,
+    /// Per erc20 token settings, keyed by the token contract address
+    #[serde(default)]
+    pub tokens: HashMap<H160, TokenConfig>,
+}
+
+/// Settings for a known erc20 token
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TokenConfig {
+    /// Human readable symbol (e.g. "USDC"), can be used instead of the contract address
+    #[serde(default)]
+    pub symbol: Option<String>,
+    #[serde(default)]
+    pub decimals: Option<u8>,
+    /// Decimals of the bridged token on ckb, the minted amount is scaled from `decimals`
+    /// to this if both are set
+    #[serde(default)]
+    pub ckb_decimals: Option<u8>,
+    /// Reset a nonzero allowance to zero before approving a new value (e.g. USDT)
+    #[serde(default)]
+    pub requires_zero_first: bool,
+    /// The token takes a fee on transfer (deflationary), the bridge contract receives less
+    /// than the locked amount, mint the amount in the lock event instead
+    #[serde(default)]
+    pub fee_on_transfer: bool,
+    /// Require `approve()` to return a bool, by default a token returning nothing (e.g. USDT)
+    /// is accepted and the receipt status decides
+    #[serde(default)]
+    pub strict_bool_return: bool,
+    /// Per transfer limits of the locked amount, checked with the limits read from the bridge
+    /// contract (`minLockAmount()` / `maxLockAmount()`) before approve and lock
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_amount: Option<u128>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_amount: Option<u128>,
+}
+
+/// The on-chain ethereum light client cell
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LightClientConfig {
+    /// Type script of the light client cell, used to locate the (unique) live cell
+    pub type_script: Script,
+    /// Cell deps required by the light client cell's lock and type script
+    pub cell_deps: Vec<CellDep>,
+    /// The light client cell when the config was written, it moves on every relay, so the
+    /// cell is searched by the type script once this is consumed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub out_point: Option<OutPoint>,
+}
+
+/// A script deployed on ckb
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScriptConfig {
+    pub code_hash: H256,
+    pub hash_type: ScriptHashType,
+    pub cell_dep: CellDep,
+    /// The error code of the toCKB type script rejecting an already used proof, a resumed
+    /// mint failing with it is treated as minted by a previous run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof_used_error: Option<i8>,
+}
+
+impl Default for BridgeConfig {
+    fn default() -> BridgeConfig {
+        BridgeConfig {
+            eth_rpc_url: DEFAULT_ETH_RPC_URL.to_string(),
+            bridge_contract: None,
+            lock_event_signature: None,
+            bridge_abi: None,
+            erc20_abi: None,
+            proof_backend: None,
+            ckb_network: None,
+            light_client: None,
+            bridge_script: None,
+            tokens: HashMap::new(),
+        }
+    }
+}
+
+impl BridgeConfig {
+    /// Load config from file, return default config if the file not exists
+    pub fn load(path: &Path) -> Result<BridgeConfig, String> {
+        if !path.exists() {
+            return Ok(BridgeConfig::default());
+        }
+        let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        serde_json::from_str(&content).map_err(|err| {
+            BridgeError::config(format!("Parse bridge config {:?} error: {}", path, err)).into()
+        })
+    }
+
+    /// Load the contract ABIs, checking the functions and events used are present
+    pub fn abis(&self) -> Result<BridgeAbis, String> {
+        BridgeAbis::load(self.bridge_abi.as_deref(), self.erc20_abi.as_deref())
+    }
+
+    pub fn proof_backend(&self) -> Result<Box<dyn ProofBackend>, String> {
+        proof::proof_backend(self.proof_backend.as_deref())
+    }
+
+    pub fn ckb_network(&self) -> Result<Option<NetworkType>, String> {
+        self.ckb_network
+            .as_ref()
+            .map(|network| {
+                NetworkType::from_raw_str(network).ok_or_else(|| {
+                    BridgeError::config(format!("Unknown ckb_network in the config: {}", network))
+                        .into()
+                })
+            })
+            .transpose()
+    }
+
+    pub fn requires_zero_first(&self, token: &H160) -> bool {
+        self.tokens
+            .get(token)
+            .map(|token_config| token_config.requires_zero_first)
+            .unwrap_or(false)
+    }
+
+    pub fn fee_on_transfer(&self, token: &H160) -> bool {
+        self.tokens
+            .get(token)
+            .map(|token_config| token_config.fee_on_transfer)
+            .unwrap_or(false)
+    }
+
+    pub fn strict_bool_return(&self, token: &H160) -> bool {
+        self.tokens
+            .get(token)
+            .map(|token_config| token_config.strict_bool_return)
+            .unwrap_or(false)
+    }
+
+    pub fn amount_limits(&self, token: &H160) -> AmountLimits {
+        self.tokens
+            .get(token)
+            .map(|token_config| AmountLimits {
+                min: token_config.min_amount,
+                max: token_config.max_amount,
+            })
+            .unwrap_or_default()
+    }
+
+    /// The configured decimals of the token on ethereum
+    pub fn token_decimals(&self, token: &H160) -> Option<u8> {
+        self.tokens
+            .get(token)
+            .and_then(|token_config| token_config.decimals)
+    }
+
+    /// How the amounts of the token (on ethereum) are shown, with the configured decimals and
+    /// symbol
+    pub fn token_unit(&self, token: &H160, trim_zeros: bool) -> TokenUnit {
+        let token_config = self.tokens.get(token);
+        TokenUnit {
+            decimals: token_config.and_then(|token_config| token_config.decimals),
+            symbol: token_config.and_then(|token_config| token_config.symbol.clone()),
+            trim_zeros,
+        }
+    }
+
+    /// The `(decimals, ckb_decimals)` of the token if the amount is scaled when minted
+    pub fn decimals_conversion(&self, token: &H160) -> Option<(u8, u8)> {
+        self.tokens.get(token).and_then(|token_config| {
+            match (token_config.decimals, token_config.ckb_decimals) {
+                (Some(decimals), Some(ckb_decimals)) if decimals != ckb_decimals => {
+                    Some((decimals, ckb_decimals))
+                }
+                _ => None,
+            }
+        })
+    }
+
+    /// Resolve a token given as contract address (hex) or a symbol configured in `tokens`
+    pub fn resolve_token(&self, input: &str) -> Result<H160, String> {
+        if input.starts_with("0x") || input.starts_with("0X") {
+            return FixedHashParser::<H160>::default().parse(input);
+        }
+        let mut matched = self.tokens.iter().filter(|(_, token_config)| {
+            token_config
+                .symbol
+                .as_ref()
+                .map(|symbol| symbol.eq_ignore_ascii_case(input))
+                .unwrap_or(false)
+        });
+        match (matched.next(), matched.next()) {
+            (Some((address, _)), None) => Ok(address.clone()),
+            (Some(_), Some(_)) => Err(format!(
+                "Token symbol {} is ambiguous, please use the contract address",
+                input
+            )),
+            (None, _) => {
+                let mut symbols = self
+                    .tokens
+                    .values()
+                    .filter_map(|token_config| token_config.symbol.clone())
+                    .collect::<Vec<_>>();
+                symbols.sort();
+                Err(format!(
+                    "Unknown token symbol: {}, known symbols: [{}]",
+                    input,
+                    symbols.join(", ")
+                ))
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        let content = serde_json::to_string_pretty(self).map_err(|err| err.to_string())?;
+        fs::write(path, content).map_err(|err| err.to_string())
+    }
+}
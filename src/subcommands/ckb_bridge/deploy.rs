@@ -0,0 +1,255 @@
+use ckb_chain_spec::consensus::TYPE_ID_CODE_HASH;
+use ckb_hash::new_blake2b;
+use ckb_index::LiveCellInfo;
+use ckb_types::{
+    bytes::Bytes,
+    core::{Capacity, ScriptHashType, TransactionBuilder, TransactionView},
+    packed::{Byte32, CellDep, CellInput, CellOutput, OutPoint, Script},
+    prelude::*,
+};
+
+use super::relay::LIGHT_CLIENT_DATA_SIZE;
+
+/// A cell holding the script binary in its data, the capacity is the minimal occupied
+/// capacity. With `type_id` the cell gets a type id type script (filled in by
+/// `build_deploy_tx`), so the code can be referenced by type and upgraded in place.
+pub fn code_cell(
+    binary: Bytes,
+    lock: Script,
+    type_id: bool,
+) -> Result<(CellOutput, Bytes), String> {
+    let type_script = if type_id {
+        Some(type_id_script(&CellInput::default(), 0))
+    } else {
+        None
+    };
+    let output = CellOutput::new_builder()
+        .lock(lock)
+        .type_(type_script.pack())
+        .build();
+    let capacity = output
+        .occupied_capacity(Capacity::bytes(binary.len()).map_err(|err| err.to_string())?)
+        .map_err(|err| err.to_string())?;
+    Ok((
+        output.as_builder().capacity(capacity.pack()).build(),
+        binary,
+    ))
+}
+
+/// Script referencing the code cell by data hash
+pub fn data_script(binary: &[u8], args: Bytes) -> Script {
+    Script::new_builder()
+        .code_hash(CellOutput::calc_data_hash(binary))
+        .hash_type(ScriptHashType::Data.into())
+        .args(args.pack())
+        .build()
+}
+
+/// The type id script of the output at `output_index` of a transaction, the args is the hash
+/// of the first input and the output index.
+pub fn type_id_script(first_input: &CellInput, output_index: u64) -> Script {
+    let mut blake2b = new_blake2b();
+    blake2b.update(first_input.as_slice());
+    blake2b.update(&output_index.to_le_bytes());
+    let mut type_id = [0u8; 32];
+    blake2b.finalize(&mut type_id);
+    Script::new_builder()
+        .code_hash(TYPE_ID_CODE_HASH.pack())
+        .hash_type(ScriptHashType::Type.into())
+        .args(Bytes::from(type_id.to_vec()).pack())
+        .build()
+}
+
+fn is_type_id_script(script: &Script) -> bool {
+    script.code_hash() == TYPE_ID_CODE_HASH.pack()
+        && script.hash_type() == ScriptHashType::Type.into()
+}
+
+/// The code hash of a script running the code cell: the data hash for `data` hash type, the
+/// type script hash for `type` hash type.
+pub fn script_code_hash(
+    code_cell: &CellOutput,
+    code_data: &[u8],
+    hash_type: ScriptHashType,
+) -> Result<Byte32, String> {
+    match hash_type {
+        ScriptHashType::Data => Ok(CellOutput::calc_data_hash(code_data)),
+        ScriptHashType::Type => code_cell
+            .type_()
+            .to_opt()
+            .map(|type_script| type_script.calc_script_hash())
+            .ok_or_else(|| {
+                "The code cell has no type script, it can't be referenced by type hash type"
+                    .to_string()
+            }),
+    }
+}
+
+/// Check the configured code hash and hash type resolve to the code cell in the cell dep,
+/// otherwise the script of the built transaction can't be found on chain.
+pub fn check_script_reference(
+    code_hash: &Byte32,
+    hash_type: ScriptHashType,
+    code_cell: &CellOutput,
+    code_data: &[u8],
+) -> Result<(), String> {
+    let expected = script_code_hash(code_cell, code_data, hash_type)?;
+    if &expected != code_hash {
+        let hint = match hash_type {
+            ScriptHashType::Data => "data hash of the code cell",
+            ScriptHashType::Type => "type script hash of the code cell",
+        };
+        return Err(format!(
+            "The code hash {} with hash type {:?} does not match the {} {}",
+            code_hash, hash_type, hint, expected
+        ));
+    }
+    Ok(())
+}
+
+/// The uninitialized light client cell (empty data), both the lock and the type script run
+/// the light client binary. The type script args is the first input of the creating
+/// transaction, which makes the type script unique. The capacity is enough to hold the
+/// tip relayed later.
+pub fn light_client_cell(
+    light_client_binary: &[u8],
+    first_input: &OutPoint,
+) -> Result<CellOutput, String> {
+    let output = CellOutput::new_builder()
+        .lock(data_script(light_client_binary, Bytes::new()))
+        .type_(
+            Some(data_script(
+                light_client_binary,
+                Bytes::from(first_input.as_slice().to_vec()),
+            ))
+            .pack(),
+        )
+        .build();
+    let capacity = output
+        .occupied_capacity(Capacity::bytes(LIGHT_CLIENT_DATA_SIZE).map_err(|err| err.to_string())?)
+        .map_err(|err| err.to_string())?;
+    Ok(output.as_builder().capacity(capacity.pack()).build())
+}
+
+/// Build the (unsigned) deploy transaction:
+///   * inputs: sighash cells to pay the capacity and the transaction fee
+///   * outputs: the given cells, followed by an optional change cell
+/// The args of the type id type scripts in the given cells are set from the first input.
+pub fn build_deploy_tx(
+    outputs: Vec<(CellOutput, Bytes)>,
+    cell_deps: Vec<CellDep>,
+    fee_cells: &[LiveCellInfo],
+    change_output: Option<CellOutput>,
+) -> TransactionView {
+    let first_input = fee_cells
+        .first()
+        .map(LiveCellInfo::input)
+        .unwrap_or_default();
+    let mut builder = TransactionBuilder::default()
+        .cell_deps(cell_deps)
+        .inputs(fee_cells.iter().map(LiveCellInfo::input));
+    for (idx, (output, data)) in outputs
+        .into_iter()
+        .chain(change_output.map(|output| (output, Bytes::new())))
+        .enumerate()
+    {
+        let output = match output.type_().to_opt() {
+            Some(type_script) if is_type_id_script(&type_script) => output
+                .as_builder()
+                .type_(Some(type_id_script(&first_input, idx as u64)).pack())
+                .build(),
+            _ => output,
+        };
+        builder = builder.output(output).output_data(data.pack());
+    }
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subcommands::ckb_bridge::{config::ScriptConfig, mint};
+    use ckb_index::CellIndex;
+    use ckb_types::{H160, H256};
+
+    #[test]
+    fn test_code_cell_capacity() {
+        let lock = Script::default();
+        let (output, data) = code_cell(Bytes::from(vec![0u8; 100]), lock.clone(), false).unwrap();
+        let capacity: u64 = output.capacity().unpack();
+        let expected = Capacity::bytes(8 + 100)
+            .unwrap()
+            .safe_add(lock.occupied_capacity().unwrap())
+            .unwrap();
+        assert_eq!(capacity, expected.as_u64());
+        assert_eq!(data.len(), 100);
+    }
+
+    #[test]
+    fn test_light_client_cell() {
+        let binary = vec![1u8; 10];
+        let out_point = OutPoint::new(Byte32::default(), 3);
+        let output = light_client_cell(&binary, &out_point).unwrap();
+        let type_script = output.type_().to_opt().unwrap();
+        assert_eq!(type_script.code_hash(), CellOutput::calc_data_hash(&binary));
+        assert_eq!(type_script.args().raw_data(), out_point.as_bytes());
+        assert_eq!(output.lock().code_hash(), type_script.code_hash());
+        // The capacity is enough to hold the relayed tip
+        let capacity: u64 = output.capacity().unpack();
+        let occupied = output
+            .occupied_capacity(Capacity::bytes(LIGHT_CLIENT_DATA_SIZE).unwrap())
+            .unwrap();
+        assert_eq!(capacity, occupied.as_u64());
+    }
+
+    #[test]
+    fn test_type_id_deployment_reference() {
+        let binary = Bytes::from(vec![2u8; 100]);
+        let (output, data) = code_cell(binary.clone(), Script::default(), true).unwrap();
+        let fee_cell = LiveCellInfo {
+            tx_hash: H256::default(),
+            output_index: 1,
+            data_bytes: 0,
+            lock_hash: H256::default(),
+            type_hashes: None,
+            capacity: 1000,
+            number: 0,
+            index: CellIndex {
+                tx_index: 1,
+                output_index: 1,
+            },
+        };
+        let tx = build_deploy_tx(vec![(output, data)], vec![], &[fee_cell.clone()], None);
+        let deployed = tx.outputs().get(0).unwrap();
+        let type_script = deployed.type_().to_opt().unwrap();
+        assert_eq!(type_script, type_id_script(&fee_cell.input(), 0));
+
+        let code_hash = script_code_hash(&deployed, &binary, ScriptHashType::Type).unwrap();
+        assert_eq!(code_hash, type_script.calc_script_hash());
+        let bridge_script = ScriptConfig {
+            code_hash: code_hash.unpack(),
+            hash_type: ScriptHashType::Type.into(),
+            cell_dep: CellDep::default().into(),
+            proof_used_error: None,
+        };
+        let (token_cell, _) =
+            mint::token_cell(&bridge_script, &H160::default(), 1, Script::default()).unwrap();
+        let token_type = token_cell.type_().to_opt().unwrap();
+        assert_eq!(token_type.hash_type(), ScriptHashType::Type.into());
+        assert_eq!(token_type.code_hash(), code_hash);
+        check_script_reference(&code_hash, ScriptHashType::Type, &deployed, &binary).unwrap();
+
+        // The data hash of a type id deployment is not a valid reference by type, and the other
+        // way around
+        let data_hash = CellOutput::calc_data_hash(&binary);
+        assert!(
+            check_script_reference(&data_hash, ScriptHashType::Type, &deployed, &binary).is_err()
+        );
+        assert!(
+            check_script_reference(&code_hash, ScriptHashType::Data, &deployed, &binary).is_err()
+        );
+        check_script_reference(&data_hash, ScriptHashType::Data, &deployed, &binary).unwrap();
+        let (plain, _) = code_cell(binary.clone(), Script::default(), false).unwrap();
+        assert!(script_code_hash(&plain, &binary, ScriptHashType::Type).is_err());
+    }
+}
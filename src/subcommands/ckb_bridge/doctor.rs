@@ -0,0 +1,147 @@
+//! Read-only checks of the rpc endpoints and the deployed artifacts, the independent requests
+//! are batched (ckb) or run concurrently (ethereum)
+
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use ckb_jsonrpc_types::OutPoint;
+use ckb_sdk::rpc::send_with_retry_after;
+use serde_derive::{Deserialize, Serialize};
+
+use super::util::MAX_THROTTLE_WAIT;
+
+// Max concurrent ethereum requests of the checks
+pub const MAX_CHECK_WORKERS: usize = 8;
+
+/// The result of one read-only check
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Check {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+impl Check {
+    pub fn new(name: String, result: Result<String, String>) -> Check {
+        match result {
+            Ok(detail) => Check {
+                name,
+                ok: true,
+                detail,
+            },
+            Err(detail) => Check {
+                name,
+                ok: false,
+                detail,
+            },
+        }
+    }
+}
+
+/// Query the status of all the cells in one ckb json-rpc batch request, return the status
+/// (e.g. "live", "dead", "unknown") in the order of `out_points`.
+pub fn batch_cell_status(
+    url: &str,
+    timeout: Duration,
+    out_points: &[OutPoint],
+) -> Result<Vec<Result<String, String>>, String> {
+    if out_points.is_empty() {
+        return Ok(Vec::new());
+    }
+    let client = reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .map_err(|err| err.to_string())?;
+    let requests = out_points
+        .iter()
+        .enumerate()
+        .map(|(id, out_point)| {
+            serde_json::json!({
+                "id": id,
+                "jsonrpc": "2.0",
+                "method": "get_live_cell",
+                "params": [out_point, false],
+            })
+        })
+        .collect::<Vec<_>>();
+    let url = reqwest::Url::parse(url).map_err(|err| format!("Invalid ckb rpc url: {}", err))?;
+    let outputs: Vec<serde_json::Value> = send_with_retry_after(&url, MAX_THROTTLE_WAIT, || {
+        client.post(url.clone()).json(&requests).send()
+    })
+    .and_then(|mut resp| resp.json())
+    .map_err(|err| format!("Ckb batch rpc error: {}", err))?;
+    // The responses of a batch may be in any order
+    let mut results = vec![Err("No response".to_string()); out_points.len()];
+    for output in outputs {
+        let id = output["id"]
+            .as_u64()
+            .map(|id| id as usize)
+            .filter(|id| *id < results.len())
+            .ok_or_else(|| format!("Invalid ckb batch rpc response: {}", output))?;
+        results[id] = match output.get("error") {
+            Some(error) => Err(format!("Ckb rpc get_live_cell failed: {}", error)),
+            None => output["result"]["status"]
+                .as_str()
+                .map(ToOwned::to_owned)
+                .ok_or_else(|| format!("Invalid ckb rpc get_live_cell result: {}", output)),
+        };
+    }
+    Ok(results)
+}
+
+/// Apply `func` to all items with at most `max_workers` threads, the results are in the
+/// order of `items` regardless of completion order.
+pub fn parallel_map<T, R, F>(items: Vec<T>, max_workers: usize, func: F) -> Vec<R>
+where
+    T: Send + 'static,
+    R: Send + 'static,
+    F: Fn(T) -> R + Send + Sync + 'static,
+{
+    let total = items.len();
+    let (job_sender, job_receiver) = crossbeam_channel::unbounded();
+    for job in items.into_iter().enumerate() {
+        job_sender.send(job).expect("send job");
+    }
+    drop(job_sender);
+    let (result_sender, result_receiver) = crossbeam_channel::unbounded();
+    let func = Arc::new(func);
+    let workers = (0..std::cmp::min(max_workers.max(1), total))
+        .map(|_| {
+            let job_receiver = job_receiver.clone();
+            let result_sender = result_sender.clone();
+            let func = Arc::clone(&func);
+            thread::spawn(move || {
+                for (index, item) in job_receiver.iter() {
+                    result_sender
+                        .send((index, func(item)))
+                        .expect("send result");
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    drop(result_sender);
+    let mut results = result_receiver.iter().collect::<Vec<_>>();
+    for worker in workers {
+        worker.join().expect("check worker panicked");
+    }
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parallel_map_keeps_order() {
+        let items = (0..20u64).collect::<Vec<_>>();
+        let results = parallel_map(items, 4, |n| {
+            // Later items finish first
+            thread::sleep(Duration::from_millis(20 - n));
+            n * 2
+        });
+        assert_eq!(results, (0..20u64).map(|n| n * 2).collect::<Vec<_>>());
+        assert!(parallel_map(Vec::<u64>::new(), 4, |n| n).is_empty());
+    }
+}
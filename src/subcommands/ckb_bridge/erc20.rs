@@ -0,0 +1,220 @@
+//! Erc20 token calls used by the bridge
+
+use ckb_types::{H160, H256};
+
+use super::abi::{decode_address, decode_uint, ContractAbi, Token};
+use super::config::TokenConfig;
+use super::eth::{keccak256, EthLog};
+
+/// The standard erc20 event, fixed whatever the `--erc20-abi`
+const TRANSFER_EVENT: &str = "Transfer(address,address,uint256)";
+
+pub fn allowance_call(abi: &ContractAbi, owner: &H160, spender: &H160) -> Result<Vec<u8>, String> {
+    abi.function("allowance")?.encode_call(&[
+        Token::Address(owner.clone()),
+        Token::Address(spender.clone()),
+    ])
+}
+
+pub fn approve_call(abi: &ContractAbi, spender: &H160, value: u128) -> Result<Vec<u8>, String> {
+    abi.function("approve")?
+        .encode_call(&[Token::Address(spender.clone()), Token::Uint(value)])
+}
+
+/// Check the return data of an erc20 call declared to return a bool (`approve`, `transfer`).
+///
+/// Some tokens (e.g. USDT) return nothing, that is accepted unless `strict`, the receipt status
+/// decides then. A returned bool must be true, some tokens return false instead of reverting.
+pub fn check_bool_return(function: &str, data: &[u8], strict: bool) -> Result<(), String> {
+    if data.is_empty() {
+        if strict {
+            return Err(format!("{}() returned no bool", function));
+        }
+        return Ok(());
+    }
+    if data.len() != 32 {
+        return Err(format!(
+            "{}() returned {} bytes, expect a bool",
+            function,
+            data.len()
+        ));
+    }
+    if decode_uint(data, 0)? == 0 {
+        return Err(format!("{}() returned false", function));
+    }
+    Ok(())
+}
+
+/// Compare the symbol (case insensitive) and decimals in the config with the ones read from
+/// the token contract, the values not configured or not read are not compared
+pub fn check_metadata(
+    token_config: &TokenConfig,
+    symbol: Option<&str>,
+    decimals: Option<u8>,
+) -> Result<String, String> {
+    if let (Some(expected), Some(symbol)) = (token_config.symbol.as_ref(), symbol) {
+        if !expected.eq_ignore_ascii_case(symbol) {
+            return Err(format!(
+                "Symbol mismatch: {} in the config, {} on chain",
+                expected, symbol
+            ));
+        }
+    }
+    if let (Some(expected), Some(decimals)) = (token_config.decimals, decimals) {
+        if expected != decimals {
+            return Err(format!(
+                "Decimals mismatch: {} in the config, {} on chain",
+                expected, decimals
+            ));
+        }
+    }
+    Ok(format!(
+        "symbol: {}, decimals: {}",
+        symbol.unwrap_or("unknown"),
+        decimals
+            .map(|decimals| decimals.to_string())
+            .unwrap_or_else(|| "unknown".to_string())
+    ))
+}
+
+/// The sum of the `Transfer` events of `token` from `from` among `logs`
+pub fn transferred_from(logs: &[EthLog], token: &H160, from: &H160) -> Result<u128, String> {
+    let topic = H256::from(keccak256(TRANSFER_EVENT.as_bytes()));
+    let mut total = 0u128;
+    for log in logs {
+        if &log.address != token || log.topics.len() != 3 || log.topics[0] != topic {
+            continue;
+        }
+        if &decode_address(log.topics[1].as_bytes(), 0)? != from {
+            continue;
+        }
+        total = total
+            .checked_add(decode_uint(log.data.as_bytes(), 0)?)
+            .ok_or_else(|| "Transferred amount overflow".to_string())?;
+    }
+    Ok(total)
+}
+
+/// The values to `approve()` in order, to make the allowance enough for `amount`.
+///
+/// Some tokens (e.g. USDT) revert when changing a nonzero allowance to another nonzero value,
+/// for them (`requires_zero_first`) the allowance is reset to zero first.
+pub fn approve_plan(allowance: u128, amount: u128, requires_zero_first: bool) -> Vec<u128> {
+    if allowance >= amount {
+        Vec::new()
+    } else if allowance > 0 && requires_zero_first {
+        vec![0, amount]
+    } else {
+        vec![amount]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subcommands::ckb_bridge::abi;
+    use ckb_jsonrpc_types::JsonBytes;
+    use ckb_types::h160;
+
+    /// A token rejects changing a nonzero allowance to another nonzero value (like USDT)
+    struct ZeroFirstToken {
+        allowance: u128,
+    }
+
+    impl ZeroFirstToken {
+        fn approve(&mut self, value: u128) -> Result<(), String> {
+            if self.allowance != 0 && value != 0 {
+                return Err("reverted".to_string());
+            }
+            self.allowance = value;
+            Ok(())
+        }
+
+        fn apply(&mut self, plan: Vec<u128>) -> Result<(), String> {
+            plan.into_iter().try_for_each(|value| self.approve(value))
+        }
+    }
+
+    #[test]
+    fn test_approve_plan() {
+        assert!(approve_plan(100, 100, true).is_empty());
+        assert!(approve_plan(200, 100, false).is_empty());
+        assert_eq!(approve_plan(0, 100, true), vec![100]);
+        assert_eq!(approve_plan(50, 100, false), vec![100]);
+        assert_eq!(approve_plan(50, 100, true), vec![0, 100]);
+    }
+
+    #[test]
+    fn test_check_bool_return() {
+        let mut word = [0u8; 32];
+        // A token returning nothing from approve (like USDT)
+        assert!(check_bool_return("approve", &[], false).is_ok());
+        assert!(check_bool_return("approve", &[], true)
+            .unwrap_err()
+            .contains("no bool"));
+        assert!(check_bool_return("approve", &word, false)
+            .unwrap_err()
+            .contains("returned false"));
+        word[31] = 1;
+        assert!(check_bool_return("approve", &word, true).is_ok());
+        assert!(check_bool_return("transfer", &word[..31], false).is_err());
+    }
+
+    #[test]
+    fn test_check_metadata() {
+        let token_config = TokenConfig {
+            symbol: Some("USDC".to_string()),
+            decimals: Some(6),
+            ..Default::default()
+        };
+        assert!(check_metadata(&token_config, Some("usdc"), Some(6)).is_ok());
+        assert!(check_metadata(&token_config, None, None).is_ok());
+        assert!(check_metadata(&token_config, Some("USDT"), Some(6))
+            .unwrap_err()
+            .contains("Symbol mismatch"));
+        assert!(check_metadata(&token_config, Some("USDC"), Some(18))
+            .unwrap_err()
+            .contains("Decimals mismatch"));
+        assert!(check_metadata(&TokenConfig::default(), Some("DAI"), Some(18)).is_ok());
+    }
+
+    #[test]
+    fn test_zero_first_token() {
+        let mut token = ZeroFirstToken { allowance: 50 };
+        assert!(token.apply(approve_plan(50, 100, false)).is_err());
+        assert_eq!(token.allowance, 50);
+
+        token.apply(approve_plan(50, 100, true)).unwrap();
+        assert_eq!(token.allowance, 100);
+    }
+
+    #[test]
+    fn test_transferred_from() {
+        let token = h160!("0x1111111111111111111111111111111111111111");
+        let bridge = h160!("0x2222222222222222222222222222222222222222");
+        let recipient = h160!("0x3333333333333333333333333333333333333333");
+        let address_topic = |address: &H160| {
+            let mut topic = [0u8; 32];
+            topic[12..].copy_from_slice(address.as_bytes());
+            H256::from(topic)
+        };
+        let transfer = |emitter: &H160, from: &H160, value: u128| EthLog {
+            address: emitter.clone(),
+            topics: vec![
+                H256::from(keccak256(TRANSFER_EVENT.as_bytes())),
+                address_topic(from),
+                address_topic(&recipient),
+            ],
+            data: JsonBytes::from_vec(abi::encode(&[Token::Uint(value)])),
+        };
+        let logs = vec![
+            transfer(&token, &bridge, 70),
+            transfer(&token, &bridge, 30),
+            // Not from the bridge, or emitted by another token
+            transfer(&token, &recipient, 5),
+            transfer(&recipient, &bridge, 7),
+        ];
+        assert_eq!(transferred_from(&logs, &token, &bridge), Ok(100));
+        assert_eq!(transferred_from(&logs[2..], &token, &bridge), Ok(0));
+    }
+}
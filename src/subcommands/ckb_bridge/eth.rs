@@ -0,0 +1,1151 @@
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use ckb_jsonrpc_types::JsonBytes;
+use ckb_sdk::{rpc::send_with_retry_after, RPC_TIMEOUT_ERROR, SECP256K1};
+use ckb_types::{H160, H256};
+use secp256k1::recovery::{RecoverableSignature, RecoveryId};
+use serde::{de::DeserializeOwned, Serializer};
+use serde_derive::{Deserialize, Serialize};
+use tungstenite::{client::AutoStream, WebSocket};
+
+use super::exit_code::BridgeError;
+use super::mint::format_token_amount;
+use super::rlp::{self, RlpItem};
+use super::subscribe;
+use super::util::MAX_THROTTLE_WAIT;
+use crate::utils::{
+    arg_parser::PrivkeyWrapper,
+    poll::{poll_until, Poll, CANCELLED},
+};
+
+// A timeout send is retried (with the identical payload) at most this many times in total
+const BROADCAST_ATTEMPTS: usize = 3;
+// Wait before looking up a transaction whose send timed out
+const BROADCAST_RETRY_DELAY: Duration = Duration::from_secs(5);
+const ETHER_DECIMALS: u8 = 18;
+
+enum Transport {
+    Http(reqwest::Client),
+    /// A `ws://` or `wss://` url, connected by the first request and again after a failed one
+    Ws(Option<WebSocket<AutoStream>>),
+}
+
+/// A tiny ethereum JSON-RPC client, only the methods used by ckb-bridge are supported
+pub struct EthRpcClient {
+    transport: Transport,
+    url: reqwest::Url,
+    timeout: Duration,
+    id: u64,
+}
+
+impl EthRpcClient {
+    /// The error of a timeout request starts with `RPC_TIMEOUT_ERROR`
+    pub fn new(uri: &str, timeout: Duration) -> Result<EthRpcClient, String> {
+        let url = reqwest::Url::parse(uri)
+            .map_err(|err| format!("Invalid ethereum rpc url {}: {}", uri, err))?;
+        let transport = if subscribe::is_ws_url(uri) {
+            Transport::Ws(None)
+        } else {
+            let client = reqwest::Client::builder()
+                .timeout(timeout)
+                .build()
+                .map_err(|err| err.to_string())?;
+            Transport::Http(client)
+        };
+        Ok(EthRpcClient {
+            transport,
+            url,
+            timeout,
+            id: 0,
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        self.url.as_str()
+    }
+
+    fn request<T: DeserializeOwned>(
+        &mut self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<T, String> {
+        self.id += 1;
+        let req_json = serde_json::json!({
+            "id": self.id,
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params,
+        });
+        let url = &self.url;
+        let mut output = match &mut self.transport {
+            Transport::Http(client) => {
+                let mut resp = send_with_retry_after(url, MAX_THROTTLE_WAIT, || {
+                    client.post(url.clone()).json(&req_json).send()
+                })
+                .map_err(|err| request_error(method, err))?;
+                resp.json::<serde_json::Value>().map_err(|err| {
+                    if err.is_timeout() {
+                        request_error(method, err)
+                    } else {
+                        format!("Ethereum rpc {} invalid response: {}", method, err)
+                    }
+                })?
+            }
+            Transport::Ws(socket) => ws_request(url, socket, &req_json, self.timeout, method)?,
+        };
+        if let Some(error) = output.get("error") {
+            return Err(format!("Ethereum rpc {} failed: {}", method, error));
+        }
+        serde_json::from_value(output["result"].take())
+            .map_err(|err| format!("Ethereum rpc {} invalid result: {}", method, err))
+    }
+
+    pub fn block_number(&mut self) -> Result<u64, String> {
+        let number: String = self.request("eth_blockNumber", serde_json::json!([]))?;
+        parse_quantity(&number)
+    }
+
+    pub fn get_code(&mut self, address: &H160) -> Result<JsonBytes, String> {
+        self.request(
+            "eth_getCode",
+            serde_json::json!([format!("{:#x}", address), "latest"]),
+        )
+    }
+
+    /// The balance of `address` in wei
+    pub fn get_balance(&mut self, address: &H160) -> Result<u128, String> {
+        let balance: String = self.request(
+            "eth_getBalance",
+            serde_json::json!([format!("{:#x}", address), "latest"]),
+        )?;
+        let hex = balance.trim_start_matches("0x");
+        u128::from_str_radix(hex, 16).map_err(|err| format!("Invalid balance {}: {}", balance, err))
+    }
+
+    /// Refuse to send `tx` if `from` can't pay its maximum cost, a transaction short of funds
+    /// is rejected by the node (or stuck in its pool) after the nonce is taken
+    pub fn check_balance(&mut self, from: &H160, tx: &EthTransaction) -> Result<(), String> {
+        let need = tx.max_cost();
+        let have = self.get_balance(from)?;
+        if have < need {
+            return Err(BridgeError::insufficient_funds(format!(
+                "Insufficient ETH for gas: need {}, have {} ({})",
+                format_token_amount(need, Some(ETHER_DECIMALS), Some("ETH"), true),
+                format_token_amount(have, Some(ETHER_DECIMALS), Some("ETH"), true),
+                checksum_address(from)
+            ))
+            .into());
+        }
+        Ok(())
+    }
+
+    pub fn chain_id(&mut self) -> Result<u64, String> {
+        let chain_id: String = self.request("eth_chainId", serde_json::json!([]))?;
+        parse_quantity(&chain_id)
+    }
+
+    pub fn gas_price(&mut self) -> Result<u64, String> {
+        let price: String = self.request("eth_gasPrice", serde_json::json!([]))?;
+        parse_quantity(&price)
+    }
+
+    /// The nonce of the next transaction of `address`, in-flight ones counted at `pending`
+    pub fn get_transaction_count(&mut self, address: &H160, tag: ChainTag) -> Result<u64, String> {
+        self.transaction_count_at(address, tag.as_str())
+    }
+
+    /// The nonce of the next transaction of `address` to be mined, pending ones not counted
+    pub fn confirmed_nonce(&mut self, address: &H160) -> Result<u64, String> {
+        self.transaction_count_at(address, "latest")
+    }
+
+    fn transaction_count_at(&mut self, address: &H160, tag: &str) -> Result<u64, String> {
+        let count: String = self.request(
+            "eth_getTransactionCount",
+            serde_json::json!([format!("{:#x}", address), tag]),
+        )?;
+        parse_quantity(&count)
+    }
+
+    /// Estimate the gas of a contract call, or a contract creation if `to` is `None`, on the
+    /// state of the `tag` block, with the access list if given
+    pub fn estimate_gas(
+        &mut self,
+        from: &H160,
+        to: Option<&H160>,
+        data: &[u8],
+        tag: ChainTag,
+        access_list: Option<&[AccessListItem]>,
+    ) -> Result<u64, String> {
+        let mut call = call_object(from, to, data);
+        if let Some(access_list) = access_list {
+            call["accessList"] = serde_json::json!(access_list);
+        }
+        let gas: String =
+            self.request("eth_estimateGas", serde_json::json!([call, tag.as_str()]))?;
+        parse_quantity(&gas)
+    }
+
+    /// The access list of the addresses and storage slots a call touches (EIP-2930),
+    /// generated by the node
+    pub fn create_access_list(
+        &mut self,
+        from: &H160,
+        to: Option<&H160>,
+        data: &[u8],
+        tag: ChainTag,
+    ) -> Result<Vec<AccessListItem>, String> {
+        let mut result: serde_json::Value = self.request(
+            "eth_createAccessList",
+            serde_json::json!([call_object(from, to, data), tag.as_str()]),
+        )?;
+        // The call reverted, the list is incomplete
+        if let Some(error) = result.get("error").filter(|error| !error.is_null()) {
+            return Err(format!(
+                "Ethereum rpc eth_createAccessList failed: {}",
+                error
+            ));
+        }
+        serde_json::from_value(result["accessList"].take())
+            .map_err(|err| format!("Ethereum rpc eth_createAccessList invalid result: {}", err))
+    }
+
+    /// Read-only contract call at the latest block
+    pub fn call(&mut self, from: &H160, to: &H160, data: &[u8]) -> Result<JsonBytes, String> {
+        self.request(
+            "eth_call",
+            serde_json::json!([call_object(from, Some(to), data), "latest"]),
+        )
+    }
+
+    pub fn send_raw_transaction(&mut self, raw_tx: &[u8]) -> Result<H256, String> {
+        self.request(
+            "eth_sendRawTransaction",
+            serde_json::json!([JsonBytes::from_vec(raw_tx.to_vec())]),
+        )
+    }
+
+    /// The transaction known by the node, pending in its mempool or mined
+    pub fn get_transaction(&mut self, tx_hash: &H256) -> Result<Option<serde_json::Value>, String> {
+        self.request(
+            "eth_getTransactionByHash",
+            serde_json::json!([format!("{:#x}", tx_hash)]),
+        )
+    }
+
+    /// Whether the transaction is known by the node, pending in its mempool or mined
+    pub fn transaction_exists(&mut self, tx_hash: &H256) -> Result<bool, String> {
+        Ok(self.get_transaction(tx_hash)?.is_some())
+    }
+
+    /// The gas price paid by a mined transaction, read from the transaction if the receipt
+    /// has no effective gas price
+    pub fn effective_gas_price(&mut self, receipt: &EthReceipt) -> Result<u64, String> {
+        if let Some(gas_price) = receipt.effective_gas_price.as_ref() {
+            return parse_quantity(gas_price);
+        }
+        self.get_transaction(&receipt.transaction_hash)?
+            .as_ref()
+            .and_then(|tx| tx["gasPrice"].as_str())
+            .ok_or_else(|| {
+                format!(
+                    "Gas price of ethereum transaction {:#x} not found",
+                    receipt.transaction_hash
+                )
+            })
+            .and_then(parse_quantity)
+    }
+
+    /// Send a signed transaction, safe to retry: after a timeout the transaction may have
+    /// reached the node, so it is looked up by hash before the identical payload is sent
+    /// again (never signed again with another nonce).
+    pub fn broadcast(&mut self, raw_tx: &[u8]) -> Result<H256, String> {
+        let tx_hash = H256::from(keccak256(raw_tx));
+        let mut attempt = 1;
+        loop {
+            let err = match self.send_raw_transaction(raw_tx) {
+                Ok(tx_hash) => return Ok(tx_hash),
+                Err(err) => err,
+            };
+            if is_already_known(&err) {
+                // The earlier attempt reached the node
+                return Ok(tx_hash);
+            }
+            if is_insufficient_funds(&err) {
+                return Err(BridgeError::insufficient_funds(err).into());
+            }
+            if !err.starts_with(RPC_TIMEOUT_ERROR) && !err.contains("nonce too low") {
+                return Err(err);
+            }
+            thread::sleep(BROADCAST_RETRY_DELAY);
+            if self.transaction_exists(&tx_hash)? {
+                return Ok(tx_hash);
+            }
+            if attempt >= BROADCAST_ATTEMPTS || !err.starts_with(RPC_TIMEOUT_ERROR) {
+                return Err(format!(
+                    "Send ethereum transaction {:#x} failed after {} attempts: {}",
+                    tx_hash, attempt, err
+                ));
+            }
+            log::warn!(
+                "Send ethereum transaction {:#x} timeout and it is not found, send it again",
+                tx_hash
+            );
+            attempt += 1;
+        }
+    }
+
+    pub fn get_transaction_receipt(
+        &mut self,
+        tx_hash: &H256,
+    ) -> Result<Option<EthReceipt>, String> {
+        self.request(
+            "eth_getTransactionReceipt",
+            serde_json::json!([format!("{:#x}", tx_hash)]),
+        )
+    }
+
+    /// Sign and send a contract call, the gas limit is the estimated gas plus a buffer
+    pub fn send_call(
+        &mut self,
+        signer: &EthSigner,
+        gas_args: &GasArgs,
+        to: &H160,
+        data: Vec<u8>,
+    ) -> Result<(H256, u64), String> {
+        self.send_transaction(signer, gas_args, Some(to), data)
+    }
+
+    /// Sign and send a contract creation transaction, `code` is the creation bytecode with
+    /// the encoded constructor arguments appended
+    pub fn send_create(
+        &mut self,
+        signer: &EthSigner,
+        gas_args: &GasArgs,
+        code: Vec<u8>,
+    ) -> Result<(H256, u64), String> {
+        self.send_transaction(signer, gas_args, None, code)
+    }
+
+    fn send_transaction(
+        &mut self,
+        signer: &EthSigner,
+        gas_args: &GasArgs,
+        to: Option<&H160>,
+        data: Vec<u8>,
+    ) -> Result<(H256, u64), String> {
+        let (tx, chain_id) = self.build_transaction(&signer.address, gas_args, to, data)?;
+        self.check_balance(&signer.address, &tx)?;
+        match self.broadcast(&tx.sign(signer, chain_id)) {
+            // The node estimated with the access list, but the chain has no typed transactions
+            Err(err) if tx.access_list.is_some() && is_typed_tx_unsupported(&err) => {
+                log::warn!(
+                    "The node rejects the EIP-2930 transaction ({}), send a legacy transaction",
+                    err
+                );
+                let (tx, chain_id) = self.build(&signer.address, gas_args, to, tx.data, None)?;
+                let tx_hash = self.broadcast(&tx.sign(signer, chain_id))?;
+                Ok((tx_hash, tx.gas_limit))
+            }
+            result => Ok((result?, tx.gas_limit)),
+        }
+    }
+
+    /// Build the unsigned transaction sent by `from` (gas limit, gas price, nonce and the
+    /// access list of `--eth-access-list` or `--auto-access-list`), return it with the chain
+    /// id to sign it
+    pub fn build_transaction(
+        &mut self,
+        from: &H160,
+        gas_args: &GasArgs,
+        to: Option<&H160>,
+        data: Vec<u8>,
+    ) -> Result<(EthTransaction, u64), String> {
+        self.build(from, gas_args, to, data, gas_args.access_list.as_ref())
+    }
+
+    fn build(
+        &mut self,
+        from: &H160,
+        gas_args: &GasArgs,
+        to: Option<&H160>,
+        data: Vec<u8>,
+        access_list: Option<&AccessListSource>,
+    ) -> Result<(EthTransaction, u64), String> {
+        let mut estimate = self.estimate_gas(from, to, &data, gas_args.chain_tag, None)?;
+        let access_list = match access_list {
+            Some(source) => self
+                .select_access_list(from, to, &data, gas_args.chain_tag, source, estimate)
+                .map(|(access_list, with_list)| {
+                    estimate = with_list;
+                    access_list
+                }),
+            None => None,
+        };
+        let gas_limit = gas_args.checked_gas_limit(estimate, data.len())?;
+        log::info!(
+            "Calldata: {} bytes, estimated gas (at {}): {}, gas limit (+{}%, max {}): {}",
+            data.len(),
+            gas_args.chain_tag.as_str(),
+            estimate,
+            gas_args.buffer_pct,
+            gas_args.max_gas_limit,
+            gas_limit
+        );
+        let gas_price = match gas_args.gas_price {
+            Some(gas_price) => gas_price,
+            None => self.gas_price()?,
+        };
+        let tx = EthTransaction {
+            nonce: self.get_transaction_count(from, gas_args.chain_tag)?,
+            gas_price,
+            gas_limit,
+            to: to.cloned(),
+            value: 0,
+            data,
+            access_list,
+        };
+        Ok((tx, self.chain_id()?))
+    }
+
+    /// The access list to send the transaction with and the gas estimate with it, compared
+    /// with the `estimate` without a list. `None` (a legacy transaction) if the node can't
+    /// use one (no `eth_createAccessList`, a chain before Berlin) or it saves no gas.
+    fn select_access_list(
+        &mut self,
+        from: &H160,
+        to: Option<&H160>,
+        data: &[u8],
+        tag: ChainTag,
+        source: &AccessListSource,
+        estimate: u64,
+    ) -> Option<(Vec<AccessListItem>, u64)> {
+        let access_list = match source {
+            AccessListSource::Given(access_list) => access_list.clone(),
+            AccessListSource::Auto => match self.create_access_list(from, to, data, tag) {
+                Ok(access_list) => access_list,
+                Err(err) => {
+                    log::warn!("No access list ({}), send a legacy transaction", err);
+                    return None;
+                }
+            },
+        };
+        let with_list = match self.estimate_gas(from, to, data, tag, Some(&access_list)) {
+            Ok(with_list) => with_list,
+            Err(err) => {
+                log::warn!(
+                    "Estimate gas with the access list failed ({}), send a legacy transaction",
+                    err
+                );
+                return None;
+            }
+        };
+        let storage_keys: usize = access_list.iter().map(|item| item.storage_keys.len()).sum();
+        if with_list >= estimate {
+            log::warn!(
+                "The access list ({} addresses, {} storage keys) saves no gas (estimated {} with it, {} without), send a legacy transaction",
+                access_list.len(),
+                storage_keys,
+                with_list,
+                estimate
+            );
+            return None;
+        }
+        log::info!(
+            "The access list ({} addresses, {} storage keys) saves {} gas (estimated {} with it, {} without)",
+            access_list.len(),
+            storage_keys,
+            estimate - with_list,
+            with_list,
+            estimate
+        );
+        Some((access_list, with_list))
+    }
+
+    /// Wait the transaction mined and check its status, the gas limit is used to detect out
+    /// of gas failures
+    pub fn wait_receipt(
+        &mut self,
+        tx_hash: &H256,
+        gas_limit: Option<u64>,
+        timeout: Duration,
+    ) -> Result<EthReceipt, String> {
+        let receipt = poll_until(
+            || {
+                Ok(match self.get_transaction_receipt(tx_hash)? {
+                    Some(receipt) => Poll::Ready(receipt),
+                    None => Poll::Pending,
+                })
+            },
+            Duration::from_secs(3),
+            Some(timeout),
+            &CANCELLED,
+        )?
+        .ready(
+            &format!("Wait ethereum transaction {:#x} mined", tx_hash),
+            Some(timeout),
+        )
+        .map_err(BridgeError::pending)?;
+        if receipt.is_success()? {
+            return Ok(receipt);
+        }
+        let gas_used = receipt.gas_used()?;
+        if let Some(gas_limit) = gas_limit.filter(|limit| gas_used >= *limit) {
+            return Err(format!(
+                "Ethereum transaction {:#x} ran out of gas (gas limit: {}), try raising --gas-buffer-pct",
+                tx_hash, gas_limit
+            ));
+        }
+        Err(format!("Ethereum transaction {:#x} reverted", tx_hash))
+    }
+
+    pub fn get_header_by_number(&mut self, number: u64) -> Result<Option<EthHeader>, String> {
+        self.request(
+            "eth_getBlockByNumber",
+            serde_json::json!([format!("{:#x}", number), false]),
+        )
+    }
+
+    /// The header of a block tag, e.g. "finalized", "safe"
+    pub fn get_header_by_tag(&mut self, tag: &str) -> Result<Option<EthHeader>, String> {
+        self.request("eth_getBlockByNumber", serde_json::json!([tag, false]))
+    }
+
+    pub fn get_header_by_hash(&mut self, hash: &H256) -> Result<Option<EthHeader>, String> {
+        self.request(
+            "eth_getBlockByHash",
+            serde_json::json!([format!("{:#x}", hash), false]),
+        )
+    }
+
+    /// All the receipts of the block in transaction order
+    pub fn get_block_receipts(&mut self, header: &EthHeader) -> Result<Vec<EthReceipt>, String> {
+        header
+            .transactions
+            .iter()
+            .map(|tx_hash| {
+                let receipt = self
+                    .get_transaction_receipt(tx_hash)?
+                    .ok_or_else(|| format!("Ethereum receipt {:#x} not found", tx_hash))?;
+                if receipt.block_hash != header.hash {
+                    return Err(format!(
+                        "Ethereum receipt {:#x} is not in block {:#x}, maybe a reorg happened",
+                        tx_hash, header.hash
+                    ));
+                }
+                Ok(receipt)
+            })
+            .collect()
+    }
+}
+
+/// The node rejects a transaction already in its mempool (geth: "already known" or "known
+/// transaction", parity: "already imported")
+fn is_already_known(err: &str) -> bool {
+    let err = err.to_lowercase();
+    err.contains("already known")
+        || err.contains("known transaction")
+        || err.contains("already imported")
+}
+
+/// The node rejects a transaction its sender can't pay (geth, parity: "insufficient funds for
+/// gas * price + value")
+fn is_insufficient_funds(err: &str) -> bool {
+    err.to_lowercase().contains("insufficient funds")
+}
+
+/// Whether a send failed since the chain (or the node) has no EIP-2718 typed transactions
+fn is_typed_tx_unsupported(err: &str) -> bool {
+    err.contains("transaction type not supported")
+        || err.contains("tx type not supported")
+        || err.contains("expected input list")
+}
+
+/// A request over the websocket, the socket is dropped after a failure (reconnected by the
+/// next request)
+fn ws_request(
+    url: &reqwest::Url,
+    socket: &mut Option<WebSocket<AutoStream>>,
+    req_json: &serde_json::Value,
+    timeout: Duration,
+    method: &str,
+) -> Result<serde_json::Value, String> {
+    let result = match socket {
+        Some(socket) => subscribe::request(socket, req_json, timeout),
+        None => subscribe::connect(url.as_str()).and_then(|mut new_socket| {
+            let output = subscribe::request(&mut new_socket, req_json, timeout)?;
+            *socket = Some(new_socket);
+            Ok(output)
+        }),
+    };
+    result.map_err(|err| {
+        *socket = None;
+        if subscribe::is_timeout(&err) {
+            BridgeError::pending(format!(
+                "{}: ethereum rpc {}: {}",
+                RPC_TIMEOUT_ERROR, method, err
+            ))
+            .into()
+        } else {
+            format!("Ethereum rpc {} error: {}", method, err)
+        }
+    })
+}
+
+fn request_error(method: &str, err: reqwest::Error) -> String {
+    if err.is_timeout() {
+        BridgeError::pending(format!(
+            "{}: ethereum rpc {}: {}",
+            RPC_TIMEOUT_ERROR, method, err
+        ))
+        .into()
+    } else {
+        format!("Ethereum rpc {} error: {}", method, err)
+    }
+}
+
+fn call_object(from: &H160, to: Option<&H160>, data: &[u8]) -> serde_json::Value {
+    let mut object = serde_json::json!({
+        "from": format!("{:#x}", from),
+        "data": JsonBytes::from_vec(data.to_vec()),
+    });
+    if let Some(to) = to {
+        object["to"] = serde_json::json!(format!("{:#x}", to));
+    }
+    object
+}
+
+/// The ethereum account used to sign transactions
+pub struct EthSigner {
+    privkey: PrivkeyWrapper,
+    pub address: H160,
+}
+
+impl EthSigner {
+    pub fn new(privkey: PrivkeyWrapper) -> EthSigner {
+        let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, &privkey);
+        let address = pubkey_address(&pubkey);
+        EthSigner { privkey, address }
+    }
+
+    /// Sign `message` as an EIP-191 personal message (`personal_sign`), return `r || s || v`
+    /// with `v` 27 or 28
+    pub fn sign_message(&self, message: &[u8]) -> Vec<u8> {
+        let hash = secp256k1::Message::from_slice(&personal_message_hash(message))
+            .expect("secp256k1 message");
+        let signature = SECP256K1.sign_recoverable(&hash, &self.privkey);
+        let (recov_id, data) = signature.serialize_compact();
+        let mut signature = data.to_vec();
+        signature.push(recov_id.to_i32() as u8 + 27);
+        signature
+    }
+}
+
+/// The signer of an EIP-191 personal message signature, `v` may be 27/28 or 0/1
+pub fn recover_message_signer(message: &[u8], signature: &[u8]) -> Result<H160, String> {
+    if signature.len() != 65 {
+        return Err(format!(
+            "Invalid signature length {}, expected 65 bytes (r, s, v)",
+            signature.len()
+        ));
+    }
+    let v = match signature[64] {
+        v @ 27..=28 => v - 27,
+        v @ 0..=1 => v,
+        v => return Err(format!("Invalid signature v: {}", v)),
+    };
+    let recov_id = RecoveryId::from_i32(i32::from(v)).map_err(|err| err.to_string())?;
+    let signature = RecoverableSignature::from_compact(&signature[0..64], recov_id)
+        .map_err(|err| format!("Invalid signature: {}", err))?;
+    let hash =
+        secp256k1::Message::from_slice(&personal_message_hash(message)).expect("secp256k1 message");
+    let pubkey = SECP256K1
+        .recover(&hash, &signature)
+        .map_err(|err| format!("Recover the signer error: {}", err))?;
+    Ok(pubkey_address(&pubkey))
+}
+
+fn pubkey_address(pubkey: &secp256k1::PublicKey) -> H160 {
+    let hash = keccak256(&pubkey.serialize_uncompressed()[1..]);
+    H160::from_slice(&hash[12..]).expect("ethereum address")
+}
+
+// keccak256("\x19Ethereum Signed Message:\n" || len(message) || message)
+fn personal_message_hash(message: &[u8]) -> [u8; 32] {
+    let mut data = format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+    data.extend_from_slice(message);
+    keccak256(&data)
+}
+
+/// The block tag the nonce and the gas estimate of a new transaction are read at.
+///
+/// `Pending` counts the sender's transactions still in the mempool of the node, so
+/// back-to-back sends get consecutive nonces, but the result depends on the node's mempool.
+/// `Latest` only sees mined transactions: deterministic (e.g. for tests), but a transaction
+/// sent before the previous one is mined reuses (and replaces or conflicts with) its nonce.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChainTag {
+    Latest,
+    Pending,
+}
+
+impl ChainTag {
+    pub const NAMES: [&'static str; 2] = ["latest", "pending"];
+
+    pub fn from_name(name: &str) -> Result<ChainTag, String> {
+        match name {
+            "latest" => Ok(ChainTag::Latest),
+            "pending" => Ok(ChainTag::Pending),
+            _ => Err(format!(
+                "Invalid chain tag {}, expected one of: {}",
+                name,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChainTag::Latest => "latest",
+            ChainTag::Pending => "pending",
+        }
+    }
+}
+
+impl Default for ChainTag {
+    fn default() -> ChainTag {
+        ChainTag::Pending
+    }
+}
+
+/// Gas limit = estimate * (100 + buffer_pct) / 100, no more than max_gas_limit
+pub struct GasArgs {
+    pub buffer_pct: u64,
+    pub max_gas_limit: u64,
+    pub gas_price: Option<u64>,
+    pub chain_tag: ChainTag,
+    pub access_list: Option<AccessListSource>,
+}
+
+/// An entry of an EIP-2930 access list, in the layout of the JSON-RPC (and the
+/// `--eth-access-list` file): `{"address": "0x..", "storageKeys": ["0x..", ..]}`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessListItem {
+    pub address: H160,
+    pub storage_keys: Vec<H256>,
+}
+
+/// Read the JSON array of `AccessListItem` of `--eth-access-list`
+pub fn read_access_list(path: &Path) -> Result<Vec<AccessListItem>, String> {
+    let content =
+        fs::read_to_string(path).map_err(|err| format!("Read {:?} error: {}", path, err))?;
+    serde_json::from_str(&content)
+        .map_err(|err| format!("Parse access list {:?} error: {}", path, err))
+}
+
+/// Where the access list of the sent transactions comes from
+pub enum AccessListSource {
+    /// Read from `--eth-access-list`
+    Given(Vec<AccessListItem>),
+    /// Generated for each transaction by `eth_createAccessList` (`--auto-access-list`)
+    Auto,
+}
+
+impl GasArgs {
+    /// The gas limit of a transaction with `calldata_size` bytes of data, fails if the
+    /// estimate plus the buffer exceeds `max_gas_limit`: capped the transaction would run out
+    /// of gas (e.g. a large proof)
+    pub fn checked_gas_limit(&self, estimate: u64, calldata_size: usize) -> Result<u64, String> {
+        let buffered = u128::from(estimate) * u128::from(100 + self.buffer_pct) / 100;
+        if buffered > u128::from(self.max_gas_limit) {
+            return Err(format!(
+                "Estimated gas {} (+{}%: {}) of the {} bytes calldata exceeds --gas-limit {}, \
+                 raise --gas-limit, split the transfer or wait for a smaller proof",
+                estimate, self.buffer_pct, buffered, calldata_size, self.max_gas_limit
+            ));
+        }
+        Ok(buffered as u64)
+    }
+}
+
+/// Legacy (EIP-155) ethereum transaction, or an EIP-2930 transaction with an access list
+pub struct EthTransaction {
+    pub nonce: u64,
+    pub gas_price: u64,
+    pub gas_limit: u64,
+    /// `None` for contract creation
+    pub to: Option<H160>,
+    pub value: u128,
+    pub data: Vec<u8>,
+    pub access_list: Option<Vec<AccessListItem>>,
+}
+
+// The EIP-2718 type of EIP-2930 transactions
+const ACCESS_LIST_TX_TYPE: u8 = 0x01;
+
+impl EthTransaction {
+    /// The most the sender pays: the whole gas limit at the gas price, plus the value
+    pub fn max_cost(&self) -> u128 {
+        u128::from(self.gas_limit) * u128::from(self.gas_price) + self.value
+    }
+
+    fn rlp_fields(&self) -> Vec<RlpItem> {
+        vec![
+            RlpItem::from_u64(self.nonce),
+            RlpItem::from_u64(self.gas_price),
+            RlpItem::from_u64(self.gas_limit),
+            RlpItem::Bytes(
+                self.to
+                    .as_ref()
+                    .map(|to| to.as_bytes().to_vec())
+                    .unwrap_or_default(),
+            ),
+            RlpItem::Bytes(rlp::strip_leading_zeros(&self.value.to_be_bytes()).to_vec()),
+            RlpItem::Bytes(self.data.clone()),
+        ]
+    }
+
+    /// Return the signed raw transaction
+    pub fn sign(&self, signer: &EthSigner, chain_id: u64) -> Vec<u8> {
+        if let Some(access_list) = self.access_list.as_ref() {
+            return self.sign_access_list_tx(signer, chain_id, access_list);
+        }
+        let mut fields = self.rlp_fields();
+        fields.push(RlpItem::from_u64(chain_id));
+        fields.push(RlpItem::from_u64(0));
+        fields.push(RlpItem::from_u64(0));
+        let message = keccak256(&rlp::encode(&RlpItem::List(fields)));
+        let message = secp256k1::Message::from_slice(&message).expect("secp256k1 message");
+        let signature = SECP256K1.sign_recoverable(&message, &signer.privkey);
+        let (recov_id, data) = signature.serialize_compact();
+
+        let mut fields = self.rlp_fields();
+        fields.push(RlpItem::from_u64(
+            recov_id.to_i32() as u64 + 35 + chain_id * 2,
+        ));
+        fields.push(RlpItem::Bytes(
+            rlp::strip_leading_zeros(&data[0..32]).to_vec(),
+        ));
+        fields.push(RlpItem::Bytes(
+            rlp::strip_leading_zeros(&data[32..64]).to_vec(),
+        ));
+        rlp::encode(&RlpItem::List(fields))
+    }
+
+    /// `0x01 || rlp([chainId, nonce, gasPrice, gasLimit, to, value, data, accessList,
+    /// yParity, r, s])`, the signed message is the same without the signature
+    fn sign_access_list_tx(
+        &self,
+        signer: &EthSigner,
+        chain_id: u64,
+        access_list: &[AccessListItem],
+    ) -> Vec<u8> {
+        let mut fields = vec![RlpItem::from_u64(chain_id)];
+        fields.extend(self.rlp_fields());
+        fields.push(RlpItem::List(
+            access_list
+                .iter()
+                .map(|item| {
+                    RlpItem::List(vec![
+                        RlpItem::Bytes(item.address.as_bytes().to_vec()),
+                        RlpItem::List(
+                            item.storage_keys
+                                .iter()
+                                .map(|key| RlpItem::Bytes(key.as_bytes().to_vec()))
+                                .collect(),
+                        ),
+                    ])
+                })
+                .collect(),
+        ));
+        let mut payload = vec![ACCESS_LIST_TX_TYPE];
+        payload.extend(rlp::encode(&RlpItem::List(fields.clone())));
+        let message = keccak256(&payload);
+        let message = secp256k1::Message::from_slice(&message).expect("secp256k1 message");
+        let signature = SECP256K1.sign_recoverable(&message, &signer.privkey);
+        let (recov_id, data) = signature.serialize_compact();
+
+        fields.push(RlpItem::from_u64(recov_id.to_i32() as u64));
+        fields.push(RlpItem::Bytes(
+            rlp::strip_leading_zeros(&data[0..32]).to_vec(),
+        ));
+        fields.push(RlpItem::Bytes(
+            rlp::strip_leading_zeros(&data[32..64]).to_vec(),
+        ));
+        let mut raw_tx = vec![ACCESS_LIST_TX_TYPE];
+        raw_tx.extend(rlp::encode(&RlpItem::List(fields)));
+        raw_tx
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EthReceipt {
+    pub transaction_hash: H256,
+    pub transaction_index: String,
+    pub block_hash: H256,
+    pub block_number: String,
+    pub cumulative_gas_used: String,
+    pub gas_used: String,
+    pub logs_bloom: JsonBytes,
+    pub logs: Vec<EthLog>,
+    #[serde(default)]
+    pub status: Option<String>,
+    /// The called contract, `None` for contract creation transactions
+    #[serde(default)]
+    pub to: Option<H160>,
+    /// The created contract, only set for contract creation transactions
+    #[serde(default)]
+    pub contract_address: Option<H160>,
+    /// The gas price actually paid, absent on old nodes
+    #[serde(default)]
+    pub effective_gas_price: Option<String>,
+    /// Transaction type (EIP-2718), absent for legacy transactions on old nodes
+    #[serde(default, rename = "type")]
+    pub tx_type: Option<String>,
+}
+
+impl EthReceipt {
+    pub fn block_number(&self) -> Result<u64, String> {
+        parse_quantity(&self.block_number)
+    }
+
+    pub fn transaction_index(&self) -> Result<u64, String> {
+        parse_quantity(&self.transaction_index)
+    }
+
+    pub fn gas_used(&self) -> Result<u64, String> {
+        parse_quantity(&self.gas_used)
+    }
+
+    /// The consensus encoding of the receipt, as the value in the receipts trie
+    pub fn rlp_bytes(&self) -> Result<Vec<u8>, String> {
+        let status = self
+            .status
+            .as_ref()
+            .ok_or_else(|| "Pre-byzantium receipt without status is not supported".to_string())?;
+        let logs = self
+            .logs
+            .iter()
+            .map(|log| {
+                RlpItem::List(vec![
+                    RlpItem::Bytes(log.address.as_bytes().to_vec()),
+                    RlpItem::List(
+                        log.topics
+                            .iter()
+                            .map(|topic| RlpItem::Bytes(topic.as_bytes().to_vec()))
+                            .collect(),
+                    ),
+                    RlpItem::Bytes(log.data.as_bytes().to_vec()),
+                ])
+            })
+            .collect();
+        let payload = rlp::encode(&RlpItem::List(vec![
+            quantity_item(status)?,
+            quantity_item(&self.cumulative_gas_used)?,
+            RlpItem::Bytes(self.logs_bloom.as_bytes().to_vec()),
+            RlpItem::List(logs),
+        ]));
+        let tx_type = match self.tx_type.as_ref() {
+            Some(tx_type) => parse_quantity(tx_type)?,
+            None => 0,
+        };
+        if tx_type == 0 {
+            Ok(payload)
+        } else {
+            let mut data = vec![tx_type as u8];
+            data.extend(payload);
+            Ok(data)
+        }
+    }
+
+    pub fn is_success(&self) -> Result<bool, String> {
+        match self.status.as_ref() {
+            Some(status) => Ok(parse_quantity(status)? == 1),
+            None => Err("Pre-byzantium receipt without status is not supported".to_string()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EthLog {
+    pub address: H160,
+    pub topics: Vec<H256>,
+    pub data: JsonBytes,
+}
+
+/// Ethereum block header as returned by `eth_getBlockByNumber`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EthHeader {
+    pub hash: H256,
+    pub parent_hash: H256,
+    pub sha3_uncles: H256,
+    pub miner: H160,
+    pub state_root: H256,
+    pub transactions_root: H256,
+    pub receipts_root: H256,
+    pub logs_bloom: JsonBytes,
+    pub difficulty: String,
+    pub number: String,
+    pub gas_limit: String,
+    pub gas_used: String,
+    pub timestamp: String,
+    pub extra_data: JsonBytes,
+    pub mix_hash: H256,
+    pub nonce: JsonBytes,
+    #[serde(default)]
+    pub base_fee_per_gas: Option<String>,
+    /// Transaction hashes of the block
+    #[serde(default)]
+    pub transactions: Vec<H256>,
+}
+
+impl EthHeader {
+    pub fn number(&self) -> Result<u64, String> {
+        parse_quantity(&self.number)
+    }
+
+    pub fn rlp_item(&self) -> Result<RlpItem, String> {
+        let mut fields = vec![
+            RlpItem::Bytes(self.parent_hash.as_bytes().to_vec()),
+            RlpItem::Bytes(self.sha3_uncles.as_bytes().to_vec()),
+            RlpItem::Bytes(self.miner.as_bytes().to_vec()),
+            RlpItem::Bytes(self.state_root.as_bytes().to_vec()),
+            RlpItem::Bytes(self.transactions_root.as_bytes().to_vec()),
+            RlpItem::Bytes(self.receipts_root.as_bytes().to_vec()),
+            RlpItem::Bytes(self.logs_bloom.as_bytes().to_vec()),
+            quantity_item(&self.difficulty)?,
+            quantity_item(&self.number)?,
+            quantity_item(&self.gas_limit)?,
+            quantity_item(&self.gas_used)?,
+            quantity_item(&self.timestamp)?,
+            RlpItem::Bytes(self.extra_data.as_bytes().to_vec()),
+            RlpItem::Bytes(self.mix_hash.as_bytes().to_vec()),
+            RlpItem::Bytes(self.nonce.as_bytes().to_vec()),
+        ];
+        if let Some(base_fee) = self.base_fee_per_gas.as_ref() {
+            fields.push(quantity_item(base_fee)?);
+        }
+        Ok(RlpItem::List(fields))
+    }
+
+    /// The rlp encoded header, the result is checked against the header hash
+    pub fn rlp_bytes(&self) -> Result<Vec<u8>, String> {
+        let data = rlp::encode(&self.rlp_item()?);
+        let hash = keccak256(&data);
+        if hash != self.hash.as_bytes() {
+            return Err(format!(
+                "Ethereum header rlp hash mismatch, number: {}, expected: {:#x}, got: 0x{}",
+                self.number,
+                self.hash,
+                faster_hex::hex_string(&hash).expect("hex string")
+            ));
+        }
+        Ok(data)
+    }
+}
+
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    tiny_keccak::keccak256(data)
+}
+
+/// The EIP-55 mixed case form of an address, how addresses are shown to the user. A letter is
+/// upper case if the same nibble of the keccak256 of the lower case hex is at least 8, input
+/// is accepted in any case.
+pub fn checksum_address(address: &H160) -> String {
+    let hex = format!("{:x}", address);
+    let hash = keccak256(hex.as_bytes());
+    let checksummed = hex
+        .chars()
+        .enumerate()
+        .map(|(index, ch)| {
+            let nibble = (hash[index / 2] >> (if index % 2 == 0 { 4 } else { 0 })) & 0x0f;
+            if nibble >= 8 {
+                ch.to_ascii_uppercase()
+            } else {
+                ch
+            }
+        })
+        .collect::<String>();
+    format!("0x{}", checksummed)
+}
+
+/// Serialize an address of an output in the EIP-55 form
+pub fn serialize_checksum_address<S: Serializer>(
+    address: &H160,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&checksum_address(address))
+}
+
+/// Serialize an optional address of an output in the EIP-55 form
+pub fn serialize_optional_checksum_address<S: Serializer>(
+    address: &Option<H160>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match address {
+        Some(address) => serialize_checksum_address(address, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Parse an ethereum hex quantity (e.g. "0x1b4")
+pub fn parse_quantity(input: &str) -> Result<u64, String> {
+    let hex = input.trim_start_matches("0x");
+    u64::from_str_radix(hex, 16).map_err(|err| format!("Invalid quantity {}: {}", input, err))
+}
+
+fn quantity_item(input: &str) -> Result<RlpItem, String> {
+    let hex = input.trim_start_matches("0x");
+    let hex = if hex.len() % 2 == 1 {
+        format!("0{}", hex)
+    } else {
+        hex.to_string()
+    };
+    let mut bytes = vec![0u8; hex.len() / 2];
+    faster_hex::hex_decode(hex.as_bytes(), &mut bytes)
+        .map_err(|err| format!("Invalid quantity {}: {}", input, err))?;
+    Ok(RlpItem::Bytes(rlp::strip_leading_zeros(&bytes).to_vec()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::arg_parser::{ArgParser, FixedHashParser};
+
+    #[test]
+    fn test_checksum_address() {
+        // The test vectors of EIP-55
+        let vectors = [
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+            "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+            "0x52908400098527886E0F7030069857D2E4169EE7",
+            "0xde709f2102306220921060314715629080e2fb77",
+        ];
+        let parser = FixedHashParser::<H160>::default();
+        for expected in vectors.iter() {
+            // Parsed in any case
+            let address = parser.parse(&expected.to_lowercase()).unwrap();
+            assert_eq!(parser.parse(expected), Ok(address.clone()));
+            assert_eq!(&checksum_address(&address), expected);
+        }
+        let json = serde_json::to_value(SerializeTest {
+            address: Some(parser.parse(vectors[0]).unwrap()),
+        })
+        .unwrap();
+        assert_eq!(json["address"], serde_json::json!(vectors[0]));
+    }
+
+    #[derive(Serialize)]
+    struct SerializeTest {
+        #[serde(serialize_with = "serialize_optional_checksum_address")]
+        address: Option<H160>,
+    }
+}
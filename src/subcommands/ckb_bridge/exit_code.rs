@@ -0,0 +1,199 @@
+//! The exit codes of the ckb-bridge subcommands, a contract for scripts to branch on. The
+//! errors are plain messages, an error with its own code is raised as a `BridgeError` and a
+//! failed subcommand is mapped to the code of its kind at the CLI boundary.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+
+/// Finished (or nothing to do)
+pub const SUCCESS: i32 = 0;
+/// Any other error, or a check (doctor, tokens --check, the bytecode of deploy-sol) found a
+/// problem
+pub const ERROR: i32 = 1;
+/// The transfer is not finished yet, run the same command again later
+pub const PENDING: i32 = 3;
+/// A transfer is `Failed`, or a step of resume-all failed
+pub const TRANSFER_FAILED: i32 = 4;
+/// The bridge is not deployed or the config is invalid
+pub const CONFIG_ERROR: i32 = 5;
+/// The ckb or ethereum node is on another chain than expected
+pub const NETWORK_MISMATCH: i32 = 6;
+/// Not enough ckb capacity or ether to pay the transaction
+pub const INSUFFICIENT_FUNDS: i32 = 7;
+
+/// The kind of an error with its own exit code, any other error is `ERROR`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    TransferFailed,
+    InsufficientFunds,
+    NetworkMismatch,
+    Config,
+    /// Timed out or cancelled while waiting, the same command can be run again later
+    Pending,
+}
+
+impl ErrorKind {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorKind::TransferFailed => TRANSFER_FAILED,
+            ErrorKind::InsufficientFunds => INSUFFICIENT_FUNDS,
+            ErrorKind::NetworkMismatch => NETWORK_MISMATCH,
+            ErrorKind::Config => CONFIG_ERROR,
+            ErrorKind::Pending => PENDING,
+        }
+    }
+}
+
+/// An error of a known kind, raised where the kind is decided. It is returned as its message
+/// (`?` converts it), the kind is recorded on the way and found back by `of_error`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BridgeError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl BridgeError {
+    pub fn new(kind: ErrorKind, message: String) -> BridgeError {
+        BridgeError { kind, message }
+    }
+
+    pub fn pending(message: String) -> BridgeError {
+        BridgeError::new(ErrorKind::Pending, message)
+    }
+
+    pub fn config(message: String) -> BridgeError {
+        BridgeError::new(ErrorKind::Config, message)
+    }
+
+    pub fn network_mismatch(message: String) -> BridgeError {
+        BridgeError::new(ErrorKind::NetworkMismatch, message)
+    }
+
+    pub fn insufficient_funds(message: String) -> BridgeError {
+        BridgeError::new(ErrorKind::InsufficientFunds, message)
+    }
+
+    pub fn transfer_failed(message: String) -> BridgeError {
+        BridgeError::new(ErrorKind::TransferFailed, message)
+    }
+}
+
+impl fmt::Display for BridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+// At most this many raised errors are kept, a command raises a few
+const MAX_RAISED: usize = 64;
+
+thread_local! {
+    // The errors raised by the running command, the latest last. The errors of the checks run
+    // on other threads (resume-all, doctor) are not recorded, they are reported as is.
+    static RAISED: RefCell<VecDeque<BridgeError>> = RefCell::new(VecDeque::new());
+}
+
+impl From<BridgeError> for String {
+    fn from(err: BridgeError) -> String {
+        let message = err.message.clone();
+        RAISED.with(|raised| {
+            let mut raised = raised.borrow_mut();
+            if raised.len() >= MAX_RAISED {
+                raised.pop_front();
+            }
+            raised.push_back(err);
+        });
+        message
+    }
+}
+
+/// The kind of a subcommand failed with `err`: the kind of the latest raised error which `err`
+/// is or wraps (a caller may add context around the message)
+pub fn kind_of(err: &str) -> Option<ErrorKind> {
+    RAISED.with(|raised| {
+        raised
+            .borrow()
+            .iter()
+            .rev()
+            .find(|raised| !raised.message.is_empty() && err.contains(raised.message.as_str()))
+            .map(|raised| raised.kind)
+    })
+}
+
+/// The exit code of a subcommand failed with `err`
+pub fn of_error(err: &str) -> i32 {
+    kind_of(err).map(ErrorKind::exit_code).unwrap_or(ERROR)
+}
+
+/// The exit code of one step of a transfer, by the status it is left in
+pub fn of_status(finished: bool, failed: bool) -> i32 {
+    if failed {
+        TRANSFER_FAILED
+    } else if finished {
+        SUCCESS
+    } else {
+        PENDING
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_of_error() {
+        let cases = [
+            (
+                BridgeError::transfer_failed(
+                    "Transfer 1a2b failed: lock transaction reverted".to_string(),
+                ),
+                TRANSFER_FAILED,
+            ),
+            (
+                BridgeError::insufficient_funds(
+                    "Capacity not enough: ckt1qyq... has 10.0 in mature plain cells, 150.0 required"
+                        .to_string(),
+                ),
+                INSUFFICIENT_FUNDS,
+            ),
+            (
+                BridgeError::network_mismatch(
+                    "The transaction is signed for chain 1, but the ethereum node is on chain 5"
+                        .to_string(),
+                ),
+                NETWORK_MISMATCH,
+            ),
+            (
+                BridgeError::config(
+                    "Light client is not configured, please run deploy-ckb first".to_string(),
+                ),
+                CONFIG_ERROR,
+            ),
+            (
+                BridgeError::pending("Wait block 100 safe cancelled, resume later".to_string()),
+                PENDING,
+            ),
+        ];
+        for (err, code) in cases.iter() {
+            let message: String = err.clone().into();
+            assert_eq!(of_error(&message), *code, "{}", message);
+            // With context added by a caller
+            assert_eq!(of_error(&format!("Transfer 1a2b: {}", message)), *code);
+        }
+        // A plain error, even with the words of another kind
+        assert_eq!(of_error("Wait mined timeout (600s)"), ERROR);
+        assert_eq!(of_error("Bridge contract is not configured"), ERROR);
+        assert_eq!(
+            of_error("Request eth_call failed: connection refused"),
+            ERROR
+        );
+    }
+
+    #[test]
+    fn test_of_status() {
+        assert_eq!(of_status(true, false), SUCCESS);
+        assert_eq!(of_status(false, false), PENDING);
+        assert_eq!(of_status(false, true), TRANSFER_FAILED);
+    }
+}
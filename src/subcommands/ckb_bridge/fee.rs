@@ -0,0 +1,190 @@
+//! Fee cell selection and the fee of a ckb transaction paid by a fee rate
+
+use ckb_sdk::constants::MIN_SECP_CELL_CAPACITY;
+use ckb_types::{
+    bytes::Bytes,
+    core::TransactionView,
+    packed::{self, WitnessArgs},
+    prelude::*,
+};
+
+// Size of the secp256k1 recoverable signature put in the witness lock
+const SIGNATURE_SIZE: usize = 65;
+// The fee depends on the selected cells, which depends on the fee, this never takes more
+// than a few rounds in practice
+const MAX_FEE_ROUNDS: usize = 16;
+
+/// Whether the taken capacity pays `target` exactly or leaves enough for a change cell
+pub fn is_enough(taken: u64, target: u64) -> bool {
+    taken == target || taken >= target + MIN_SECP_CELL_CAPACITY
+}
+
+/// The fee of a transaction of `size` bytes, `fee_rate` is in shannons per KB (rounded up)
+pub fn tx_fee(size: usize, fee_rate: u64) -> u64 {
+    (size as u64 * fee_rate + 999) / 1000
+}
+
+/// The serialized size (in block) of the transaction after the input at `input_idx` is signed
+pub fn signed_size(tx: &TransactionView, input_idx: usize) -> usize {
+    with_signature_placeholder(tx, input_idx)
+        .data()
+        .serialized_size_in_block()
+}
+
+/// Put a zeroed signature of the signed size in the witness lock of the input at `input_idx`,
+/// the other witness fields are kept. Signing only replaces the lock bytes, so the size (and
+/// the fee paid by it) is the same before and after signing.
+pub fn with_signature_placeholder(tx: &TransactionView, input_idx: usize) -> TransactionView {
+    let mut witnesses: Vec<packed::Bytes> = tx.witnesses().into_iter().collect();
+    while witnesses.len() < tx.inputs().len() {
+        witnesses.push(Default::default());
+    }
+    let witness_args =
+        WitnessArgs::from_slice(&witnesses[input_idx].raw_data()).unwrap_or_default();
+    witnesses[input_idx] = witness_args
+        .as_builder()
+        .lock(Some(Bytes::from(vec![0u8; SIGNATURE_SIZE])).pack())
+        .build()
+        .as_bytes()
+        .pack();
+    tx.as_advanced_builder().set_witnesses(witnesses).build()
+}
+
+/// Find a fee no less than `min_fee` covering `fee_rate`. `build` returns the (signed) size
+/// of the transaction paying `fee`, a higher fee may take more fee cells and a change cell,
+/// so the size is measured again until the fee is stable.
+pub fn settle_fee<F>(min_fee: u64, fee_rate: u64, mut build: F) -> Result<u64, String>
+where
+    F: FnMut(u64) -> Result<usize, String>,
+{
+    let mut fee = min_fee;
+    for _ in 0..MAX_FEE_ROUNDS {
+        let size = build(fee)?;
+        let required = std::cmp::max(min_fee, tx_fee(size, fee_rate));
+        if required <= fee {
+            return Ok(fee);
+        }
+        fee = required;
+    }
+    Err(format!(
+        "The transaction fee is not stable after {} rounds (fee rate: {} shannons/KB)",
+        MAX_FEE_ROUNDS, fee_rate
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_types::{core::TransactionBuilder, packed::CellInput};
+
+    #[test]
+    fn test_settle_fee_fragmented() {
+        // 30 small cells of 70 CKB, none covers the target alone
+        let cells = vec![70 * 100_000_000u64; 30];
+        let target = 142 * 100_000_000u64;
+        let input_size = 44;
+        let base_size = 600;
+        let fee_rate = 100_000;
+        let select = |fee: u64| -> Result<(usize, u64), String> {
+            let mut taken = 0;
+            for (count, capacity) in cells.iter().enumerate() {
+                taken += capacity;
+                if is_enough(taken, target + fee) {
+                    return Ok((count + 1, taken - target - fee));
+                }
+            }
+            Err("Capacity not enough".to_string())
+        };
+        let mut rounds = 0;
+        let fee = settle_fee(1000, fee_rate, |fee| {
+            rounds += 1;
+            let (count, change) = select(fee)?;
+            let change_size = if change > 0 { 100 } else { 0 };
+            Ok(base_size + count * input_size + change_size)
+        })
+        .unwrap();
+        assert!(rounds > 1);
+        let (count, change) = select(fee).unwrap();
+        assert_eq!(count, 3);
+        assert!(change >= MIN_SECP_CELL_CAPACITY);
+        let size = base_size + count * input_size + 100;
+        assert!(fee >= tx_fee(size, fee_rate));
+
+        // A min fee covering the rate is kept
+        assert_eq!(settle_fee(1_000_000, 1000, |_| Ok(500)).unwrap(), 1_000_000);
+        assert!(settle_fee(0, 1000, |fee| Ok(fee as usize * 2 + 1000)).is_err());
+    }
+
+    #[test]
+    fn test_fee_rate_of_resumed_step() {
+        // The mint transaction is built again with the fee rate of the current run, nothing
+        // of the fee is kept in the transfer
+        let tx = TransactionBuilder::default()
+            .input(CellInput::new(packed::OutPoint::default(), 0))
+            .build();
+        let build = |_fee: u64| Ok(signed_size(&tx, 0));
+        let paused_fee = settle_fee(1000, 1000, build).unwrap();
+        let resumed_fee = settle_fee(1000, 10_000, build).unwrap();
+        assert!(resumed_fee > paused_fee);
+        assert_eq!(resumed_fee, tx_fee(signed_size(&tx, 0), 10_000));
+    }
+
+    #[test]
+    fn test_signed_size() {
+        assert_eq!(tx_fee(1000, 1000), 1000);
+        assert_eq!(tx_fee(1001, 1000), 1001);
+        assert_eq!(tx_fee(1, 1), 1);
+
+        let tx = TransactionBuilder::default()
+            .input(CellInput::new(packed::OutPoint::default(), 0))
+            .input(CellInput::new(packed::OutPoint::default(), 0))
+            .build();
+        let size = tx.data().serialized_size_in_block();
+        let signed = signed_size(&tx, 0);
+        let witness = WitnessArgs::new_builder()
+            .lock(Some(Bytes::from(vec![0u8; SIGNATURE_SIZE])).pack())
+            .build();
+        // One signed witness and one empty witness
+        assert_eq!(signed, size + (4 + witness.as_slice().len()) + (4 + 4) + 4);
+    }
+
+    #[test]
+    fn test_placeholder_size_matches_signed_tx() {
+        use super::super::util::sign_sighash_inputs;
+        use crate::utils::arg_parser::PrivkeyWrapper;
+        use ckb_hash::blake2b_256;
+        use ckb_types::{packed::CellOutput, H160};
+
+        let privkey = secp256k1::SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let pubkey = secp256k1::PublicKey::from_secret_key(&secp256k1::Secp256k1::new(), &privkey);
+        let account = H160::from_slice(&blake2b_256(&pubkey.serialize()[..])[0..20]).unwrap();
+        // The first witness carries a type field, which is kept by the placeholder and signing
+        let type_witness = WitnessArgs::new_builder()
+            .output_type(Some(Bytes::from(vec![2u8; 100])).pack())
+            .build();
+        let tx = TransactionBuilder::default()
+            .input(CellInput::new(packed::OutPoint::default(), 0))
+            .input(CellInput::new(packed::OutPoint::default(), 0))
+            .output(CellOutput::default())
+            .output_data(Bytes::new().pack())
+            .witness(type_witness.as_bytes().pack())
+            .build();
+        let estimated = signed_size(&tx, 0);
+        let unsigned = with_signature_placeholder(&tx, 0);
+        assert_eq!(unsigned.data().serialized_size_in_block(), estimated);
+        assert_eq!(unsigned.hash(), tx.hash());
+
+        for tx in vec![tx, unsigned] {
+            let signed =
+                sign_sighash_inputs(tx, PrivkeyWrapper(privkey), account.clone(), &[0, 1]).unwrap();
+            assert_eq!(signed.data().serialized_size_in_block(), estimated);
+            let witness =
+                WitnessArgs::from_slice(&signed.witnesses().get(0).unwrap().raw_data()).unwrap();
+            assert_eq!(witness.output_type(), type_witness.output_type());
+            assert_ne!(
+                witness.lock().to_opt().unwrap().raw_data(),
+                vec![0u8; SIGNATURE_SIZE]
+            );
+        }
+    }
+}
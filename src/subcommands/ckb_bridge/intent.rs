@@ -0,0 +1,184 @@
+//! Transfer intents, the holder of the tokens authorizes one transfer off-chain and an operator
+//! relays it. The holder signs (as an EIP-191 personal message) the keccak256 of
+//! `abi.encode(token, amount, recipientLockscript, nonce, deadline)`, the operator
+//! verifies the signer before anything is sent and passes the authorization to `lock()` as a
+//! fourth argument `bytes authorization = abi.encode(signer, nonce, deadline, signature)`.
+
+use ckb_jsonrpc_types::JsonBytes;
+use ckb_types::H160;
+use serde_derive::{Deserialize, Serialize};
+
+use super::abi::{self, Token};
+use super::eth::{checksum_address, keccak256, recover_message_signer, EthSigner};
+
+/// The parameters of a transfer the holder signs
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransferIntent {
+    pub token: H160,
+    pub amount: u128,
+    /// The molecule encoded recipient lock script, as passed to `lock()`
+    pub recipient_lock: Vec<u8>,
+    /// Chosen by the holder, an intent is used once
+    pub nonce: u64,
+    /// Unix time in seconds the intent expires at
+    pub deadline: u64,
+}
+
+impl TransferIntent {
+    /// The signed message: the hash of the encoded parameters
+    pub fn message(&self) -> [u8; 32] {
+        keccak256(&abi::encode(&[
+            Token::Address(self.token.clone()),
+            Token::Uint(self.amount),
+            Token::Bytes(self.recipient_lock.clone()),
+            Token::Uint(u128::from(self.nonce)),
+            Token::Uint(u128::from(self.deadline)),
+        ]))
+    }
+
+    pub fn sign(&self, signer: &EthSigner) -> IntentAuthorization {
+        IntentAuthorization {
+            signer: signer.address.clone(),
+            nonce: self.nonce,
+            deadline: self.deadline,
+            signature: JsonBytes::from_vec(signer.sign_message(&self.message())),
+        }
+    }
+
+    /// Check `signature` is signed by `expected_signer` over this intent and the intent is not
+    /// expired at `now`
+    pub fn verify(
+        &self,
+        signature: &[u8],
+        expected_signer: &H160,
+        now: u64,
+    ) -> Result<IntentAuthorization, String> {
+        let signer = recover_message_signer(&self.message(), signature)
+            .map_err(|err| format!("Invalid intent signature: {}", err))?;
+        if &signer != expected_signer {
+            return Err(format!(
+                "The intent is signed by {}, not the expected signer {} (or the token, amount, recipient, nonce or deadline differ from the signed ones)",
+                checksum_address(&signer),
+                checksum_address(expected_signer)
+            ));
+        }
+        let authorization = IntentAuthorization {
+            signer,
+            nonce: self.nonce,
+            deadline: self.deadline,
+            signature: JsonBytes::from_vec(signature.to_vec()),
+        };
+        authorization.check_deadline(now)?;
+        Ok(authorization)
+    }
+}
+
+/// A verified intent, persisted with the transfer and passed to `lock()`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IntentAuthorization {
+    pub signer: H160,
+    pub nonce: u64,
+    pub deadline: u64,
+    pub signature: JsonBytes,
+}
+
+impl IntentAuthorization {
+    pub fn check_deadline(&self, now: u64) -> Result<(), String> {
+        if now > self.deadline {
+            return Err(format!(
+                "The transfer intent of {} (nonce {}) expired at {}, ask for a new one",
+                checksum_address(&self.signer),
+                self.nonce,
+                self.deadline
+            ));
+        }
+        Ok(())
+    }
+
+    /// The `bytes` argument of `lock()`
+    pub fn encode(&self) -> Vec<u8> {
+        abi::encode(&[
+            Token::Address(self.signer.clone()),
+            Token::Uint(u128::from(self.nonce)),
+            Token::Uint(u128::from(self.deadline)),
+            Token::Bytes(self.signature.as_bytes().to_vec()),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::arg_parser::PrivkeyWrapper;
+
+    fn signer(byte: u8) -> EthSigner {
+        EthSigner::new(PrivkeyWrapper(
+            secp256k1::SecretKey::from_slice(&[byte; 32]).unwrap(),
+        ))
+    }
+
+    fn intent() -> TransferIntent {
+        TransferIntent {
+            token: H160([0x11; 20]),
+            amount: 1_000_000,
+            recipient_lock: vec![0x22; 53],
+            nonce: 7,
+            deadline: 1_600_000_000,
+        }
+    }
+
+    #[test]
+    fn test_intent_verify() {
+        let holder = signer(0x11);
+        let authorization = intent().sign(&holder);
+        let signature = authorization.signature.as_bytes();
+        assert_eq!(
+            intent().verify(signature, &holder.address, 1_600_000_000),
+            Ok(authorization.clone())
+        );
+        // Expired
+        assert!(intent()
+            .verify(signature, &holder.address, 1_600_000_001)
+            .unwrap_err()
+            .contains("expired"));
+        // Another signer
+        let other = signer(0x22);
+        assert!(intent()
+            .verify(signature, &other.address, 1_600_000_000)
+            .is_err());
+        // Any parameter changed
+        let mut changed = intent();
+        changed.amount += 1;
+        assert!(changed.verify(signature, &holder.address, 0).is_err());
+        let mut changed = intent();
+        changed.recipient_lock[0] = 0;
+        assert!(changed.verify(signature, &holder.address, 0).is_err());
+        let mut changed = intent();
+        changed.nonce = 8;
+        assert!(changed.verify(signature, &holder.address, 0).is_err());
+
+        // v of 0/1 is accepted, a truncated signature is not
+        let mut signature = signature.to_vec();
+        signature[64] -= 27;
+        assert!(intent().verify(&signature, &holder.address, 0).is_ok());
+        assert!(intent()
+            .verify(&signature[0..64], &holder.address, 0)
+            .is_err());
+    }
+
+    #[test]
+    fn test_intent_encode() {
+        let authorization = intent().sign(&signer(0x11));
+        let encoded = authorization.encode();
+        assert_eq!(
+            abi::decode_address(&encoded, 0),
+            Ok(authorization.signer.clone())
+        );
+        assert_eq!(abi::decode_uint(&encoded, 1), Ok(7));
+        assert_eq!(abi::decode_uint(&encoded, 2), Ok(1_600_000_000));
+        assert_eq!(
+            abi::decode_bytes(&encoded, 3),
+            Ok(authorization.signature.as_bytes().to_vec())
+        );
+    }
+}
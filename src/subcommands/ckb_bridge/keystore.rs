@@ -0,0 +1,185 @@
+//! Ethereum V3 keystores (scrypt and aes-128-ctr), `--eth-keystore` is one keystore file or a
+//! directory of them, the keystore of a directory is selected by `--eth-from-address`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ckb_sdk::wallet::Crypto;
+use ckb_types::H160;
+
+use super::eth::{checksum_address, EthSigner};
+use crate::utils::arg_parser::{ArgParser, FixedHashParser, PrivkeyWrapper};
+
+const KEYSTORE_VERSION: u64 = 3;
+
+/// Decrypt the keystore at `path` (a file, or the one of `address` in a directory), the
+/// decrypted key must be of `address` if given
+pub fn load_signer(
+    path: &Path,
+    address: Option<&H160>,
+    password: &[u8],
+) -> Result<EthSigner, String> {
+    let path = if path.is_dir() {
+        let address = address.ok_or_else(|| {
+            format!(
+                "{:?} is a directory, select the keystore with --eth-from-address",
+                path
+            )
+        })?;
+        select_keystore(path, scan_keystores(path)?, address)?
+    } else {
+        path.to_path_buf()
+    };
+    let signer = EthSigner::new(decrypt_keystore(&path, password)?);
+    if let Some(address) = address.filter(|address| **address != signer.address) {
+        return Err(format!(
+            "The keystore {:?} holds the key of {}, not {}",
+            path,
+            checksum_address(&signer.address),
+            checksum_address(address)
+        ));
+    }
+    Ok(signer)
+}
+
+/// The V3 keystores in `dir` with their (unverified) `address` field, other files are skipped
+pub fn scan_keystores(dir: &Path) -> Result<Vec<(PathBuf, Option<H160>)>, String> {
+    let entries = fs::read_dir(dir).map_err(|err| format!("Read {:?} error: {}", dir, err))?;
+    let mut keystores = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|err| err.to_string())?.path();
+        if !path.is_file() {
+            continue;
+        }
+        let json = match fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        {
+            Some(json) if json["version"].as_u64() == Some(KEYSTORE_VERSION) => json,
+            _ => continue,
+        };
+        let address = json["address"]
+            .as_str()
+            .and_then(|address| FixedHashParser::<H160>::default().parse(address).ok());
+        keystores.push((path, address));
+    }
+    keystores.sort();
+    Ok(keystores)
+}
+
+/// The only keystore of `address`, the address is verified once the keystore is decrypted
+pub fn select_keystore(
+    dir: &Path,
+    keystores: Vec<(PathBuf, Option<H160>)>,
+    address: &H160,
+) -> Result<PathBuf, String> {
+    let total = keystores.len();
+    let mut matched = keystores
+        .into_iter()
+        .filter(|(_, keystore_address)| keystore_address.as_ref() == Some(address))
+        .map(|(path, _)| path)
+        .collect::<Vec<_>>();
+    match matched.len() {
+        0 => Err(format!(
+            "No keystore of {} in {:?} ({} keystores)",
+            checksum_address(address),
+            dir,
+            total
+        )),
+        1 => Ok(matched.remove(0)),
+        _ => Err(format!(
+            "Multiple keystores of {} in {:?}: {:?}, give the keystore file instead",
+            checksum_address(address),
+            dir,
+            matched
+        )),
+    }
+}
+
+pub fn decrypt_keystore(path: &Path, password: &[u8]) -> Result<PrivkeyWrapper, String> {
+    let content =
+        fs::read_to_string(path).map_err(|err| format!("Read {:?} error: {}", path, err))?;
+    let json: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|err| format!("Parse keystore {:?} error: {}", path, err))?;
+    if json["version"].as_u64() != Some(KEYSTORE_VERSION) {
+        return Err(format!("{:?} is not a V3 keystore", path));
+    }
+    // Some tools write the field capitalized
+    let crypto = json
+        .get("crypto")
+        .or_else(|| json.get("Crypto"))
+        .ok_or_else(|| format!("Keystore {:?} has no crypto field", path))?;
+    let key = Crypto::from_json(crypto)
+        .and_then(|crypto| crypto.decrypt(password))
+        .map_err(|err| format!("Decrypt keystore {:?} error: {}", path, err))?;
+    secp256k1::SecretKey::from_slice(&key)
+        .map(PrivkeyWrapper)
+        .map_err(|err| format!("Invalid secp256k1 secret key in {:?}: {}", path, err))
+}
+
+/// The first line of the password file, without the line ending
+pub fn read_password_file(path: &Path) -> Result<String, String> {
+    let content =
+        fs::read_to_string(path).map_err(|err| format!("Read {:?} error: {}", path, err))?;
+    Ok(content
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches('\r')
+        .to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PASSWORD: &[u8] = b"bridge-operator";
+
+    fn fixture_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src/subcommands/ckb_bridge/test-data/eth-keystores")
+    }
+
+    fn address(input: &str) -> H160 {
+        FixedHashParser::<H160>::default().parse(input).unwrap()
+    }
+
+    #[test]
+    fn test_keystore_directory() {
+        let dir = fixture_dir();
+        let keystores = scan_keystores(&dir).unwrap();
+        assert_eq!(keystores.len(), 2);
+
+        let first = address("0x19e7e376e7c213b7e7e7e46cc70a5dd086daff2a");
+        let second = address("0x1563915e194d8cfba1943570603f7606a3115508");
+        for (byte, address) in vec![(0x11u8, &first), (0x22u8, &second)] {
+            let signer = load_signer(&dir, Some(address), PASSWORD).unwrap();
+            let privkey = secp256k1::SecretKey::from_slice(&[byte; 32]).unwrap();
+            assert_eq!(&signer.address, address);
+            assert_eq!(
+                signer.address,
+                EthSigner::new(PrivkeyWrapper(privkey)).address
+            );
+        }
+        // A keystore file is used as is
+        let path = keystores[0].0.clone();
+        let signer = load_signer(&path, None, PASSWORD).unwrap();
+        assert_eq!(Some(signer.address.clone()), keystores[0].1);
+        let other = if signer.address == first {
+            &second
+        } else {
+            &first
+        };
+        assert!(load_signer(&path, Some(other), PASSWORD).is_err());
+
+        assert!(load_signer(&dir, None, PASSWORD).is_err());
+        assert!(load_signer(&dir, Some(&first), b"wrong").is_err());
+        let err = load_signer(&dir, Some(&H160::default()), PASSWORD).unwrap_err();
+        assert!(err.contains("No keystore"));
+
+        let mut duplicated = keystores.clone();
+        duplicated.push((dir.join("copy"), Some(first.clone())));
+        let err = select_keystore(&dir, duplicated, &first).unwrap_err();
+        assert!(err.contains("Multiple keystores"));
+    }
+}
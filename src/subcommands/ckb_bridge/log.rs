@@ -0,0 +1,1405 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ckb_jsonrpc_types::{JsonBytes, Script, TransactionView};
+use ckb_sdk::NetworkType;
+use ckb_types::{H160, H256};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::eth::{checksum_address, serialize_optional_checksum_address, EthHeader};
+use super::exit_code;
+use super::intent::IntentAuthorization;
+use super::mint::{format_decimal, ScriptGroups};
+use super::price::UsdEstimate;
+
+/// Persisted state of an erc20 transfer from ethereum to ckb.
+///
+/// Only what the transfer is (token, amount, recipient) and what it has done (transactions,
+/// proof) is persisted. The fee rate, gas, confirmation and timeout arguments are read again on
+/// every run, so a resumed step uses the current ones. A transaction already built with the old
+/// values (sent, or written for offline signing) is kept until it is built again.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ToCkbLog {
+    pub id: String,
+    pub status: ToCkbLogStatus,
+    /// The erc20 token contract
+    pub token: H160,
+    pub amount: u128,
+    /// The configured decimals of the token when the transfer started, to print the amount
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decimals: Option<u8>,
+    /// The ckb address to receive the bridged token, absent if given as raw lock script
+    pub recipient: Option<String>,
+    /// The lock script of the minted token cell
+    pub recipient_lock: Script,
+    pub approve_tx: Option<H256>,
+    pub lock_tx: Option<H256>,
+    /// The approve or lock transaction written by `--eth-build-only`, waiting to be signed
+    /// offline and sent by `broadcast-eth-tx`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_eth_tx: Option<PendingEthTx>,
+    /// The receipt proof of the lock transaction, see `proof::ReceiptProof`
+    #[serde(default)]
+    pub proof: Option<JsonBytes>,
+    /// The block of `proof`, absent in the logs written before it was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lock_block: Option<LockBlock>,
+    /// The hash of the mint transaction built with `--build-only`, to be signed offline
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unsigned_mint_tx: Option<H256>,
+    #[serde(default)]
+    pub mint_tx: Option<H256>,
+    /// The ckb address paying the token cell capacity and the fee of the mint transaction,
+    /// set when it is sent or written for offline signing. A resumed mint may be paid by
+    /// another account than the first attempt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mint_payer: Option<String>,
+    /// Set when the mint transaction is built
+    #[serde(default)]
+    pub amount_check: Option<AmountCheck>,
+    /// The amount truncated by the lower decimals on ckb (with `--allow-precision-loss`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncated_amount: Option<String>,
+    /// Set when the mint step failed with `--include-debug-proof`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debug_proof: Option<DebugProof>,
+    /// The actual cost of the mined/committed transactions, for bookkeeping
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<TransferCost>,
+    /// The recipient's token balance around the mint, set when the mint transaction is sent
+    /// and when it is committed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balance_check: Option<BalanceCheck>,
+    /// Set when the committed mint did not land as expected, the transfer is still `Finished`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+    /// The transfer fails if not finished by this time (unix seconds), set by
+    /// `--transfer-deadline` when the transfer starts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<u64>,
+    /// The holder's authorization of a transfer relayed by an operator
+    /// (`--intent-signature`), passed to `lock()`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intent: Option<IntentAuthorization>,
+    /// The steps done out-of-band and skipped (`--skip-approve`, `--skip-lock`), with why they
+    /// were considered done
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped_steps: Vec<String>,
+    /// Why the transfer is `Failed`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<String>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PendingEthTx {
+    /// "approve" or "lock"
+    pub step: String,
+    pub nonce: u64,
+}
+
+/// Diagnostics of a failed mint step for offline analysis (e.g. by `decode-proof`), this may be
+/// large since it includes the whole proof and the attempted ckb transaction
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DebugProof {
+    pub error: String,
+    pub timestamp: u64,
+    pub proof: JsonBytes,
+    /// The ethereum header of the proof block, absent if it can't be fetched
+    pub block_header: Option<EthHeader>,
+    /// Absent if the step failed before the mint transaction was built
+    pub ckb_tx: Option<TransactionView>,
+}
+
+/// Persisted state of an erc20 transfer from ckb to ethereum
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FromCkbLog {
+    pub id: String,
+    pub status: FromCkbLogStatus,
+    /// The erc20 token contract of the bridged token
+    #[serde(default)]
+    pub token: Option<H160>,
+    /// The amount of the bridged token to burn, in its unit on ckb
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount: Option<u128>,
+    /// The ethereum address to receive the unlocked erc20 token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recipient: Option<H160>,
+    /// `--check-supply`, the burn step checks the amount against the bridged supply
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub check_supply: bool,
+    /// The ckb transaction burning the bridged token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub burn_tx: Option<H256>,
+    /// The ckb block of `burn_tx` the proof is built against, cleared when the block is
+    /// reorged out so the proof is built again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub burn_block: Option<BurnBlock>,
+    /// The proof of `burn_tx` in `burn_block`, see `burn::BurnProof`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof: Option<JsonBytes>,
+    /// The ethereum transaction releasing the erc20 tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unlock_tx: Option<H256>,
+    /// Set when the unlock transaction is checked against the burn transaction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount_check: Option<AmountCheck>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug, Serialize, Deserialize)]
+pub enum ToCkbLogStatus {
+    UnKnow = 0,
+    Approved = 1,
+    Locked = 2,
+    ParseProof = 3,
+    WaitBlockSafe = 4,
+    /// The unsigned mint transaction is written to a file (`--build-only`) and waits to be
+    /// signed offline
+    Signing = 5,
+    Mint = 6,
+    Finished = 7,
+    /// The deadline is exceeded before the transfer is finished, no step runs any more
+    Failed = 8,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum FromCkbLogStatus {
+    UnKnow = 0,
+    Burned = 1,
+    ParseProof = 2,
+    WaitBlockSafe = 3,
+    Mint = 4,
+    Finished = 5,
+}
+
+impl ToCkbLogStatus {
+    pub const ALL: [ToCkbLogStatus; 9] = [
+        ToCkbLogStatus::UnKnow,
+        ToCkbLogStatus::Approved,
+        ToCkbLogStatus::Locked,
+        ToCkbLogStatus::ParseProof,
+        ToCkbLogStatus::WaitBlockSafe,
+        ToCkbLogStatus::Signing,
+        ToCkbLogStatus::Mint,
+        ToCkbLogStatus::Finished,
+        ToCkbLogStatus::Failed,
+    ];
+
+    pub fn from_name(name: &str) -> Result<ToCkbLogStatus, String> {
+        parse_status_name(&ToCkbLogStatus::ALL, name)
+    }
+
+    /// No step runs in a terminal status
+    pub fn is_terminal(self) -> bool {
+        self == ToCkbLogStatus::Finished || self == ToCkbLogStatus::Failed
+    }
+}
+
+impl FromCkbLogStatus {
+    pub const ALL: [FromCkbLogStatus; 6] = [
+        FromCkbLogStatus::UnKnow,
+        FromCkbLogStatus::Burned,
+        FromCkbLogStatus::ParseProof,
+        FromCkbLogStatus::WaitBlockSafe,
+        FromCkbLogStatus::Mint,
+        FromCkbLogStatus::Finished,
+    ];
+
+    pub fn from_name(name: &str) -> Result<FromCkbLogStatus, String> {
+        parse_status_name(&FromCkbLogStatus::ALL, name)
+    }
+}
+
+fn parse_status_name<T: Copy + std::fmt::Debug>(all: &[T], name: &str) -> Result<T, String> {
+    all.iter()
+        .find(|status| format!("{:?}", status) == name)
+        .cloned()
+        .ok_or_else(|| {
+            let names = all
+                .iter()
+                .map(|status| format!("{:?}", status))
+                .collect::<Vec<_>>();
+            format!(
+                "Invalid status: {}, expected one of: {}",
+                name,
+                names.join(", ")
+            )
+        })
+}
+
+/// A status transition of a transfer, streamed to stdout in watch mode.
+///
+/// Amounts in the output are decimal strings, never JSON numbers, which lose precision beyond
+/// 2^53 in most consumers: `amount` is the raw integer and `amount_decimal` the amount with the
+/// token decimals (if configured).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TransferEvent {
+    pub transfer_id: String,
+    pub timestamp: u64,
+    pub status: String,
+    pub tx_hash: Option<H256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount_decimal: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount_check: Option<AmountCheck>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncated_amount: Option<String>,
+    /// The ckb address paying the mint transaction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mint_payer: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<TransferCost>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balance_check: Option<BalanceCheck>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<String>,
+    /// The transaction built but not sent by `--dry-run-ethereum` or `--dry-run-ckb`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dry_run: Option<DryRun>,
+    /// Why the step is not done yet, the transfer stays in its status until the same command
+    /// is run again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending: Option<String>,
+    /// The script groups of the built mint transaction (`--show-script-groups`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script_groups: Option<ScriptGroups>,
+    /// Only set with `--price-source`, informational
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usd_estimate: Option<UsdEstimate>,
+}
+
+/// A transaction of a step built and checked but not sent, the transfer stays in its status
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DryRun {
+    /// The simulated side, "ethereum" or "ckb"
+    pub side: String,
+    /// The step of the transaction: approve, lock, mint, burn or unlock
+    pub step: String,
+    pub tx: serde_json::Value,
+}
+
+/// One transfer in the `history` listing
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub transfer_id: String,
+    pub direction: String,
+    pub status: String,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_checksum_address"
+    )]
+    pub token: Option<H160>,
+    /// A string since it may exceed u64
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount_decimal: Option<String>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// Number of the transfers of one direction by state, a transfer is failed if its log can't
+/// be loaded or its mint step failed (with a recorded debug proof)
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TransferCounts {
+    pub pending: usize,
+    pub completed: usize,
+    pub failed: usize,
+}
+
+impl TransferCounts {
+    pub fn add(&mut self, finished: bool, failed: bool) {
+        if failed {
+            self.failed += 1;
+        } else if finished {
+            self.completed += 1;
+        } else {
+            self.pending += 1;
+        }
+    }
+}
+
+/// The aggregates of the transfer logs (`--summary-only`), the logs are added one by one so
+/// they are never all loaded at once. Amounts and fees are summed as integers and written as
+/// strings since they may exceed u64.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TransferSummary {
+    /// Number of the transfers by direction and status, the logs that can't be loaded are
+    /// counted as "Unreadable"
+    pub counts: BTreeMap<String, BTreeMap<String, usize>>,
+    /// Raw amount of the finished to-ckb transfers by token
+    pub bridged: BTreeMap<H160, u128>,
+    /// Ethereum fees paid in wei
+    pub eth_fees: u128,
+    /// Ckb fees paid in shannons
+    pub ckb_fees: u128,
+}
+
+impl TransferSummary {
+    pub fn add_to_ckb(&mut self, log: &ToCkbLog) {
+        self.count(DIRECTION_TO_CKB, &format!("{:?}", log.status));
+        if log.status == ToCkbLogStatus::Finished {
+            *self.bridged.entry(log.token.clone()).or_insert(0) += log.amount;
+        }
+        if let Some(cost) = log.cost.as_ref() {
+            self.eth_fees += cost.eth_total_wei.parse::<u128>().unwrap_or(0);
+            self.ckb_fees += u128::from(cost.ckb_fee.unwrap_or(0));
+        }
+    }
+
+    pub fn add_from_ckb(&mut self, log: &FromCkbLog) {
+        self.count(DIRECTION_FROM_CKB, &format!("{:?}", log.status));
+    }
+
+    pub fn add_unreadable(&mut self, direction: &str) {
+        self.count(direction, "Unreadable");
+    }
+
+    fn count(&mut self, direction: &str, status: &str) {
+        *self
+            .counts
+            .entry(direction.to_string())
+            .or_default()
+            .entry(status.to_string())
+            .or_insert(0) += 1;
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts
+            .values()
+            .flat_map(|counts| counts.values())
+            .sum()
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let bridged = self
+            .bridged
+            .iter()
+            .map(|(token, amount)| (checksum_address(token), amount.to_string().into()))
+            .collect::<serde_json::Map<_, _>>();
+        serde_json::json!({
+            "total": self.total(),
+            "counts": self.counts,
+            "bridged": bridged,
+            "eth_fees_wei": self.eth_fees.to_string(),
+            "ckb_fees_shannons": self.ckb_fees.to_string(),
+        })
+    }
+}
+
+/// The result of one step of a transfer in `resume-all`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StepResult {
+    pub transfer_id: String,
+    pub direction: String,
+    pub old_status: String,
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx_hash: Option<H256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl StepResult {
+    /// A step still pending or timed out is waiting for the next run, not failed
+    pub fn is_failed(&self) -> bool {
+        self.error
+            .as_ref()
+            .map(|err| exit_code::of_error(err) != exit_code::PENDING)
+            .unwrap_or(false)
+    }
+}
+
+/// The actual cost of a transfer, read from the ethereum receipts and the committed ckb
+/// transaction. Wei totals are strings since they may exceed u64.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TransferCost {
+    pub eth_txs: Vec<EthTxCost>,
+    /// Sum of the cost of `eth_txs` in wei
+    pub eth_total_wei: String,
+    /// Fee of the mint transaction in shannons
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "optional_u64_string"
+    )]
+    pub ckb_fee: Option<u64>,
+}
+
+/// An optional u64 written as a decimal string, numbers written before are still read
+mod optional_u64_string {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(value: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => serializer.collect_str(value),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<u64>, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Number(u64),
+            String(String),
+        }
+        Option::<Repr>::deserialize(deserializer)?
+            .map(|repr| match repr {
+                Repr::Number(value) => Ok(value),
+                Repr::String(value) => value.parse().map_err(serde::de::Error::custom),
+            })
+            .transpose()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EthTxCost {
+    pub tx_hash: H256,
+    pub gas_used: u64,
+    /// The effective gas price in wei
+    pub gas_price: u64,
+}
+
+impl TransferCost {
+    /// Record a mined ethereum transaction, a transaction recorded before is replaced (its
+    /// receipt is read again when a step is resumed)
+    pub fn add_eth_tx(&mut self, cost: EthTxCost) {
+        self.eth_txs.retain(|tx| tx.tx_hash != cost.tx_hash);
+        self.eth_txs.push(cost);
+        let total: u128 = self
+            .eth_txs
+            .iter()
+            .map(|tx| u128::from(tx.gas_used) * u128::from(tx.gas_price))
+            .sum();
+        self.eth_total_wei = total.to_string();
+    }
+}
+
+/// The recipient's balance of the token in the index before the mint transaction is sent and
+/// after it is committed, it must grow by the minted amount. Amounts are strings since they
+/// may exceed u64.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BalanceCheck {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+    /// Whether the minted token cell is live in the index after the commit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minted_cell_live: Option<bool>,
+}
+
+/// The ethereum block of the lock transaction the proof is built against
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockBlock {
+    pub number: u64,
+    pub hash: H256,
+}
+
+/// The ckb block of the burn transaction the proof is built against
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BurnBlock {
+    pub number: u64,
+    pub hash: H256,
+}
+
+impl BurnBlock {
+    /// The burn block is safe to prove once it has `confirmations` blocks on top of it
+    pub fn is_confirmed(&self, tip_number: u64, confirmations: u64) -> bool {
+        tip_number.saturating_sub(self.number) >= confirmations
+    }
+}
+
+/// The amount locked on one chain and the amount to be released on the other chain, they
+/// must be equal. Amounts are strings since they may exceed u64.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AmountCheck {
+    pub locked: String,
+    pub minted: String,
+}
+
+impl AmountCheck {
+    pub fn check(locked: u128, minted: u128) -> Result<AmountCheck, String> {
+        if locked != minted {
+            return Err(format!(
+                "Amount mismatch: locked {}, but going to mint {}, abort",
+                locked, minted
+            ));
+        }
+        Ok(AmountCheck {
+            locked: locked.to_string(),
+            minted: minted.to_string(),
+        })
+    }
+
+    /// The from-ckb side: the amount burned on ckb (in the erc20 decimals) and the amount
+    /// unlocked on ethereum
+    pub fn check_unlock(burned: u128, unlocked: u128) -> Result<AmountCheck, String> {
+        if burned != unlocked {
+            return Err(format!(
+                "Amount mismatch: burned {} on ckb, but {} unlocked on ethereum",
+                burned, unlocked
+            ));
+        }
+        Ok(AmountCheck {
+            locked: burned.to_string(),
+            minted: unlocked.to_string(),
+        })
+    }
+}
+
+impl ToCkbLog {
+    pub fn new(
+        id: String,
+        token: H160,
+        amount: u128,
+        recipient: Option<String>,
+        recipient_lock: Script,
+    ) -> ToCkbLog {
+        let now = now_secs();
+        ToCkbLog {
+            id,
+            status: ToCkbLogStatus::UnKnow,
+            token,
+            amount,
+            decimals: None,
+            recipient,
+            recipient_lock,
+            approve_tx: None,
+            lock_tx: None,
+            pending_eth_tx: None,
+            proof: None,
+            lock_block: None,
+            unsigned_mint_tx: None,
+            mint_tx: None,
+            mint_payer: None,
+            amount_check: None,
+            truncated_amount: None,
+            debug_proof: None,
+            cost: None,
+            balance_check: None,
+            warning: None,
+            deadline: None,
+            intent: None,
+            skipped_steps: Vec::new(),
+            failure: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn set_status(&mut self, status: ToCkbLogStatus) {
+        self.status = status;
+        self.updated_at = now_secs();
+    }
+
+    pub fn set_proof(&mut self, proof: Vec<u8>, block: LockBlock) {
+        self.proof = Some(JsonBytes::from_vec(proof));
+        self.lock_block = Some(block);
+    }
+
+    pub fn clear_proof(&mut self) {
+        self.proof = None;
+        self.lock_block = None;
+    }
+
+    /// Check the block of the stored proof is the stored `lock_block`, they are written
+    /// together so a mismatch means the log is corrupted or edited. Skipped for the logs
+    /// without `lock_block`.
+    pub fn check_lock_block(&self, number: u64, hash: &H256) -> Result<(), String> {
+        match self.lock_block.as_ref() {
+            Some(block) if block.number != number || &block.hash != hash => Err(format!(
+                "Transfer {}: the stored proof is of block {} ({:#x}) but the stored lock block is {} ({:#x}), the log is corrupted or edited, rebuild the proof with `rebuild-proof --transfer-id {}`",
+                self.id, number, hash, block.number, block.hash, self.id
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn history_entry(&self) -> HistoryEntry {
+        HistoryEntry {
+            transfer_id: self.id.clone(),
+            direction: DIRECTION_TO_CKB.to_string(),
+            status: format!("{:?}", self.status),
+            token: Some(self.token.clone()),
+            amount: Some(self.amount.to_string()),
+            amount_decimal: self.amount_decimal(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+
+    pub fn event(&self, tx_hash: Option<H256>) -> TransferEvent {
+        TransferEvent {
+            transfer_id: self.id.clone(),
+            timestamp: self.updated_at,
+            status: format!("{:?}", self.status),
+            tx_hash,
+            amount: Some(self.amount.to_string()),
+            amount_decimal: self.amount_decimal(),
+            amount_check: self.amount_check.clone(),
+            truncated_amount: self.truncated_amount.clone(),
+            mint_payer: self.mint_payer.clone(),
+            cost: self.cost.clone(),
+            balance_check: self.balance_check.clone(),
+            warning: self.warning.clone(),
+            failure: self.failure.clone(),
+            dry_run: None,
+            pending: None,
+            script_groups: None,
+            usd_estimate: None,
+        }
+    }
+
+    /// The amount with the token decimals, `None` if the decimals are not configured
+    pub fn amount_decimal(&self) -> Option<String> {
+        self.decimals
+            .map(|decimals| format_decimal(self.amount, decimals))
+    }
+
+    pub fn record_eth_cost(&mut self, cost: EthTxCost) {
+        self.cost
+            .get_or_insert_with(Default::default)
+            .add_eth_tx(cost);
+    }
+
+    pub fn record_ckb_fee(&mut self, fee: u64) {
+        self.cost.get_or_insert_with(Default::default).ckb_fee = Some(fee);
+    }
+
+    /// Fail the transfer if the deadline is exceeded, return whether it failed. The sent mint
+    /// transaction may still be committed, so a transfer in `Mint` is not failed.
+    pub fn check_deadline(&mut self, now: u64) -> bool {
+        let exceeded = self
+            .deadline
+            .map(|deadline| now > deadline)
+            .unwrap_or(false);
+        if !exceeded || self.status.is_terminal() || self.status == ToCkbLogStatus::Mint {
+            return false;
+        }
+        self.failure = Some(format!("deadline exceeded at status {:?}", self.status));
+        self.set_status(ToCkbLogStatus::Failed);
+        true
+    }
+}
+
+impl FromCkbLog {
+    pub fn new(id: String, token: Option<H160>) -> FromCkbLog {
+        let now = now_secs();
+        FromCkbLog {
+            id,
+            status: FromCkbLogStatus::UnKnow,
+            token,
+            amount: None,
+            recipient: None,
+            check_supply: false,
+            burn_tx: None,
+            burn_block: None,
+            proof: None,
+            unlock_tx: None,
+            amount_check: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn set_status(&mut self, status: FromCkbLogStatus) {
+        self.status = status;
+        self.updated_at = now_secs();
+    }
+
+    pub fn history_entry(&self) -> HistoryEntry {
+        HistoryEntry {
+            transfer_id: self.id.clone(),
+            direction: DIRECTION_FROM_CKB.to_string(),
+            status: format!("{:?}", self.status),
+            token: self.token.clone(),
+            amount: self.amount.map(|amount| amount.to_string()),
+            amount_decimal: None,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+
+    pub fn event(&self, tx_hash: Option<H256>) -> TransferEvent {
+        TransferEvent {
+            transfer_id: self.id.clone(),
+            timestamp: self.updated_at,
+            status: format!("{:?}", self.status),
+            tx_hash,
+            amount: self.amount.map(|amount| amount.to_string()),
+            amount_decimal: None,
+            amount_check: self.amount_check.clone(),
+            truncated_amount: None,
+            mint_payer: None,
+            cost: None,
+            balance_check: None,
+            warning: None,
+            failure: None,
+            dry_run: None,
+            pending: None,
+            script_groups: None,
+            usd_estimate: None,
+        }
+    }
+}
+
+pub const DIRECTION_TO_CKB: &str = "to_ckb";
+pub const DIRECTION_FROM_CKB: &str = "from_ckb";
+// The lock of the whole store is listed by `clean-locks` with this id, never a transfer id
+const STORE_LOCK_ID: &str = "store";
+
+/// Move the bridge state (the config and the transfer logs of any layout) found in
+/// `legacy_dir` into `data_dir`, return the moved paths. An entry already in `data_dir` is
+/// never overwritten.
+pub fn migrate_state(legacy_dir: &Path, data_dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let networks = [
+        NetworkType::Mainnet,
+        NetworkType::Testnet,
+        NetworkType::Staging,
+        NetworkType::Dev,
+    ];
+    let names = ["config.json", DIRECTION_TO_CKB, DIRECTION_FROM_CKB]
+        .iter()
+        .cloned()
+        .chain(networks.iter().map(|network| network.to_str()));
+    let mut moved = Vec::new();
+    for name in names {
+        let path = legacy_dir.join(name);
+        if !path.exists() {
+            continue;
+        }
+        let target = data_dir.join(name);
+        if target.exists() {
+            log::warn!(
+                "Skip migrating bridge state {:?}, {:?} already exists",
+                path,
+                target
+            );
+            continue;
+        }
+        fs::create_dir_all(data_dir).map_err(|err| err.to_string())?;
+        fs::rename(&path, &target)
+            .map_err(|err| format!("Move {:?} to {:?} error: {}", path, target, err))?;
+        moved.push(target);
+    }
+    Ok(moved)
+}
+
+/// Transfer logs are stored as `<bridge-dir>/<network>/<direction>/<id>.json`
+pub struct LogStore {
+    dir: PathBuf,
+}
+
+impl LogStore {
+    pub fn new(bridge_dir: &Path, network: NetworkType, direction: &str) -> LogStore {
+        LogStore {
+            dir: bridge_dir.join(network.to_str()).join(direction),
+        }
+    }
+
+    /// Move logs of the old flat layout (`<bridge-dir>/<direction>/<id>.json`) into this
+    /// store, return the number of migrated logs. Existing logs in this store are never
+    /// overwritten.
+    pub fn migrate_flat(&self, legacy_dir: &Path) -> Result<usize, String> {
+        if !legacy_dir.is_dir() {
+            return Ok(0);
+        }
+        fs::create_dir_all(&self.dir).map_err(|err| err.to_string())?;
+        let mut count = 0;
+        for entry in fs::read_dir(legacy_dir).map_err(|err| err.to_string())? {
+            let path = entry.map_err(|err| err.to_string())?.path();
+            let is_log = path.is_file()
+                && path.extension().map(|ext| ext == "json").unwrap_or(false)
+                && !path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .map(|name| name.starts_with('.'))
+                    .unwrap_or(true);
+            if !is_log {
+                continue;
+            }
+            let target = self.dir.join(path.file_name().expect("checked file name"));
+            if target.exists() {
+                log::warn!(
+                    "Skip migrating transfer log {:?}, {:?} already exists",
+                    path,
+                    target
+                );
+                continue;
+            }
+            fs::rename(&path, &target).map_err(|err| err.to_string())?;
+            count += 1;
+        }
+        // Remove the legacy directory if it is empty now
+        let _ = fs::remove_dir(legacy_dir);
+        Ok(count)
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    fn lock_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.lock", id))
+    }
+
+    /// Take the advisory lock of the transfer `id` (`<id>.lock`, holding the pid of this
+    /// process), fails if it is held by another process
+    pub fn lock(&self, id: &str) -> Result<TransferLock, String> {
+        self.take_lock(self.lock_path(id), &format!("Transfer {}", id))
+    }
+
+    /// Take the advisory lock of the whole store (`store.lock`), held while the logs are
+    /// searched for a duplicate and the new log is saved
+    pub fn lock_store(&self) -> Result<TransferLock, String> {
+        self.take_lock(self.lock_path(STORE_LOCK_ID), "The transfer store")
+    }
+
+    fn take_lock(&self, path: PathBuf, what: &str) -> Result<TransferLock, String> {
+        fs::create_dir_all(&self.dir).map_err(|err| err.to_string())?;
+        let mut file = match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                let holder = match read_json::<LockHolder>(&path) {
+                    Ok(holder) => format!("process {} since {}", holder.pid, holder.created_at),
+                    Err(_) => "an unknown process".to_string(),
+                };
+                return Err(format!(
+                    "{} is locked by {} ({:?}), remove the lock with `clean-locks` if that process is gone",
+                    what, holder, path
+                ));
+            }
+            Err(err) => return Err(format!("Create lock file {:?} error: {}", path, err)),
+        };
+        let lock = TransferLock { path };
+        let holder = LockHolder {
+            pid: std::process::id(),
+            created_at: now_secs(),
+        };
+        let content = serde_json::to_string(&holder).map_err(|err| err.to_string())?;
+        file.write_all(content.as_bytes())
+            .map_err(|err| format!("Write lock file {:?} error: {}", lock.path, err))?;
+        Ok(lock)
+    }
+
+    /// The transfer locks of this store and their holder (`None` if the lock file can not be
+    /// parsed), sorted by transfer id
+    pub fn list_locks(&self) -> Result<Vec<(String, Option<LockHolder>)>, String> {
+        if !self.dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut locks = Vec::new();
+        for entry in fs::read_dir(&self.dir).map_err(|err| err.to_string())? {
+            let path = entry.map_err(|err| err.to_string())?.path();
+            if path.extension().map(|ext| ext != "lock").unwrap_or(true) {
+                continue;
+            }
+            if let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) {
+                locks.push((id.to_string(), read_json(&path).ok()));
+            }
+        }
+        locks.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(locks)
+    }
+
+    /// Remove the lock of the transfer `id` whoever holds it
+    pub fn remove_lock(&self, id: &str) -> Result<(), String> {
+        let path = self.lock_path(id);
+        fs::remove_file(&path).map_err(|err| format!("Remove lock file {:?} error: {}", path, err))
+    }
+
+    /// Ids of all the logs in this store, sorted
+    pub fn list_ids(&self) -> Result<Vec<String>, String> {
+        if !self.dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut ids = Vec::new();
+        for entry in fs::read_dir(&self.dir).map_err(|err| err.to_string())? {
+            let path = entry.map_err(|err| err.to_string())?.path();
+            if path.extension().map(|ext| ext != "json").unwrap_or(true) {
+                continue;
+            }
+            if let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) {
+                if !id.starts_with('.') {
+                    ids.push(id.to_string());
+                }
+            }
+        }
+        ids.sort();
+        Ok(ids)
+    }
+
+    /// Ids of all the logs with the last modified time, only the file metadata is read
+    pub fn list_modified(&self) -> Result<Vec<(String, SystemTime)>, String> {
+        self.list_ids()?
+            .into_iter()
+            .map(|id| {
+                fs::metadata(self.path(&id))
+                    .and_then(|metadata| metadata.modified())
+                    .map(|modified| (id, modified))
+                    .map_err(|err| err.to_string())
+            })
+            .collect()
+    }
+
+    pub fn load<T: DeserializeOwned>(&self, id: &str) -> Result<T, String> {
+        let path = self.path(id);
+        if !path.exists() {
+            return Err(format!("Transfer log not found: {}", id));
+        }
+        read_json(&path)
+    }
+
+    pub fn save<T: Serialize>(&self, id: &str, log: &T) -> Result<(), String> {
+        fs::create_dir_all(&self.dir).map_err(|err| err.to_string())?;
+        let content = serde_json::to_string_pretty(log).map_err(|err| err.to_string())?;
+        // Write to a temp file then rename, so a crash never leaves a broken log
+        let tmp_path = self.dir.join(format!(".{}.json.tmp", id));
+        fs::write(&tmp_path, content).map_err(|err| err.to_string())?;
+        fs::rename(&tmp_path, self.path(id)).map_err(|err| err.to_string())
+    }
+}
+
+/// The process holding a transfer lock, the content of the lock file
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LockHolder {
+    pub pid: u32,
+    pub created_at: u64,
+}
+
+impl LockHolder {
+    /// Whether the holder process is still running, `None` if it can not be told on this
+    /// platform
+    pub fn is_alive(&self) -> Option<bool> {
+        process_alive(self.pid)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn process_alive(pid: u32) -> Option<bool> {
+    Some(Path::new("/proc").join(pid.to_string()).exists())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_alive(_pid: u32) -> Option<bool> {
+    None
+}
+
+/// The advisory lock of a transfer, so that two processes never step the same transfer. It
+/// is released when dropped, a lock left by a killed process is removed by `clean-locks`.
+pub struct TransferLock {
+    path: PathBuf,
+}
+
+impl Drop for TransferLock {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_file(&self.path) {
+            log::warn!("Remove lock file {:?} error: {}", self.path, err);
+        }
+    }
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    serde_json::from_str(&content)
+        .map_err(|err| format!("Parse transfer log {:?} error: {}", path, err))
+}
+
+pub fn new_transfer_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time before unix epoch")
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_jsonrpc_types::ScriptHashType;
+
+    // The on-disk layout of the transfer logs, in-progress transfers are resumed from these
+    // files across versions, so any change here must stay readable by the old layout
+    const TO_CKB_GOLDEN: &str = include_str!("test-data/to_ckb_log.json");
+    const FROM_CKB_GOLDEN: &str = include_str!("test-data/from_ckb_log.json");
+
+    fn golden_to_ckb_log() -> ToCkbLog {
+        let proof = JsonBytes::from_vec(vec![1, 2]);
+        ToCkbLog {
+            id: "golden".to_string(),
+            status: ToCkbLogStatus::Mint,
+            token: H160([0x11; 20]),
+            amount: 1_000_000,
+            decimals: None,
+            recipient: Some("ckt1qyqxgp7za7dajm5wzjkye52asc8fxvvqy9eqlhp82g".to_string()),
+            recipient_lock: Script {
+                code_hash: H256([
+                    0x9b, 0xd7, 0xe0, 0x6f, 0x3e, 0xcf, 0x4b, 0xe0, 0xf2, 0xfc, 0xd2, 0x18, 0x8b,
+                    0x23, 0xf1, 0xb9, 0xfc, 0xc8, 0x8e, 0x5d, 0x4b, 0x65, 0xa8, 0x63, 0x7b, 0x17,
+                    0x72, 0x3b, 0xbd, 0xa3, 0xcc, 0xe8,
+                ]),
+                hash_type: ScriptHashType::Type,
+                args: JsonBytes::from_vec(vec![
+                    0x64, 0x07, 0xc2, 0xef, 0xb7, 0xd9, 0x6e, 0x8e, 0x14, 0xac, 0x4c, 0xd1, 0x5d,
+                    0x86, 0x0e, 0x93, 0x31, 0x80, 0x21, 0x72,
+                ]),
+            },
+            approve_tx: Some(H256([0x22; 32])),
+            lock_tx: Some(H256([0x33; 32])),
+            pending_eth_tx: Some(PendingEthTx {
+                step: "lock".to_string(),
+                nonce: 7,
+            }),
+            proof: Some(proof.clone()),
+            lock_block: None,
+            unsigned_mint_tx: Some(H256([0x44; 32])),
+            mint_tx: Some(H256([0x55; 32])),
+            mint_payer: None,
+            amount_check: Some(AmountCheck::check(1_000_000, 1_000_000).unwrap()),
+            truncated_amount: Some("0".to_string()),
+            debug_proof: Some(DebugProof {
+                error: "mint failed".to_string(),
+                timestamp: 1_600_000_050,
+                proof,
+                block_header: None,
+                ckb_tx: None,
+            }),
+            cost: None,
+            balance_check: None,
+            warning: None,
+            deadline: None,
+            intent: None,
+            skipped_steps: Vec::new(),
+            failure: None,
+            created_at: 1_600_000_000,
+            updated_at: 1_600_000_100,
+        }
+    }
+
+    fn golden_from_ckb_log() -> FromCkbLog {
+        FromCkbLog {
+            id: "golden".to_string(),
+            status: FromCkbLogStatus::WaitBlockSafe,
+            token: Some(H160([0x11; 20])),
+            amount: None,
+            recipient: None,
+            check_supply: false,
+            burn_tx: None,
+            burn_block: None,
+            proof: None,
+            unlock_tx: None,
+            amount_check: None,
+            created_at: 1_600_000_000,
+            updated_at: 1_600_000_100,
+        }
+    }
+
+    // Serialize, parse and serialize again, the two serializations must be identical
+    fn round_trip<T: Serialize + DeserializeOwned>(value: &T) -> (T, String) {
+        let json = serde_json::to_string_pretty(value).unwrap();
+        let parsed: T = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string_pretty(&parsed).unwrap(), json);
+        (parsed, json)
+    }
+
+    #[test]
+    fn test_to_ckb_log_round_trip() {
+        let empty = ToCkbLog::new(
+            new_transfer_id(),
+            H160::default(),
+            u128::max_value(),
+            None,
+            Script::default(),
+        );
+        for log in &[golden_to_ckb_log(), empty] {
+            for status in ToCkbLogStatus::ALL.iter() {
+                let mut log = log.clone();
+                log.status = *status;
+                let (parsed, _) = round_trip(&log);
+                assert_eq!(parsed.status, *status);
+                assert_eq!(parsed.amount, log.amount);
+            }
+        }
+    }
+
+    #[test]
+    fn test_burn_block_confirmed() {
+        let block = BurnBlock {
+            number: 100,
+            hash: H256::default(),
+        };
+        assert!(block.is_confirmed(100, 0));
+        assert!(!block.is_confirmed(123, 24));
+        assert!(block.is_confirmed(124, 24));
+        // The tip of a lagging node may be behind the burn block
+        assert!(!block.is_confirmed(90, 24));
+    }
+
+    #[test]
+    fn test_from_ckb_log_round_trip() {
+        let mut burning = golden_from_ckb_log();
+        burning.amount = Some(u128::max_value());
+        burning.recipient = Some(H160([0x22; 20]));
+        burning.check_supply = true;
+        for log in &[
+            golden_from_ckb_log(),
+            FromCkbLog::new(new_transfer_id(), None),
+            burning,
+        ] {
+            for status in FromCkbLogStatus::ALL.iter() {
+                let mut log = log.clone();
+                log.status = *status;
+                let (parsed, _) = round_trip(&log);
+                assert_eq!(parsed.status, *status);
+                assert_eq!(parsed.token, log.token);
+                assert_eq!(parsed.amount, log.amount);
+                assert_eq!(parsed.recipient, log.recipient);
+                assert_eq!(parsed.check_supply, log.check_supply);
+            }
+        }
+    }
+
+    #[test]
+    fn test_status_serialized_by_name() {
+        for status in ToCkbLogStatus::ALL.iter() {
+            let json = serde_json::to_string(status).unwrap();
+            assert_eq!(json, format!("\"{:?}\"", status));
+            assert_eq!(
+                ToCkbLogStatus::from_name(&format!("{:?}", status)),
+                Ok(*status)
+            );
+        }
+        for status in FromCkbLogStatus::ALL.iter() {
+            let json = serde_json::to_string(status).unwrap();
+            assert_eq!(json, format!("\"{:?}\"", status));
+            assert_eq!(
+                FromCkbLogStatus::from_name(&format!("{:?}", status)),
+                Ok(*status)
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_deadline() {
+        let mut log = ToCkbLog::new(
+            new_transfer_id(),
+            H160::default(),
+            1,
+            None,
+            Script::default(),
+        );
+        assert!(!log.check_deadline(u64::max_value()));
+        log.deadline = Some(1_600_000_000);
+        assert!(!log.check_deadline(1_600_000_000));
+        log.status = ToCkbLogStatus::Mint;
+        assert!(!log.check_deadline(1_600_000_001));
+        log.status = ToCkbLogStatus::Locked;
+        assert!(log.check_deadline(1_600_000_001));
+        assert_eq!(log.status, ToCkbLogStatus::Failed);
+        assert_eq!(
+            log.failure.as_deref(),
+            Some("deadline exceeded at status Locked")
+        );
+        assert_eq!(
+            log.event(None).failure.as_deref(),
+            Some("deadline exceeded at status Locked")
+        );
+        // Failed only once
+        assert!(!log.check_deadline(1_600_000_002));
+    }
+
+    #[test]
+    fn test_check_lock_block() {
+        let mut log = golden_to_ckb_log();
+        let hash = H256([0x66; 32]);
+        // Logs written before the lock block was recorded are not checked
+        assert!(log.check_lock_block(100, &hash).is_ok());
+
+        log.set_proof(
+            vec![1, 2],
+            LockBlock {
+                number: 100,
+                hash: hash.clone(),
+            },
+        );
+        assert!(log.check_lock_block(100, &hash).is_ok());
+        let err = log.check_lock_block(101, &hash).unwrap_err();
+        assert!(err.contains("rebuild-proof --transfer-id golden"));
+        assert!(log.check_lock_block(100, &H256([0x77; 32])).is_err());
+
+        let (parsed, _) = round_trip(&log);
+        assert_eq!(parsed.lock_block, log.lock_block);
+        log.clear_proof();
+        assert_eq!(log.lock_block, None);
+        assert!(log.check_lock_block(101, &hash).is_ok());
+    }
+
+    #[test]
+    fn test_transfer_lock() {
+        let dir = std::env::temp_dir().join(format!("ckb-bridge-lock-{}", std::process::id()));
+        let store = LogStore::new(&dir, NetworkType::Dev, DIRECTION_TO_CKB);
+        let lock = store.lock("a").unwrap();
+        let err = store.lock("a").err().unwrap();
+        assert!(err.contains("clean-locks"));
+        assert!(err.contains(&std::process::id().to_string()));
+        // Lock files are not transfer logs
+        assert!(store.list_ids().unwrap().is_empty());
+
+        let locks = store.list_locks().unwrap();
+        assert_eq!(locks.len(), 1);
+        let holder = locks[0].1.clone().unwrap();
+        assert_eq!(holder.pid, std::process::id());
+        if cfg!(target_os = "linux") {
+            assert_eq!(holder.is_alive(), Some(true));
+            // Above the max pid of linux
+            let gone = LockHolder {
+                pid: u32::max_value(),
+                created_at: 0,
+            };
+            assert_eq!(gone.is_alive(), Some(false));
+        }
+        drop(lock);
+        assert!(store.list_locks().unwrap().is_empty());
+
+        // A lock left by a killed process is only removed explicitly
+        std::mem::forget(store.lock("b").unwrap());
+        assert!(store.lock("b").is_err());
+        store.remove_lock("b").unwrap();
+        assert!(store.lock("b").is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_golden_layout() {
+        let json = serde_json::to_string_pretty(&golden_to_ckb_log()).unwrap();
+        assert_eq!(format!("{}\n", json), TO_CKB_GOLDEN);
+        let parsed: ToCkbLog = serde_json::from_str(TO_CKB_GOLDEN).unwrap();
+        assert_eq!(round_trip(&parsed).1, json);
+
+        let json = serde_json::to_string_pretty(&golden_from_ckb_log()).unwrap();
+        assert_eq!(format!("{}\n", json), FROM_CKB_GOLDEN);
+        let parsed: FromCkbLog = serde_json::from_str(FROM_CKB_GOLDEN).unwrap();
+        assert_eq!(round_trip(&parsed).1, json);
+    }
+
+    #[test]
+    fn test_load_log_without_optional_fields() {
+        // Logs written before the optional fields were added
+        let json = r#"{
+            "id": "legacy",
+            "status": "Locked",
+            "token": "0x1111111111111111111111111111111111111111",
+            "amount": 1000,
+            "recipient": null,
+            "recipient_lock": {
+                "code_hash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                "hash_type": "data",
+                "args": "0x"
+            },
+            "approve_tx": null,
+            "lock_tx": null,
+            "created_at": 1600000000,
+            "updated_at": 1600000000
+        }"#;
+        let log: ToCkbLog = serde_json::from_str(json).unwrap();
+        assert_eq!(log.status, ToCkbLogStatus::Locked);
+        assert!(log.proof.is_none() && log.mint_tx.is_none() && log.debug_proof.is_none());
+        let (_, json) = round_trip(&log);
+        assert!(!json.contains("unsigned_mint_tx") && !json.contains("truncated_amount"));
+        assert!(!json.contains("pending_eth_tx") && !json.contains("cost"));
+
+        let json = r#"{"id": "legacy", "status": "Burned", "created_at": 0, "updated_at": 0}"#;
+        let log: FromCkbLog = serde_json::from_str(json).unwrap();
+        assert_eq!(log.status, FromCkbLogStatus::Burned);
+        assert!(log.token.is_none());
+    }
+
+    #[test]
+    fn test_transfer_cost() {
+        let mut log = golden_to_ckb_log();
+        let approve = EthTxCost {
+            tx_hash: H256([0x22; 32]),
+            gas_used: 46_000,
+            gas_price: 20_000_000_000,
+        };
+        log.record_eth_cost(approve.clone());
+        // Recorded again when the step is resumed
+        log.record_eth_cost(approve);
+        log.record_eth_cost(EthTxCost {
+            tx_hash: H256([0x33; 32]),
+            gas_used: 120_000,
+            gas_price: u64::max_value(),
+        });
+        log.record_ckb_fee(1_000);
+        let cost = log.event(None).cost.unwrap();
+        assert_eq!(cost.eth_txs.len(), 2);
+        let total = 46_000u128 * 20_000_000_000 + 120_000u128 * u128::from(u64::max_value());
+        assert_eq!(cost.eth_total_wei, total.to_string());
+        assert_eq!(cost.ckb_fee, Some(1_000));
+        assert_eq!(round_trip(&log).0.cost, log.cost);
+        let json = serde_json::to_value(log.event(None)).unwrap();
+        assert_eq!(json["cost"]["ckb_fee"], serde_json::json!("1000"));
+        // Written as a number before
+        let old: TransferCost =
+            serde_json::from_str(r#"{"eth_txs": [], "eth_total_wei": "0", "ckb_fee": 1000}"#)
+                .unwrap();
+        assert_eq!(old.ckb_fee, Some(1_000));
+    }
+
+    #[test]
+    fn test_transfer_summary() {
+        let mut summary = TransferSummary::default();
+        let mut finished = golden_to_ckb_log();
+        finished.status = ToCkbLogStatus::Finished;
+        finished.record_ckb_fee(1_000);
+        summary.add_to_ckb(&finished);
+        summary.add_to_ckb(&finished);
+        // Not bridged yet, its fees are paid anyway
+        let mut pending = golden_to_ckb_log();
+        pending.record_eth_cost(EthTxCost {
+            tx_hash: H256([0x22; 32]),
+            gas_used: 46_000,
+            gas_price: 20_000_000_000,
+        });
+        summary.add_to_ckb(&pending);
+        summary.add_from_ckb(&golden_from_ckb_log());
+        summary.add_unreadable(DIRECTION_FROM_CKB);
+
+        assert_eq!(summary.total(), 5);
+        assert_eq!(summary.counts[DIRECTION_TO_CKB]["Finished"], 2);
+        assert_eq!(summary.counts[DIRECTION_TO_CKB]["Mint"], 1);
+        assert_eq!(summary.counts[DIRECTION_FROM_CKB]["WaitBlockSafe"], 1);
+        assert_eq!(summary.counts[DIRECTION_FROM_CKB]["Unreadable"], 1);
+        assert_eq!(summary.bridged[&H160([0x11; 20])], 2_000_000);
+        assert_eq!(summary.eth_fees, 46_000 * 20_000_000_000);
+        assert_eq!(summary.ckb_fees, 2_000);
+        let json = summary.to_json();
+        assert_eq!(
+            json["bridged"][checksum_address(&H160([0x11; 20]))],
+            serde_json::json!("2000000")
+        );
+        assert_eq!(json["ckb_fees_shannons"], serde_json::json!("2000"));
+    }
+}
@@ -0,0 +1,841 @@
+use std::time::Duration;
+
+use ckb_index::LiveCellInfo;
+use ckb_jsonrpc_types::OutPoint;
+use ckb_sdk::check_lock_script;
+use ckb_types::{
+    bytes::Bytes,
+    core::{Capacity, ScriptHashType, TransactionBuilder, TransactionView},
+    packed::{CellDep, CellOutput, Script, WitnessArgs},
+    prelude::*,
+    H160, H256,
+};
+use serde_derive::{Deserialize, Serialize};
+
+use super::config::ScriptConfig;
+use super::proof::ReceiptProof;
+
+/// The type script of the bridged token cells, args is the erc20 token contract address
+pub fn token_type_script(bridge_script: &ScriptConfig, token: &H160) -> Script {
+    let hash_type: ScriptHashType = bridge_script.hash_type.clone().into();
+    Script::new_builder()
+        .code_hash(bridge_script.code_hash.pack())
+        .hash_type(hash_type.into())
+        .args(Bytes::from(token.as_bytes().to_vec()).pack())
+        .build()
+}
+
+/// The minted token cell: the amount (u128, little endian) is stored in cell data, the
+/// capacity is the minimal occupied capacity.
+pub fn token_cell(
+    bridge_script: &ScriptConfig,
+    token: &H160,
+    amount: u128,
+    recipient_lock: Script,
+) -> Result<(CellOutput, Bytes), String> {
+    let data = Bytes::from(amount.to_le_bytes().to_vec());
+    let output = CellOutput::new_builder()
+        .lock(recipient_lock)
+        .type_(Some(token_type_script(bridge_script, token)).pack())
+        .build();
+    let capacity = output
+        .occupied_capacity(Capacity::bytes(data.len()).map_err(|err| err.to_string())?)
+        .map_err(|err| err.to_string())?;
+    Ok((output.as_builder().capacity(capacity.pack()).build(), data))
+}
+
+/// Parse the token amount from the token cell data
+pub fn token_amount(data: &[u8]) -> Result<u128, String> {
+    if data.len() != 16 {
+        return Err(format!("Invalid token cell data length: {}", data.len()));
+    }
+    let mut amount = [0u8; 16];
+    amount.copy_from_slice(data);
+    Ok(u128::from_le_bytes(amount))
+}
+
+/// The max age of the proof block when the mint transaction is built (`--max-proof-age`), an
+/// old proof may reference a block pruned by the light client or changed by a deep reorg
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MaxProofAge {
+    Blocks(u64),
+    Duration(Duration),
+}
+
+impl MaxProofAge {
+    /// Check the age of the proof block against the ethereum tip and the current time (unix
+    /// seconds)
+    pub fn check(
+        &self,
+        block_number: u64,
+        block_timestamp: u64,
+        tip_number: u64,
+        now: u64,
+    ) -> Result<(), String> {
+        let blocks = tip_number.saturating_sub(block_number);
+        let secs = now.saturating_sub(block_timestamp);
+        let (too_old, limit) = match self {
+            MaxProofAge::Blocks(max) => (blocks > *max, format!("{} blocks", max)),
+            MaxProofAge::Duration(max) => (secs > max.as_secs(), format!("{}s", max.as_secs())),
+        };
+        if too_old {
+            return Err(format!(
+                "The proof block {} is {} blocks ({}s) old, more than --max-proof-age {}",
+                block_number, blocks, secs, limit
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The recipient lock script in the lock event must be the recipient of the transfer, the
+/// lock event is what the bridge script checks the minted cell against
+pub fn check_recipient(event_recipient: &[u8], expected: &Script) -> Result<(), String> {
+    if event_recipient == expected.as_slice() {
+        return Ok(());
+    }
+    let event_lock_hash = Script::from_slice(event_recipient)
+        .map(|script| script.calc_script_hash().to_string())
+        .unwrap_or_else(|_| "invalid script".to_string());
+    Err(format!(
+        "Recipient mismatch: lock hash {} in the lock event, {} in the transfer, abort",
+        event_lock_hash,
+        expected.calc_script_hash()
+    ))
+}
+
+/// The sighash and multisig recipient locks must be well-formed (a multisig args is the hash of
+/// the multisig config with an optional since), the cell would be unspendable otherwise. Other
+/// locks are not checked.
+pub fn check_recipient_lock(lock: &Script) -> Result<(), String> {
+    check_lock_script(lock, true).map_err(|err| format!("Invalid recipient: {}", err))
+}
+
+/// The recipient's token balance must grow by exactly the minted amount once the mint is
+/// committed. Other transactions of the recipient committed in between change it too.
+pub fn check_balance_increase(before: u128, after: u128, minted: u128) -> Result<(), String> {
+    if after.checked_sub(before) == Some(minted) {
+        return Ok(());
+    }
+    Err(format!(
+        "The recipient's token balance changed from {} to {}, expected an increase of {}",
+        before, after, minted
+    ))
+}
+
+/// The amount to mint: the requested amount, or the amount in the lock event (the amount
+/// measured as received by the bridge contract) for fee-on-transfer tokens.
+pub fn mint_amount(requested: u128, locked: u128, fee_on_transfer: bool) -> Result<u128, String> {
+    if !fee_on_transfer {
+        return Ok(requested);
+    }
+    if locked > requested {
+        return Err(format!(
+            "Locked amount {} is more than the requested amount {} of a fee-on-transfer token, abort",
+            locked, requested
+        ));
+    }
+    Ok(locked)
+}
+
+/// Scale `amount` from `decimals` to `ckb_decimals`, return the converted amount and the
+/// truncated amount (in the original unit, nonzero only if `ckb_decimals` is lower).
+pub fn convert_decimals(
+    amount: u128,
+    decimals: u8,
+    ckb_decimals: u8,
+) -> Result<(u128, u128), String> {
+    let overflow = || {
+        format!(
+            "Amount {} overflows when converted from {} to {} decimals",
+            amount, decimals, ckb_decimals
+        )
+    };
+    if ckb_decimals >= decimals {
+        let factor = 10u128
+            .checked_pow(u32::from(ckb_decimals - decimals))
+            .ok_or_else(overflow)?;
+        let converted = amount.checked_mul(factor).ok_or_else(overflow)?;
+        Ok((converted, 0))
+    } else {
+        match 10u128.checked_pow(u32::from(decimals - ckb_decimals)) {
+            Some(factor) => Ok((amount / factor, amount % factor)),
+            // All digits are truncated
+            None => Ok((0, amount)),
+        }
+    }
+}
+
+/// The raw amount of a token with `decimals` as a decimal number, e.g. "1.5" for
+/// 1500000000000000000 with 18 decimals. Trailing zeros of the fraction are dropped, so the
+/// same amount is always printed the same.
+pub fn format_decimal(amount: u128, decimals: u8) -> String {
+    decimal_string(amount, decimals, true)
+}
+
+/// An amount shown to the user: with the decimals and the symbol of the token if known, e.g.
+/// "1.500000 USDC" (or "1.5 USDC" with `trim_zeros`), the raw integer without decimals
+pub fn format_token_amount(
+    amount: u128,
+    decimals: Option<u8>,
+    symbol: Option<&str>,
+    trim_zeros: bool,
+) -> String {
+    let number = match decimals {
+        Some(decimals) => decimal_string(amount, decimals, trim_zeros),
+        None => amount.to_string(),
+    };
+    match symbol {
+        Some(symbol) => format!("{} {}", number, symbol),
+        None => number,
+    }
+}
+
+fn decimal_string(amount: u128, decimals: u8, trim_zeros: bool) -> String {
+    let decimals = usize::from(decimals);
+    let digits = format!("{:0>width$}", amount, width = decimals + 1);
+    let (integer, fraction) = digits.split_at(digits.len() - decimals);
+    let fraction = if trim_zeros {
+        fraction.trim_end_matches('0')
+    } else {
+        fraction
+    };
+    if fraction.is_empty() {
+        integer.to_string()
+    } else {
+        format!("{}.{}", integer, fraction)
+    }
+}
+
+/// How the amounts of a token are shown, see `format_token_amount`. The default shows the raw
+/// integer.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TokenUnit {
+    pub decimals: Option<u8>,
+    pub symbol: Option<String>,
+    pub trim_zeros: bool,
+}
+
+impl TokenUnit {
+    pub fn format(&self, amount: u128) -> String {
+        format_token_amount(
+            amount,
+            self.decimals,
+            self.symbol.as_deref(),
+            self.trim_zeros,
+        )
+    }
+}
+
+/// Check the precision of the amount scaled to the ckb decimals, return the truncated amount
+/// (`None` if nothing is truncated). Losing precision is an error unless allowed.
+pub fn check_precision(
+    amount: u128,
+    conversion: Option<(u8, u8)>,
+    allow_precision_loss: bool,
+) -> Result<Option<u128>, String> {
+    let (decimals, ckb_decimals) = match conversion {
+        Some(conversion) => conversion,
+        None => return Ok(None),
+    };
+    let (converted, truncated) = convert_decimals(amount, decimals, ckb_decimals)?;
+    if truncated == 0 {
+        return Ok(None);
+    }
+    if !allow_precision_loss {
+        return Err(format!(
+            "Amount {} ({} decimals) loses precision on ckb ({} decimals): {} would be minted and {} truncated, use an amount that is a multiple of 10^{} or pass --allow-precision-loss",
+            amount,
+            decimals,
+            ckb_decimals,
+            converted,
+            truncated,
+            decimals - ckb_decimals
+        ));
+    }
+    Ok(Some(truncated))
+}
+
+/// Reject a zero amount, or a dust amount converted to zero in the ckb decimals, nothing would
+/// be minted but the fees are paid
+pub fn check_dust(
+    amount: u128,
+    conversion: Option<(u8, u8)>,
+    unit: &TokenUnit,
+) -> Result<(), String> {
+    if amount == 0 {
+        return Err("Amount must be greater than 0".to_string());
+    }
+    if let Some((decimals, ckb_decimals)) = conversion {
+        if convert_decimals(amount, decimals, ckb_decimals)?.0 == 0 {
+            return Err(format!(
+                "Amount {} ({} decimals) is dust on ckb ({} decimals), the minimum is 10^{}",
+                unit.format(amount),
+                decimals,
+                ckb_decimals,
+                decimals - ckb_decimals
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Per transfer amount limits of the bridge, a lock out of them is reverted by the contract
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AmountLimits {
+    pub min: Option<u128>,
+    pub max: Option<u128>,
+}
+
+impl AmountLimits {
+    /// The stricter limits of both
+    pub fn merge(self, other: AmountLimits) -> AmountLimits {
+        fn stricter(
+            a: Option<u128>,
+            b: Option<u128>,
+            pick: fn(u128, u128) -> u128,
+        ) -> Option<u128> {
+            match (a, b) {
+                (Some(a), Some(b)) => Some(pick(a, b)),
+                (a, b) => a.or(b),
+            }
+        }
+        AmountLimits {
+            min: stricter(self.min, other.min, std::cmp::max),
+            max: stricter(self.max, other.max, std::cmp::min),
+        }
+    }
+
+    pub fn check(&self, amount: u128, unit: &TokenUnit) -> Result<(), String> {
+        if let Some(min) = self.min.filter(|min| amount < *min) {
+            return Err(format!(
+                "Amount {} is below the minimum {} of the bridge",
+                unit.format(amount),
+                unit.format(min)
+            ));
+        }
+        if let Some(max) = self.max.filter(|max| amount > *max) {
+            return Err(format!(
+                "Amount {} is above the maximum {} of the bridge",
+                unit.format(amount),
+                unit.format(max)
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The bridged supply of a token in the ckb decimals: minted on ckb (the live token cells) and
+/// releasable on ethereum (the token balance of the bridge contract)
+#[derive(Clone, Debug, PartialEq)]
+pub struct BridgedSupply {
+    pub minted: u128,
+    pub releasable: u128,
+}
+
+impl BridgedSupply {
+    /// Every minted token must be backed by a locked one
+    pub fn check_backing(&self) -> Result<(), String> {
+        if self.minted > self.releasable {
+            return Err(format!(
+                "The minted supply {} on ckb exceeds the releasable {} on ethereum, the bridge is under-backed",
+                self.minted, self.releasable
+            ));
+        }
+        Ok(())
+    }
+
+    /// A burn on ckb can't exceed the minted supply nor what can be released on ethereum
+    pub fn check_burn(&self, amount: u128) -> Result<(), String> {
+        let available = std::cmp::min(self.minted, self.releasable);
+        if amount > available {
+            return Err(format!(
+                "Burn amount {} exceeds the available bridged supply {} (minted on ckb: {}, releasable on ethereum: {})",
+                amount, available, self.minted, self.releasable
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Whether a send transaction error is the toCKB type script rejecting an already used proof
+pub fn is_proof_used_error(err: &str, proof_used_error: Option<i8>) -> bool {
+    proof_used_error.is_some() && script_error_code(err) == proof_used_error
+}
+
+/// The error code returned by the failed script, from a send or verify transaction error
+pub fn script_error_code(err: &str) -> Option<i8> {
+    const PREFIX: &str = "ValidationFailure(";
+    let start = err.find(PREFIX)? + PREFIX.len();
+    let end = start + err[start..].find(')')?;
+    err[start..end].trim().parse().ok()
+}
+
+/// Whether a send or verify transaction error is a script running out of the cycle limit
+pub fn is_cycles_exceeded_error(err: &str) -> bool {
+    err.contains("ExceededMaximumCycles")
+}
+
+/// A warning if the verification cycles are close to (over 80% of) the limit
+pub fn cycles_warning(cycles: u64, max_cycles: u64) -> Option<String> {
+    if u128::from(cycles) * 100 > u128::from(max_cycles) * 80 {
+        Some(format!(
+            "The mint transaction consumes {} cycles, {:.1}% of the limit {}, a larger proof may exceed it",
+            cycles,
+            cycles as f64 * 100.0 / max_cycles as f64,
+            max_cycles
+        ))
+    } else {
+        None
+    }
+}
+
+/// The proof in the witness of a mint transaction (`output_type` of witness 0)
+pub fn witness_proof(witness: &[u8]) -> Option<ReceiptProof> {
+    let witness_args = WitnessArgs::from_slice(witness).ok()?;
+    let proof = witness_args.output_type().to_opt()?;
+    ReceiptProof::from_bytes(&proof.raw_data()).ok()
+}
+
+/// Two proofs are the same if they prove the same receipt, the proof nodes may differ
+pub fn same_proof(a: &ReceiptProof, b: &ReceiptProof) -> bool {
+    a.block_hash == b.block_hash && a.receipt_index == b.receipt_index
+}
+
+/// Build the (unsigned) mint transaction:
+///   * inputs: sighash cells to pay the token cell capacity and the transaction fee
+///   * output 0: the token cell, the proof is put in witness 0 (output_type)
+///   * output 1 (optional): change cell
+pub fn build_mint_tx(
+    proof: &ReceiptProof,
+    token_cell: (CellOutput, Bytes),
+    cell_deps: Vec<CellDep>,
+    fee_cells: &[LiveCellInfo],
+    change_output: Option<CellOutput>,
+) -> TransactionView {
+    let witness = WitnessArgs::new_builder()
+        .output_type(Some(Bytes::from(proof.to_bytes())).pack())
+        .build();
+    let (output, data) = token_cell;
+    let mut builder = TransactionBuilder::default()
+        .cell_deps(cell_deps)
+        .inputs(fee_cells.iter().map(LiveCellInfo::input))
+        .output(output)
+        .output_data(data.pack())
+        .witness(witness.as_bytes().pack());
+    if let Some(change_output) = change_output {
+        builder = builder
+            .output(change_output)
+            .output_data(Bytes::new().pack());
+    }
+    builder.build()
+}
+
+/// The script groups of a built mint transaction and its cell deps (`--show-script-groups`)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScriptGroups {
+    pub groups: Vec<ScriptGroupInfo>,
+    pub cell_deps: Vec<CellDepInfo>,
+}
+
+/// A script with the cells it runs for, a lock group covers inputs only
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScriptGroupInfo {
+    /// "lock" or "type"
+    pub group_type: String,
+    pub script: ckb_jsonrpc_types::Script,
+    pub input_indices: Vec<usize>,
+    pub output_indices: Vec<usize>,
+    /// The cell dep whose data hash (hash type data) or type hash (hash type type) is the code
+    /// hash, `None` if not a direct cell dep (e.g. in a dep group)
+    pub cell_dep: Option<usize>,
+}
+
+/// A cell dep resolved from the ckb node, with the hashes a script references its code by
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CellDepInfo {
+    pub out_point: OutPoint,
+    pub dep_type: String,
+    pub data_hash: H256,
+    pub type_hash: Option<H256>,
+}
+
+/// Group the scripts of a transaction as the ckb script verifier does, in the order they first
+/// appear (locks of the inputs, then types of the inputs and the outputs)
+pub fn script_groups(
+    inputs: &[CellOutput],
+    outputs: &[CellOutput],
+    cell_deps: &[CellDepInfo],
+) -> Vec<ScriptGroupInfo> {
+    let mut groups: Vec<(Script, ScriptGroupInfo)> = Vec::new();
+    let mut add =
+        |group_type: &str, script: Script, input: Option<usize>, output: Option<usize>| {
+            let index = match groups.iter().position(|(existing, group)| {
+                group.group_type == group_type && existing.as_slice() == script.as_slice()
+            }) {
+                Some(index) => index,
+                None => {
+                    let cell_dep = script_cell_dep(&script, cell_deps);
+                    groups.push((
+                        script.clone(),
+                        ScriptGroupInfo {
+                            group_type: group_type.to_string(),
+                            script: script.into(),
+                            input_indices: Vec::new(),
+                            output_indices: Vec::new(),
+                            cell_dep,
+                        },
+                    ));
+                    groups.len() - 1
+                }
+            };
+            groups[index].1.input_indices.extend(input);
+            groups[index].1.output_indices.extend(output);
+        };
+    for (index, input) in inputs.iter().enumerate() {
+        add("lock", input.lock(), Some(index), None);
+    }
+    for (index, input) in inputs.iter().enumerate() {
+        if let Some(type_script) = input.type_().to_opt() {
+            add("type", type_script, Some(index), None);
+        }
+    }
+    for (index, output) in outputs.iter().enumerate() {
+        if let Some(type_script) = output.type_().to_opt() {
+            add("type", type_script, None, Some(index));
+        }
+    }
+    groups.into_iter().map(|(_, group)| group).collect()
+}
+
+fn script_cell_dep(script: &Script, cell_deps: &[CellDepInfo]) -> Option<usize> {
+    let code_hash: H256 = script.code_hash().unpack();
+    let by_type = script.hash_type() == ScriptHashType::Type.into();
+    cell_deps.iter().position(|cell_dep| {
+        let hash = if by_type {
+            cell_dep.type_hash.as_ref()
+        } else {
+            Some(&cell_dep.data_hash)
+        };
+        hash == Some(&code_hash)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bridged_supply() {
+        let supply = BridgedSupply {
+            minted: 100,
+            releasable: 80,
+        };
+        assert!(supply.check_backing().unwrap_err().contains("under-backed"));
+        assert!(supply.check_burn(80).is_ok());
+        assert!(supply
+            .check_burn(81)
+            .unwrap_err()
+            .contains("available bridged supply 80"));
+
+        let supply = BridgedSupply {
+            minted: 50,
+            releasable: 80,
+        };
+        assert!(supply.check_backing().is_ok());
+        assert!(supply.check_burn(51).is_err());
+    }
+
+    #[test]
+    fn test_max_proof_age() {
+        let by_blocks = MaxProofAge::Blocks(100);
+        assert!(by_blocks.check(1000, 0, 1100, 1_000_000).is_ok());
+        let err = by_blocks.check(1000, 1000, 1101, 1060).unwrap_err();
+        assert!(err.contains("101 blocks (60s) old"));
+        // A tip behind the proof block (another node) is not an error
+        assert!(by_blocks.check(1000, 0, 900, 0).is_ok());
+
+        let by_duration = MaxProofAge::Duration(Duration::from_secs(3600));
+        assert!(by_duration.check(1000, 10_000, 1_000_000, 13_600).is_ok());
+        assert!(by_duration
+            .check(1000, 10_000, 1001, 13_601)
+            .unwrap_err()
+            .contains("3600s"));
+    }
+
+    #[test]
+    fn test_check_recipient() {
+        let recipient = Script::new_builder()
+            .args(Bytes::from(vec![1u8; 20]).pack())
+            .build();
+        let other = recipient
+            .clone()
+            .as_builder()
+            .args(Bytes::from(vec![2u8; 20]).pack())
+            .build();
+        assert!(check_recipient(recipient.as_slice(), &recipient).is_ok());
+        assert!(check_recipient(other.as_slice(), &recipient)
+            .unwrap_err()
+            .contains("Recipient mismatch"));
+        assert!(check_recipient(&[0u8; 3], &recipient)
+            .unwrap_err()
+            .contains("invalid script"));
+    }
+
+    #[test]
+    fn test_multisig_recipient() {
+        use super::super::abi::{self, Token};
+        use ckb_sdk::{constants::MULTISIG_TYPE_HASH, AddressPayload, MultisigConfig};
+
+        let sighash_addresses = vec![
+            AddressPayload::from_pubkey_hash(H160([1u8; 20])),
+            AddressPayload::from_pubkey_hash(H160([2u8; 20])),
+            AddressPayload::from_pubkey_hash(H160([3u8; 20])),
+        ];
+        let config = MultisigConfig::new_with(sighash_addresses, 1, 2).unwrap();
+        for since in &[None, Some(100)] {
+            let lock = Script::from(&config.to_address_payload(*since));
+            assert!(check_recipient_lock(&lock).is_ok());
+
+            // The recipient passed to the ethereum lock() is the multisig lock script as is
+            let data = abi::encode(&[
+                Token::Address(H160([0x11; 20])),
+                Token::Uint(100),
+                Token::Bytes(lock.as_slice().to_vec()),
+            ]);
+            let encoded = abi::decode_bytes(&data, 2).unwrap();
+            let decoded = Script::from_slice(&encoded).unwrap();
+            assert_eq!(decoded, lock);
+            let code_hash: H256 = decoded.code_hash().unpack();
+            assert_eq!(code_hash, MULTISIG_TYPE_HASH);
+            assert_eq!(
+                &decoded.args().raw_data()[0..20],
+                config.hash160().as_bytes()
+            );
+            assert!(check_recipient(&encoded, &lock).is_ok());
+        }
+
+        let malformed = Script::from(&config.to_address_payload(None))
+            .as_builder()
+            .args(Bytes::from(vec![1u8; 21]).pack())
+            .build();
+        assert!(check_recipient_lock(&malformed)
+            .unwrap_err()
+            .contains("Invalid multisig lock script"));
+        let other = Script::new_builder()
+            .args(Bytes::from(vec![1u8; 3]).pack())
+            .build();
+        assert!(check_recipient_lock(&other).is_ok());
+    }
+
+    #[test]
+    fn test_check_balance_increase() {
+        assert!(check_balance_increase(0, 100, 100).is_ok());
+        assert!(check_balance_increase(50, 150, 100).is_ok());
+        assert!(check_balance_increase(50, 149, 100)
+            .unwrap_err()
+            .contains("from 50 to 149"));
+        assert!(check_balance_increase(50, 151, 100).is_err());
+        // Spent in between
+        assert!(check_balance_increase(150, 100, 100).is_err());
+    }
+
+    #[test]
+    fn test_convert_decimals() {
+        assert_eq!(
+            convert_decimals(1_500_000_000_000_000_000, 18, 8).unwrap(),
+            (150_000_000, 0)
+        );
+        assert_eq!(
+            convert_decimals(1_000_000_000_123, 18, 8).unwrap(),
+            (100, 123)
+        );
+        assert_eq!(convert_decimals(15, 6, 8).unwrap(), (1500, 0));
+        assert_eq!(convert_decimals(15, 8, 8).unwrap(), (15, 0));
+        assert_eq!(convert_decimals(15, 255, 0).unwrap(), (0, 15));
+        assert!(convert_decimals(u128::max_value(), 0, 1).is_err());
+
+        assert_eq!(format_decimal(1_500_000_000_000_000_000, 18), "1.5");
+        assert_eq!(format_decimal(1_000_000, 6), "1");
+        assert_eq!(format_decimal(15, 8), "0.00000015");
+        assert_eq!(format_decimal(0, 18), "0");
+        assert_eq!(format_decimal(1234, 0), "1234");
+        assert_eq!(
+            format_token_amount(1_500_000, Some(6), Some("USDC"), false),
+            "1.500000 USDC"
+        );
+        assert_eq!(
+            format_token_amount(1_500_000, Some(6), Some("USDC"), true),
+            "1.5 USDC"
+        );
+        assert_eq!(format_token_amount(1_000_000, Some(6), None, true), "1");
+        assert_eq!(format_token_amount(15, None, Some("USDC"), true), "15 USDC");
+        assert_eq!(format_token_amount(15, Some(0), None, false), "15");
+        assert_eq!(
+            format_decimal(u128::max_value(), 18),
+            "340282366920938463463.374607431768211455"
+        );
+
+        assert_eq!(
+            check_precision(1_000_000_000_123, None, false).unwrap(),
+            None
+        );
+        assert_eq!(
+            check_precision(1_000_000_000_000, Some((18, 8)), false).unwrap(),
+            None
+        );
+        assert!(check_precision(1_000_000_000_123, Some((18, 8)), false).is_err());
+        assert_eq!(
+            check_precision(1_000_000_000_123, Some((18, 8)), true).unwrap(),
+            Some(123)
+        );
+    }
+
+    #[test]
+    fn test_check_dust() {
+        assert!(check_dust(0, None, &TokenUnit::default()).is_err());
+        assert!(check_dust(0, Some((6, 8)), &TokenUnit::default()).is_err());
+        assert!(check_dust(1, None, &TokenUnit::default()).is_ok());
+        assert!(check_dust(1, Some((6, 8)), &TokenUnit::default()).is_ok());
+        // 10^10 is the smallest unit of an 18 decimals token on ckb (8 decimals)
+        assert!(check_dust(9_999_999_999, Some((18, 8)), &TokenUnit::default()).is_err());
+        assert!(check_dust(10_000_000_000, Some((18, 8)), &TokenUnit::default()).is_ok());
+        assert!(check_dust(u128::max_value(), Some((255, 0)), &TokenUnit::default()).is_err());
+    }
+
+    #[test]
+    fn test_proof_used() {
+        let err = "Send transaction error: TransactionFailedToVerify: Script(TransactionScriptError { source: Outputs[0].Type, cause: ValidationFailure(12) })";
+        assert!(is_proof_used_error(err, Some(12)));
+        assert!(!is_proof_used_error(err, Some(1)));
+        assert!(!is_proof_used_error(err, None));
+        assert_eq!(script_error_code(err), Some(12));
+        assert_eq!(
+            script_error_code("Verify script error: ValidationFailure(-31)"),
+            Some(-31)
+        );
+        assert_eq!(
+            script_error_code("Resolve transaction error: Unknown"),
+            None
+        );
+        let err = "Send transaction error: TransactionFailedToVerify: Script(TransactionScriptError { source: Inputs[0].Lock, cause: ExceededMaximumCycles: expect cycles <= 3500000000 })";
+        assert!(is_cycles_exceeded_error(err));
+        assert_eq!(script_error_code(err), None);
+        assert!(cycles_warning(2_000_000_000, 3_500_000_000).is_none());
+        assert!(cycles_warning(3_000_000_000, 3_500_000_000)
+            .unwrap()
+            .contains("85.7%"));
+
+        let proof = ReceiptProof {
+            block_number: 1,
+            block_hash: H256([1u8; 32]),
+            receipts_root: H256([2u8; 32]),
+            receipt_index: 3,
+            receipt: vec![0xc0],
+            nodes: vec![vec![0xc0]],
+        };
+        let witness = WitnessArgs::new_builder()
+            .output_type(Some(Bytes::from(proof.to_bytes())).pack())
+            .build();
+        let minted_proof = witness_proof(&witness.as_bytes()).unwrap();
+        assert!(same_proof(&minted_proof, &proof));
+        let mut other = proof.clone();
+        other.receipt_index = 4;
+        assert!(!same_proof(&minted_proof, &other));
+        assert!(witness_proof(&WitnessArgs::default().as_bytes()).is_none());
+    }
+
+    #[test]
+    fn test_mint_amount() {
+        assert_eq!(mint_amount(100, 100, false).unwrap(), 100);
+        // Mismatch of normal tokens is rejected by the amount check later
+        assert_eq!(mint_amount(100, 98, false).unwrap(), 100);
+        // The bridge contract received less than requested, mint the smaller amount
+        assert_eq!(mint_amount(100, 98, true).unwrap(), 98);
+        assert_eq!(mint_amount(100, 100, true).unwrap(), 100);
+        assert!(mint_amount(100, 101, true).is_err());
+    }
+
+    #[test]
+    fn test_amount_limits() {
+        let config = AmountLimits {
+            min: Some(10),
+            max: None,
+        };
+        let contract = AmountLimits {
+            min: Some(100),
+            max: Some(1000),
+        };
+        let limits = config.merge(contract);
+        assert_eq!(
+            limits,
+            AmountLimits {
+                min: Some(100),
+                max: Some(1000)
+            }
+        );
+        let raw = TokenUnit::default();
+        assert!(limits
+            .check(99, &raw)
+            .unwrap_err()
+            .contains("below the minimum 100"));
+        assert!(limits
+            .check(1001, &raw)
+            .unwrap_err()
+            .contains("above the maximum 1000"));
+        assert!(limits.check(100, &raw).is_ok() && limits.check(1000, &raw).is_ok());
+        assert!(AmountLimits::default()
+            .check(u128::max_value(), &raw)
+            .is_ok());
+        let usdc = TokenUnit {
+            decimals: Some(6),
+            symbol: Some("USDC".to_string()),
+            trim_zeros: false,
+        };
+        assert!(limits
+            .check(99, &usdc)
+            .unwrap_err()
+            .contains("Amount 0.000099 USDC is below the minimum 0.000100 USDC"));
+    }
+
+    #[test]
+    fn test_script_groups() {
+        let script = |code_hash: H256, hash_type: ScriptHashType, args: u8| {
+            Script::new_builder()
+                .code_hash(code_hash.pack())
+                .hash_type(hash_type.into())
+                .args(Bytes::from(vec![args]).pack())
+                .build()
+        };
+        let sighash = script(H256([1; 32]), ScriptHashType::Type, 1);
+        let token = script(H256([2; 32]), ScriptHashType::Data, 0);
+        let cell = |lock: &Script, type_script: Option<&Script>| {
+            CellOutput::new_builder()
+                .lock(lock.clone())
+                .type_(type_script.cloned().pack())
+                .build()
+        };
+        let recipient = script(H256([1; 32]), ScriptHashType::Type, 2);
+        let inputs = vec![cell(&sighash, None), cell(&sighash, None)];
+        let outputs = vec![cell(&recipient, Some(&token)), cell(&sighash, None)];
+        let cell_deps = vec![CellDepInfo {
+            out_point: OutPoint::default(),
+            dep_type: "code".to_string(),
+            data_hash: H256([2; 32]),
+            type_hash: None,
+        }];
+        let groups = script_groups(&inputs, &outputs, &cell_deps);
+        assert_eq!(groups.len(), 2);
+        // The outputs of the sighash lock run no script
+        assert_eq!(groups[0].group_type, "lock");
+        assert_eq!(groups[0].script, sighash.into());
+        assert_eq!(groups[0].input_indices, vec![0, 1]);
+        assert!(groups[0].output_indices.is_empty());
+        assert_eq!(groups[0].cell_dep, None);
+        assert_eq!(groups[1].group_type, "type");
+        assert_eq!(groups[1].script, token.into());
+        assert!(groups[1].input_indices.is_empty());
+        assert_eq!(groups[1].output_indices, vec![0]);
+        assert_eq!(groups[1].cell_dep, Some(0));
+    }
+}
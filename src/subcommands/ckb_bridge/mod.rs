@@ -1 +1,30 @@
-pub mod command;
\ No newline at end of file
+mod abi;
+mod address_cache;
+mod args;
+mod burn;
+mod bytecode;
+mod command;
+mod config;
+mod deploy;
+mod doctor;
+mod erc20;
+mod eth;
+mod exit_code;
+mod fee;
+mod intent;
+mod keystore;
+mod log;
+mod mint;
+mod notify;
+mod offline;
+mod price;
+mod progress;
+mod proof;
+mod reconcile;
+mod registry;
+mod relay;
+mod rlp;
+mod subscribe;
+mod util;
+
+pub use command::CkbBridgeSubCommand;
@@ -0,0 +1,85 @@
+//! Best-effort webhook notification of transfer status transitions
+
+use std::thread;
+use std::time::Duration;
+
+use ckb_types::H256;
+use serde_derive::{Deserialize, Serialize};
+use url::Url;
+
+use super::log::TransferEvent;
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+const WEBHOOK_MAX_ATTEMPTS: usize = 3;
+const WEBHOOK_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The json payload posted to the webhook
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Transition {
+    pub transfer_id: String,
+    pub direction: String,
+    pub old_status: String,
+    pub new_status: String,
+    pub tx_hash: Option<H256>,
+    pub timestamp: u64,
+}
+
+impl Transition {
+    pub fn new(direction: &str, old_status: String, event: &TransferEvent) -> Transition {
+        Transition {
+            transfer_id: event.transfer_id.clone(),
+            direction: direction.to_string(),
+            old_status,
+            new_status: event.status.clone(),
+            tx_hash: event.tx_hash.clone(),
+            timestamp: event.timestamp,
+        }
+    }
+}
+
+pub struct Webhook {
+    client: reqwest::Client,
+    url: Url,
+}
+
+impl Webhook {
+    pub fn new(url: Url) -> Result<Webhook, String> {
+        let client = reqwest::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .build()
+            .map_err(|err| err.to_string())?;
+        Ok(Webhook { client, url })
+    }
+
+    /// Post the transition, failures are logged as warnings and never returned
+    pub fn notify(&self, transition: &Transition) {
+        for attempt in 1..=WEBHOOK_MAX_ATTEMPTS {
+            let result = self
+                .client
+                .post(self.url.clone())
+                .json(transition)
+                .send()
+                .map_err(|err| err.to_string())
+                .and_then(|resp| {
+                    if resp.status().is_success() {
+                        Ok(())
+                    } else {
+                        Err(format!("response status {}", resp.status()))
+                    }
+                });
+            match result {
+                Ok(()) => return,
+                Err(err) => log::warn!(
+                    "Notify webhook {} failed (attempt {}/{}): {}",
+                    self.url,
+                    attempt,
+                    WEBHOOK_MAX_ATTEMPTS,
+                    err
+                ),
+            }
+            if attempt < WEBHOOK_MAX_ATTEMPTS {
+                thread::sleep(WEBHOOK_RETRY_INTERVAL);
+            }
+        }
+    }
+}
@@ -0,0 +1,284 @@
+//! The files exchanged in the offline signing workflows: `--build-only` writes the unsigned ckb
+//! transaction, `sign-tx` signs it on an offline machine and `--broadcast-signed` sends it.
+//! The ethereum transactions are written by `--eth-build-only`, signed by `sign-eth-tx` and
+//! sent by `broadcast-eth-tx`. `--dump-unsigned` writes the unsigned ckb transaction in the
+//! transaction file format of `ckb-cli tx` instead, for the existing signing tools.
+
+use std::convert::TryFrom;
+use std::fs;
+use std::path::Path;
+
+use ckb_jsonrpc_types::{JsonBytes, Transaction};
+use ckb_sdk::{NetworkType, TxHelper};
+use ckb_types::{core::TransactionView, packed, prelude::*, H160, H256};
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+
+use super::eth::{checksum_address, keccak256, AccessListItem, EthSigner, EthTransaction};
+use crate::subcommands::tx::ReprTxHelper;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OfflineTx {
+    /// The transfer this transaction belongs to
+    pub transfer_id: String,
+    /// The sighash lock args of the inputs to sign, the signing key must match
+    pub sighash_args: H160,
+    /// The indexes of the inputs locked by `sighash_args`
+    pub input_idxs: Vec<usize>,
+    pub signed: bool,
+    pub transaction: Transaction,
+}
+
+impl OfflineTx {
+    pub fn new(
+        transfer_id: String,
+        sighash_args: H160,
+        input_idxs: Vec<usize>,
+        tx: &TransactionView,
+    ) -> OfflineTx {
+        OfflineTx {
+            transfer_id,
+            sighash_args,
+            input_idxs,
+            signed: false,
+            transaction: tx.data().into(),
+        }
+    }
+
+    pub fn tx_view(&self) -> TransactionView {
+        packed::Transaction::from(self.transaction.clone()).into_view()
+    }
+
+    /// The transaction hash, not changed by signing (witnesses are not hashed)
+    pub fn tx_hash(&self) -> H256 {
+        self.tx_view().hash().unpack()
+    }
+
+    pub fn read(path: &Path) -> Result<OfflineTx, String> {
+        read_file(path)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        write_file(path, self)
+    }
+}
+
+/// An ethereum transaction (approve or lock) of a transfer for air-gapped signing
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OfflineEthTx {
+    /// The transfer this transaction belongs to
+    pub transfer_id: String,
+    /// The transfer step sending this transaction, "approve" or "lock"
+    pub step: String,
+    /// The sender, the signing key must match
+    pub from: H160,
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub gas_price: u64,
+    pub gas_limit: u64,
+    pub to: Option<H160>,
+    pub data: JsonBytes,
+    /// Signed as an EIP-2930 transaction if set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access_list: Option<Vec<AccessListItem>>,
+    /// The signed raw transaction, set by `sign-eth-tx`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_tx: Option<JsonBytes>,
+}
+
+impl OfflineEthTx {
+    pub fn new(
+        transfer_id: String,
+        step: &str,
+        from: H160,
+        chain_id: u64,
+        tx: EthTransaction,
+    ) -> OfflineEthTx {
+        OfflineEthTx {
+            transfer_id,
+            step: step.to_string(),
+            from,
+            chain_id,
+            nonce: tx.nonce,
+            gas_price: tx.gas_price,
+            gas_limit: tx.gas_limit,
+            to: tx.to,
+            data: JsonBytes::from_vec(tx.data),
+            access_list: tx.access_list,
+            raw_tx: None,
+        }
+    }
+
+    pub fn sign(&mut self, signer: &EthSigner) -> Result<(), String> {
+        if signer.address != self.from {
+            return Err(format!(
+                "The private key (address {}) does not match the sender {}",
+                checksum_address(&signer.address),
+                checksum_address(&self.from)
+            ));
+        }
+        let tx = EthTransaction {
+            nonce: self.nonce,
+            gas_price: self.gas_price,
+            gas_limit: self.gas_limit,
+            to: self.to.clone(),
+            value: 0,
+            data: self.data.as_bytes().to_vec(),
+            access_list: self.access_list.clone(),
+        };
+        self.raw_tx = Some(JsonBytes::from_vec(tx.sign(signer, self.chain_id)));
+        Ok(())
+    }
+
+    /// The hash of the signed transaction, `None` if not signed
+    pub fn tx_hash(&self) -> Option<H256> {
+        self.raw_tx
+            .as_ref()
+            .map(|raw_tx| H256::from(keccak256(raw_tx.as_bytes())))
+    }
+
+    pub fn read(path: &Path) -> Result<OfflineEthTx, String> {
+        read_file(path)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        write_file(path, self)
+    }
+}
+
+/// Write the unsigned transaction as a `ckb-cli tx` transaction file, the signatures added by
+/// `tx sign-inputs --add-signatures` are put in the witnesses by `--broadcast-signed`
+pub fn write_tx_file(
+    path: &Path,
+    tx: &TransactionView,
+    network: NetworkType,
+) -> Result<(), String> {
+    write_file(path, &ReprTxHelper::new(TxHelper::new(tx.clone()), network))
+}
+
+pub fn read_tx_file(path: &Path) -> Result<TxHelper, String> {
+    let repr: ReprTxHelper = read_file(path)?;
+    TxHelper::try_from(repr)
+}
+
+fn read_file<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let content =
+        fs::read_to_string(path).map_err(|err| format!("Read {:?} error: {}", path, err))?;
+    serde_json::from_str(&content)
+        .map_err(|err| format!("Parse offline transaction {:?} error: {}", path, err))
+}
+
+fn write_file<T: serde::Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(value).map_err(|err| err.to_string())?;
+    fs::write(path, content).map_err(|err| format!("Write {:?} error: {}", path, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subcommands::ckb_bridge::rlp;
+    use crate::utils::arg_parser::PrivkeyWrapper;
+    use ckb_types::{bytes::Bytes, core::TransactionBuilder};
+
+    #[test]
+    fn test_tx_hash_unchanged_by_signing() {
+        let tx = TransactionBuilder::default()
+            .input(packed::CellInput::new(packed::OutPoint::default(), 0))
+            .witness(Bytes::new().pack())
+            .build();
+        let mut offline_tx = OfflineTx::new("id".to_string(), H160::default(), vec![0], &tx);
+        let unsigned_hash = offline_tx.tx_hash();
+        assert_eq!(unsigned_hash, tx.hash().unpack());
+
+        let signed = tx
+            .as_advanced_builder()
+            .set_witnesses(vec![Bytes::from(vec![1u8; 85]).pack()])
+            .build();
+        offline_tx.transaction = signed.data().into();
+        offline_tx.signed = true;
+        let json = serde_json::to_string(&offline_tx).unwrap();
+        let parsed: OfflineTx = serde_json::from_str(&json).unwrap();
+        assert!(parsed.signed);
+        assert_eq!(parsed.tx_hash(), unsigned_hash);
+        assert_eq!(parsed.tx_view().witnesses(), signed.witnesses());
+    }
+
+    #[test]
+    fn test_tx_file() {
+        let tx = TransactionBuilder::default()
+            .input(packed::CellInput::new(packed::OutPoint::default(), 0))
+            .witness(Bytes::from(vec![0u8; 85]).pack())
+            .build();
+        let path = std::env::temp_dir().join(format!("ckb-bridge-tx-file-{}", std::process::id()));
+        write_tx_file(&path, &tx, NetworkType::Testnet).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        let helper = read_tx_file(&path);
+        fs::remove_file(&path).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&content).unwrap();
+        assert!(json["signatures"].as_object().unwrap().is_empty());
+        let helper = helper.unwrap();
+        assert_eq!(helper.transaction().hash(), tx.hash());
+        assert_eq!(helper.transaction().witnesses(), tx.witnesses());
+        // An OfflineTx file is not a tx file
+        let offline_tx = OfflineTx::new("id".to_string(), H160::default(), vec![0], &tx);
+        offline_tx.write(&path).unwrap();
+        assert!(read_tx_file(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_sign_eth_tx() {
+        let privkey = PrivkeyWrapper(secp256k1::SecretKey::from_slice(&[1u8; 32]).unwrap());
+        let signer = EthSigner::new(privkey);
+        let tx = EthTransaction {
+            nonce: 7,
+            gas_price: 1_000_000_000,
+            gas_limit: 60_000,
+            to: Some(H160::default()),
+            value: 0,
+            data: vec![0x09, 0x5e, 0xa7, 0xb3],
+            access_list: None,
+        };
+        let mut offline_tx = OfflineEthTx::new("id".to_string(), "approve", H160::default(), 1, tx);
+        assert!(offline_tx.sign(&signer).is_err());
+        assert!(offline_tx.tx_hash().is_none());
+
+        offline_tx.from = signer.address.clone();
+        offline_tx.sign(&signer).unwrap();
+        let json = serde_json::to_string(&offline_tx).unwrap();
+        let parsed: OfflineEthTx = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.nonce, 7);
+        assert_eq!(parsed.raw_tx, offline_tx.raw_tx);
+        let raw_tx = parsed.raw_tx.as_ref().unwrap().as_bytes();
+        assert_eq!(parsed.tx_hash(), Some(H256::from(keccak256(raw_tx))));
+        assert!(raw_tx[0] >= 0xc0);
+
+        // EIP-2930: 0x01 || rlp([chainId, nonce, gasPrice, gasLimit, to, value, data,
+        // accessList, yParity, r, s])
+        offline_tx.access_list = Some(vec![AccessListItem {
+            address: H160([0x11; 20]),
+            storage_keys: vec![H256([0x22; 32])],
+        }]);
+        offline_tx.sign(&signer).unwrap();
+        let json = serde_json::to_value(&offline_tx).unwrap();
+        assert_eq!(
+            json["access_list"][0]["storageKeys"][0],
+            serde_json::json!(format!("{:#x}", H256([0x22; 32])))
+        );
+        let raw_tx = offline_tx.raw_tx.as_ref().unwrap().as_bytes();
+        assert_eq!(raw_tx[0], 0x01);
+        let item = rlp::decode(&raw_tx[1..]).unwrap();
+        let fields = item.as_list().unwrap();
+        assert_eq!(fields.len(), 11);
+        assert_eq!(fields[0].as_bytes().unwrap(), &[1]);
+        assert_eq!(fields[1].as_bytes().unwrap(), &[7]);
+        let access_list = fields[7].as_list().unwrap();
+        let entry = access_list[0].as_list().unwrap();
+        assert_eq!(entry[0].as_bytes().unwrap(), &[0x11; 20][..]);
+        assert_eq!(
+            entry[1].as_list().unwrap()[0].as_bytes().unwrap(),
+            &[0x22; 32][..]
+        );
+        assert!(fields[8].as_bytes().unwrap().len() <= 1);
+    }
+}
@@ -0,0 +1,126 @@
+//! Informational USD estimates of transfer amounts, never required by any transfer step
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use ckb_types::H160;
+use serde_derive::{Deserialize, Serialize};
+use url::Url;
+
+const PRICE_SOURCE_TIMEOUT: Duration = Duration::from_secs(5);
+// The key of the CKB price in the price source response
+const CKB_PRICE_KEY: &str = "CKB";
+const CKB_DECIMALS: u8 = 8;
+
+/// Approximate USD values of a transfer, for display only
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UsdEstimate {
+    /// Approximate USD value of the transferred amount
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount: Option<f64>,
+    /// Approximate USD value of the ckb transaction fee
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ckb_fee: Option<f64>,
+    pub source: String,
+}
+
+/// The price source is a http endpoint returning a json object from token (contract address
+/// in lowercase hex, or symbol) to USD price, e.g. `{"0xdac1...": 1.0, "CKB": 0.007}`.
+/// It is requested at most once, the result is cached for the run.
+pub struct PriceSource {
+    client: reqwest::Client,
+    url: Url,
+    prices: Option<HashMap<String, f64>>,
+}
+
+impl PriceSource {
+    pub fn new(url: Url) -> Result<PriceSource, String> {
+        let client = reqwest::Client::builder()
+            .timeout(PRICE_SOURCE_TIMEOUT)
+            .build()
+            .map_err(|err| err.to_string())?;
+        Ok(PriceSource {
+            client,
+            url,
+            prices: None,
+        })
+    }
+
+    /// The USD price of the first key found, `None` if the price source is unavailable or has
+    /// no price for any of the keys (failures are only logged).
+    fn usd_price(&mut self, keys: &[&str]) -> Option<f64> {
+        if self.prices.is_none() {
+            let prices = self.fetch().unwrap_or_else(|err| {
+                log::warn!("Get prices from {} failed: {}", self.url, err);
+                HashMap::new()
+            });
+            self.prices = Some(prices);
+        }
+        let prices = self.prices.as_ref().expect("prices fetched");
+        keys.iter().find_map(|key| prices.get(*key)).cloned()
+    }
+
+    /// Estimate the USD value of a token amount (with known decimals) and a ckb fee (in
+    /// shannons), `None` if neither can be estimated.
+    pub fn estimate(
+        &mut self,
+        token: &H160,
+        symbol: Option<&str>,
+        amount: u128,
+        decimals: Option<u8>,
+        ckb_fee: Option<u64>,
+    ) -> Option<UsdEstimate> {
+        let amount = decimals.and_then(|decimals| {
+            let address = format!("{:#x}", token);
+            let keys = match symbol {
+                Some(symbol) => vec![address.as_str(), symbol],
+                None => vec![address.as_str()],
+            };
+            self.usd_price(&keys)
+                .map(|price| usd_value(amount, decimals, price))
+        });
+        let ckb_fee = ckb_fee.and_then(|fee| {
+            self.usd_price(&[CKB_PRICE_KEY])
+                .map(|price| usd_value(u128::from(fee), CKB_DECIMALS, price))
+        });
+        if amount.is_none() && ckb_fee.is_none() {
+            return None;
+        }
+        Some(UsdEstimate {
+            amount,
+            ckb_fee,
+            source: self.url.to_string(),
+        })
+    }
+
+    fn fetch(&self) -> Result<HashMap<String, f64>, String> {
+        let mut resp = self
+            .client
+            .get(self.url.clone())
+            .send()
+            .map_err(|err| err.to_string())?;
+        if !resp.status().is_success() {
+            return Err(format!("response status {}", resp.status()));
+        }
+        resp.json().map_err(|err| err.to_string())
+    }
+}
+
+/// The USD value of `amount` (in the token's smallest unit)
+pub fn usd_value(amount: u128, decimals: u8, price: f64) -> f64 {
+    amount as f64 / 10f64.powi(i32::from(decimals)) * price
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usd_value() {
+        assert!((usd_value(1_500_000, 6, 1.0) - 1.5).abs() < 1e-9);
+        assert!((usd_value(2_000_000_000_000_000_000, 18, 1800.5) - 3601.0).abs() < 1e-6);
+        assert!((usd_value(0, 18, 1800.5)).abs() < 1e-9);
+        // 1000 CKB fee
+        assert!((usd_value(100_000_000_000, CKB_DECIMALS, 0.007) - 7.0).abs() < 1e-9);
+    }
+}
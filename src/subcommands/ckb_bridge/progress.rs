@@ -0,0 +1,203 @@
+//! Progress of the transfer steps run by `resume-all`, written to stderr so stdout keeps only
+//! the final json summary
+
+use std::io::{self, Write};
+
+use serde_derive::Serialize;
+
+use super::log::StepResult;
+
+// Print the aggregate line every so many rows (and after the last one)
+const AGGREGATE_INTERVAL: usize = 10;
+// The error in a progress line is cut to this many chars, the full error is in the summary
+const MAX_ERROR_CHARS: usize = 80;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProgressMode {
+    /// One line per row, rewritten in place
+    Tty,
+    /// One plain line per row, for a pipe or a file
+    Plain,
+    /// One json object per row
+    Ndjson,
+    None,
+}
+
+impl ProgressMode {
+    /// Parse `--progress`, `auto` is `Tty` if stderr is a terminal and `Plain` otherwise
+    pub fn parse(input: &str, stderr_is_tty: bool) -> Result<ProgressMode, String> {
+        match input {
+            "auto" if stderr_is_tty => Ok(ProgressMode::Tty),
+            "auto" | "plain" => Ok(ProgressMode::Plain),
+            "ndjson" => Ok(ProgressMode::Ndjson),
+            "none" => Ok(ProgressMode::None),
+            _ => Err(format!("Invalid progress mode: {}", input)),
+        }
+    }
+}
+
+/// The per-row event of the ndjson progress
+#[derive(Serialize)]
+struct ProgressEvent<'a> {
+    index: usize,
+    total: usize,
+    #[serde(flatten)]
+    result: &'a StepResult,
+}
+
+pub struct Progress<W: Write> {
+    mode: ProgressMode,
+    writer: W,
+    total: usize,
+    done: usize,
+    advanced: usize,
+    waiting: usize,
+    failed: usize,
+}
+
+impl Progress<io::Stderr> {
+    pub fn stderr(mode: ProgressMode, total: usize) -> Progress<io::Stderr> {
+        Progress::new(mode, io::stderr(), total)
+    }
+}
+
+impl<W: Write> Progress<W> {
+    pub fn new(mode: ProgressMode, writer: W, total: usize) -> Progress<W> {
+        Progress {
+            mode,
+            writer,
+            total,
+            done: 0,
+            advanced: 0,
+            waiting: 0,
+            failed: 0,
+        }
+    }
+
+    /// Record the result of one row, the progress is best effort and never fails the run
+    pub fn record(&mut self, result: &StepResult) {
+        self.done += 1;
+        if result.is_failed() {
+            self.failed += 1;
+        } else if result.status != result.old_status {
+            self.advanced += 1;
+        } else {
+            self.waiting += 1;
+        }
+        let _ = self.write_row(result);
+    }
+
+    /// Counts of the rows so far: advanced, waiting and failed
+    pub fn aggregate(&self) -> String {
+        format!(
+            "advanced {}, waiting {}, failed {}",
+            self.advanced, self.waiting, self.failed
+        )
+    }
+
+    fn write_row(&mut self, result: &StepResult) -> io::Result<()> {
+        let aggregate = self.done % AGGREGATE_INTERVAL == 0 || self.done == self.total;
+        match self.mode {
+            ProgressMode::None => return Ok(()),
+            ProgressMode::Ndjson => {
+                let event = ProgressEvent {
+                    index: self.done,
+                    total: self.total,
+                    result,
+                };
+                let line = serde_json::to_string(&event)
+                    .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+                writeln!(self.writer, "{}", line)?;
+            }
+            ProgressMode::Plain => {
+                writeln!(self.writer, "{}", self.row_line(result))?;
+                if aggregate {
+                    writeln!(self.writer, "{}", self.aggregate())?;
+                }
+            }
+            ProgressMode::Tty => {
+                // Clear the line and rewrite it, the last one is kept
+                write!(
+                    self.writer,
+                    "\r\x1b[2K{} ({})",
+                    self.row_line(result),
+                    self.aggregate()
+                )?;
+                if self.done == self.total {
+                    writeln!(self.writer)?;
+                }
+            }
+        }
+        self.writer.flush()
+    }
+
+    fn row_line(&self, result: &StepResult) -> String {
+        let mut line = format!(
+            "[{}/{}] transfer {} {} status {}",
+            self.done, self.total, result.transfer_id, result.direction, result.status
+        );
+        if let Some(error) = result.error.as_ref() {
+            let error = error.chars().take(MAX_ERROR_CHARS).collect::<String>();
+            let state = if result.is_failed() {
+                "failed"
+            } else {
+                "waiting"
+            };
+            line.push_str(&format!(" {}: {}", state, error));
+        }
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(id: &str, old_status: &str, status: &str, error: Option<&str>) -> StepResult {
+        StepResult {
+            transfer_id: id.to_string(),
+            direction: "to-ckb".to_string(),
+            old_status: old_status.to_string(),
+            status: status.to_string(),
+            tx_hash: None,
+            error: error.map(ToString::to_string),
+        }
+    }
+
+    #[test]
+    fn test_plain_progress() {
+        let mut progress = Progress::new(ProgressMode::Plain, Vec::new(), 4);
+        progress.record(&result("a", "Init", "Approved", None));
+        progress.record(&result("b", "Locked", "Locked", None));
+        progress.record(&result("c", "Locked", "Locked", Some("rpc error")));
+        let timeout = "Wait mined timeout (600s)";
+        progress.record(&result("d", "Locked", "Locked", Some(timeout)));
+        let output = String::from_utf8(progress.writer.clone()).unwrap();
+        assert_eq!(
+            output.lines().collect::<Vec<_>>(),
+            vec![
+                "[1/4] transfer a to-ckb status Approved",
+                "[2/4] transfer b to-ckb status Locked",
+                "[3/4] transfer c to-ckb status Locked failed: rpc error",
+                "[4/4] transfer d to-ckb status Locked waiting: Wait mined timeout (600s)",
+                "advanced 1, waiting 2, failed 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ndjson_progress() {
+        let mut progress = Progress::new(ProgressMode::Ndjson, Vec::new(), 2);
+        progress.record(&result("a", "Init", "Approved", None));
+        let output = String::from_utf8(progress.writer.clone()).unwrap();
+        let event: serde_json::Value = serde_json::from_str(output.trim_end()).unwrap();
+        assert_eq!(event["index"], 1);
+        assert_eq!(event["total"], 2);
+        assert_eq!(event["transfer_id"], "a");
+        assert_eq!(event["status"], "Approved");
+
+        assert_eq!(ProgressMode::parse("auto", true), Ok(ProgressMode::Tty));
+        assert_eq!(ProgressMode::parse("auto", false), Ok(ProgressMode::Plain));
+        assert!(ProgressMode::parse("fancy", false).is_err());
+    }
+}
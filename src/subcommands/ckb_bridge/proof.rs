@@ -0,0 +1,785 @@
+//! Receipt proof of an ethereum transaction, verified by the bridge script on ckb.
+//!
+//! The proof is the rlp list
+//! `[block_number, block_hash, receipts_root, receipt_index, receipt, [node, ...]]`,
+//! where `receipt` is the consensus encoding of the receipt and the nodes are the encoded
+//! receipts trie nodes on the path from the root to the receipt.
+//!
+//! The proof is built and verified through the `ProofBackend` traits, the receipt MPT proof
+//! above is the default (and only) backend, a new scheme of the bridge contracts is added as
+//! another backend selected by `--proof-backend`.
+
+use ckb_jsonrpc_types::JsonBytes;
+use ckb_types::{H160, H256};
+use serde_derive::{Deserialize, Serialize};
+
+use super::abi::{AbiItem, Token};
+use super::eth::{checksum_address, keccak256, serialize_checksum_address, EthLog};
+use super::rlp::{self, RlpItem};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReceiptProof {
+    pub block_number: u64,
+    pub block_hash: H256,
+    pub receipts_root: H256,
+    pub receipt_index: u64,
+    pub receipt: Vec<u8>,
+    pub nodes: Vec<Vec<u8>>,
+}
+
+impl ReceiptProof {
+    /// Build the proof of `receipts[receipt_index]`, `receipts` are all the (encoded) receipts
+    /// of the block in order.
+    pub fn build(
+        block_number: u64,
+        block_hash: H256,
+        receipts: &[Vec<u8>],
+        receipt_index: u64,
+    ) -> Result<ReceiptProof, String> {
+        let receipt = receipts
+            .get(receipt_index as usize)
+            .cloned()
+            .ok_or_else(|| {
+                format!(
+                    "Receipt index {} out of range ({} receipts)",
+                    receipt_index,
+                    receipts.len()
+                )
+            })?;
+        let items = receipts
+            .iter()
+            .enumerate()
+            .map(|(index, receipt)| (receipt_key(index as u64), receipt.clone()))
+            .collect::<Vec<_>>();
+        let key = receipt_key(receipt_index);
+        let mut nodes = Vec::new();
+        let root = encode_node(&items, 0, Some(&key), &mut nodes);
+        let root_bytes = rlp::encode(&root);
+        Ok(ReceiptProof {
+            block_number,
+            block_hash,
+            receipts_root: H256::from(keccak256(&root_bytes)),
+            receipt_index,
+            receipt,
+            nodes,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        rlp::encode(&RlpItem::List(vec![
+            RlpItem::from_u64(self.block_number),
+            RlpItem::Bytes(self.block_hash.as_bytes().to_vec()),
+            RlpItem::Bytes(self.receipts_root.as_bytes().to_vec()),
+            RlpItem::from_u64(self.receipt_index),
+            RlpItem::Bytes(self.receipt.clone()),
+            RlpItem::List(self.nodes.iter().cloned().map(RlpItem::Bytes).collect()),
+        ]))
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<ReceiptProof, String> {
+        let item = rlp::decode(data)?;
+        let fields = item.as_list()?;
+        if fields.len() != 6 {
+            return Err(format!(
+                "Invalid proof: expected 6 fields, got {}",
+                fields.len()
+            ));
+        }
+        let nodes = fields[5]
+            .as_list()?
+            .iter()
+            .map(|node| node.as_bytes().map(|bytes| bytes.to_vec()))
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(ReceiptProof {
+            block_number: fields[0].as_u64()?,
+            block_hash: parse_h256(fields[1].as_bytes()?)?,
+            receipts_root: parse_h256(fields[2].as_bytes()?)?,
+            receipt_index: fields[3].as_u64()?,
+            receipt: fields[4].as_bytes()?.to_vec(),
+            nodes,
+        })
+    }
+
+    /// Check the nodes prove the receipt is at `receipt_index` of the trie with `receipts_root`
+    pub fn verify(&self) -> Result<(), String> {
+        let value = verify_proof(
+            &self.receipts_root,
+            &receipt_key(self.receipt_index),
+            &self.nodes,
+        )?;
+        if value != self.receipt {
+            return Err("Proof value mismatch with the receipt".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Name of the default backend, the receipt proof in the receipts trie
+pub const RECEIPT_MPT_BACKEND: &str = "receipt-mpt";
+
+/// Builds the proof of a receipt, the block and its receipts are fetched by the caller
+pub trait ProofGenerator {
+    /// Prove `receipts[receipt_index]` of the block, `receipts` are all the (encoded) receipts
+    /// of the block in order and `receipts_root` is the root in the block header
+    fn generate(
+        &self,
+        block_number: u64,
+        block_hash: H256,
+        receipts_root: &H256,
+        receipts: &[Vec<u8>],
+        receipt_index: u64,
+    ) -> Result<ReceiptProof, String>;
+}
+
+/// Verifies a proof offline, as the bridge script does on ckb
+pub trait ProofVerifier {
+    fn verify(&self, proof: &ReceiptProof) -> Result<(), String>;
+}
+
+pub trait ProofBackend: ProofGenerator + ProofVerifier {
+    fn name(&self) -> &'static str;
+}
+
+/// The backend of `name`, the receipt MPT backend if not given
+pub fn proof_backend(name: Option<&str>) -> Result<Box<dyn ProofBackend>, String> {
+    match name.unwrap_or(RECEIPT_MPT_BACKEND) {
+        RECEIPT_MPT_BACKEND => Ok(Box::new(ReceiptMptBackend)),
+        name => Err(format!(
+            "Unknown proof backend: {}, supported: {}",
+            name, RECEIPT_MPT_BACKEND
+        )),
+    }
+}
+
+pub struct ReceiptMptBackend;
+
+impl ProofGenerator for ReceiptMptBackend {
+    fn generate(
+        &self,
+        block_number: u64,
+        block_hash: H256,
+        receipts_root: &H256,
+        receipts: &[Vec<u8>],
+        receipt_index: u64,
+    ) -> Result<ReceiptProof, String> {
+        let proof = ReceiptProof::build(block_number, block_hash, receipts, receipt_index)?;
+        if &proof.receipts_root != receipts_root {
+            return Err(format!(
+                "Receipts root mismatch in block {}, expected: {:#x}, got: {:#x}",
+                block_number, receipts_root, proof.receipts_root
+            ));
+        }
+        Ok(proof)
+    }
+}
+
+impl ProofVerifier for ReceiptMptBackend {
+    fn verify(&self, proof: &ReceiptProof) -> Result<(), String> {
+        proof.verify()
+    }
+}
+
+impl ProofBackend for ReceiptMptBackend {
+    fn name(&self) -> &'static str {
+        RECEIPT_MPT_BACKEND
+    }
+}
+
+/// The event emitted by the bridge contract `lock()`:
+/// `Locked(address indexed token, address indexed sender, uint256 lockedAmount, bytes recipientLockscript)`
+#[derive(Clone, Debug, PartialEq)]
+pub struct LockEvent {
+    pub token: H160,
+    pub sender: H160,
+    pub amount: u128,
+    pub recipient_lockscript: Vec<u8>,
+}
+
+impl LockEvent {
+    /// Find the lock event emitted by `contract` in the receipt, `event` is the `Locked` event
+    /// in the bridge ABI. The addresses are the token and the sender in order, the amount and
+    /// the recipient are the uint and bytes inputs.
+    pub fn from_receipt(
+        receipt: &DecodedReceipt,
+        contract: &H160,
+        event: &AbiItem,
+    ) -> Result<LockEvent, String> {
+        LockEvent::from_logs(&receipt.logs, contract, event)
+    }
+
+    /// Find the lock event emitted by `contract` among `logs` (of a receipt from the node or
+    /// in a proof), the log is matched by the emitter and the topic of `event`
+    pub fn from_logs(
+        logs: &[DecodedLog],
+        contract: &H160,
+        event: &AbiItem,
+    ) -> Result<LockEvent, String> {
+        let event_topic = event.topic();
+        let log = logs
+            .iter()
+            .find(|log| &log.address == contract && log.topics.first() == Some(&event_topic))
+            .ok_or_else(|| {
+                match logs
+                    .iter()
+                    .find(|log| log.topics.first() == Some(&event_topic))
+                {
+                    Some(log) => format!(
+                        "Lock event not found in the receipt of {}, the {} event is emitted by {} (wrong bridge contract?)",
+                        checksum_address(contract), event.signature, checksum_address(&log.address)
+                    ),
+                    None => format!(
+                        "Lock event not found in the receipt of {}, no {} event (topic {:#x}) in its {} logs (not a lock transaction?)",
+                        checksum_address(contract),
+                        event.signature,
+                        event_topic,
+                        logs.len()
+                    ),
+                }
+            })?;
+        let mut addresses = Vec::new();
+        let mut amount = None;
+        let mut recipient_lockscript = None;
+        for token in event.decode_log(&log.topics, log.data.as_bytes())? {
+            match token {
+                Token::Address(address) => addresses.push(address),
+                Token::Uint(value) => amount = amount.or(Some(value)),
+                Token::Bytes(bytes) => recipient_lockscript = recipient_lockscript.or(Some(bytes)),
+            }
+        }
+        match (addresses.as_slice(), amount, recipient_lockscript) {
+            ([token, sender], Some(amount), Some(recipient_lockscript)) => Ok(LockEvent {
+                token: token.clone(),
+                sender: sender.clone(),
+                amount,
+                recipient_lockscript,
+            }),
+            _ => Err(format!(
+                "Invalid lock event {}: expected the token and sender addresses, the amount and the recipient lock script",
+                event.signature
+            )),
+        }
+    }
+}
+
+/// Check (`--strict-abi`) that the receipt has a log of the `signature` event emitted by
+/// `contract`, telling a contract ABI mismatch from a log emitted by another address. The
+/// signature must also be the `event` in the bridge ABI used to parse it.
+pub fn check_lock_event_abi(
+    logs: &[DecodedLog],
+    contract: &H160,
+    signature: &str,
+    event: &AbiItem,
+) -> Result<(), String> {
+    let event_topic = H256::from(keccak256(signature.as_bytes()));
+    let mut emitters = logs
+        .iter()
+        .filter(|log| log.topics.first() == Some(&event_topic))
+        .map(|log| &log.address);
+    match emitters.clone().find(|address| *address == contract) {
+        Some(_) => {}
+        None => match emitters.next() {
+            Some(address) => {
+                return Err(format!(
+                    "Event {} is emitted by {}, not the configured bridge contract {}",
+                    signature,
+                    checksum_address(address),
+                    checksum_address(contract)
+                ));
+            }
+            None => {
+                return Err(format!(
+                    "Event {} (topic {:#x}) not found in the receipt, ABI mismatch between \
+                     the config and the bridge contract {}?",
+                    signature,
+                    event_topic,
+                    checksum_address(contract)
+                ));
+            }
+        },
+    }
+    if signature != event.signature {
+        return Err(format!(
+            "The configured lock event {} does not match {} in the bridge ABI",
+            signature, event.signature
+        ));
+    }
+    Ok(())
+}
+
+/// A receipt decoded from its consensus encoding
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DecodedReceipt {
+    /// Transaction type of typed receipts (EIP-2718), `None` for legacy receipts
+    pub tx_type: Option<u8>,
+    /// `None` for pre-byzantium receipts (with state root)
+    pub status: Option<u64>,
+    pub cumulative_gas_used: u64,
+    pub logs: Vec<DecodedLog>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DecodedLog {
+    #[serde(serialize_with = "serialize_checksum_address")]
+    pub address: H160,
+    pub topics: Vec<H256>,
+    pub data: JsonBytes,
+}
+
+impl From<EthLog> for DecodedLog {
+    fn from(log: EthLog) -> DecodedLog {
+        DecodedLog {
+            address: log.address,
+            topics: log.topics,
+            data: log.data,
+        }
+    }
+}
+
+pub fn decode_receipt(data: &[u8]) -> Result<DecodedReceipt, String> {
+    let (tx_type, payload) = match data.first() {
+        Some(first) if *first < 0x80 => (Some(*first), &data[1..]),
+        Some(_) => (None, data),
+        None => return Err("Empty receipt".to_string()),
+    };
+    let item = rlp::decode(payload)?;
+    let fields = item.as_list()?;
+    if fields.len() != 4 {
+        return Err(format!(
+            "Invalid receipt: expected 4 fields, got {}",
+            fields.len()
+        ));
+    }
+    let status_bytes = fields[0].as_bytes()?;
+    let status = if status_bytes.len() == 32 {
+        None
+    } else {
+        Some(fields[0].as_u64()?)
+    };
+    let logs = fields[3]
+        .as_list()?
+        .iter()
+        .map(decode_log)
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(DecodedReceipt {
+        tx_type,
+        status,
+        cumulative_gas_used: fields[1].as_u64()?,
+        logs,
+    })
+}
+
+fn decode_log(item: &RlpItem) -> Result<DecodedLog, String> {
+    let fields = item.as_list()?;
+    if fields.len() != 3 {
+        return Err(format!(
+            "Invalid log: expected 3 fields, got {}",
+            fields.len()
+        ));
+    }
+    let address = H160::from_slice(fields[0].as_bytes()?)
+        .map_err(|err| format!("Invalid log address: {}", err))?;
+    let topics = fields[1]
+        .as_list()?
+        .iter()
+        .map(|topic| parse_h256(topic.as_bytes()?))
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(DecodedLog {
+        address,
+        topics,
+        data: JsonBytes::from_vec(fields[2].as_bytes()?.to_vec()),
+    })
+}
+
+fn parse_h256(bytes: &[u8]) -> Result<H256, String> {
+    H256::from_slice(bytes).map_err(|err| format!("Invalid hash: {}", err))
+}
+
+/// The receipts trie key: rlp encoded transaction index
+fn receipt_key(index: u64) -> Vec<u8> {
+    rlp::encode(&RlpItem::from_u64(index))
+}
+
+fn to_nibbles(key: &[u8]) -> Vec<u8> {
+    key.iter()
+        .flat_map(|byte| vec![byte >> 4, byte & 0x0f])
+        .collect()
+}
+
+/// Hex prefix encoding of a nibble path
+fn hex_prefix(nibbles: &[u8], is_leaf: bool) -> Vec<u8> {
+    let flag = if is_leaf { 2 } else { 0 };
+    let mut ret = Vec::with_capacity(nibbles.len() / 2 + 1);
+    let rest = if nibbles.len() % 2 == 1 {
+        ret.push(((flag + 1) << 4) | nibbles[0]);
+        &nibbles[1..]
+    } else {
+        ret.push(flag << 4);
+        nibbles
+    };
+    for pair in rest.chunks(2) {
+        ret.push((pair[0] << 4) | pair[1]);
+    }
+    ret
+}
+
+/// Inverse of `hex_prefix`, return the nibble path and whether it is a leaf
+fn decode_hex_prefix(data: &[u8]) -> Result<(Vec<u8>, bool), String> {
+    let first = *data
+        .first()
+        .ok_or_else(|| "Empty hex prefix path".to_string())?;
+    let flag = first >> 4;
+    if flag > 3 {
+        return Err(format!("Invalid hex prefix flag: {}", flag));
+    }
+    let mut nibbles = Vec::with_capacity(data.len() * 2);
+    if flag & 1 == 1 {
+        nibbles.push(first & 0x0f);
+    }
+    nibbles.extend(to_nibbles(&data[1..]));
+    Ok((nibbles, flag & 2 == 2))
+}
+
+/// Encode the trie node of `items` (their nibble keys share the first `depth` nibbles).
+///
+/// Nodes on the path to `key` which are referenced by hash are pushed to `proof`, root first.
+fn encode_node(
+    items: &[(Vec<u8>, Vec<u8>)],
+    depth: usize,
+    key: Option<&[u8]>,
+    proof: &mut Vec<Vec<u8>>,
+) -> RlpItem {
+    let nibble_items = items
+        .iter()
+        .map(|(key, value)| (to_nibbles(key), value.clone()))
+        .collect::<Vec<_>>();
+    let key_nibbles = key.map(to_nibbles);
+    encode_nibble_node(&nibble_items, depth, key_nibbles.as_deref(), proof)
+}
+
+fn encode_nibble_node(
+    items: &[(Vec<u8>, Vec<u8>)],
+    depth: usize,
+    key: Option<&[u8]>,
+    proof: &mut Vec<Vec<u8>>,
+) -> RlpItem {
+    if items.is_empty() {
+        return RlpItem::Bytes(Vec::new());
+    }
+    let slot = proof.len();
+    if key.is_some() {
+        proof.push(Vec::new());
+    }
+    let node = if items.len() == 1 {
+        let (item_key, value) = &items[0];
+        RlpItem::List(vec![
+            RlpItem::Bytes(hex_prefix(&item_key[depth..], true)),
+            RlpItem::Bytes(value.clone()),
+        ])
+    } else {
+        let first_key = &items[0].0;
+        let mut prefix_len = 0;
+        while depth + prefix_len < first_key.len()
+            && items.iter().all(|(item_key, _)| {
+                item_key.len() > depth + prefix_len
+                    && item_key[depth + prefix_len] == first_key[depth + prefix_len]
+            })
+        {
+            prefix_len += 1;
+        }
+        if prefix_len > 0 {
+            let child_key = key.filter(|key| {
+                key.len() >= depth + prefix_len
+                    && key[depth..depth + prefix_len] == first_key[depth..depth + prefix_len]
+            });
+            let child = encode_nibble_node(items, depth + prefix_len, child_key, proof);
+            RlpItem::List(vec![
+                RlpItem::Bytes(hex_prefix(&first_key[depth..depth + prefix_len], false)),
+                node_ref(child),
+            ])
+        } else {
+            let mut fields = Vec::with_capacity(17);
+            for nibble in 0..16u8 {
+                let children = items
+                    .iter()
+                    .filter(|(item_key, _)| item_key.len() > depth && item_key[depth] == nibble)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                let child_key = key.filter(|key| key.len() > depth && key[depth] == nibble);
+                let child = encode_nibble_node(&children, depth + 1, child_key, proof);
+                fields.push(node_ref(child));
+            }
+            let value = items
+                .iter()
+                .find(|(item_key, _)| item_key.len() == depth)
+                .map(|(_, value)| value.clone())
+                .unwrap_or_default();
+            fields.push(RlpItem::Bytes(value));
+            RlpItem::List(fields)
+        }
+    };
+    if key.is_some() {
+        let encoded = rlp::encode(&node);
+        if encoded.len() >= 32 || depth == 0 {
+            proof[slot] = encoded;
+        } else {
+            // Embedded in the parent node, so are all its children
+            proof.truncate(slot);
+        }
+    }
+    node
+}
+
+/// Reference a child node: embed it if its encoding is shorter than 32 bytes, otherwise by hash
+fn node_ref(node: RlpItem) -> RlpItem {
+    if let RlpItem::Bytes(ref bytes) = node {
+        if bytes.is_empty() {
+            return node;
+        }
+    }
+    let encoded = rlp::encode(&node);
+    if encoded.len() < 32 {
+        node
+    } else {
+        RlpItem::Bytes(keccak256(&encoded).to_vec())
+    }
+}
+
+/// Walk the proof nodes from `root` along `key`, return the value
+pub fn verify_proof(root: &H256, key: &[u8], nodes: &[Vec<u8>]) -> Result<Vec<u8>, String> {
+    let key = to_nibbles(key);
+    let mut depth = 0;
+    let mut expected_hash = root.as_bytes().to_vec();
+    let mut nodes = nodes.iter();
+    loop {
+        let encoded = nodes
+            .next()
+            .ok_or_else(|| "Proof is incomplete".to_string())?;
+        if keccak256(encoded)[..] != expected_hash[..] {
+            return Err(format!(
+                "Proof node hash mismatch at depth {}, expected 0x{}",
+                depth,
+                faster_hex::hex_string(&expected_hash).expect("hex")
+            ));
+        }
+        let mut node = rlp::decode(encoded)?;
+        // Follow the embedded nodes until a hash reference or the value
+        loop {
+            let fields = node.as_list()?.to_vec();
+            let next = match fields.len() {
+                2 => {
+                    let (path, is_leaf) = decode_hex_prefix(fields[0].as_bytes()?)?;
+                    if key.len() < depth + path.len() || key[depth..depth + path.len()] != path[..]
+                    {
+                        return Err("Key not found in proof".to_string());
+                    }
+                    depth += path.len();
+                    if is_leaf {
+                        if depth != key.len() {
+                            return Err("Key not found in proof".to_string());
+                        }
+                        return Ok(fields[1].as_bytes()?.to_vec());
+                    }
+                    fields[1].clone()
+                }
+                17 => {
+                    if depth == key.len() {
+                        return Ok(fields[16].as_bytes()?.to_vec());
+                    }
+                    let child = fields[key[depth] as usize].clone();
+                    depth += 1;
+                    child
+                }
+                len => return Err(format!("Invalid trie node with {} fields", len)),
+            };
+            match next {
+                RlpItem::List(_) => node = next,
+                RlpItem::Bytes(hash) if hash.len() == 32 => {
+                    expected_hash = hash;
+                    break;
+                }
+                RlpItem::Bytes(_) => return Err("Key not found in proof".to_string()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subcommands::ckb_bridge::abi;
+    use ckb_types::{h160, h256};
+
+    #[test]
+    fn test_trie_root() {
+        let items = vec![
+            (b"doe".to_vec(), b"reindeer".to_vec()),
+            (b"dog".to_vec(), b"puppy".to_vec()),
+            (b"dogglesworth".to_vec(), b"cat".to_vec()),
+        ];
+        let root = encode_node(&items, 0, None, &mut Vec::new());
+        assert_eq!(
+            H256::from(keccak256(&rlp::encode(&root))),
+            h256!("0x8aad789dff2f538bca5d8ea56e8abe10f4c7ba3a5dea95fea4cd6e7c3a1168d3")
+        );
+        for (key, value) in &items {
+            let mut nodes = Vec::new();
+            let root = encode_node(&items, 0, Some(key), &mut nodes);
+            let root_hash = H256::from(keccak256(&rlp::encode(&root)));
+            assert_eq!(&verify_proof(&root_hash, key, &nodes).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_lock_event() {
+        let contract = h160!("0x2222222222222222222222222222222222222222");
+        let token = h160!("0x1111111111111111111111111111111111111111");
+        let bridge_abi = abi::ContractAbi::parse(abi::DEFAULT_BRIDGE_ABI).unwrap();
+        let locked = bridge_abi.event("Locked").unwrap();
+        let address_topic = |address: &H160| {
+            let mut topic = [0u8; 32];
+            topic[12..].copy_from_slice(address.as_bytes());
+            H256::from(topic)
+        };
+        let receipt = DecodedReceipt {
+            tx_type: None,
+            status: Some(1),
+            cumulative_gas_used: 21000,
+            logs: vec![DecodedLog {
+                address: contract.clone(),
+                topics: vec![
+                    locked.topic(),
+                    address_topic(&token),
+                    address_topic(&contract),
+                ],
+                data: JsonBytes::from_vec(abi::encode(&[
+                    Token::Uint(1000),
+                    Token::Bytes(vec![1, 2, 3]),
+                ])),
+            }],
+        };
+        let event = LockEvent::from_receipt(&receipt, &contract, locked).unwrap();
+        assert_eq!(event.token, token);
+        assert_eq!(event.sender, contract);
+        assert_eq!(event.amount, 1000);
+        assert_eq!(event.recipient_lockscript, vec![1, 2, 3]);
+        let err = LockEvent::from_receipt(&receipt, &token, locked).unwrap_err();
+        assert!(err.contains("wrong bridge contract"));
+        // The logs of a receipt from the node decode the same
+        let logs = receipt
+            .logs
+            .iter()
+            .map(|log| {
+                DecodedLog::from(EthLog {
+                    address: log.address.clone(),
+                    topics: log.topics.clone(),
+                    data: log.data.clone(),
+                })
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(LockEvent::from_logs(&logs, &contract, locked), Ok(event));
+        let err = LockEvent::from_logs(&logs[..0], &contract, locked).unwrap_err();
+        assert!(err.contains("not a lock transaction"));
+
+        let signature = &locked.signature;
+        assert!(check_lock_event_abi(&receipt.logs, &contract, signature, locked).is_ok());
+        let err = check_lock_event_abi(&receipt.logs, &token, signature, locked).unwrap_err();
+        assert!(err.contains("not the configured bridge contract"));
+        let err = check_lock_event_abi(&receipt.logs, &contract, "Locked(address,uint256)", locked)
+            .unwrap_err();
+        assert!(err.contains("ABI mismatch"));
+    }
+
+    #[test]
+    fn test_receipt_proof() {
+        let receipts = (0..200u64)
+            .map(|index| {
+                rlp::encode(&RlpItem::List(vec![
+                    RlpItem::from_u64(1),
+                    RlpItem::from_u64(21000 * (index + 1)),
+                    RlpItem::Bytes(vec![0u8; 256]),
+                    RlpItem::List(Vec::new()),
+                ]))
+            })
+            .collect::<Vec<_>>();
+        for index in &[0u64, 1, 127, 128, 199] {
+            let proof = ReceiptProof::build(1, H256::default(), &receipts, *index).unwrap();
+            proof.verify().unwrap();
+            let decoded = ReceiptProof::from_bytes(&proof.to_bytes()).unwrap();
+            assert_eq!(decoded, proof);
+            let receipt = decode_receipt(&decoded.receipt).unwrap();
+            assert_eq!(receipt.status, Some(1));
+            assert_eq!(receipt.cumulative_gas_used, 21000 * (index + 1));
+        }
+
+        let mut proof = ReceiptProof::build(1, H256::default(), &receipts, 5).unwrap();
+        proof.receipt_index = 6;
+        assert!(proof.verify().is_err());
+    }
+
+    /// Proves nothing, the receipt is taken as is
+    struct MockBackend;
+
+    impl ProofGenerator for MockBackend {
+        fn generate(
+            &self,
+            block_number: u64,
+            block_hash: H256,
+            receipts_root: &H256,
+            receipts: &[Vec<u8>],
+            receipt_index: u64,
+        ) -> Result<ReceiptProof, String> {
+            Ok(ReceiptProof {
+                block_number,
+                block_hash,
+                receipts_root: receipts_root.clone(),
+                receipt_index,
+                receipt: receipts[receipt_index as usize].clone(),
+                nodes: Vec::new(),
+            })
+        }
+    }
+
+    impl ProofVerifier for MockBackend {
+        fn verify(&self, proof: &ReceiptProof) -> Result<(), String> {
+            if proof.nodes.is_empty() {
+                Ok(())
+            } else {
+                Err("Mock proof has nodes".to_string())
+            }
+        }
+    }
+
+    impl ProofBackend for MockBackend {
+        fn name(&self) -> &'static str {
+            "mock"
+        }
+    }
+
+    #[test]
+    fn test_proof_backend() {
+        let receipts = vec![vec![1u8; 40], vec![2u8; 40]];
+        let root = ReceiptProof::build(1, H256::default(), &receipts, 1)
+            .unwrap()
+            .receipts_root;
+        let default = proof_backend(None).unwrap();
+        assert_eq!(default.name(), RECEIPT_MPT_BACKEND);
+        assert!(proof_backend(Some("zk")).is_err());
+
+        let mock: Box<dyn ProofBackend> = Box::new(MockBackend);
+        for backend in &[default, mock] {
+            let proof = backend
+                .generate(1, H256::default(), &root, &receipts, 1)
+                .unwrap();
+            assert_eq!(proof.receipt, receipts[1]);
+            backend.verify(&proof).unwrap();
+        }
+        // The built root must be the one in the header
+        let err = proof_backend(None)
+            .unwrap()
+            .generate(1, H256::default(), &H256::default(), &receipts, 1)
+            .unwrap_err();
+        assert!(err.contains("Receipts root mismatch"));
+        let proof = ReceiptProof::build(1, H256::default(), &receipts, 1).unwrap();
+        assert!(MockBackend.verify(&proof).is_err());
+    }
+}
@@ -0,0 +1,176 @@
+//! Correct the status of transfer logs from the on-chain state of their recorded transactions
+
+use ckb_types::{H160, H256};
+use serde_derive::{Deserialize, Serialize};
+
+use super::log::{ToCkbLog, ToCkbLogStatus};
+
+/// On-chain state of the transactions recorded in a to-ckb transfer log, `None` means the
+/// transaction is not recorded or its state can't be decided yet (e.g. not mined).
+#[derive(Clone, Debug, Default)]
+pub struct ToCkbFacts {
+    /// Receipt status of the approve transaction
+    pub approve_success: Option<bool>,
+    /// Receipt status of the lock transaction
+    pub lock_success: Option<bool>,
+    /// `Some(true)` if the mint transaction is committed, `Some(false)` if the ckb node does
+    /// not know it at all
+    pub mint_committed: Option<bool>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Correction {
+    pub transfer_id: String,
+    pub from: String,
+    pub to: String,
+    pub reason: String,
+}
+
+/// Correct the log according to the facts, return the correction made (if any)
+pub fn reconcile_to_ckb(log: &mut ToCkbLog, facts: &ToCkbFacts) -> Option<Correction> {
+    let from = log.status;
+    let reason = if facts.mint_committed == Some(true) {
+        if from == ToCkbLogStatus::Finished {
+            return None;
+        }
+        log.set_status(ToCkbLogStatus::Finished);
+        "mint transaction is committed"
+    } else if facts.lock_success == Some(false) {
+        // Approved with a failed lock_tx would fail again on resume
+        log.lock_tx = None;
+        log.clear_proof();
+        log.mint_tx = None;
+        log.set_status(ToCkbLogStatus::Approved);
+        "lock transaction failed"
+    } else if facts.lock_success == Some(true) && from < ToCkbLogStatus::Locked {
+        log.set_status(ToCkbLogStatus::Locked);
+        "lock transaction succeeded"
+    } else if facts.mint_committed == Some(false) && from == ToCkbLogStatus::Mint {
+        log.mint_tx = None;
+        log.set_status(ToCkbLogStatus::WaitBlockSafe);
+        "mint transaction is unknown to the ckb node"
+    } else if facts.approve_success == Some(true) && from == ToCkbLogStatus::UnKnow {
+        log.set_status(ToCkbLogStatus::Approved);
+        "approve transaction succeeded"
+    } else {
+        return None;
+    };
+    Some(Correction {
+        transfer_id: log.id.clone(),
+        from: format!("{:?}", from),
+        to: format!("{:?}", log.status),
+        reason: reason.to_string(),
+    })
+}
+
+/// State of an ethereum transaction recorded in a transfer log
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EthTxState {
+    /// Known by the node but not mined
+    Pending,
+    Mined,
+    Reverted,
+    /// Unknown to the node: dropped from the mempool or replaced
+    Dropped,
+    /// Written by `--eth-build-only`, waiting to be signed and sent
+    Unsigned,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedEthTx {
+    pub transfer_id: String,
+    /// "approve" or "lock"
+    pub step: String,
+    /// `None` if unsigned
+    pub tx_hash: Option<H256>,
+    /// `None` if the node doesn't know the transaction
+    pub from: Option<H160>,
+    pub nonce: Option<u64>,
+    pub state: EthTxState,
+}
+
+/// The nonces from the confirmed nonce (the next to be mined) to the highest pending nonce of
+/// a sender not taken by any pending transaction, the transactions after a gap never mine
+pub fn nonce_gaps(confirmed: u64, pending: &[u64]) -> Vec<u64> {
+    let max = match pending.iter().max() {
+        Some(max) => *max,
+        None => return Vec::new(),
+    };
+    (confirmed..max)
+        .filter(|nonce| !pending.contains(nonce))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_types::{h160, h256};
+
+    fn new_log(status: ToCkbLogStatus) -> ToCkbLog {
+        let mut log = ToCkbLog::new(
+            "id".to_string(),
+            h160!("0x1111111111111111111111111111111111111111"),
+            100,
+            None,
+            Default::default(),
+        );
+        log.status = status;
+        log
+    }
+
+    #[test]
+    fn test_reconcile_mint_committed() {
+        let mut log = new_log(ToCkbLogStatus::WaitBlockSafe);
+        let facts = ToCkbFacts {
+            mint_committed: Some(true),
+            ..Default::default()
+        };
+        let correction = reconcile_to_ckb(&mut log, &facts).unwrap();
+        assert_eq!(correction.from, "WaitBlockSafe");
+        assert_eq!(log.status, ToCkbLogStatus::Finished);
+        assert!(reconcile_to_ckb(&mut log, &facts).is_none());
+    }
+
+    #[test]
+    fn test_reconcile_lock() {
+        let mut log = new_log(ToCkbLogStatus::Approved);
+        let facts = ToCkbFacts {
+            lock_success: Some(true),
+            ..Default::default()
+        };
+        reconcile_to_ckb(&mut log, &facts).unwrap();
+        assert_eq!(log.status, ToCkbLogStatus::Locked);
+
+        let mut log = new_log(ToCkbLogStatus::ParseProof);
+        log.lock_tx = Some(h256!("0x1"));
+        let facts = ToCkbFacts {
+            lock_success: Some(false),
+            ..Default::default()
+        };
+        reconcile_to_ckb(&mut log, &facts).unwrap();
+        assert_eq!(log.status, ToCkbLogStatus::Approved);
+        assert!(log.lock_tx.is_none());
+    }
+
+    #[test]
+    fn test_reconcile_nothing() {
+        let mut log = new_log(ToCkbLogStatus::Mint);
+        let facts = ToCkbFacts {
+            approve_success: Some(true),
+            lock_success: Some(true),
+            mint_committed: None,
+        };
+        assert!(reconcile_to_ckb(&mut log, &facts).is_none());
+        assert_eq!(log.status, ToCkbLogStatus::Mint);
+    }
+
+    #[test]
+    fn test_nonce_gaps() {
+        assert!(nonce_gaps(5, &[]).is_empty());
+        assert!(nonce_gaps(5, &[5, 6, 7]).is_empty());
+        assert_eq!(nonce_gaps(5, &[8, 6]), vec![5, 7]);
+        // Mined already, nothing pending after the confirmed nonce
+        assert!(nonce_gaps(5, &[3]).is_empty());
+    }
+}
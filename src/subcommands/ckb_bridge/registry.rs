@@ -0,0 +1,255 @@
+//! The canonical deployment published by an on-chain registry contract (`--registry-address`),
+//! it overrides or fills the config for the run. The registry has the getters
+//! `bridgeContract() returns (address)`, `tokenCount() returns (uint256)`,
+//! `tokenAt(uint256) returns (address)` and `lightClientTypeScript() returns (bytes)` (the
+//! molecule encoded type script of the light client cell), a getter returning nothing or zero
+//! publishes nothing.
+
+use ckb_jsonrpc_types::Script;
+use ckb_types::{packed, prelude::*, H160, H256};
+
+use super::abi::{self, Token};
+use super::config::{BridgeConfig, LightClientConfig, TokenConfig};
+use super::eth::{checksum_address, EthRpcClient};
+
+// More tokens than this is not a plausible registry
+const MAX_REGISTRY_TOKENS: u128 = 1024;
+
+/// The deployment resolved from the registry once per run
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Registry {
+    pub address: H160,
+    pub bridge_contract: Option<H160>,
+    pub tokens: Vec<H160>,
+    pub light_client_type_script: Option<Script>,
+}
+
+impl Registry {
+    /// Call the getters of the registry at `address`, the contracts it publishes must have code
+    pub fn resolve(eth_client: &mut EthRpcClient, address: &H160) -> Result<Registry, String> {
+        if eth_client.get_code(address)?.as_bytes().is_empty() {
+            return Err(format!(
+                "No registry contract code at {}",
+                checksum_address(address)
+            ));
+        }
+        let mut call = |signature: &str, tokens: &[Token]| -> Result<Vec<u8>, String> {
+            eth_client
+                .call(
+                    &H160::default(),
+                    address,
+                    &abi::encode_call(signature, tokens),
+                )
+                .map(|result| result.as_bytes().to_vec())
+                .map_err(|err| format!("Call registry {} error: {}", signature, err))
+        };
+        let bridge_contract = decode_optional_address(&call("bridgeContract()", &[])?)?;
+        let count = match call("tokenCount()", &[])? {
+            data if data.is_empty() => 0,
+            data => abi::decode_uint(&data, 0)?,
+        };
+        if count > MAX_REGISTRY_TOKENS {
+            return Err(format!(
+                "The registry {} lists {} tokens, not a bridge registry?",
+                checksum_address(address),
+                count
+            ));
+        }
+        let mut tokens = Vec::new();
+        for index in 0..count {
+            let data = call("tokenAt(uint256)", &[Token::Uint(index)])?;
+            let token = decode_optional_address(&data)?
+                .ok_or_else(|| format!("The registry token {} is the zero address", index))?;
+            tokens.push(token);
+        }
+        let light_client_type_script = match call("lightClientTypeScript()", &[])? {
+            data if data.is_empty() => None,
+            data => decode_script(&abi::decode_bytes(&data, 0)?)?,
+        };
+        let registry = Registry {
+            address: address.clone(),
+            bridge_contract,
+            tokens,
+            light_client_type_script,
+        };
+        registry.check()?;
+        let contracts = registry
+            .bridge_contract
+            .iter()
+            .chain(registry.tokens.iter());
+        for contract in contracts {
+            if eth_client.get_code(contract)?.as_bytes().is_empty() {
+                return Err(format!(
+                    "The registry {} publishes {}, which has no contract code",
+                    checksum_address(address),
+                    checksum_address(contract)
+                ));
+            }
+        }
+        Ok(registry)
+    }
+
+    /// The published addresses are plausible: the tokens are distinct and none of them is
+    /// the bridge contract or the registry itself
+    pub fn check(&self) -> Result<(), String> {
+        let mut seen = vec![&self.address];
+        seen.extend(self.bridge_contract.as_ref());
+        for token in &self.tokens {
+            if seen.contains(&token) {
+                return Err(format!(
+                    "The registry {} publishes {} twice",
+                    checksum_address(&self.address),
+                    checksum_address(token)
+                ));
+            }
+            seen.push(token);
+        }
+        if self.bridge_contract.as_ref() == Some(&self.address) {
+            return Err(format!(
+                "The registry {} publishes itself as the bridge contract",
+                checksum_address(&self.address)
+            ));
+        }
+        Ok(())
+    }
+
+    /// Override the config by the published deployment, the tokens not in the config are
+    /// added with the default settings
+    pub fn apply(&self, config: &mut BridgeConfig) {
+        if let Some(contract) = self.bridge_contract.as_ref() {
+            config.bridge_contract = Some(contract.clone());
+        }
+        for token in &self.tokens {
+            config
+                .tokens
+                .entry(token.clone())
+                .or_insert_with(TokenConfig::default);
+        }
+        if let Some(type_script) = self.light_client_type_script.as_ref() {
+            match config.light_client.as_mut() {
+                Some(light_client) => {
+                    if &light_client.type_script != type_script {
+                        // The configured cell is of another light client
+                        light_client.out_point = None;
+                    }
+                    light_client.type_script = type_script.clone();
+                }
+                None => {
+                    config.light_client = Some(LightClientConfig {
+                        type_script: type_script.clone(),
+                        cell_deps: Vec::new(),
+                        out_point: None,
+                    })
+                }
+            }
+        }
+    }
+}
+
+/// An address return value, `None` if nothing or the zero address is returned
+fn decode_optional_address(data: &[u8]) -> Result<Option<H160>, String> {
+    if data.is_empty() {
+        return Ok(None);
+    }
+    let address = abi::decode_address(data, 0)?;
+    Ok(Some(address).filter(|address| *address != H160::default()))
+}
+
+/// A molecule encoded script, `None` if empty
+fn decode_script(data: &[u8]) -> Result<Option<Script>, String> {
+    if data.is_empty() {
+        return Ok(None);
+    }
+    let script = packed::Script::from_slice(data)
+        .map_err(|err| format!("Invalid light client type script in the registry: {}", err))?;
+    let code_hash: H256 = script.code_hash().unpack();
+    if code_hash == H256::default() {
+        return Err(
+            "The light client type script in the registry has a zero code hash".to_string(),
+        );
+    }
+    Ok(Some(script.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_types::{h160, h256};
+
+    fn registry() -> Registry {
+        Registry {
+            address: h160!("0x1111111111111111111111111111111111111111"),
+            bridge_contract: Some(h160!("0x2222222222222222222222222222222222222222")),
+            tokens: vec![h160!("0x3333333333333333333333333333333333333333")],
+            light_client_type_script: None,
+        }
+    }
+
+    #[test]
+    fn test_registry_check() {
+        assert!(registry().check().is_ok());
+        let mut duplicated = registry();
+        duplicated.tokens.push(duplicated.tokens[0].clone());
+        assert!(duplicated.check().is_err());
+        let mut itself = registry();
+        itself.tokens.push(itself.address.clone());
+        assert!(itself.check().is_err());
+        let mut bridge = registry();
+        bridge.bridge_contract = Some(bridge.address.clone());
+        assert!(bridge.check().is_err());
+    }
+
+    #[test]
+    fn test_registry_decode() {
+        assert_eq!(decode_optional_address(&[]), Ok(None));
+        assert_eq!(decode_optional_address(&[0u8; 32]), Ok(None));
+        let token = h160!("0x3333333333333333333333333333333333333333");
+        assert_eq!(
+            decode_optional_address(&abi::encode(&[Token::Address(token.clone())])),
+            Ok(Some(token))
+        );
+
+        let script = packed::Script::new_builder()
+            .code_hash(h256!("0x1234").pack())
+            .build();
+        assert_eq!(
+            decode_script(script.as_slice()),
+            Ok(Some(Script::from(script)))
+        );
+        assert!(decode_script(&[1, 2, 3]).is_err());
+        assert!(decode_script(packed::Script::default().as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_registry_apply() {
+        let mut config = BridgeConfig::default();
+        let mut registry = registry();
+        let type_script = Script::from(
+            packed::Script::new_builder()
+                .code_hash(h256!("0x1234").pack())
+                .build(),
+        );
+        registry.light_client_type_script = Some(type_script.clone());
+        registry.apply(&mut config);
+        assert_eq!(config.bridge_contract, registry.bridge_contract);
+        assert!(config.tokens.contains_key(&registry.tokens[0]));
+        assert_eq!(
+            config
+                .light_client
+                .map(|light_client| light_client.type_script),
+            Some(type_script)
+        );
+
+        // The configured token settings are kept
+        let mut config = BridgeConfig::default();
+        config.tokens.insert(
+            registry.tokens[0].clone(),
+            TokenConfig {
+                decimals: Some(6),
+                ..Default::default()
+            },
+        );
+        registry.apply(&mut config);
+        assert_eq!(config.tokens[&registry.tokens[0]].decimals, Some(6));
+    }
+}
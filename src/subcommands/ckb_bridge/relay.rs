@@ -0,0 +1,189 @@
+use std::fs;
+use std::path::Path;
+
+use ckb_index::LiveCellInfo;
+use ckb_types::{
+    bytes::Bytes,
+    core::{Capacity, TransactionBuilder, TransactionView},
+    packed::{CellDep, CellInput, CellOutput, OutPoint, WitnessArgs},
+    prelude::*,
+    H256,
+};
+use serde_derive::{Deserialize, Serialize};
+
+use super::eth::EthHeader;
+use super::exit_code::BridgeError;
+use super::rlp::{self, RlpItem};
+
+/// Size of the light client cell data: tip number (u64, little endian) + tip hash
+pub const LIGHT_CLIENT_DATA_SIZE: usize = 8 + 32;
+
+/// The latest ethereum header relayed to the light client cell
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LightClientTip {
+    pub number: u64,
+    pub hash: H256,
+}
+
+impl LightClientTip {
+    /// Empty data means the light client is not initialized yet
+    pub fn from_slice(data: &[u8]) -> Result<Option<LightClientTip>, String> {
+        if data.is_empty() {
+            return Ok(None);
+        }
+        if data.len() != LIGHT_CLIENT_DATA_SIZE {
+            return Err(format!(
+                "Invalid light client cell data length: {}, expected: {}",
+                data.len(),
+                LIGHT_CLIENT_DATA_SIZE
+            ));
+        }
+        let mut number_bytes = [0u8; 8];
+        number_bytes.copy_from_slice(&data[0..8]);
+        Ok(Some(LightClientTip {
+            number: u64::from_le_bytes(number_bytes),
+            hash: H256::from_slice(&data[8..]).expect("light client tip hash"),
+        }))
+    }
+
+    pub fn to_bytes(&self) -> Bytes {
+        let mut data = Vec::with_capacity(LIGHT_CLIENT_DATA_SIZE);
+        data.extend_from_slice(&self.number.to_le_bytes());
+        data.extend_from_slice(self.hash.as_bytes());
+        Bytes::from(data)
+    }
+}
+
+/// The last header relayed by `relay-header`, saved after every committed relay transaction so
+/// that a re-run without `--from-block` resumes after it. The light client tip on chain is
+/// still the source of truth, headers at or below it are never relayed again.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RelayCheckpoint {
+    pub number: u64,
+    pub hash: H256,
+    /// The relay transaction of the header
+    pub tx_hash: H256,
+    pub updated_at: u64,
+}
+
+impl RelayCheckpoint {
+    pub fn load(path: &Path) -> Result<Option<RelayCheckpoint>, String> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content =
+            fs::read_to_string(path).map_err(|err| format!("Read {:?} error: {}", path, err))?;
+        serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|err| format!("Parse relay checkpoint {:?} error: {}", path, err))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        let content = serde_json::to_string_pretty(self).map_err(|err| err.to_string())?;
+        // Write to a temp file then rename, so a crash never leaves a broken checkpoint
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, content).map_err(|err| err.to_string())?;
+        fs::rename(&tmp_path, path).map_err(|err| err.to_string())
+    }
+}
+
+pub struct LightClientCell {
+    pub out_point: OutPoint,
+    pub output: CellOutput,
+    pub tip: Option<LightClientTip>,
+}
+
+/// Check the headers are continuous and follow the light client tip
+pub fn check_headers(tip: Option<&LightClientTip>, headers: &[EthHeader]) -> Result<(), String> {
+    let mut parent = tip.map(|tip| (tip.number, tip.hash.clone()));
+    for header in headers {
+        let number = header.number()?;
+        if let Some((parent_number, parent_hash)) = parent {
+            if number != parent_number + 1 || header.parent_hash != parent_hash {
+                return Err(format!(
+                    "Ethereum header {} ({:#x}) is not a child of {} ({:#x}), maybe a reorg happened",
+                    number, header.hash, parent_number, parent_hash
+                ));
+            }
+        }
+        parent = Some((number, header.hash.clone()));
+    }
+    Ok(())
+}
+
+/// The first header to relay up to `to_block`, it is the child of the light client tip (the
+/// only header accepted): headers at or below the tip are present already and a gap between
+/// the tip and `from_block` is relayed first. `None` if the light client is up to date.
+pub fn relay_start(tip: Option<u64>, from_block: u64, to_block: u64) -> Option<u64> {
+    let start = tip.map(|tip| tip + 1).unwrap_or(from_block);
+    if start > to_block {
+        None
+    } else {
+        Some(start)
+    }
+}
+
+/// The missing headers between the light client tip and `from_block`
+pub fn gap_range(tip: Option<u64>, from_block: u64) -> Option<(u64, u64)> {
+    tip.filter(|tip| from_block > tip + 1)
+        .map(|tip| (tip + 1, from_block - 1))
+}
+
+/// Build the (unsigned) relay transaction:
+///   * input 0 / output 0: the light client cell, headers are put in witness 0 (input_type)
+///   * other inputs: sighash cells to pay the transaction fee
+///   * output 1 (optional): change cell
+pub fn build_relay_tx(
+    light_client: &LightClientCell,
+    headers: &[EthHeader],
+    cell_deps: Vec<CellDep>,
+    fee_cells: &[LiveCellInfo],
+    change_output: Option<CellOutput>,
+) -> Result<TransactionView, String> {
+    let last = headers
+        .last()
+        .ok_or_else(|| "No header to relay".to_string())?;
+    let new_tip = LightClientTip {
+        number: last.number()?,
+        hash: last.hash.clone(),
+    };
+    let headers_rlp = headers
+        .iter()
+        .map(|header| header.rlp_bytes().map(RlpItem::Bytes))
+        .collect::<Result<Vec<_>, String>>()?;
+    let witness = WitnessArgs::new_builder()
+        .input_type(Some(Bytes::from(rlp::encode(&RlpItem::List(headers_rlp)))).pack())
+        .build();
+
+    let mut builder = TransactionBuilder::default()
+        .cell_deps(cell_deps)
+        .input(CellInput::new(light_client.out_point.clone(), 0))
+        .inputs(fee_cells.iter().map(LiveCellInfo::input))
+        .output(light_client.output.clone())
+        .output_data(new_tip.to_bytes().pack())
+        .witness(witness.as_bytes().pack());
+    if let Some(change_output) = change_output {
+        builder = builder
+            .output(change_output)
+            .output_data(Bytes::new().pack());
+    }
+    Ok(builder.build())
+}
+
+/// The capacity of fee cells after paying the transaction fee
+pub fn change_capacity(fee_cells: &[LiveCellInfo], tx_fee: u64) -> Result<Capacity, String> {
+    let total = fee_cells.iter().map(|cell| cell.capacity).sum::<u64>();
+    total
+        .checked_sub(tx_fee)
+        .map(Capacity::shannons)
+        .ok_or_else(|| {
+            BridgeError::insufficient_funds(format!(
+                "Capacity not enough to pay tx fee: {} < {}",
+                total, tx_fee
+            ))
+            .into()
+        })
+}
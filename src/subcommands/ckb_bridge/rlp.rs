@@ -0,0 +1,183 @@
+//! Minimal RLP codec, only cover what the bridge needs (ethereum headers, receipts and proofs)
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RlpItem {
+    Bytes(Vec<u8>),
+    List(Vec<RlpItem>),
+}
+
+impl RlpItem {
+    /// Encode an unsigned integer as big endian bytes without leading zeros
+    pub fn from_u64(value: u64) -> RlpItem {
+        RlpItem::Bytes(strip_leading_zeros(&value.to_be_bytes()).to_vec())
+    }
+
+    pub fn as_bytes(&self) -> Result<&[u8], String> {
+        match self {
+            RlpItem::Bytes(bytes) => Ok(bytes),
+            RlpItem::List(_) => Err("Expect rlp bytes, got list".to_string()),
+        }
+    }
+
+    pub fn as_list(&self) -> Result<&[RlpItem], String> {
+        match self {
+            RlpItem::List(items) => Ok(items),
+            RlpItem::Bytes(_) => Err("Expect rlp list, got bytes".to_string()),
+        }
+    }
+
+    pub fn as_u64(&self) -> Result<u64, String> {
+        let bytes = self.as_bytes()?;
+        if bytes.len() > 8 {
+            return Err(format!("Rlp integer overflow u64: {} bytes", bytes.len()));
+        }
+        Ok(bytes
+            .iter()
+            .fold(0u64, |value, byte| (value << 8) | u64::from(*byte)))
+    }
+}
+
+pub fn strip_leading_zeros(bytes: &[u8]) -> &[u8] {
+    let start = bytes
+        .iter()
+        .position(|byte| *byte != 0)
+        .unwrap_or_else(|| bytes.len());
+    &bytes[start..]
+}
+
+pub fn encode(item: &RlpItem) -> Vec<u8> {
+    match item {
+        RlpItem::Bytes(bytes) => {
+            if bytes.len() == 1 && bytes[0] < 0x80 {
+                bytes.clone()
+            } else {
+                let mut data = encode_length(bytes.len(), 0x80);
+                data.extend_from_slice(bytes);
+                data
+            }
+        }
+        RlpItem::List(items) => {
+            let payload = items.iter().flat_map(encode).collect::<Vec<_>>();
+            let mut data = encode_length(payload.len(), 0xc0);
+            data.extend_from_slice(&payload);
+            data
+        }
+    }
+}
+
+fn encode_length(length: usize, offset: u8) -> Vec<u8> {
+    if length < 56 {
+        vec![offset + length as u8]
+    } else {
+        let length_bytes = strip_leading_zeros(&(length as u64).to_be_bytes()).to_vec();
+        let mut data = vec![offset + 55 + length_bytes.len() as u8];
+        data.extend_from_slice(&length_bytes);
+        data
+    }
+}
+
+/// Decode exactly one item, trailing bytes are treated as error
+pub fn decode(data: &[u8]) -> Result<RlpItem, String> {
+    let (item, consumed) = decode_item(data)?;
+    if consumed != data.len() {
+        return Err(format!(
+            "Invalid rlp data: {} trailing bytes",
+            data.len() - consumed
+        ));
+    }
+    Ok(item)
+}
+
+fn decode_item(data: &[u8]) -> Result<(RlpItem, usize), String> {
+    let prefix = *data
+        .first()
+        .ok_or_else(|| "Invalid rlp data: empty".to_string())?;
+    match prefix {
+        0x00..=0x7f => Ok((RlpItem::Bytes(vec![prefix]), 1)),
+        0x80..=0xbf => {
+            let (offset, length) = decode_length(data, 0x80)?;
+            let bytes = slice(data, offset, length)?.to_vec();
+            Ok((RlpItem::Bytes(bytes), offset + length))
+        }
+        _ => {
+            let (offset, length) = decode_length(data, 0xc0)?;
+            let mut payload = slice(data, offset, length)?;
+            let mut items = Vec::new();
+            while !payload.is_empty() {
+                let (item, consumed) = decode_item(payload)?;
+                items.push(item);
+                payload = &payload[consumed..];
+            }
+            Ok((RlpItem::List(items), offset + length))
+        }
+    }
+}
+
+fn decode_length(data: &[u8], offset: u8) -> Result<(usize, usize), String> {
+    let prefix = data[0] - offset;
+    if prefix < 56 {
+        Ok((1, prefix as usize))
+    } else {
+        let length_size = (prefix - 55) as usize;
+        if length_size > 8 {
+            return Err(format!("Invalid rlp length size: {}", length_size));
+        }
+        let length = slice(data, 1, length_size)?
+            .iter()
+            .fold(0u64, |value, byte| (value << 8) | u64::from(*byte));
+        Ok((1 + length_size, length as usize))
+    }
+}
+
+fn slice(data: &[u8], offset: usize, length: usize) -> Result<&[u8], String> {
+    data.get(offset..offset + length).ok_or_else(|| {
+        format!(
+            "Invalid rlp data: expect {} bytes, got {}",
+            offset + length,
+            data.len()
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        let cases = vec![
+            (RlpItem::Bytes(vec![]), vec![0x80]),
+            (RlpItem::Bytes(vec![0x0f]), vec![0x0f]),
+            (
+                RlpItem::Bytes(b"dog".to_vec()),
+                vec![0x83, b'd', b'o', b'g'],
+            ),
+            (RlpItem::List(vec![]), vec![0xc0]),
+            (RlpItem::from_u64(1024), vec![0x82, 0x04, 0x00]),
+            (
+                RlpItem::List(vec![
+                    RlpItem::Bytes(b"cat".to_vec()),
+                    RlpItem::Bytes(b"dog".to_vec()),
+                ]),
+                vec![0xc8, 0x83, b'c', b'a', b't', 0x83, b'd', b'o', b'g'],
+            ),
+        ];
+        for (item, expected) in cases {
+            assert_eq!(encode(&item), expected);
+            assert_eq!(decode(&expected).unwrap(), item);
+        }
+
+        let long = RlpItem::Bytes(vec![0xaa; 60]);
+        let data = encode(&long);
+        assert_eq!(&data[0..2], &[0xb8, 60]);
+        assert_eq!(decode(&data).unwrap(), long);
+        assert_eq!(RlpItem::from_u64(1024).as_u64().unwrap(), 1024);
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        assert!(decode(&[]).is_err());
+        assert!(decode(&[0x83, b'd', b'o']).is_err());
+        assert!(decode(&[0x0f, 0x0f]).is_err());
+    }
+}
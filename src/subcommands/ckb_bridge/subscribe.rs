@@ -0,0 +1,201 @@
+//! Ethereum JSON-RPC over websocket (`ws://` or `wss://` rpc url): the requests of
+//! `EthRpcClient` and the `newHeads` subscription used to wake up waiting loops early
+
+use std::cmp;
+use std::io;
+use std::time::{Duration, Instant};
+
+use tungstenite::{client::AutoStream, stream::Stream, Message, WebSocket};
+
+use super::eth::parse_quantity;
+use crate::utils::poll::{poll_until, Poll, CANCELLED};
+
+// Max time of the `eth_subscribe` request (sent and answered)
+const SUBSCRIBE_TIMEOUT: Duration = Duration::from_secs(10);
+// A read of the socket blocks at most this long, the cancellation is checked between reads
+const READ_SLICE: Duration = Duration::from_millis(500);
+// The sleep between two reads, or between two checks once the subscription failed
+const READ_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A lazily connected `newHeads` subscription. If the socket drops, it is reconnected (and
+/// subscribed again) by the next wait. The socket is closed when dropped.
+pub struct NewHeadsSubscription {
+    url: String,
+    socket: Option<WebSocket<AutoStream>>,
+}
+
+impl NewHeadsSubscription {
+    pub fn new(url: String) -> NewHeadsSubscription {
+        NewHeadsSubscription { url, socket: None }
+    }
+
+    /// Wait until a new head arrives, the timeout elapsed or the waiting is cancelled, return
+    /// the number of the new head. Socket failures are only logged, the wait then degrades to
+    /// a plain sleep.
+    pub fn wait_new_head(&mut self, timeout: Duration) -> Option<u64> {
+        let start = Instant::now();
+        let mut subscribe_failed = false;
+        let result = poll_until(
+            || {
+                if subscribe_failed {
+                    return Ok(Poll::Pending);
+                }
+                let remaining = timeout.checked_sub(start.elapsed()).unwrap_or_default();
+                if remaining == Duration::from_millis(0) {
+                    return Ok(Poll::TimedOut);
+                }
+                if self.socket.is_none() {
+                    match subscribe(&self.url) {
+                        Ok(socket) => self.socket = Some(socket),
+                        Err(err) => {
+                            log::warn!("Subscribe new heads from {} failed: {}", self.url, err);
+                            subscribe_failed = true;
+                            return Ok(Poll::Pending);
+                        }
+                    }
+                }
+                Ok(match self.read_new_head(cmp::min(remaining, READ_SLICE)) {
+                    Some(number) => Poll::Ready(number),
+                    None => Poll::Pending,
+                })
+            },
+            READ_INTERVAL,
+            Some(timeout),
+            &CANCELLED,
+        );
+        match result {
+            Ok(Poll::Ready(number)) => Some(number),
+            _ => None,
+        }
+    }
+
+    /// Read the socket for at most `timeout`, return the number of a new head if one arrived.
+    /// A dropped socket is reconnected by the next read.
+    fn read_new_head(&mut self, timeout: Duration) -> Option<u64> {
+        let socket = self.socket.as_mut()?;
+        let result = set_read_timeout(socket.get_ref(), timeout)
+            .map_err(tungstenite::Error::Io)
+            .and_then(|()| socket.read_message());
+        match result {
+            Ok(Message::Text(text)) => parse_new_head(&text),
+            // Ping/pong are handled by tungstenite
+            Ok(_) => None,
+            Err(ref err) if is_timeout(err) => None,
+            Err(err) => {
+                log::warn!(
+                    "New heads subscription to {} dropped, reconnect: {}",
+                    self.url,
+                    err
+                );
+                self.socket = None;
+                None
+            }
+        }
+    }
+}
+
+impl Drop for NewHeadsSubscription {
+    fn drop(&mut self) {
+        if let Some(mut socket) = self.socket.take() {
+            let _ = socket.close(None);
+        }
+    }
+}
+
+/// Whether a websocket url (`ws://` or `wss://`), the other urls are requested over http
+pub fn is_ws_url(url: &str) -> bool {
+    let url = url.to_lowercase();
+    url.starts_with("ws://") || url.starts_with("wss://")
+}
+
+pub fn connect(url: &str) -> Result<WebSocket<AutoStream>, tungstenite::Error> {
+    tungstenite::connect(url).map(|(socket, _)| socket)
+}
+
+/// Send a JSON-RPC request and read until its response (by id), the messages in between (e.g.
+/// subscription notifications) are skipped. The whole request takes at most `timeout`.
+pub fn request(
+    socket: &mut WebSocket<AutoStream>,
+    req_json: &serde_json::Value,
+    timeout: Duration,
+) -> Result<serde_json::Value, tungstenite::Error> {
+    let start = Instant::now();
+    socket.write_message(Message::Text(req_json.to_string()))?;
+    loop {
+        let remaining = timeout.checked_sub(start.elapsed()).unwrap_or_default();
+        if remaining == Duration::from_millis(0) {
+            return Err(tungstenite::Error::Io(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("no response in {}s", timeout.as_secs()),
+            )));
+        }
+        set_read_timeout(socket.get_ref(), remaining)?;
+        if let Message::Text(text) = socket.read_message()? {
+            let output: serde_json::Value = match serde_json::from_str(&text) {
+                Ok(output) => output,
+                Err(_) => continue,
+            };
+            if output["id"] == req_json["id"] {
+                return Ok(output);
+            }
+        }
+    }
+}
+
+/// Whether a websocket error is a read timed out
+pub fn is_timeout(err: &tungstenite::Error) -> bool {
+    match err {
+        tungstenite::Error::Io(err) => {
+            err.kind() == io::ErrorKind::WouldBlock || err.kind() == io::ErrorKind::TimedOut
+        }
+        _ => false,
+    }
+}
+
+fn subscribe(url: &str) -> Result<WebSocket<AutoStream>, String> {
+    let mut socket = connect(url).map_err(|err| err.to_string())?;
+    let req_json = serde_json::json!({
+        "id": 1,
+        "jsonrpc": "2.0",
+        "method": "eth_subscribe",
+        "params": ["newHeads"],
+    });
+    let output =
+        request(&mut socket, &req_json, SUBSCRIBE_TIMEOUT).map_err(|err| err.to_string())?;
+    if let Some(error) = output.get("error") {
+        return Err(format!("eth_subscribe failed: {}", error));
+    }
+    Ok(socket)
+}
+
+fn set_read_timeout(stream: &AutoStream, timeout: Duration) -> io::Result<()> {
+    match stream {
+        Stream::Plain(stream) => stream.set_read_timeout(Some(timeout)),
+        Stream::Tls(stream) => stream.get_ref().set_read_timeout(Some(timeout)),
+    }
+}
+
+/// The block number of a `newHeads` notification, `None` for other messages
+fn parse_new_head(text: &str) -> Option<u64> {
+    let message: serde_json::Value = serde_json::from_str(text).ok()?;
+    if message["method"] != "eth_subscription" {
+        return None;
+    }
+    message["params"]["result"]["number"]
+        .as_str()
+        .and_then(|number| parse_quantity(number).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_new_head() {
+        let notification = r#"{"jsonrpc":"2.0","method":"eth_subscription","params":{"subscription":"0x9ce59a13059e417087c02d3236a0b1cc","result":{"hash":"0x7736fab79e05dc611604d22470dadad26f56fe494421b5b333de816ce1f25701","number":"0x1b4"}}}"#;
+        assert_eq!(parse_new_head(notification), Some(0x1b4));
+        let response = r#"{"jsonrpc":"2.0","id":1,"result":"0x9ce59a13059e417087c02d3236a0b1cc"}"#;
+        assert_eq!(parse_new_head(response), None);
+        assert_eq!(parse_new_head("not json"), None);
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use ckb_chain_spec::consensus::MAX_BLOCK_BYTES;
+use ckb_jsonrpc_types::Status;
+use ckb_sdk::{build_signature, HttpRpcClient};
+use ckb_types::{
+    core::TransactionView,
+    packed::{self, WitnessArgs},
+    prelude::*,
+    H160, H256,
+};
+
+use super::exit_code::BridgeError;
+use crate::utils::{
+    arg_parser::PrivkeyWrapper,
+    other::{check_lack_of_capacity, get_privkey_signer},
+    poll::{poll_until, Poll, PollInterval, CANCELLED},
+    printer::{OutputFormat, Printable},
+};
+
+/// Max total time a bridge rpc request (ckb or ethereum) waits for the `Retry-After` of the
+/// throttled responses, the rpc clients of the other commands never retry
+pub const MAX_THROTTLE_WAIT: Duration = Duration::from_secs(120);
+
+/// Sign the sighash inputs (all belong to the same lock arg) with the given private key,
+/// other inputs' witnesses are kept untouched.
+pub(crate) fn sign_sighash_inputs(
+    transaction: TransactionView,
+    privkey: PrivkeyWrapper,
+    account: H160,
+    input_idxs: &[usize],
+) -> Result<TransactionView, String> {
+    let input_size = transaction.inputs().len();
+    let mut witnesses: Vec<packed::Bytes> = transaction.witnesses().into_iter().collect();
+    while witnesses.len() < input_size {
+        witnesses.push(Default::default());
+    }
+    let mut signer = get_privkey_signer(privkey);
+    let accounts = vec![account].into_iter().collect::<HashSet<H160>>();
+    let signature = build_signature(
+        &transaction,
+        input_size,
+        input_idxs,
+        &witnesses,
+        None,
+        |message: &H256, tx: &ckb_jsonrpc_types::Transaction| {
+            signer(&accounts, message, tx)?.ok_or_else(|| "signer missed".to_string())
+        },
+    )?;
+    let init_witness = if witnesses[input_idxs[0]].raw_data().is_empty() {
+        WitnessArgs::default()
+    } else {
+        WitnessArgs::from_slice(witnesses[input_idxs[0]].raw_data().as_ref())
+            .map_err(|err| err.to_string())?
+    };
+    witnesses[input_idxs[0]] = init_witness
+        .as_builder()
+        .lock(Some(signature).pack())
+        .build()
+        .as_bytes()
+        .pack();
+    Ok(transaction
+        .as_advanced_builder()
+        .set_witnesses(witnesses)
+        .build())
+}
+
+pub(crate) fn send_transaction(
+    rpc_client: &mut HttpRpcClient,
+    transaction: &TransactionView,
+    debug: bool,
+) -> Result<H256, String> {
+    check_lack_of_capacity(transaction)?;
+    check_tx_size(transaction)?;
+    if debug {
+        let transaction_view: ckb_jsonrpc_types::TransactionView = transaction.clone().into();
+        eprintln!(
+            "[Send Transaction]:\n{}",
+            transaction_view.render(OutputFormat::Yaml, false)
+        );
+    }
+    rpc_client
+        .send_transaction(transaction.data())
+        .map_err(|err| format!("Send transaction error: {}", err))
+}
+
+/// A transaction larger than a block is rejected by the node with an opaque error, check it
+/// before sending
+pub(crate) fn check_tx_size(transaction: &TransactionView) -> Result<(), String> {
+    let size = transaction.data().serialized_size_in_block() as u64;
+    if size > MAX_BLOCK_BYTES {
+        let witness_size: usize = transaction
+            .witnesses()
+            .into_iter()
+            .map(|witness| witness.raw_data().len())
+            .sum();
+        return Err(format!(
+            "Transaction too large: {} bytes, the limit is {} bytes ({} inputs, {} bytes of witnesses), consolidate the small cells of the fee payer or reduce the proof size",
+            size,
+            MAX_BLOCK_BYTES,
+            transaction.inputs().len(),
+            witness_size
+        ));
+    }
+    Ok(())
+}
+
+/// Wait until the transaction is committed on ckb chain
+pub(crate) fn wait_tx_committed(
+    rpc_client: &mut HttpRpcClient,
+    tx_hash: &H256,
+    timeout: Duration,
+) -> Result<(), String> {
+    poll_until(
+        || {
+            let status = rpc_client
+                .get_transaction(tx_hash.clone())?
+                .map(|tx_with_status| tx_with_status.tx_status.status);
+            match status {
+                Some(Status::Committed) => Ok(Poll::Ready(())),
+                Some(_) => Ok(Poll::Pending),
+                None => Err(format!("Transaction {:#x} not found", tx_hash)),
+            }
+        },
+        Duration::from_secs(1),
+        Some(timeout),
+        &CANCELLED,
+    )?
+    .ready(
+        &format!("Wait transaction {:#x} committed", tx_hash),
+        Some(timeout),
+    )
+    .map_err(|err| BridgeError::pending(err).into())
+}
+
+pub(crate) enum CommitStatus {
+    Committed,
+    /// Still pending or proposed when timeout
+    Pending,
+    /// The transaction is not found (e.g. evicted from the tx pool)
+    Unknown,
+}
+
+/// Poll the transaction status until committed, the poll interval starts at `interval` and
+/// doubles each time up to `max_interval`. Return `Pending` instead of an error when timeout.
+pub(crate) fn wait_tx_committed_with_backoff(
+    rpc_client: &mut HttpRpcClient,
+    tx_hash: &H256,
+    interval: Duration,
+    max_interval: Duration,
+    timeout: Duration,
+) -> Result<CommitStatus, String> {
+    let status = poll_until(
+        || {
+            let status = rpc_client
+                .get_transaction(tx_hash.clone())?
+                .map(|tx_with_status| tx_with_status.tx_status.status);
+            Ok(match status {
+                Some(Status::Committed) => Poll::Ready(CommitStatus::Committed),
+                Some(_) => Poll::Pending,
+                None => Poll::Ready(CommitStatus::Unknown),
+            })
+        },
+        PollInterval::backoff(interval, max_interval),
+        Some(timeout),
+        &CANCELLED,
+    )?;
+    match status {
+        Poll::Ready(status) => Ok(status),
+        Poll::Pending | Poll::TimedOut => Ok(CommitStatus::Pending),
+        Poll::Cancelled => Err(BridgeError::pending(format!(
+            "Wait transaction {:#x} committed cancelled",
+            tx_hash
+        ))
+        .into()),
+    }
+}
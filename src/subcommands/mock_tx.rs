@@ -418,8 +418,9 @@ impl<'a> CliSubCommand for MockTxSubCommand<'a> {
     }
 }
 
-struct Loader<'a> {
-    rpc_client: &'a mut HttpRpcClient,
+/// Load the cells and headers of a mock transaction from the ckb node
+pub(crate) struct Loader<'a> {
+    pub(crate) rpc_client: &'a mut HttpRpcClient,
 }
 
 impl<'a> MockResourceLoader for Loader<'a> {
@@ -1,5 +1,6 @@
 pub mod account;
 pub mod api_server;
+pub mod ckb_bridge;
 pub mod dao;
 pub mod mock_tx;
 pub mod molecule;
@@ -10,13 +11,13 @@ pub mod tui;
 pub mod tx;
 pub mod util;
 pub mod wallet;
-pub mod ckb_bridge;
 
 #[cfg(unix)]
 pub use self::tui::TuiSubCommand;
 
 pub use account::AccountSubCommand;
 pub use api_server::ApiServerSubCommand;
+pub use ckb_bridge::CkbBridgeSubCommand;
 pub use dao::DAOSubCommand;
 pub use mock_tx::MockTxSubCommand;
 pub use molecule::MoleculeSubCommand;
@@ -26,7 +27,11 @@ pub use tx::TxSubCommand;
 pub use util::UtilSubCommand;
 pub use wallet::{start_index_thread, LiveCells, TransferArgs, WalletSubCommand};
 
+use std::fs;
+use std::path::Path;
+
 use clap::ArgMatches;
+use colored::Colorize;
 use serde::Serialize;
 
 use crate::utils::printer::{OutputFormat, Printable};
@@ -34,7 +39,14 @@ use crate::utils::printer::{OutputFormat, Printable};
 pub struct Output {
     stdout: Option<serde_json::Value>,
     stderr: Option<serde_json::Value>,
+    // The error message of a failed command, printed as is to stderr
+    error: Option<String>,
     success: bool,
+    exit_code: i32,
+    // Non-fatal conditions the user should not miss, apart from the result
+    warnings: Vec<String>,
+    // Print nothing on success except a json result, the warnings go to stderr
+    quiet: bool,
 }
 
 impl Output {
@@ -42,7 +54,11 @@ impl Output {
         Output {
             stdout: None,
             stderr: None,
+            error: None,
             success: true,
+            exit_code: 0,
+            warnings: Vec::new(),
+            quiet: false,
         }
     }
 
@@ -50,7 +66,11 @@ impl Output {
         Output {
             stdout: Some(serde_json::to_value(value).expect("serialize stdout error")),
             stderr: None,
+            error: None,
             success: false,
+            exit_code: 0,
+            warnings: Vec::new(),
+            quiet: false,
         }
     }
 
@@ -58,24 +78,136 @@ impl Output {
         Output {
             stdout: None,
             stderr: Some(serde_json::to_value(value).expect("serialize stderr error")),
+            error: None,
             success: false,
+            exit_code: 0,
+            warnings: Vec::new(),
+            quiet: false,
         }
     }
 
+    /// A failed command printed as an error, with the exit code the caller should see (the
+    /// warnings are still printed)
+    pub fn new_failure(error: String, exit_code: i32) -> Output {
+        Output {
+            stdout: None,
+            stderr: None,
+            error: Some(error),
+            success: false,
+            exit_code,
+            warnings: Vec::new(),
+            quiet: false,
+        }
+    }
+
+    /// Printed normally, but the process exits with this code (non-interactive mode only)
+    pub fn with_exit_code(mut self, exit_code: i32) -> Output {
+        self.exit_code = exit_code;
+        self
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        self.exit_code
+    }
+
+    pub fn with_warnings(mut self, warnings: Vec<String>) -> Output {
+        self.warnings.extend(warnings);
+        self
+    }
+
+    pub fn with_quiet(mut self, quiet: bool) -> Output {
+        self.quiet = quiet;
+        self
+    }
+
+    /// The warnings are a top-level `warnings` array of the json output (printed to stderr if
+    /// the output is not an object), or `[WARNING]` lines after the output otherwise. When
+    /// quiet only a json output is printed and the warnings always go to stderr.
     pub fn print(&self, format: OutputFormat, color: bool) {
-        if let Some(ref stdout) = self.stdout {
-            println!("{}", stdout.render(format, color));
+        self.print_to(format, color, None)
+            .expect("print to stdout never fails");
+    }
+
+    /// Print as `print`, but the result (stdout) is written to `output_file` if given, without
+    /// color. The file is replaced atomically and its parent directories are created, the
+    /// warnings not in the json result, the errors and the success status stay on stderr.
+    pub fn print_to(
+        &self,
+        format: OutputFormat,
+        color: bool,
+        output_file: Option<&Path>,
+    ) -> Result<(), String> {
+        let stdout_color = color && output_file.is_none();
+        let mut warnings_printed = self.warnings.is_empty();
+        let mut result = None;
+        let stdout = self
+            .stdout
+            .as_ref()
+            .filter(|_| !self.quiet || format == OutputFormat::Json);
+        if let Some(stdout) = stdout {
+            match stdout {
+                serde_json::Value::Object(map)
+                    if format == OutputFormat::Json && !warnings_printed && !self.quiet =>
+                {
+                    let mut map = map.clone();
+                    map.insert("warnings".to_string(), serde_json::json!(self.warnings));
+                    let stdout = serde_json::Value::Object(map);
+                    result = Some(stdout.render(format, stdout_color));
+                    warnings_printed = true;
+                }
+                _ => result = Some(stdout.render(format, stdout_color)),
+            }
+        }
+        match (result, output_file) {
+            (Some(result), Some(path)) => write_file_atomic(path, &format!("{}\n", result))?,
+            (Some(result), None) => println!("{}", result),
+            (None, _) => {}
+        }
+        if !warnings_printed {
+            if format == OutputFormat::Json {
+                let resp = serde_json::json!({ "warnings": self.warnings });
+                eprintln!("{}", resp.render(format, color));
+            } else {
+                for warning in &self.warnings {
+                    let line = format!("[WARNING] {}", warning);
+                    if color {
+                        eprintln!("{}", line.yellow().bold());
+                    } else {
+                        eprintln!("{}", line);
+                    }
+                }
+            }
         }
         if let Some(ref stderr) = self.stderr {
             eprintln!("{}", stderr.render(format, color));
         }
-        if self.success {
+        if let Some(ref error) = self.error {
+            eprintln!("{}", error);
+        }
+        if self.success && !self.quiet {
             let resp = serde_json::json!({
                 "status": "success",
             });
             eprintln!("{}", resp.render(OutputFormat::Yaml, color));
         }
+        Ok(())
+    }
+}
+
+/// Write `content` to a temp file next to `path` then rename it, so a reader never sees a
+/// partial file, the parent directories are created
+fn write_file_atomic(path: &Path, content: &str) -> Result<(), String> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|err| format!("Create {:?} error: {}", dir, err))?;
     }
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| format!("Invalid output file {:?}", path))?;
+    let mut tmp_name = file_name.to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+    fs::write(&tmp_path, content).map_err(|err| format!("Write {:?} error: {}", tmp_path, err))?;
+    fs::rename(&tmp_path, path).map_err(|err| format!("Write {:?} error: {}", path, err))
 }
 
 pub trait CliSubCommand {
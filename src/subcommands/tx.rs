@@ -691,14 +691,14 @@ fn modify_tx_file<T, F: FnOnce(&mut TxHelper) -> Result<T, String>>(
 
 #[derive(Clone, Default, Serialize, Deserialize, PartialEq, Eq, Debug)]
 #[serde(deny_unknown_fields)]
-struct ReprTxHelper {
+pub(crate) struct ReprTxHelper {
     transaction: json_types::Transaction,
     multisig_configs: HashMap<H160, ReprMultisigConfig>,
     signatures: HashMap<JsonBytes, Vec<JsonBytes>>,
 }
 
 impl ReprTxHelper {
-    fn new(tx: TxHelper, network: NetworkType) -> Self {
+    pub(crate) fn new(tx: TxHelper, network: NetworkType) -> Self {
         ReprTxHelper {
             transaction: tx.transaction().data().into(),
             multisig_configs: tx
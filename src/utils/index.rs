@@ -101,6 +101,15 @@ impl IndexThreadState {
             _ => false,
         }
     }
+    /// The indexed block number and the tip number while processing
+    pub fn sync_progress(&self) -> Option<(u64, u64)> {
+        match self {
+            IndexThreadState::Processing(Some(SimpleBlockInfo { number, .. }), tip_number) => {
+                Some((*number, *tip_number))
+            }
+            _ => None,
+        }
+    }
     pub fn is_error(&self) -> bool {
         match self {
             IndexThreadState::Error(_) => true,
@@ -2,7 +2,6 @@ use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::Read;
 use std::path::PathBuf;
-use std::thread;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use ckb_hash::blake2b_256;
@@ -32,6 +31,7 @@ use super::arg_parser::{
     AddressParser, ArgParser, FixedHashParser, HexParser, PrivkeyWrapper, PubkeyHexParser,
 };
 use super::index::{IndexController, IndexRequest, IndexThreadState};
+use super::poll::{poll_until, Poll, CANCELLED};
 use crate::plugin::{KeyStoreHandler, SignTarget};
 
 pub fn read_password(repeat: bool, prompt: Option<&str>) -> Result<String, String> {
@@ -237,17 +237,22 @@ pub fn index_dirname() -> String {
 pub fn sync_to_tip(index_controller: &IndexController) -> Result<(), String> {
     // Kick index thread to start
     Request::call(index_controller.sender(), IndexRequest::Kick);
-    loop {
-        let state = IndexThreadState::clone(&index_controller.state().read());
-        if state.is_synced() {
-            break;
-        } else if state.is_error() {
-            return Err(state.get_error().unwrap());
-        } else {
-            thread::sleep(Duration::from_millis(200));
-        }
-    }
-    Ok(())
+    poll_until(
+        || {
+            let state = IndexThreadState::clone(&index_controller.state().read());
+            if state.is_synced() {
+                Ok(Poll::Ready(()))
+            } else if state.is_error() {
+                Err(state.get_error().unwrap())
+            } else {
+                Ok(Poll::Pending)
+            }
+        },
+        Duration::from_millis(200),
+        None,
+        &CANCELLED,
+    )?
+    .ready("Wait for the index synced", None)
 }
 
 pub fn check_capacity(capacity: u64, to_data_len: usize) -> Result<(), String> {
@@ -0,0 +1,251 @@
+use std::cmp;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// The longest sleep between two checks of the cancellation flag
+const CANCEL_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Set once the running command is cancelled, every wait loop polling with `poll_until` stops
+/// at its next check
+pub static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// Cancel the waits of the running command (e.g. on Ctrl-C)
+pub fn cancel() {
+    CANCELLED.store(true, Ordering::SeqCst);
+}
+
+/// Cancel the waits of a one-shot command on SIGINT or SIGTERM, a second signal exits at once
+/// (a wait not polled by `poll_until`, e.g. an rpc request, is not cancelled). Not installed
+/// in the interactive mode, which handles Ctrl-C itself.
+#[cfg(unix)]
+pub fn cancel_on_signal() {
+    extern "C" fn on_signal(signal: libc::c_int) {
+        // Only async-signal-safe calls here
+        if CANCELLED.swap(true, Ordering::SeqCst) {
+            unsafe { libc::_exit(128 + signal) };
+        }
+    }
+    unsafe {
+        libc::signal(libc::SIGINT, on_signal as libc::sighandler_t);
+        libc::signal(libc::SIGTERM, on_signal as libc::sighandler_t);
+    }
+}
+
+#[cfg(not(unix))]
+pub fn cancel_on_signal() {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Poll<T> {
+    /// Not ready yet, only returned by the polled condition
+    Pending,
+    Ready(T),
+    Cancelled,
+    TimedOut,
+}
+
+impl<T> Poll<T> {
+    /// The ready value, or an error of `waiting` (what was waited for) cancelled or timed out
+    pub fn ready(self, waiting: &str, timeout: Option<Duration>) -> Result<T, String> {
+        match self {
+            Poll::Ready(value) => Ok(value),
+            Poll::Cancelled => Err(format!("{} cancelled", waiting)),
+            Poll::Pending | Poll::TimedOut => Err(match timeout {
+                Some(timeout) => format!("{} timeout ({}s)", waiting, timeout.as_secs()),
+                None => format!("{} timeout", waiting),
+            }),
+        }
+    }
+}
+
+/// The sleep between two polls, starting at `initial` and doubled after each pending poll up
+/// to `max`. A plain `Duration` is a fixed interval.
+#[derive(Clone, Copy, Debug)]
+pub struct PollInterval {
+    pub initial: Duration,
+    pub max: Duration,
+}
+
+impl PollInterval {
+    pub fn backoff(initial: Duration, max: Duration) -> PollInterval {
+        PollInterval { initial, max }
+    }
+}
+
+impl From<Duration> for PollInterval {
+    fn from(interval: Duration) -> PollInterval {
+        PollInterval {
+            initial: interval,
+            max: interval,
+        }
+    }
+}
+
+/// Poll `cond` until it is ready, `cancel` is set or `timeout` (`None` for no timeout) is
+/// exceeded. `cond` is polled at least once, and may stop the waiting by returning
+/// `Cancelled` or `TimedOut` itself. An error of `cond` is returned as is.
+pub fn poll_until<T, F, I>(
+    mut cond: F,
+    interval: I,
+    timeout: Option<Duration>,
+    cancel: &AtomicBool,
+) -> Result<Poll<T>, String>
+where
+    F: FnMut() -> Result<Poll<T>, String>,
+    I: Into<PollInterval>,
+{
+    let PollInterval { mut initial, max } = interval.into();
+    let start = Instant::now();
+    loop {
+        if cancel.load(Ordering::SeqCst) {
+            return Ok(Poll::Cancelled);
+        }
+        match cond()? {
+            Poll::Pending => {}
+            done => return Ok(done),
+        }
+        let mut sleep = initial;
+        if let Some(timeout) = timeout {
+            let elapsed = start.elapsed();
+            if elapsed >= timeout {
+                return Ok(Poll::TimedOut);
+            }
+            sleep = cmp::min(sleep, timeout - elapsed);
+        }
+        if !sleep_unless_cancelled(sleep, cancel) {
+            return Ok(Poll::Cancelled);
+        }
+        initial = cmp::min(initial * 2, max);
+    }
+}
+
+/// Sleep in short slices checking `cancel`, return false if cancelled
+fn sleep_unless_cancelled(duration: Duration, cancel: &AtomicBool) -> bool {
+    let deadline = Instant::now() + duration;
+    loop {
+        if cancel.load(Ordering::SeqCst) {
+            return false;
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return true;
+        }
+        thread::sleep(cmp::min(deadline - now, CANCEL_CHECK_INTERVAL));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn test_poll_until() {
+        let cancel = AtomicBool::new(false);
+        let mut polls = 0;
+        let result = poll_until(
+            || {
+                polls += 1;
+                Ok(if polls == 3 {
+                    Poll::Ready(polls)
+                } else {
+                    Poll::Pending
+                })
+            },
+            INTERVAL,
+            None,
+            &cancel,
+        );
+        assert_eq!(result, Ok(Poll::Ready(3)));
+
+        let result = poll_until(
+            || Ok(Poll::<()>::Pending),
+            INTERVAL,
+            Some(Duration::from_millis(20)),
+            &cancel,
+        );
+        assert_eq!(result, Ok(Poll::TimedOut));
+        // Polled once even with no time left
+        let result = poll_until(
+            || Ok(Poll::Ready(())),
+            INTERVAL,
+            Some(Duration::from_secs(0)),
+            &cancel,
+        );
+        assert_eq!(result, Ok(Poll::Ready(())));
+
+        let result = poll_until(
+            || Err::<Poll<()>, _>("rpc error".to_string()),
+            INTERVAL,
+            None,
+            &cancel,
+        );
+        assert_eq!(result, Err("rpc error".to_string()));
+        let result = poll_until(|| Ok(Poll::<()>::Cancelled), INTERVAL, None, &cancel);
+        assert_eq!(result, Ok(Poll::Cancelled));
+
+        assert_eq!(Poll::Ready(1).ready("Wait", None), Ok(1));
+        assert_eq!(
+            Poll::<()>::TimedOut.ready("Wait mined", Some(Duration::from_secs(3))),
+            Err("Wait mined timeout (3s)".to_string())
+        );
+        assert_eq!(
+            Poll::<()>::Cancelled.ready("Wait mined", Some(Duration::from_secs(3))),
+            Err("Wait mined cancelled".to_string())
+        );
+    }
+
+    #[test]
+    fn test_poll_cancelled() {
+        let cancel = AtomicBool::new(false);
+        let mut polls = 0;
+        // Cancelled while sleeping, long before the interval is over
+        let start = Instant::now();
+        let result = poll_until(
+            || {
+                polls += 1;
+                cancel.store(true, Ordering::SeqCst);
+                Ok(Poll::<()>::Pending)
+            },
+            Duration::from_secs(60),
+            None,
+            &cancel,
+        );
+        assert_eq!(result, Ok(Poll::Cancelled));
+        assert_eq!(polls, 1);
+        assert!(start.elapsed() < Duration::from_secs(10));
+
+        // Not polled once cancelled
+        let result = poll_until(|| Ok(Poll::Ready(())), INTERVAL, None, &cancel);
+        assert_eq!(result, Ok(Poll::Cancelled));
+    }
+
+    #[test]
+    fn test_poll_backoff() {
+        let cancel = AtomicBool::new(false);
+        let mut times = Vec::new();
+        let interval = PollInterval::backoff(Duration::from_millis(10), Duration::from_millis(40));
+        let result = poll_until(
+            || {
+                times.push(Instant::now());
+                Ok(if times.len() == 5 {
+                    Poll::Ready(())
+                } else {
+                    Poll::Pending
+                })
+            },
+            interval,
+            None,
+            &cancel,
+        );
+        assert_eq!(result, Ok(Poll::Ready(())));
+        let sleeps = times
+            .windows(2)
+            .map(|pair| pair[1] - pair[0])
+            .collect::<Vec<_>>();
+        for (sleep, expected) in sleeps.iter().zip(&[10, 20, 40, 40]) {
+            assert!(*sleep >= Duration::from_millis(*expected));
+        }
+    }
+}
@@ -5,6 +5,7 @@ pub struct App {
     ckb_bin: String,
     cli_bin: String,
     keystore_plugin_bin: String,
+    bridge_scripts_dir: Option<String>,
 }
 
 impl App {
@@ -13,6 +14,9 @@ impl App {
         let ckb_bin = matches.value_of("ckb-bin").unwrap().to_string();
         let cli_bin = matches.value_of("cli-bin").unwrap().to_string();
         let keystore_plugin_bin = matches.value_of("keystore-plugin").unwrap().to_string();
+        let bridge_scripts_dir = matches
+            .value_of("bridge-scripts-dir")
+            .map(ToOwned::to_owned);
         assert!(
             Path::new(&ckb_bin).exists(),
             "ckb-bin binary not exists: {}",
@@ -28,10 +32,18 @@ impl App {
             "keystore plugin binary not exists: {}",
             keystore_plugin_bin,
         );
+        if let Some(dir) = bridge_scripts_dir.as_ref() {
+            assert!(
+                Path::new(dir).exists(),
+                "bridge scripts dir not exists: {}",
+                dir
+            );
+        }
         Self {
             ckb_bin,
             cli_bin,
             keystore_plugin_bin,
+            bridge_scripts_dir,
         }
     }
 
@@ -47,6 +59,10 @@ impl App {
         &self.keystore_plugin_bin
     }
 
+    pub fn bridge_scripts_dir(&self) -> Option<&str> {
+        self.bridge_scripts_dir.as_deref()
+    }
+
     fn matches() -> clap::ArgMatches {
         clap::App::new("ckb-cli-test")
             .arg(
@@ -73,6 +89,13 @@ impl App {
                     .value_name("PATH")
                     .about("Path to keystore plugin executable"),
             )
+            .arg(
+                clap::Arg::with_name("bridge-scripts-dir")
+                    .long("bridge-scripts-dir")
+                    .takes_value(true)
+                    .value_name("PATH")
+                    .about("Directory of the ckb-bridge script binaries (bridge, light-client), ckb-bridge specs are skipped if not given"),
+            )
             .get_matches()
     }
 }
@@ -7,8 +7,8 @@ pub mod util;
 use crate::app::App;
 use crate::setup::Setup;
 use crate::spec::{
-    DaoPrepareMultiple, DaoPrepareOne, DaoWithdrawMultiple, Plugin, RpcGetTipBlockNumber, Spec,
-    Util, WalletTimelockedAddress, WalletTransfer,
+    CkbBridgeDeploy, DaoPrepareMultiple, DaoPrepareOne, DaoWithdrawMultiple, Plugin,
+    RpcGetTipBlockNumber, Spec, Util, WalletTimelockedAddress, WalletTransfer,
 };
 use crate::util::{find_available_port, run_cmd, temp_dir};
 use std::env;
@@ -21,7 +21,7 @@ fn main() {
         env_logger::builder().parse_filters(&filter).try_init()
     };
     let app = app::App::init();
-    for spec in all_specs() {
+    for spec in all_specs(&app) {
         run_spec(spec, &app);
     }
 }
@@ -61,8 +61,8 @@ fn run_spec(spec: Box<dyn Spec>, app: &App) {
     spec.run(&mut setup);
 }
 
-fn all_specs() -> Vec<Box<dyn Spec>> {
-    vec![
+fn all_specs(app: &App) -> Vec<Box<dyn Spec>> {
+    let mut specs: Vec<Box<dyn Spec>> = vec![
         Box::new(Plugin),
         Box::new(RpcGetTipBlockNumber),
         Box::new(WalletTransfer),
@@ -71,5 +71,10 @@ fn all_specs() -> Vec<Box<dyn Spec>> {
         Box::new(DaoPrepareMultiple),
         Box::new(DaoWithdrawMultiple),
         Box::new(Util),
-    ]
+    ];
+    // The bridge scripts are not shipped with ckb-cli
+    if let Some(dir) = app.bridge_scripts_dir() {
+        specs.push(Box::new(CkbBridgeDeploy::new(dir)));
+    }
+    specs
 }
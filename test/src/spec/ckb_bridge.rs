@@ -0,0 +1,112 @@
+use crate::miner::Miner;
+use crate::setup::Setup;
+use crate::spec::{Spec, ACCOUNT1_ADDRESS, ACCOUNT1_PRIVKEY};
+use tempfile::tempdir;
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Deploy the toCKB scripts by `ckb-bridge deploy-ckb`, the script binaries are read from
+/// `<scripts-dir>/bridge` and `<scripts-dir>/light-client`.
+pub struct CkbBridgeDeploy {
+    scripts_dir: PathBuf,
+}
+
+impl CkbBridgeDeploy {
+    pub fn new(scripts_dir: &str) -> Self {
+        Self {
+            scripts_dir: PathBuf::from(scripts_dir),
+        }
+    }
+}
+
+impl Spec for CkbBridgeDeploy {
+    fn run(&self, setup: &mut Setup) {
+        let tempdir = tempdir().expect("create tempdir failed");
+        let privkey_path = tempdir.path().join("account1");
+        fs::write(&privkey_path, ACCOUNT1_PRIVKEY).unwrap();
+
+        setup.miner().generate_blocks(30);
+        let fund_tx_hash = fund_account(setup, ACCOUNT1_ADDRESS, 100_000);
+
+        let output = with_auto_mining(setup, |setup| {
+            setup.cli(&format!(
+                "ckb-bridge deploy-ckb --privkey-path {} --tx-fee 0.001 --bridge-script {} --light-client-script {}",
+                privkey_path.display(),
+                self.scripts_dir.join("bridge").display(),
+                self.scripts_dir.join("light-client").display(),
+            ))
+        });
+        log::info!("deploy toCKB scripts: {}", output);
+        let resp: serde_yaml::Value = serde_yaml::from_str(&output)
+            .unwrap_or_else(|_| panic!("deploy-ckb failed: {}", output));
+        let transactions = resp["transactions"]
+            .as_sequence()
+            .expect("deploy-ckb transactions")
+            .iter()
+            .map(|tx_hash| tx_hash.as_str().expect("tx hash").to_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(transactions.len(), 2);
+
+        // The funded cell is consumed
+        assert_eq!(cell_status(setup, &fund_tx_hash, 0), "unknown");
+        // The bridge code cell and the light client code cell
+        assert_eq!(cell_status(setup, &transactions[0], 0), "live");
+        assert_eq!(cell_status(setup, &transactions[0], 1), "live");
+        // The light client cell
+        assert_eq!(cell_status(setup, &transactions[1], 0), "live");
+        assert!(resp["light_client"]["type_script"].is_mapping());
+        assert!(resp["bridge_script"]["code_hash"].is_string());
+    }
+}
+
+/// Transfer `capacity` CKB from the miner (funded in the dev genesis) to `address`, return
+/// the transaction hash, the funded cell is output 0.
+pub fn fund_account(setup: &mut Setup, address: &str, capacity: u64) -> String {
+    let miner_privkey = setup.miner().privkey_path().to_string();
+    let tx_hash = setup.cli(&format!(
+        "wallet transfer --privkey-path {} --to-address {} --capacity {} --tx-fee 0.00001",
+        miner_privkey, address, capacity,
+    ));
+    assert!(
+        tx_hash.starts_with("0x"),
+        "fund account failed: {}",
+        tx_hash
+    );
+    setup.miner().generate_blocks(3);
+    tx_hash
+}
+
+/// Keep generating blocks in background while `f` is running, for commands waiting their
+/// transactions committed.
+fn with_auto_mining<T>(setup: &Setup, f: impl FnOnce(&Setup) -> T) -> T {
+    let stop = Arc::new(AtomicBool::new(false));
+    let handle = {
+        let stop = Arc::clone(&stop);
+        let miner = Miner::init(setup.rpc_url());
+        thread::spawn(move || {
+            while !stop.load(Ordering::SeqCst) {
+                miner.generate_block();
+                thread::sleep(Duration::from_millis(200));
+            }
+        })
+    };
+    let result = f(setup);
+    stop.store(true, Ordering::SeqCst);
+    handle.join().expect("join auto mining thread");
+    result
+}
+
+fn cell_status(setup: &Setup, tx_hash: &str, index: u32) -> String {
+    let output = setup.cli(&format!(
+        "rpc get_live_cell --tx-hash {} --index {}",
+        tx_hash, index
+    ));
+    let resp: serde_yaml::Value = serde_yaml::from_str(&output)
+        .unwrap_or_else(|_| panic!("get_live_cell failed: {}", output));
+    resp["status"].as_str().expect("cell status").to_owned()
+}
@@ -1,9 +1,11 @@
+mod ckb_bridge;
 mod dao;
 mod plugin;
 mod rpc;
 mod util;
 mod wallet;
 
+pub use ckb_bridge::*;
 pub use dao::*;
 pub use plugin::*;
 pub use rpc::*;